@@ -67,13 +67,18 @@ pub enum Code {
     BadParameter,
     BadRequest,
     DocumentNotFound,
+    ExpiredToken,
+    ForbiddenKeyScope,
     Internal,
     InvalidToken,
     MissingAuthorizationHeader,
     NotFound,
     PayloadTooLarge,
+    ReadOnly,
     RetrieveDocument,
     SearchDocuments,
+    ServiceUnavailable,
+    Timeout,
     UnsupportedMediaType,
 
     DumpAlreadyInProgress,
@@ -121,16 +126,25 @@ impl Code {
             BadRequest => ErrCode::invalid("bad_request", StatusCode::BAD_REQUEST),
             DocumentNotFound => ErrCode::invalid("document_not_found", StatusCode::NOT_FOUND),
             Internal => ErrCode::internal("internal", StatusCode::INTERNAL_SERVER_ERROR),
+            ExpiredToken => ErrCode::authentication("expired_token", StatusCode::FORBIDDEN),
+            ForbiddenKeyScope => {
+                ErrCode::authentication("forbidden_key_scope", StatusCode::FORBIDDEN)
+            }
             InvalidToken => ErrCode::authentication("invalid_token", StatusCode::FORBIDDEN),
             MissingAuthorizationHeader => {
                 ErrCode::authentication("missing_authorization_header", StatusCode::UNAUTHORIZED)
             }
             NotFound => ErrCode::invalid("not_found", StatusCode::NOT_FOUND),
             PayloadTooLarge => ErrCode::invalid("payload_too_large", StatusCode::PAYLOAD_TOO_LARGE),
+            ReadOnly => ErrCode::invalid("read_only", StatusCode::FORBIDDEN),
             RetrieveDocument => {
                 ErrCode::internal("unretrievable_document", StatusCode::BAD_REQUEST)
             }
             SearchDocuments => ErrCode::internal("search_error", StatusCode::BAD_REQUEST),
+            ServiceUnavailable => {
+                ErrCode::internal("service_unavailable", StatusCode::SERVICE_UNAVAILABLE)
+            }
+            Timeout => ErrCode::internal("timeout", StatusCode::GATEWAY_TIMEOUT),
             UnsupportedMediaType => {
                 ErrCode::invalid("unsupported_media_type", StatusCode::UNSUPPORTED_MEDIA_TYPE)
             }
@@ -1,47 +1,47 @@
-use std::hash::{Hash, Hasher};
-use std::{error, thread};
+use std::path::Path;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use log::error;
+use log::{error, info};
+use reqwest::header::CONTENT_TYPE;
 use serde::Serialize;
-use serde_qs as qs;
-use siphasher::sip::SipHasher;
-use walkdir::WalkDir;
+use tokio::time::sleep;
+use uuid::Uuid;
 
-use crate::helpers::EnvSizer;
 use crate::Data;
 use crate::Opt;
 
 const AMPLITUDE_API_KEY: &str = "f7fba398780e06d8fe6666a9be7e3d47";
+const AMPLITUDE_URL: &str = "https://api2.amplitude.com/2/httpapi";
+const INSTANCE_UID_FILE: &str = "instance-uid";
+const SEND_INTERVAL: Duration = Duration::from_secs(3600); // one hour
 
+/// The anonymized usage of the instance, it never contains the names of the indexes nor their
+/// documents.
 #[derive(Debug, Serialize)]
 struct EventProperties {
     database_size: u64,
-    last_update_timestamp: Option<i64>, //timestamp
+    last_update_timestamp: Option<i64>,
+    number_of_indexes: usize,
     number_of_documents: Vec<u64>,
 }
 
 impl EventProperties {
-    fn from(data: Data) -> Result<EventProperties, Box<dyn error::Error>> {
-        let mut index_list = Vec::new();
+    async fn from(data: &Data) -> anyhow::Result<EventProperties> {
+        let stats = data.get_stats().await?;
 
-        let reader = data.db.main_read_txn()?;
-
-        for index_uid in data.db.indexes_uids() {
-            if let Some(index) = data.db.open_index(&index_uid) {
-                let number_of_documents = index.main.number_of_documents(&reader)?;
-                index_list.push(number_of_documents);
-            }
-        }
-
-        let database_size = data.env.size();
-
-        let last_update_timestamp = data.db.last_update(&reader)?.map(|u| u.timestamp());
+        // The counts are sorted so that they can't be matched with the indexes.
+        let mut number_of_documents: Vec<_> = stats
+            .indexes
+            .values()
+            .map(|index| index.number_of_documents)
+            .collect();
+        number_of_documents.sort_unstable();
 
         Ok(EventProperties {
-            database_size,
-            last_update_timestamp,
-            number_of_documents: index_list,
+            database_size: stats.database_size,
+            last_update_timestamp: stats.last_update.map(|u| u.timestamp()),
+            number_of_indexes: stats.indexes.len(),
+            number_of_documents,
         })
     }
 }
@@ -68,66 +68,102 @@ struct Event<'a> {
 #[derive(Debug, Serialize)]
 struct AmplitudeRequest<'a> {
     api_key: &'a str,
-    event: &'a str,
+    events: Vec<Event<'a>>,
 }
 
-pub fn analytics_sender(data: Data, opt: Opt) {
-    let username = whoami::username();
-    let hostname = whoami::hostname();
-    let platform = whoami::platform();
+/// Returns the random identifier of the instance, it is generated at the first launch and stored
+/// in the database directory.
+fn instance_uid(db_path: &Path) -> String {
+    let path = db_path.join(INSTANCE_UID_FILE);
+    if let Ok(uid) = std::fs::read_to_string(&path) {
+        let uid = uid.trim();
+        if !uid.is_empty() {
+            return uid.to_string();
+        }
+    }
+
+    let uid = Uuid::new_v4().to_string();
+    if let Err(e) = std::fs::write(&path, &uid) {
+        error!("Could not persist the instance uid: {}", e);
+    }
+    uid
+}
 
-    let uid = username + &hostname + &platform.to_string();
+/// Builds the json payload sent to Amplitude.
+pub async fn payload(
+    data: &Data,
+    opt: &Opt,
+    user_id: &str,
+    start_since_days: u64,
+) -> anyhow::Result<String> {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let event_properties = match EventProperties::from(data).await {
+        Ok(properties) => Some(properties),
+        Err(e) => {
+            error!("Could not compute the analytics: {}", e);
+            None
+        }
+    };
+    let user_properties = UserProperties {
+        env: &opt.env,
+        start_since_days,
+        user_email: std::env::var("MEILI_USER_EMAIL").ok(),
+        server_provider: std::env::var("MEILI_SERVER_PROVIDER").ok(),
+    };
+
+    let event = Event {
+        user_id,
+        event_type: "runtime_tick",
+        device_id: std::env::consts::OS,
+        time,
+        app_version: env!("CARGO_PKG_VERSION"),
+        user_properties,
+        event_properties,
+    };
+    let request = AmplitudeRequest {
+        api_key: AMPLITUDE_API_KEY,
+        events: vec![event],
+    };
+
+    Ok(serde_json::to_string(&request)?)
+}
 
-    let mut hasher = SipHasher::new();
-    uid.hash(&mut hasher);
-    let hash = hasher.finish();
+/// Periodically sends the anonymized usage of the instance, or only logs it in dry run mode.
+/// Nothing is computed when the analytics are disabled.
+pub async fn analytics_sender(data: Data, opt: Opt) {
+    if opt.no_analytics {
+        return;
+    }
 
-    let uid = format!("{:X}", hash);
-    let platform = platform.to_string();
+    let user_id = instance_uid(&opt.db_path);
     let first_start = Instant::now();
+    let client = reqwest::Client::new();
 
     loop {
-        let n = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-        let user_id = &uid;
-        let device_id = &platform;
-        let time = n.as_secs();
-        let event_type = "runtime_tick";
-        let elapsed_since_start = first_start.elapsed().as_secs() / 86_400; // One day
-        let event_properties = EventProperties::from(data.clone()).ok();
-        let app_version = env!("CARGO_PKG_VERSION").to_string();
-        let app_version = app_version.as_str();
-        let user_email = std::env::var("MEILI_USER_EMAIL").ok();
-        let server_provider = std::env::var("MEILI_SERVER_PROVIDER").ok();
-        let user_properties = UserProperties {
-            env: &opt.env,
-            start_since_days: elapsed_since_start,
-            user_email,
-            server_provider,
-        };
-
-        let event = Event {
-            user_id,
-            event_type,
-            device_id,
-            time,
-            app_version,
-            user_properties,
-            event_properties,
-        };
-        let event = serde_json::to_string(&event).unwrap();
-
-        let request = AmplitudeRequest {
-            api_key: AMPLITUDE_API_KEY,
-            event: &event,
-        };
-
-        let body = qs::to_string(&request).unwrap();
-        let response = ureq::post("https://api.amplitude.com/httpapi").send_string(&body);
-        if !response.ok() {
-            let body = response.into_string().unwrap();
-            error!("Unsuccessful call to Amplitude: {}", body);
+        let start_since_days = first_start.elapsed().as_secs() / 86_400; // One day
+        match payload(&data, &opt, &user_id, start_since_days).await {
+            Ok(payload) if opt.analytics_dry_run => {
+                info!("Analytics payload (dry run, not sent): {}", payload);
+            }
+            Ok(payload) => {
+                let response = client
+                    .post(AMPLITUDE_URL)
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(payload)
+                    .send()
+                    .await;
+                match response {
+                    Ok(response) if !response.status().is_success() => {
+                        let body = response.text().await.unwrap_or_default();
+                        error!("Unsuccessful call to Amplitude: {}", body);
+                    }
+                    Ok(_) => (),
+                    Err(e) => error!("Unsuccessful call to Amplitude: {}", e),
+                }
+            }
+            Err(e) => error!("Could not build the analytics payload: {}", e),
         }
 
-        thread::sleep(Duration::from_secs(3600)) // one hour
+        sleep(SEND_INTERVAL).await;
     }
 }
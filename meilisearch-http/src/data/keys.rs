@@ -0,0 +1,177 @@
+use std::fs::{self, create_dir_all};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use heed::types::{SerdeJson, Str};
+use heed::{CompactionOption, Database, Env, EnvOpenOptions};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+
+use super::ApiKeys;
+
+const KEY_STORE_SIZE: usize = 10 * 1024 * 1024; // 10MiB
+const KEY_LENGTH: usize = 32;
+/// The file holding the keys of the master key set by a rotation, in the directory of the key store.
+const ROTATED_KEYS_FILE: &str = "rotated-keys";
+
+/// Grants a scoped key the access to all the indexes.
+pub const ALL_INDEXES: &str = "*";
+
+/// The actions a scoped key can be allowed to perform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum KeyAction {
+    Search,
+    DocumentsWrite,
+    Settings,
+}
+
+/// The indexes a scoped key gives access to, and the actions it allows on them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyScope {
+    pub indexes: Vec<String>,
+    pub actions: Vec<KeyAction>,
+    pub created_at: DateTime<Utc>,
+    /// The key can't be used anymore after this date, it never expires if unset.
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// The filter applied to all the searches made with the key, in addition to their own filter.
+    #[serde(default)]
+    pub filter: Option<String>,
+}
+
+impl KeyScope {
+    /// Returns whether `action` is allowed on `index`. The routes that don't target a single index
+    /// are only allowed to the keys giving access to all the indexes.
+    pub fn allows(&self, action: KeyAction, index: Option<&str>) -> bool {
+        self.actions.contains(&action)
+            && self
+                .indexes
+                .iter()
+                .any(|uid| uid == ALL_INDEXES || Some(uid.as_str()) == index)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at
+            .map_or(false, |expires_at| expires_at <= now)
+    }
+}
+
+/// Stores the scopes of the keys. The keys themselves are never stored, the scopes are indexed by
+/// the hash of their key. The master key set by a rotation isn't stored either, only its hash and
+/// the private and public keys derived from it are, in the `rotated-keys` file of the store.
+#[derive(Clone)]
+pub struct KeyStore {
+    path: PathBuf,
+    env: Env,
+    db: Database<Str, SerdeJson<KeyScope>>,
+}
+
+impl KeyStore {
+    pub fn new(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref().join("keys");
+        create_dir_all(&path)?;
+        let mut options = EnvOpenOptions::new();
+        options.map_size(KEY_STORE_SIZE);
+        let env = options.open(&path)?;
+        let db = env.create_database(None)?;
+        Ok(Self { path, env, db })
+    }
+
+    /// Returns the keys of the master key set by the last rotation, if any.
+    pub fn rotated_keys(&self) -> anyhow::Result<Option<ApiKeys>> {
+        match fs::read(self.path.join(ROTATED_KEYS_FILE)) {
+            Ok(keys) => Ok(Some(serde_json::from_slice(&keys)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Persists the keys of a new master key, they replace the ones of the master key given at
+    /// launch from then on. The scoped keys were granted with the previous master key, they are
+    /// all revoked.
+    ///
+    /// The private key is a secret, the file is only readable by the owner of the database. It is
+    /// written to a temporary file first, so that a failure can't leave truncated keys behind, and
+    /// the scoped keys are revoked before the keys replace the previous ones.
+    pub fn rotate(&self, api_keys: &ApiKeys) -> anyhow::Result<()> {
+        let tmp = self.path.join(format!("{}.tmp", ROTATED_KEYS_FILE));
+        write_secret(&tmp, &serde_json::to_string(api_keys)?)?;
+
+        let mut txn = self.env.write_txn()?;
+        self.db.clear(&mut txn)?;
+        txn.commit()?;
+
+        fs::rename(tmp, self.path.join(ROTATED_KEYS_FILE))?;
+        Ok(())
+    }
+
+    /// Copies the scopes of the keys in the `keys` directory of `path`. The keys of a rotated
+    /// master key are left out: the snapshot is protected by the master key given at launch.
+    pub fn snapshot(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref().join("keys");
+        create_dir_all(&path)?;
+        self.env
+            .copy_to_path(path.join("data.mdb"), CompactionOption::Enabled)?;
+        Ok(())
+    }
+
+    /// Generates a new key with the given scope, the key is returned and can't be retrieved later.
+    pub fn create(
+        &self,
+        indexes: Vec<String>,
+        actions: Vec<KeyAction>,
+        expires_at: Option<DateTime<Utc>>,
+        filter: Option<String>,
+    ) -> anyhow::Result<(String, KeyScope)> {
+        let key: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(KEY_LENGTH)
+            .collect();
+        let scope = KeyScope {
+            indexes,
+            actions,
+            created_at: Utc::now(),
+            expires_at,
+            filter,
+        };
+
+        let mut txn = self.env.write_txn()?;
+        self.db.put(&mut txn, &hash(&key), &scope)?;
+        txn.commit()?;
+
+        Ok((key, scope))
+    }
+
+    pub fn get(&self, key: &str) -> anyhow::Result<Option<KeyScope>> {
+        let txn = self.env.read_txn()?;
+        Ok(self.db.get(&txn, &hash(key))?)
+    }
+
+    pub fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let mut txn = self.env.write_txn()?;
+        self.db.delete(&mut txn, &hash(key))?;
+        txn.commit()?;
+        Ok(())
+    }
+}
+
+/// Writes `secret` to `path`, the file is only readable and writable by its owner.
+fn write_secret(path: &Path, secret: &str) -> io::Result<()> {
+    let mut file = fs::File::create(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(secret.as_bytes())?;
+    file.sync_all()
+}
+
+pub(super) fn hash(key: &str) -> String {
+    format!("{:x}", sha2::Sha256::digest(key.as_bytes()))
+}
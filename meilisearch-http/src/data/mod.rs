@@ -1,15 +1,25 @@
 use std::collections::HashMap;
 use std::ops::Deref;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::time::Duration;
 
 use chrono::{DateTime, Utc};
-use sha2::Digest;
+use log::warn;
+use serde::{Deserialize, Serialize};
 
-use crate::index::Settings;
-use crate::index_controller::{IndexController, IndexStats};
-use crate::index_controller::{IndexMetadata, IndexSettings};
+use crate::helpers::logging::LogFormat;
+use crate::helpers::metrics::Metrics;
+use crate::helpers::Clock;
+use crate::index::{Settings, SettingsValidation};
+use crate::index_controller::{IndexController, IndexStats, QueueStats};
+use crate::index_controller::{IndexMetadata, IndexSettings, IndexesSort};
 use crate::option::Opt;
 
+pub use keys::{KeyAction, KeyScope, KeyStore};
+pub use updates::DocumentsAdditionStatus;
+
+mod keys;
 pub mod search;
 mod updates;
 
@@ -28,15 +38,20 @@ impl Deref for Data {
 
 pub struct DataInner {
     pub index_controller: IndexController,
-    pub api_keys: ApiKeys,
+    /// Replaced when the master key is rotated.
+    api_keys: RwLock<ApiKeys>,
+    key_store: KeyStore,
+    metrics: Metrics,
     options: Opt,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ApiKeys {
     pub public: Option<String>,
     pub private: Option<String>,
-    pub master: Option<String>,
+    /// The hash of the master key, the master key itself isn't kept.
+    pub master_hash: Option<String>,
 }
 
 #[derive(Default)]
@@ -47,20 +62,19 @@ pub struct Stats {
 }
 
 impl ApiKeys {
-    pub fn generate_missing_api_keys(&mut self) {
-        if let Some(master_key) = &self.master {
-            if self.private.is_none() {
-                let key = format!("{}-private", master_key);
-                let sha = sha2::Sha256::digest(key.as_bytes());
-                self.private = Some(format!("{:x}", sha));
-            }
-            if self.public.is_none() {
-                let key = format!("{}-public", master_key);
-                let sha = sha2::Sha256::digest(key.as_bytes());
-                self.public = Some(format!("{:x}", sha));
-            }
+    /// Derives the private and public keys from `master_key`, no key is required without it.
+    pub fn new(master_key: Option<&str>) -> Self {
+        let derive = |suffix| master_key.map(|key| keys::hash(&format!("{}-{}", key, suffix)));
+        Self {
+            public: derive("public"),
+            private: derive("private"),
+            master_hash: master_key.map(keys::hash),
         }
     }
+
+    pub fn is_master(&self, key: &str) -> bool {
+        self.master_hash.as_deref() == Some(keys::hash(key).as_str())
+    }
 }
 
 impl Data {
@@ -68,19 +82,30 @@ impl Data {
         let path = options.db_path.clone();
 
         let index_controller = IndexController::new(&path, &options)?;
+        let key_store = index_controller.key_store().clone();
+        let metrics = Metrics::new()?;
 
-        let mut api_keys = ApiKeys {
-            master: options.clone().master_key,
-            private: None,
-            public: None,
+        // A rotated master key takes precedence over the one given at launch.
+        let api_keys = match key_store.rotated_keys()? {
+            Some(api_keys) => {
+                let ignored = options
+                    .master_key
+                    .as_deref()
+                    .map_or(true, |key| !api_keys.is_master(key));
+                if ignored {
+                    warn!("The master key was rotated, the one given at launch is ignored.");
+                }
+                api_keys
+            }
+            None => ApiKeys::new(options.master_key.as_deref()),
         };
 
-        api_keys.generate_missing_api_keys();
-
         let inner = DataInner {
             index_controller,
             options,
-            api_keys,
+            api_keys: RwLock::new(api_keys),
+            key_store,
+            metrics,
         };
         let inner = Arc::new(inner);
 
@@ -91,10 +116,39 @@ impl Data {
         self.index_controller.settings(uid).await
     }
 
+    pub async fn validate_settings(
+        &self,
+        uid: String,
+        settings: Settings,
+    ) -> anyhow::Result<SettingsValidation> {
+        self.index_controller.validate_settings(uid, settings).await
+    }
+
+    pub async fn infer_facet_types(
+        &self,
+        uid: String,
+        attributes: Vec<String>,
+    ) -> anyhow::Result<HashMap<String, String>> {
+        self.index_controller
+            .infer_facet_types(uid, attributes)
+            .await
+    }
+
     pub async fn list_indexes(&self) -> anyhow::Result<Vec<IndexMetadata>> {
         self.index_controller.list_indexes().await
     }
 
+    pub async fn list_indexes_page(
+        &self,
+        offset: usize,
+        limit: Option<usize>,
+        sort: IndexesSort,
+    ) -> anyhow::Result<Vec<IndexMetadata>> {
+        self.index_controller
+            .list_indexes_page(offset, limit, sort)
+            .await
+    }
+
     pub async fn index(&self, uid: String) -> anyhow::Result<IndexMetadata> {
         self.index_controller.get_index(uid).await
     }
@@ -103,20 +157,71 @@ impl Data {
         &self,
         uid: String,
         primary_key: Option<String>,
+        map_size: Option<usize>,
     ) -> anyhow::Result<IndexMetadata> {
         let settings = IndexSettings {
             uid: Some(uid),
             primary_key,
+            map_size,
         };
 
         let meta = self.index_controller.create_index(settings).await?;
         Ok(meta)
     }
 
+    pub async fn create_indexes(
+        &self,
+        indexes: Vec<(String, Option<String>, Option<usize>)>,
+    ) -> Vec<anyhow::Result<IndexMetadata>> {
+        let settings = indexes
+            .into_iter()
+            .map(|(uid, primary_key, map_size)| IndexSettings {
+                uid: Some(uid),
+                primary_key,
+                map_size,
+            })
+            .collect();
+
+        self.index_controller.create_indexes(settings).await
+    }
+
+    pub async fn restore_index(&self, uid: String) -> anyhow::Result<IndexMetadata> {
+        self.index_controller.restore_index(uid).await
+    }
+
+    /// Purges the deleted indexes whose retention has elapsed at the time given by `clock`.
+    pub async fn purge_index_trash(&self, clock: &Clock) -> anyhow::Result<usize> {
+        self.index_controller.purge_index_trash(clock).await
+    }
+
+    pub async fn swap_indexes(&self, first: String, second: String) -> anyhow::Result<()> {
+        self.index_controller.swap_indexes(first, second).await
+    }
+
+    pub async fn create_alias(&self, alias: String, uid: String) -> anyhow::Result<()> {
+        self.index_controller.create_alias(alias, uid).await
+    }
+
+    pub async fn delete_alias(&self, alias: String) -> anyhow::Result<()> {
+        self.index_controller.delete_alias(alias).await
+    }
+
     pub async fn get_index_stats(&self, uid: String) -> anyhow::Result<IndexStats> {
         Ok(self.index_controller.get_stats(uid).await?)
     }
 
+    pub async fn get_update_queue_stats(&self, uid: String) -> anyhow::Result<QueueStats> {
+        self.index_controller.get_queue_stats(uid).await
+    }
+
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        self.index_controller.shutdown(timeout).await
+    }
+
+    pub async fn unresponsive_actors(&self, timeout: Duration) -> Vec<&'static str> {
+        self.index_controller.unresponsive_actors(timeout).await
+    }
+
     pub async fn get_stats(&self) -> anyhow::Result<Stats> {
         let mut stats = Stats::default();
         stats.database_size += self.index_controller.get_uuids_size().await?;
@@ -147,7 +252,113 @@ impl Data {
     }
 
     #[inline]
-    pub fn api_keys(&self) -> &ApiKeys {
-        &self.api_keys
+    pub fn http_json_payload_size_limit(&self) -> usize {
+        self.options.http_json_payload_size_limit.get_bytes() as usize
+    }
+
+    #[inline]
+    pub fn max_index_map_size(&self) -> usize {
+        self.options.max_index_map_size.get_bytes() as usize
+    }
+
+    #[inline]
+    pub fn log_format(&self) -> LogFormat {
+        self.options.log_format
+    }
+
+    #[inline]
+    pub fn metrics_enabled(&self) -> bool {
+        self.options.enable_metrics
+    }
+
+    #[inline]
+    pub fn response_compression_enabled(&self) -> bool {
+        !self.options.no_response_compression
+    }
+
+    #[inline]
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    #[inline]
+    pub fn api_keys(&self) -> RwLockReadGuard<'_, ApiKeys> {
+        self.api_keys.read().unwrap()
+    }
+
+    /// Replaces the master key by `master_key` and persists it. The private and public keys are
+    /// derived from the new key, the previous ones and all the scoped keys are rejected from then
+    /// on.
+    pub async fn rotate_master_key(&self, master_key: String) -> anyhow::Result<ApiKeys> {
+        if self.api_keys().master_hash.is_none() {
+            anyhow::bail!("A master key must be set to be rotated.");
+        }
+
+        let api_keys = ApiKeys::new(Some(&master_key));
+        let key_store = self.key_store.clone();
+        let keys = api_keys.clone();
+        tokio::task::spawn_blocking(move || key_store.rotate(&keys)).await??;
+        *self.api_keys.write().unwrap() = api_keys.clone();
+
+        Ok(api_keys)
+    }
+
+    #[inline]
+    pub fn auth_header_name(&self) -> Option<&str> {
+        self.options.auth_header_name.as_deref()
+    }
+
+    #[inline]
+    pub fn cors_allowed_origins(&self) -> Vec<String> {
+        self.options.cors_allowed_origins()
+    }
+
+    #[inline]
+    pub fn key_store(&self) -> &KeyStore {
+        &self.key_store
+    }
+
+    /// Creates a key only allowed to perform `actions` on `indexes`, until `expires_at` if any. The
+    /// searches made with the key are restricted to the documents matching `filter`.
+    pub async fn create_key(
+        &self,
+        indexes: Vec<String>,
+        actions: Vec<KeyAction>,
+        expires_at: Option<DateTime<Utc>>,
+        filter: Option<String>,
+    ) -> anyhow::Result<(String, KeyScope)> {
+        let key_store = self.key_store.clone();
+        tokio::task::spawn_blocking(move || key_store.create(indexes, actions, expires_at, filter))
+            .await?
+    }
+
+    #[inline]
+    pub fn dumps_dir(&self) -> &Path {
+        &self.options.dumps_dir
+    }
+
+    #[inline]
+    pub fn dump_batch_size(&self) -> usize {
+        self.options.dump_batch_size
+    }
+
+    /// Dumps every index in a sub-directory of `path` named after its uid, see
+    /// `IndexController::dump`.
+    pub async fn dump(&self, path: PathBuf) -> anyhow::Result<Vec<IndexMetadata>> {
+        self.index_controller.dump(path).await
+    }
+
+    pub async fn dump_index(&self, uid: String, path: PathBuf) -> anyhow::Result<IndexMetadata> {
+        self.index_controller.dump_index(uid, path).await
+    }
+
+    /// Restores the indexes of the dump at `path`, see `dump::import_dump`.
+    pub async fn import_dump(&self, path: &Path) -> anyhow::Result<()> {
+        crate::dump::import_dump(self, path, self.dump_batch_size()).await
+    }
+
+    #[inline]
+    pub fn max_search_limit(&self) -> usize {
+        self.options.max_search_limit
     }
 }
@@ -1,4 +1,5 @@
 use serde_json::{Map, Value};
+use tokio::sync::mpsc;
 
 use super::Data;
 use crate::index::{SearchQuery, SearchResult};
@@ -12,18 +13,32 @@ impl Data {
         self.index_controller.search(index, search_query).await
     }
 
+    pub async fn document_count(&self, index: String) -> anyhow::Result<u64> {
+        self.index_controller.document_count(index).await
+    }
+
     pub async fn retrieve_documents(
         &self,
         index: String,
         offset: usize,
         limit: usize,
         attributes_to_retrieve: Option<Vec<String>>,
-    ) -> anyhow::Result<Vec<Map<String, Value>>> {
+    ) -> anyhow::Result<(Vec<Map<String, Value>>, u64)> {
         self.index_controller
             .documents(index, offset, limit, attributes_to_retrieve)
             .await
     }
 
+    pub async fn export_documents(
+        &self,
+        index: String,
+        attributes_to_retrieve: Option<Vec<String>>,
+    ) -> anyhow::Result<mpsc::Receiver<anyhow::Result<Vec<u8>>>> {
+        self.index_controller
+            .export_documents(index, attributes_to_retrieve)
+            .await
+    }
+
     pub async fn retrieve_document(
         &self,
         index: String,
@@ -34,4 +49,25 @@ impl Data {
             .document(index, document_id, attributes_to_retrieve)
             .await
     }
+
+    pub async fn document_exists(
+        &self,
+        index: String,
+        document_id: String,
+    ) -> anyhow::Result<bool> {
+        self.index_controller
+            .document_exists(index, document_id)
+            .await
+    }
+
+    pub async fn retrieve_documents_by_ids(
+        &self,
+        index: String,
+        document_ids: Vec<String>,
+        attributes_to_retrieve: Option<Vec<String>>,
+    ) -> anyhow::Result<Vec<Option<Map<String, Value>>>> {
+        self.index_controller
+            .documents_by_ids(index, document_ids, attributes_to_retrieve)
+            .await
+    }
 }
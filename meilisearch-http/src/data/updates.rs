@@ -1,11 +1,28 @@
 use actix_web::web::Payload;
 use milli::update::{IndexDocumentsMethod, UpdateFormat};
+use serde::Serialize;
+use tokio::sync::mpsc;
 
 use super::Data;
 use crate::index::Settings;
-use crate::index_controller::{IndexMetadata, IndexSettings, UpdateStatus};
+use crate::index_controller::{
+    IndexMetadata, IndexSettings, Priority, PruneBefore, UpdateState, UpdateStatus,
+};
+
+/// The outcome of the registration of a documents addition.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentsAdditionStatus {
+    pub update_id: u64,
+    /// Whether the index was created for this addition.
+    pub index_created: bool,
+    /// The primary key the documents are indexed with, `None` if it is yet to be inferred from
+    /// the documents when the update is processed.
+    pub primary_key: Option<String>,
+}
 
 impl Data {
+    #[allow(clippy::too_many_arguments)]
     pub async fn add_documents(
         &self,
         index: String,
@@ -13,12 +30,40 @@ impl Data {
         format: UpdateFormat,
         stream: Payload,
         primary_key: Option<String>,
-    ) -> anyhow::Result<UpdateStatus> {
-        let update_status = self
+        strict: bool,
+        csv_delimiter: Option<u8>,
+        priority: Priority,
+    ) -> anyhow::Result<DocumentsAdditionStatus> {
+        let (update_status, index_created) = self
             .index_controller
-            .add_documents(index, method, format, stream, primary_key)
+            .add_documents(
+                index.clone(),
+                method,
+                format,
+                stream,
+                primary_key.clone(),
+                strict,
+                csv_delimiter,
+                priority,
+            )
             .await?;
-        Ok(update_status)
+
+        // The given primary key is ignored if the index already has one. The index may not exist
+        // yet if its first update is still pending.
+        let primary_key = if index_created {
+            primary_key
+        } else {
+            match self.index_controller.get_index(index).await {
+                Ok(index) => index.meta.primary_key.or(primary_key),
+                Err(_) => primary_key,
+            }
+        };
+
+        Ok(DocumentsAdditionStatus {
+            update_id: update_status.id(),
+            index_created,
+            primary_key,
+        })
     }
 
     pub async fn update_settings(
@@ -26,14 +71,30 @@ impl Data {
         index: String,
         settings: Settings,
         create: bool,
+        priority: Priority,
+    ) -> anyhow::Result<UpdateStatus> {
+        let update = self
+            .index_controller
+            .update_settings(index, settings, create, priority)
+            .await?;
+        Ok(update)
+    }
+
+    pub async fn merge_documents(
+        &self,
+        index: String,
+        documents: Vec<u8>,
+        primary_key: Option<String>,
     ) -> anyhow::Result<UpdateStatus> {
         let update = self
             .index_controller
-            .update_settings(index, settings, create)
+            .merge_documents(index, documents, primary_key)
             .await?;
         Ok(update)
     }
 
+    /// Removes all the documents of the index in a single update, its primary key and settings are
+    /// kept.
     pub async fn clear_documents(&self, index: String) -> anyhow::Result<UpdateStatus> {
         let update = self.index_controller.clear_documents(index).await?;
         Ok(update)
@@ -51,17 +112,77 @@ impl Data {
         Ok(update)
     }
 
+    pub async fn delete_documents_by_filter(
+        &self,
+        index: String,
+        filter: String,
+    ) -> anyhow::Result<UpdateStatus> {
+        let update = self
+            .index_controller
+            .delete_documents_by_filter(index, filter)
+            .await?;
+        Ok(update)
+    }
+
     pub async fn delete_index(&self, index: String) -> anyhow::Result<()> {
         self.index_controller.delete_index(index).await?;
         Ok(())
     }
 
+    pub async fn delete_indexes(&self, indexes: Vec<String>) -> Vec<anyhow::Result<()>> {
+        self.index_controller.delete_indexes(indexes).await
+    }
+
     pub async fn get_update_status(&self, index: String, uid: u64) -> anyhow::Result<UpdateStatus> {
         self.index_controller.update_status(index, uid).await
     }
 
-    pub async fn get_updates_status(&self, index: String) -> anyhow::Result<Vec<UpdateStatus>> {
-        self.index_controller.all_update_status(index).await
+    pub async fn abort_update(&self, index: String, uid: u64) -> anyhow::Result<UpdateStatus> {
+        self.index_controller.abort_update(index, uid).await
+    }
+
+    pub async fn prune_updates(
+        &self,
+        index: String,
+        before: PruneBefore,
+        state: Option<UpdateState>,
+    ) -> anyhow::Result<u64> {
+        self.index_controller
+            .prune_updates(index, before, state)
+            .await
+    }
+
+    pub async fn watch_update(
+        &self,
+        index: String,
+        uid: u64,
+    ) -> anyhow::Result<mpsc::Receiver<UpdateStatus>> {
+        self.index_controller.watch_update(index, uid).await
+    }
+
+    pub async fn get_updates_status(
+        &self,
+        index: String,
+        offset: usize,
+        limit: Option<usize>,
+        state: Option<UpdateState>,
+    ) -> anyhow::Result<Vec<UpdateStatus>> {
+        self.index_controller
+            .all_update_status(index, offset, limit, state)
+            .await
+    }
+
+    /// Streams the statuses of the updates of an index as JSON lines.
+    pub async fn stream_updates_status(
+        &self,
+        index: String,
+        offset: usize,
+        limit: Option<usize>,
+        state: Option<UpdateState>,
+    ) -> anyhow::Result<mpsc::Receiver<Vec<u8>>> {
+        self.index_controller
+            .stream_update_status(index, offset, limit, state)
+            .await
     }
 
     pub async fn update_index(
@@ -73,6 +194,7 @@ impl Data {
         let settings = IndexSettings {
             uid: new_uid,
             primary_key,
+            map_size: None,
         };
 
         self.index_controller.update_index(uid, settings).await
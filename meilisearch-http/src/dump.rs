@@ -1,24 +1,26 @@
 use std::fs::{create_dir_all, File};
 use std::io::prelude::*;
+use std::io::BufReader;
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
-use std::thread;
 
-use actix_web::web;
+use actix_web::web::Payload;
+use anyhow::bail;
 use chrono::offset::Utc;
-use indexmap::IndexMap;
+use futures::stream::StreamExt;
 use log::{error, info};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tempfile::TempDir;
+use tempfile::NamedTempFile;
+use tokio::io::AsyncWriteExt;
+use tokio::task::spawn_blocking;
 
-use crate::Data;
 use crate::error::{Error, ResponseError};
-use crate::helpers::compression;
-use crate::routes::index;
-use crate::routes::setting::Settings;
-use crate::routes::index::IndexResponse;
+use crate::helpers::compression::{self, CompressionFormat};
+use crate::index::Settings;
+use crate::index_controller::{IndexMetadata, PayloadTooLarge, Priority, UpdateStatus};
+use crate::Data;
 
 // Mutex to share dump progress.
 static DUMP_INFO: Lazy<Mutex<Option<DumpInfo>>> = Lazy::new(Mutex::default);
@@ -35,14 +37,14 @@ impl DumpVersion {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DumpMetadata {
-    indexes: Vec<crate::routes::index::IndexResponse>,
+    indexes: Vec<IndexMetadata>,
     db_version: String,
     dump_version: DumpVersion,
 }
 
 impl DumpMetadata {
     /// Create a DumpMetadata with the current dump version of meilisearch.
-    pub fn new(indexes: Vec<crate::routes::index::IndexResponse>, db_version: String) -> Self {
+    pub fn new(indexes: Vec<IndexMetadata>, db_version: String) -> Self {
         DumpMetadata {
             indexes,
             db_version,
@@ -50,16 +52,6 @@ impl DumpMetadata {
         }
     }
 
-    /// Extract DumpMetadata from `metadata.json` file present at provided `dir_path`
-    fn from_path(dir_path: &Path) -> Result<Self, Error> {
-        let path = dir_path.join("metadata.json");
-        let file = File::open(path)?;
-        let reader = std::io::BufReader::new(file);
-        let metadata = serde_json::from_reader(reader)?;
-
-        Ok(metadata)
-    }
-
     /// Write DumpMetadata in `metadata.json` file at provided `dir_path`
     fn to_path(&self, dir_path: &Path) -> Result<(), Error> {
         let path = dir_path.join("metadata.json");
@@ -69,129 +61,26 @@ impl DumpMetadata {
 
         Ok(())
     }
-}
-
-/// Extract Settings from `settings.json` file present at provided `dir_path`
-fn settings_from_path(dir_path: &Path) -> Result<Settings, Error> {
-    let path = dir_path.join("settings.json");
-    let file = File::open(path)?;
-    let reader = std::io::BufReader::new(file);
-    let metadata = serde_json::from_reader(reader)?;
-
-    Ok(metadata)
-}
-
-/// Write Settings in `settings.json` file at provided `dir_path`
-fn settings_to_path(settings: &Settings, dir_path: &Path) -> Result<(), Error> {
-    let path = dir_path.join("settings.json");
-    let file = File::create(path)?;
-
-    serde_json::to_writer(file, settings)?;
-
-    Ok(())
-}
-
-/// Import settings and documents of a dump with version `DumpVersion::V1` in specified index.
-fn import_index_v1(
-    data: &Data,
-    dumps_dir: &Path,
-    index_uid: &str,
-    document_batch_size: usize,
-    write_txn: &mut MainWriter,
-) -> Result<(), Error> {
 
-    // open index
-    let index = data
-        .db
-        .open_index(index_uid)
-        .ok_or(Error::index_not_found(index_uid))?;
-
-    // index dir path in  dump dir
-    let index_path = &dumps_dir.join(index_uid);
-
-    // extract `settings.json` file and import content
-    let settings = settings_from_path(&index_path)?;
-    let settings = settings.to_update().map_err(|e| Error::dump_failed(format!("importing settings for index {}; {}", index_uid, e)))?;
-    apply_settings_update(write_txn, &index, settings)?;
-
-    // create iterator over documents in `documents.jsonl` to make batch importation
-    // create iterator over documents in `documents.jsonl` to make batch importation
-    let documents = {
-        let file = File::open(&index_path.join("documents.jsonl"))?;
-        let reader = std::io::BufReader::new(file);
-        let deserializer = serde_json::Deserializer::from_reader(reader);
-        deserializer.into_iter::<IndexMap<String, serde_json::Value>>()
-    };
+    /// Read the DumpMetadata from the `metadata.json` file at provided `dir_path`
+    fn from_path(dir_path: &Path) -> anyhow::Result<Self> {
+        let path = dir_path.join("metadata.json");
+        let file = File::open(path)?;
 
-    // batch import document every `document_batch_size`:
-    // create a Vec to bufferize documents
-    let mut values = Vec::with_capacity(document_batch_size);
-    // iterate over documents
-    for document in documents {
-        // push document in buffer
-        values.push(document?);
-        // if buffer is full, create and apply a batch, and clean buffer
-        if values.len() == document_batch_size { 
-            let batch = std::mem::replace(&mut values, Vec::with_capacity(document_batch_size));
-            apply_documents_addition(write_txn, &index, batch)?;
+        // The version is checked first, the rest of the metadata may not be readable otherwise.
+        let metadata: serde_json::Value = serde_json::from_reader(file)?;
+        let current_version = serde_json::to_value(DumpVersion::CURRENT)?;
+        if metadata["dumpVersion"] != current_version {
+            bail!(
+                "Unsupported dump version {}, only version {} can be imported.",
+                metadata["dumpVersion"],
+                current_version
+            );
         }
-    }
-
-    // apply documents remaining in the buffer 
-    if !values.is_empty() { 
-        apply_documents_addition(write_txn, &index, values)?;
-    }
+        let metadata = serde_json::from_value(metadata)?;
 
-    // sync index information: stats, updated_at, last_update
-    if let Err(e) = crate::index_update_callback_txn(index, index_uid, data, write_txn) {
-        return Err(Error::Internal(e));
-    }
-
-    Ok(())
-}
-
-/// Import dump from `dump_path` in database.
-pub fn import_dump(
-    data: &Data,
-    dump_path: &Path,
-    document_batch_size: usize,
-) -> Result<(), Error> {
-    info!("Importing dump from {:?}...", dump_path);
-
-    // create a temporary directory
-    let tmp_dir = TempDir::new()?;
-    let tmp_dir_path = tmp_dir.path();
-
-    // extract dump in temporary directory
-    compression::from_tar_gz(dump_path, tmp_dir_path)?;
-
-    // read dump metadata
-    let metadata = DumpMetadata::from_path(&tmp_dir_path)?;
-
-    // choose importation function from DumpVersion of metadata
-    let import_index = match metadata.dump_version {
-        DumpVersion::V1 => import_index_v1,
-    };
-
-    // remove indexes which have same `uid` than indexes to import and create empty indexes
-    let existing_index_uids = data.db.indexes_uids();
-    for index in metadata.indexes.iter() {
-        if existing_index_uids.contains(&index.uid) {
-            data.db.delete_index(index.uid.clone())?;
-        }
-        index::create_index_sync(&data.db, index.uid.clone(), index.name.clone(), index.primary_key.clone())?;
+        Ok(metadata)
     }
-
-    // import each indexes content
-    data.db.main_write::<_, _, Error>(|mut writer| {
-        for index in metadata.indexes {
-            import_index(&data, tmp_dir_path, &index.uid, document_batch_size, &mut writer)?;
-        }
-        Ok(())
-    })?;
-
-    info!("Dump importation from {:?} succeed", dump_path);
-    Ok(())
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
@@ -213,7 +102,11 @@ pub struct DumpInfo {
 
 impl DumpInfo {
     pub fn new(uid: String, status: DumpStatus) -> Self {
-        Self { uid, status, error: None }
+        Self {
+            uid,
+            status,
+            error: None,
+        }
     }
 
     pub fn with_error(mut self, error: ResponseError) -> Self {
@@ -246,178 +139,259 @@ pub fn compressed_dumps_dir(dumps_dir: &Path, dump_uid: &str) -> PathBuf {
     dumps_dir.join(format!("{}.dump", dump_uid))
 }
 
-/// Write metadata in dump
-fn dump_metadata(data: &web::Data<Data>, dir_path: &Path, indexes: Vec<IndexResponse>) -> Result<(), Error> {
-    let (db_major, db_minor, db_patch) = data.db.version();
-    let metadata = DumpMetadata::new(indexes, format!("{}.{}.{}", db_major, db_minor, db_patch));
-
-    metadata.to_path(dir_path)
-}
+/// Export the metadata and the settings, documents and updates of every index in the
+/// `{dump_uid}.dump` file in `dumps_dir`.
+///
+/// The indexes are dumped by the update and index actors, as for the snapshots, but the documents
+/// are serialized as JSON lines rather than copied from LMDB, so the dump doesn't depend on the
+/// on-disk format of the index and can be imported by any version of MeiliSearch.
+async fn perform_dump(data: &Data, dumps_dir: &Path, dump_uid: &str) -> anyhow::Result<()> {
+    // create a temporary directory
+    let tmp_dir = tempfile::tempdir_in(dumps_dir)?;
+    let tmp_dir_path = tmp_dir.path().to_owned();
 
-/// Export settings of provided index in dump
-fn dump_index_settings(data: &web::Data<Data>, reader: &MainReader, dir_path: &Path, index_uid: &str) -> Result<(), Error> {
-    let settings = crate::routes::setting::get_all_sync(data, reader, index_uid)?;
+    // export settings, updates and documents for each indexes
+    let indexes = data.dump(tmp_dir_path.clone()).await?;
 
-    settings_to_path(&settings, dir_path)
-}
+    // create metadata
+    let metadata = DumpMetadata::new(indexes, env!("CARGO_PKG_VERSION").to_string());
+    metadata.to_path(&tmp_dir_path)?;
 
-/// Export updates of provided index in dump
-fn dump_index_updates(data: &web::Data<Data>, reader: &UpdateReader, dir_path: &Path, index_uid: &str) -> Result<(), Error> {
-    let updates_path = dir_path.join("updates.jsonl");
-    let updates = crate::routes::index::get_all_updates_status_sync(data, reader, index_uid)?;
+    // compress dump in a file named `{dump_uid}.dump` in `dumps_dir`
+    let dump_path = compressed_dumps_dir(dumps_dir, dump_uid);
+    spawn_blocking(move || {
+        compression::to_tar_gz(
+            tmp_dir_path,
+            dump_path,
+            compression::DEFAULT_COMPRESSION_LEVEL,
+        )
+    })
+    .await??;
 
-    let file = File::create(updates_path)?;
+    Ok(())
+}
 
-    for update in updates {
-        serde_json::to_writer(&file, &update)?;
-        writeln!(&file)?;
-    }
+/// Export the metadata, the settings and the documents of a single index in a gzipped tarball, created in
+/// `dumps_dir` and removed once the returned file is dropped.
+pub async fn export_index(
+    data: &Data,
+    dumps_dir: &Path,
+    index_uid: &str,
+) -> anyhow::Result<NamedTempFile> {
+    create_dir_all(dumps_dir)?;
+    let tmp_dir = tempfile::tempdir_in(dumps_dir)?;
+    let tmp_dir_path = tmp_dir.path().to_owned();
 
-    Ok(())
+    let index = data
+        .dump_index(index_uid.to_string(), tmp_dir_path.clone())
+        .await?;
+    let metadata = DumpMetadata::new(vec![index], env!("CARGO_PKG_VERSION").to_string());
+    metadata.to_path(&tmp_dir_path)?;
+
+    let export_file = NamedTempFile::new_in(dumps_dir)?;
+    let export_path = export_file.path().to_owned();
+    spawn_blocking(move || {
+        compression::to_tar_gz(
+            tmp_dir_path,
+            export_path,
+            compression::DEFAULT_COMPRESSION_LEVEL,
+        )
+    })
+    .await??;
+
+    Ok(export_file)
 }
 
-/// Export documents of provided index in dump
-fn dump_index_documents(data: &web::Data<Data>, reader: &MainReader, dir_path: &Path, index_uid: &str) -> Result<(), Error> {
-    let documents_path = dir_path.join("documents.jsonl");
-    let file = File::create(documents_path)?;
-    let dump_batch_size = data.dump_batch_size;
+/// Main function of dump.
+async fn dump_process(data: Data, dumps_dir: PathBuf, dump_info: DumpInfo) {
+    info!("Performing dump {}.", dump_info.uid);
 
-    let mut offset = 0;
-    loop {
-        let documents = crate::routes::document::get_all_documents_sync(data, reader, index_uid, offset, dump_batch_size, None)?;
-        if documents.is_empty() { break; } else { offset += dump_batch_size; }
+    match perform_dump(&data, &dumps_dir, &dump_info.uid).await {
+        Ok(()) => {
+            info!("Dump {} created in {:?}.", dump_info.uid, dumps_dir);
 
-        for document in documents {
-            serde_json::to_writer(&file, &document)?;
-            writeln!(&file)?;
+            // update dump info to `done`
+            let resume = DumpInfo::new(dump_info.uid, DumpStatus::Done);
+            resume.set_current();
+        }
+        Err(e) => {
+            error!("Something went wrong during dump process: {}", e);
+            dump_info
+                .with_error(Error::dump_failed(e.to_string()).into())
+                .set_current();
         }
     }
-
-    Ok(())
 }
 
-/// Write error with a context.
-fn fail_dump_process<E: std::error::Error>(dump_info: DumpInfo, context: &str, error: E) {
-        let error_message = format!("{}; {}", context, error);
-        
-        error!("Something went wrong during dump process: {}", &error_message);
-        dump_info.with_error(Error::dump_failed(error_message).into()).set_current();
-}
+pub fn init_dump_process(data: &Data, dumps_dir: &Path) -> Result<DumpInfo, Error> {
+    create_dir_all(dumps_dir)
+        .map_err(|e| Error::dump_failed(format!("creating temporary directory {}", e)))?;
 
-/// Main function of dump.
-fn dump_process(data: web::Data<Data>, dumps_dir: PathBuf, dump_info: DumpInfo) {
-    // open read transaction on Update
-    let update_reader = match data.db.update_read_txn() {
-        Ok(r) => r,
-        Err(e) => {
-            fail_dump_process(dump_info, "creating RO transaction on updates", e);
-            return ;
+    // check if a dump is already in progress
+    if let Some(resume) = DumpInfo::get_current() {
+        if resume.dump_already_in_progress() {
+            return Err(Error::dump_conflict());
         }
-    };
+    }
 
-    // open read transaction on Main
-    let main_reader = match data.db.main_read_txn() {
-        Ok(r) => r,
-        Err(e) => {
-            fail_dump_process(dump_info, "creating RO transaction on main", e);
-            return ;
-        }
-    };
+    // generate a new dump info
+    let info = DumpInfo::new(generate_uid(), DumpStatus::InProgress);
 
-    // create a temporary directory
-    let tmp_dir = match TempDir::new() {
-        Ok(tmp_dir) => tmp_dir,
-        Err(e) => {
-            fail_dump_process(dump_info, "creating temporary directory", e);
-            return ;
-        }
-    };
-    let tmp_dir_path = tmp_dir.path();
+    info.set_current();
 
-    // fetch indexes
-    let indexes = match crate::routes::index::list_indexes_sync(&data, &main_reader) {
-        Ok(indexes) => indexes,
-        Err(e) => {
-            fail_dump_process(dump_info, "listing indexes", e);
-            return ;
-        }
-    };
+    let data = data.clone();
+    let dumps_dir = dumps_dir.to_path_buf();
+    let info_cloned = info.clone();
+    // run dump process in a new task
+    tokio::task::spawn(dump_process(data, dumps_dir, info_cloned));
 
-    // create metadata
-    if let Err(e) = dump_metadata(&data, &tmp_dir_path, indexes.clone()) {
-        fail_dump_process(dump_info, "generating metadata", e);
-        return ;
-    }
+    Ok(info)
+}
 
-    // export settings, updates and documents for each indexes
-    for index in indexes {
-        let index_path = tmp_dir_path.join(&index.uid);
+/// Import the documents of the `documents.jsonl` file at `dir_path` in batches of `batch_size`
+/// documents, merging them with the existing ones. Returns the update of the last batch, if any.
+async fn import_index_documents(
+    data: &Data,
+    dir_path: &Path,
+    index_uid: &str,
+    primary_key: Option<String>,
+    batch_size: usize,
+) -> anyhow::Result<Option<UpdateStatus>> {
+    let file = File::open(dir_path.join("documents.jsonl"))?;
+    let mut lines = BufReader::new(file).lines();
 
-        // create index sub-dircetory
-        if let Err(e) = create_dir_all(&index_path) {
-            fail_dump_process(dump_info, &format!("creating directory for index {}", &index.uid), e);
-            return ;
+    let mut last_update = None;
+    loop {
+        let mut batch = Vec::new();
+        for line in lines.by_ref().take(batch_size.max(1)) {
+            batch.extend_from_slice(line?.as_bytes());
+            batch.push(b'\n');
         }
-
-        // export settings
-        if let Err(e) = dump_index_settings(&data, &main_reader, &index_path, &index.uid) {
-            fail_dump_process(dump_info, &format!("generating settings for index {}", &index.uid), e);
-            return ;
+        if batch.is_empty() {
+            break;
         }
 
-        // export documents
-        if let Err(e) = dump_index_documents(&data, &main_reader, &index_path, &index.uid) {
-            fail_dump_process(dump_info, &format!("generating documents for index {}", &index.uid), e);
-            return ;
-        }
+        let update = data
+            .merge_documents(index_uid.to_string(), batch, primary_key.clone())
+            .await?;
+        last_update = Some(update);
+    }
+
+    Ok(last_update)
+}
 
-        // export updates
-        if let Err(e) = dump_index_updates(&data, &update_reader, &index_path, &index.uid) {
-            fail_dump_process(dump_info, &format!("generating updates for index {}", &index.uid), e);
-            return ;
+/// Restores the indexes of the dump at `src`. The indexes that don't exist are created with the
+/// settings and the map size of the dump, the documents of the dump are merged in the existing
+/// ones. The indexes absent from the dump are left untouched and the update history isn't
+/// restored.
+///
+/// The imported updates are registered but not awaited.
+pub async fn import_dump(data: &Data, src: &Path, batch_size: usize) -> anyhow::Result<()> {
+    info!("Importing dump from {:?}.", src);
+
+    let tmp_dir = tempfile::tempdir()?;
+    let tmp_dir_path = tmp_dir.path().to_owned();
+    let src_path = src.to_owned();
+    spawn_blocking(move || {
+        CompressionFormat::from_path(&src_path).decompress(src_path, tmp_dir_path)
+    })
+    .await??;
+
+    let metadata = DumpMetadata::from_path(tmp_dir.path())?;
+
+    for index in metadata.indexes {
+        let index_path = tmp_dir.path().join(&index.uid);
+        let map_size = dumped_map_size(data, &index);
+        let primary_key = index.meta.primary_key;
+
+        if data.index(index.uid.clone()).await.is_err() {
+            data.create_index(index.uid.clone(), primary_key.clone(), map_size)
+                .await?;
+
+            let file = File::open(index_path.join("settings.json"))?;
+            let settings: Settings = serde_json::from_reader(file)?;
+            // The settings keep the normal priority so the updates are imported in order.
+            data.update_settings(index.uid.clone(), settings, false, Priority::Normal)
+                .await?;
         }
-    }
 
-    // compress dump in a file named `{dump_uid}.dump` in `dumps_dir`
-    if let Err(e) = crate::helpers::compression::to_tar_gz(&tmp_dir_path, &compressed_dumps_dir(&dumps_dir, &dump_info.uid)) {
-        fail_dump_process(dump_info, "compressing dump", e);
-        return ;
+        import_index_documents(data, &index_path, &index.uid, primary_key, batch_size).await?;
+        info!("Index {} imported.", index.uid);
     }
 
-    // update dump info to `done`
-    let resume = DumpInfo::new(
-        dump_info.uid,
-        DumpStatus::Done
-    );
-
-    resume.set_current();
+    Ok(())
 }
 
-pub fn init_dump_process(data: &web::Data<Data>, dumps_dir: &Path) -> Result<DumpInfo, Error> {
-    create_dir_all(dumps_dir).map_err(|e| Error::dump_failed(format!("creating temporary directory {}", e)))?;
+/// Returns the map size of the dumped `index`, up to the maximum map size of the indexes. The
+/// indexes dumped without their size are created with the default map size.
+fn dumped_map_size(data: &Data, index: &IndexMetadata) -> Option<usize> {
+    index
+        .meta
+        .map_size()
+        .map(|map_size| map_size.min(data.max_index_map_size()))
+}
 
-    // check if a dump is already in progress
-    if let Some(resume) = DumpInfo::get_current() {
-        if resume.dump_already_in_progress() {
-            return Err(Error::dump_conflict())
+/// Imports the export of an index, sent as `payload`, in the index `index_uid`. The index is
+/// created if it doesn't exist, the settings of the export are then applied and its documents are
+/// merged with the existing ones.
+///
+/// Returns the last enqueued update, the whole export is imported once it is processed.
+pub async fn import_index(
+    data: &Data,
+    dumps_dir: &Path,
+    index_uid: &str,
+    mut payload: Payload,
+) -> anyhow::Result<UpdateStatus> {
+    create_dir_all(dumps_dir)?;
+    let export_file = NamedTempFile::new_in(dumps_dir)?;
+    let mut file = tokio::fs::File::from_std(export_file.reopen()?);
+    let limit = data.http_payload_size_limit();
+    let mut size = 0;
+    while let Some(bytes) = payload.next().await {
+        let bytes = bytes?;
+        size += bytes.len();
+        if size > limit {
+            return Err(PayloadTooLarge(limit).into());
         }
+        file.write_all(&bytes).await?;
     }
+    file.flush().await?;
 
-    // generate a new dump info
-    let info = DumpInfo::new(
-        generate_uid(),
-        DumpStatus::InProgress
-    );
+    let tmp_dir = tempfile::tempdir_in(dumps_dir)?;
+    let tmp_dir_path = tmp_dir.path().to_owned();
+    let export_path = export_file.path().to_owned();
+    spawn_blocking(move || CompressionFormat::Gzip.decompress(export_path, tmp_dir_path)).await??;
 
-    info.set_current();
-
-    let data = data.clone();
-    let dumps_dir = dumps_dir.to_path_buf();
-    let info_cloned = info.clone();
-    // run dump process in a new thread
-    thread::spawn(move || 
-        dump_process(data, dumps_dir, info_cloned)
-    );
+    let metadata = DumpMetadata::from_path(tmp_dir.path())?;
+    let index = match metadata.indexes.as_slice() {
+        [index] => index,
+        _ => bail!("The archive isn't the export of a single index."),
+    };
+    let primary_key = index.meta.primary_key.clone();
+
+    if data.index(index_uid.to_string()).await.is_err() {
+        data.create_index(
+            index_uid.to_string(),
+            primary_key.clone(),
+            dumped_map_size(data, index),
+        )
+        .await?;
+    }
 
-    Ok(info)
+    let file = File::open(tmp_dir.path().join("settings.json"))?;
+    let settings: Settings = serde_json::from_reader(file)?;
+    let settings_update = data
+        .update_settings(index_uid.to_string(), settings, false, Priority::Normal)
+        .await?;
+
+    let documents_update = import_index_documents(
+        data,
+        tmp_dir.path(),
+        index_uid,
+        primary_key,
+        data.dump_batch_size(),
+    )
+    .await?;
+
+    Ok(documents_update.unwrap_or(settings_update))
 }
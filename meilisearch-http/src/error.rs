@@ -9,9 +9,15 @@ use actix_web::http::StatusCode;
 use meilisearch_error::{Code, ErrorCode};
 use serde::ser::{Serialize, SerializeStruct, Serializer};
 
+use crate::helpers::request_id::{RequestId, REQUEST_ID_HEADER};
+use crate::index_controller::{ActorError, ReadOnly, SearchTimeout};
+
 #[derive(Debug)]
 pub struct ResponseError {
     inner: Box<dyn ErrorCode>,
+    /// The id of the request the error was raised for. It is kept along the error since the
+    /// errors of the middlewares are rendered by actix once the request is no longer identified.
+    request_id: Option<String>,
 }
 
 impl error::Error for ResponseError {}
@@ -31,8 +37,18 @@ impl fmt::Display for ResponseError {
 // TODO: remove this when implementing actual error handling
 impl From<anyhow::Error> for ResponseError {
     fn from(other: anyhow::Error) -> ResponseError {
+        let error = if ActorError::is_cause_of(&other) {
+            Error::ServiceUnavailable(other.to_string())
+        } else if SearchTimeout::is_cause_of(&other) {
+            Error::Timeout(other.to_string())
+        } else if ReadOnly::is_cause_of(&other) {
+            Error::ReadOnly(other.to_string())
+        } else {
+            Error::NotFound(other.to_string())
+        };
         ResponseError {
-            inner: Box::new(Error::NotFound(other.to_string())),
+            inner: Box::new(error),
+            request_id: RequestId::current(),
         }
     }
 }
@@ -41,6 +57,7 @@ impl From<Error> for ResponseError {
     fn from(error: Error) -> ResponseError {
         ResponseError {
             inner: Box::new(error),
+            request_id: RequestId::current(),
         }
     }
 }
@@ -49,6 +66,7 @@ impl From<FacetCountError> for ResponseError {
     fn from(err: FacetCountError) -> ResponseError {
         ResponseError {
             inner: Box::new(err),
+            request_id: RequestId::current(),
         }
     }
 }
@@ -59,20 +77,27 @@ impl Serialize for ResponseError {
         S: Serializer,
     {
         let struct_name = "ResponseError";
-        let field_count = 4;
+        let field_count = 4 + self.request_id.is_some() as usize;
 
         let mut state = serializer.serialize_struct(struct_name, field_count)?;
         state.serialize_field("message", &self.to_string())?;
         state.serialize_field("errorCode", &self.error_name())?;
         state.serialize_field("errorType", &self.error_type())?;
         state.serialize_field("errorLink", &self.error_url())?;
+        if let Some(ref request_id) = self.request_id {
+            state.serialize_field("requestId", &request_id)?;
+        }
         state.end()
     }
 }
 
 impl aweb::error::ResponseError for ResponseError {
     fn error_response(&self) -> aweb::HttpResponse {
-        HttpResponseBuilder::new(self.status_code()).json(&self)
+        let mut response = HttpResponseBuilder::new(self.status_code());
+        if let Some(ref request_id) = self.request_id {
+            response.insert_header((REQUEST_ID_HEADER, request_id.as_str()));
+        }
+        response.json(&self)
     }
 
     fn status_code(&self) -> StatusCode {
@@ -86,6 +111,8 @@ pub enum Error {
     BadRequest(String),
     CreateIndex(String),
     DocumentNotFound(String),
+    ExpiredToken(String),
+    ForbiddenKeyScope,
     IndexNotFound(String),
     IndexAlreadyExists(String),
     Internal(String),
@@ -97,6 +124,9 @@ pub enum Error {
     RetrieveDocument(u32, String),
     SearchDocuments(String),
     PayloadTooLarge,
+    ReadOnly(String),
+    ServiceUnavailable(String),
+    Timeout(String),
     UnsupportedMediaType,
     DumpAlreadyInProgress,
     DumpProcessFailed(String),
@@ -112,6 +142,8 @@ impl ErrorCode for Error {
             BadRequest(_) => Code::BadRequest,
             CreateIndex(_) => Code::CreateIndex,
             DocumentNotFound(_) => Code::DocumentNotFound,
+            ExpiredToken(_) => Code::ExpiredToken,
+            ForbiddenKeyScope => Code::ForbiddenKeyScope,
             IndexNotFound(_) => Code::IndexNotFound,
             IndexAlreadyExists(_) => Code::IndexAlreadyExists,
             Internal(_) => Code::Internal,
@@ -123,10 +155,12 @@ impl ErrorCode for Error {
             RetrieveDocument(_, _) => Code::RetrieveDocument,
             SearchDocuments(_) => Code::SearchDocuments,
             PayloadTooLarge => Code::PayloadTooLarge,
+            ReadOnly(_) => Code::ReadOnly,
+            ServiceUnavailable(_) => Code::ServiceUnavailable,
+            Timeout(_) => Code::Timeout,
             UnsupportedMediaType => Code::UnsupportedMediaType,
-            _ => unreachable!()
-            //DumpAlreadyInProgress => Code::DumpAlreadyInProgress,
-            //DumpProcessFailed(_) => Code::DumpProcessFailed,
+            DumpAlreadyInProgress => Code::DumpAlreadyInProgress,
+            DumpProcessFailed(_) => Code::DumpProcessFailed,
         }
     }
 }
@@ -250,6 +284,8 @@ impl fmt::Display for Error {
             Self::BadRequest(err) => f.write_str(err),
             Self::CreateIndex(err) => write!(f, "Impossible to create index; {}", err),
             Self::DocumentNotFound(document_id) => write!(f, "Document with id {} not found", document_id),
+            Self::ExpiredToken(err) => write!(f, "The API key {} has expired", err),
+            Self::ForbiddenKeyScope => f.write_str("The API key is not allowed to perform this action"),
             Self::IndexNotFound(index_uid) => write!(f, "Index {} not found", index_uid),
             Self::IndexAlreadyExists(index_uid) => write!(f, "Index {} already exists", index_uid),
             Self::Internal(err) => f.write_str(err),
@@ -261,6 +297,9 @@ impl fmt::Display for Error {
             Self::RetrieveDocument(id, err) => write!(f, "Impossible to retrieve the document with id: {}; {}", id, err),
             Self::SearchDocuments(err) => write!(f, "Impossible to search documents; {}", err),
             Self::PayloadTooLarge => f.write_str("Payload too large"),
+            Self::ReadOnly(message) => f.write_str(message),
+            Self::ServiceUnavailable(message) => f.write_str(message),
+            Self::Timeout(message) => f.write_str(message),
             Self::UnsupportedMediaType => f.write_str("Unsupported media type"),
             Self::DumpAlreadyInProgress => f.write_str("Another dump is already in progress"),
             Self::DumpProcessFailed(message) => write!(f, "Dump process failed: {}", message),
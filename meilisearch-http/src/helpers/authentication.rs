@@ -1,22 +1,84 @@
 use std::cell::RefCell;
 use std::pin::Pin;
 use std::rc::Rc;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderMap, AUTHORIZATION};
 use actix_web::web;
+use chrono::{DateTime, Utc};
 use futures::future::{err, ok, Future, Ready};
+use log::error;
 
+use crate::data::KeyAction;
 use crate::error::{Error, ResponseError};
 use crate::Data;
 
+/// The keys allowed to access a route. The routes accessible with the private key can also be
+/// accessed with a scoped key allowing the matching action on the index of the route.
 #[derive(Clone, Copy)]
 pub enum Authentication {
     Public,
     Private,
+    DocumentsWrite,
+    Settings,
     Admin,
 }
 
+impl Authentication {
+    /// Returns the action a scoped key must allow to access the route, if scoped keys are accepted.
+    fn scope(&self) -> Option<KeyAction> {
+        match self {
+            Authentication::Public => Some(KeyAction::Search),
+            Authentication::DocumentsWrite => Some(KeyAction::DocumentsWrite),
+            Authentication::Settings => Some(KeyAction::Settings),
+            Authentication::Private | Authentication::Admin => None,
+        }
+    }
+}
+
+/// The clock the expiration of the scoped keys is checked against. The system clock is used unless
+/// another clock is registered as app data.
+#[derive(Clone)]
+pub struct Clock(Arc<dyn Fn() -> DateTime<Utc> + Send + Sync>);
+
+impl Clock {
+    pub fn system() -> Self {
+        Self(Arc::new(Utc::now))
+    }
+
+    /// A clock always returning `now`.
+    pub fn fixed(now: DateTime<Utc>) -> Self {
+        Self(Arc::new(move || now))
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        (self.0)()
+    }
+}
+
+/// The filter of the scoped key of a request, inserted in the request extensions. It must be
+/// applied to the searches of the request in addition to their own filter.
+#[derive(Clone)]
+pub struct EnforcedFilter(pub String);
+
+/// Returns the key of the `custom_header`, if any, of the `X-Meili-API-Key` header, or the bearer
+/// token of the `Authorization` header.
+fn api_key<'a>(headers: &'a HeaderMap, custom_header: Option<&str>) -> Option<&'a str> {
+    if let Some(key) = custom_header.and_then(|name| headers.get(name)) {
+        return key.to_str().ok();
+    }
+    match headers.get("X-Meili-API-Key") {
+        Some(key) => key.to_str().ok(),
+        None => headers
+            .get(AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer "),
+    }
+}
+
 impl<S: 'static, B> Transform<S, ServiceRequest> for Authentication
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
@@ -63,19 +125,14 @@ where
         // it means that actix-web has an issue or someone changes the type `Data`.
         let data = req.app_data::<web::Data<Data>>().unwrap();
 
-        if data.api_keys().master.is_none() {
+        // The keys are cloned, as not to hold the lock while the request is handled.
+        let api_keys = data.api_keys().clone();
+        if api_keys.master_hash.is_none() {
             return Box::pin(svc.call(req));
         }
 
-        let auth_header = match req.headers().get("X-Meili-API-Key") {
-            Some(auth) => match auth.to_str() {
-                Ok(auth) => auth,
-                Err(_) => {
-                    return Box::pin(err(
-                        ResponseError::from(Error::MissingAuthorizationHeader).into()
-                    ))
-                }
-            },
+        let auth_header = match api_key(req.headers(), data.auth_header_name()) {
+            Some(auth) => auth,
             None => {
                 return Box::pin(err(
                     ResponseError::from(Error::MissingAuthorizationHeader).into()
@@ -84,25 +141,57 @@ where
         };
 
         let authenticated = match self.acl {
-            Authentication::Admin => data.api_keys().master.as_deref() == Some(auth_header),
-            Authentication::Private => {
-                data.api_keys().master.as_deref() == Some(auth_header)
-                    || data.api_keys().private.as_deref() == Some(auth_header)
+            Authentication::Admin => api_keys.is_master(auth_header),
+            Authentication::Private | Authentication::DocumentsWrite | Authentication::Settings => {
+                api_keys.is_master(auth_header) || api_keys.private.as_deref() == Some(auth_header)
             }
             Authentication::Public => {
-                data.api_keys().master.as_deref() == Some(auth_header)
-                    || data.api_keys().private.as_deref() == Some(auth_header)
-                    || data.api_keys().public.as_deref() == Some(auth_header)
+                api_keys.is_master(auth_header)
+                    || api_keys.private.as_deref() == Some(auth_header)
+                    || api_keys.public.as_deref() == Some(auth_header)
             }
         };
 
         if authenticated {
+            return Box::pin(svc.call(req));
+        }
+
+        let now = req.app_data::<Clock>().map_or_else(Utc::now, Clock::now);
+        let allowed = match data.key_store().get(auth_header) {
+            Ok(Some(scope)) if scope.is_expired(now) => {
+                // The expired keys are removed the first time they are used.
+                if let Err(e) = data.key_store().delete(auth_header) {
+                    error!("Could not remove an expired key: {}", e);
+                }
+                return Box::pin(err(ResponseError::from(Error::ExpiredToken(
+                    auth_header.to_string(),
+                ))
+                .into()));
+            }
+            Ok(Some(scope)) => {
+                let index = req.match_info().get("index_uid");
+                let allowed = self
+                    .acl
+                    .scope()
+                    .map_or(false, |action| scope.allows(action, index));
+                if let (true, Some(filter)) = (allowed, scope.filter) {
+                    req.extensions_mut().insert(EnforcedFilter(filter));
+                }
+                allowed
+            }
+            Ok(None) => {
+                return Box::pin(err(ResponseError::from(Error::InvalidToken(
+                    auth_header.to_string(),
+                ))
+                .into()))
+            }
+            Err(e) => return Box::pin(err(ResponseError::from(Error::internal(e)).into())),
+        };
+
+        if allowed {
             Box::pin(svc.call(req))
         } else {
-            Box::pin(err(ResponseError::from(Error::InvalidToken(
-                auth_header.to_string(),
-            ))
-            .into()))
+            Box::pin(err(ResponseError::from(Error::ForbiddenKeyScope).into()))
         }
     }
 }
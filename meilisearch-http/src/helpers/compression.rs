@@ -1,15 +1,166 @@
-use std::fs::{create_dir_all, File};
-use std::io::Write;
-use std::path::Path;
+use std::fs::{self, create_dir_all, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::mem;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
 
+use aes_gcm::aead::stream::{DecryptorBE32, EncryptorBE32};
+use aes_gcm::aead::NewAead;
+use aes_gcm::{Aes256Gcm, Key};
+use anyhow::bail;
 use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use hmac::Hmac;
+use pbkdf2::pbkdf2;
+use rand::Rng;
+use sha2::Sha256;
 use tar::{Archive, Builder};
+use walkdir::WalkDir;
 
-pub fn to_tar_gz(src: impl AsRef<Path>, dest: impl AsRef<Path>) -> anyhow::Result<()> {
-    let mut f = File::create(dest)?;
-    let gz_encoder = GzEncoder::new(&mut f, Compression::default());
+/// The gzip compression level used when none is specified.
+pub const DEFAULT_COMPRESSION_LEVEL: u32 = 6;
+
+/// The size of the buffer between the compressor and the tarball file, the entries are streamed
+/// through it so the memory used doesn't depend on the size of the compressed directory.
+const WRITE_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The first bytes of the encrypted files, followed by the salt of the key, the nonce prefix of
+/// the stream and the encrypted chunks.
+const ENCRYPTION_MAGIC: &[u8] = b"MEILIENC1";
+/// The size of the random salt the key of each file is derived with.
+const SALT_SIZE: usize = 16;
+/// The number of PBKDF2 rounds used to derive the keys from the passphrase.
+const KEY_DERIVATION_ROUNDS: u32 = 100_000;
+/// The size of the plaintext chunks, each chunk is encrypted and authenticated on its own.
+const ENCRYPTION_CHUNK_SIZE: usize = 64 * 1024;
+/// The size of the random part of the nonces, the rest is the chunk counter.
+const NONCE_PREFIX_SIZE: usize = 7;
+/// The size of the authentication tag appended to each encrypted chunk.
+const TAG_SIZE: usize = 16;
+
+/// The compression algorithm used for the tarballs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionFormat {
+    Gzip,
+    Zstd,
+}
+
+impl CompressionFormat {
+    /// Infers the compression format of a tarball from its extension, files that don't end with
+    /// `.zst` are considered as gzip tarballs.
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension() {
+            Some(ext) if ext == "zst" => Self::Zstd,
+            _ => Self::Gzip,
+        }
+    }
+
+    /// Compresses the `src` directory into the `dest` tarball using this format.
+    pub fn compress(
+        &self,
+        src: impl AsRef<Path>,
+        dest: impl AsRef<Path>,
+        compression_level: u32,
+    ) -> anyhow::Result<()> {
+        self.compress_with_progress(src, dest, compression_level, |_| ())
+    }
+
+    /// Compresses the `src` directory into `writer` using this format, and returns the writer
+    /// once the whole tarball is written to it. `progress` is called as with
+    /// `compress_with_progress`.
+    pub fn compress_into<W: Write>(
+        &self,
+        src: impl AsRef<Path>,
+        writer: W,
+        compression_level: u32,
+        progress: impl FnMut(&Path),
+    ) -> anyhow::Result<W> {
+        match self {
+            Self::Gzip => {
+                let gz_encoder = GzEncoder::new(writer, Compression::new(compression_level));
+                let mut tar_encoder = Builder::new(gz_encoder);
+                append_dir(&mut tar_encoder, src.as_ref(), progress)?;
+                Ok(tar_encoder.into_inner()?.finish()?)
+            }
+            Self::Zstd => {
+                let zst_encoder = zstd::Encoder::new(writer, compression_level as i32)?;
+                let mut tar_encoder = Builder::new(zst_encoder);
+                append_dir(&mut tar_encoder, src.as_ref(), progress)?;
+                Ok(tar_encoder.into_inner()?.finish()?)
+            }
+        }
+    }
+
+    /// Compresses the `src` directory into the `dest` tarball using this format, `progress` is
+    /// called with the path, relative to `src`, of each file once it is written.
+    pub fn compress_with_progress(
+        &self,
+        src: impl AsRef<Path>,
+        dest: impl AsRef<Path>,
+        compression_level: u32,
+        progress: impl FnMut(&Path),
+    ) -> anyhow::Result<()> {
+        match self {
+            Self::Gzip => to_tar_gz_with_progress(src, dest, compression_level, progress),
+            Self::Zstd => to_tar_zst_with_progress(src, dest, compression_level, progress),
+        }
+    }
+
+    /// Decompresses the `src` tarball into the `dest` directory using this format.
+    pub fn decompress(&self, src: impl AsRef<Path>, dest: impl AsRef<Path>) -> anyhow::Result<()> {
+        match self {
+            Self::Gzip => from_tar_gz(src, dest),
+            Self::Zstd => from_tar_zst(src, dest),
+        }
+    }
+
+    /// Reads the whole `src` tarball and returns the paths of the files it contains, this fails
+    /// if the tarball is corrupted or truncated.
+    pub fn list_files(&self, src: impl AsRef<Path>) -> anyhow::Result<Vec<PathBuf>> {
+        self.list_files_from(File::open(&src)?)
+    }
+
+    /// Same as `list_files`, the tarball is read from `reader`.
+    pub fn list_files_from(&self, reader: impl Read) -> anyhow::Result<Vec<PathBuf>> {
+        match self {
+            Self::Gzip => list_tar_files(GzDecoder::new(reader)),
+            Self::Zstd => list_tar_files(zstd::Decoder::new(reader)?),
+        }
+    }
+}
+
+impl FromStr for CompressionFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gzip" => Ok(Self::Gzip),
+            "zstd" => Ok(Self::Zstd),
+            other => bail!("invalid compression format: {}", other),
+        }
+    }
+}
+
+/// Compresses the `src` directory into the `dest` tarball, `compression_level` is a gzip level
+/// between 0 (no compression) and 9 (best compression).
+pub fn to_tar_gz(
+    src: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    compression_level: u32,
+) -> anyhow::Result<()> {
+    to_tar_gz_with_progress(src, dest, compression_level, |_| ())
+}
+
+/// Same as `to_tar_gz`, `progress` is called with the path of each file once it is written.
+pub fn to_tar_gz_with_progress(
+    src: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    compression_level: u32,
+    progress: impl FnMut(&Path),
+) -> anyhow::Result<()> {
+    let mut f = BufWriter::with_capacity(WRITE_BUFFER_SIZE, File::create(dest)?);
+    let gz_encoder = GzEncoder::new(&mut f, Compression::new(compression_level));
     let mut tar_encoder = Builder::new(gz_encoder);
-    tar_encoder.append_dir_all(".", src)?;
+    append_dir(&mut tar_encoder, src.as_ref(), progress)?;
     let gz_encoder = tar_encoder.into_inner()?;
     gz_encoder.finish()?;
     f.flush()?;
@@ -24,3 +175,464 @@ pub fn from_tar_gz(src: impl AsRef<Path>, dest: impl AsRef<Path>) -> anyhow::Res
     ar.unpack(&dest)?;
     Ok(())
 }
+
+/// Compresses the `src` directory into the `dest` tarball with zstd, `compression_level` is
+/// given as is to zstd, 0 meaning zstd's default level.
+pub fn to_tar_zst(
+    src: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    compression_level: u32,
+) -> anyhow::Result<()> {
+    to_tar_zst_with_progress(src, dest, compression_level, |_| ())
+}
+
+/// Same as `to_tar_zst`, `progress` is called with the path of each file once it is written.
+pub fn to_tar_zst_with_progress(
+    src: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    compression_level: u32,
+    progress: impl FnMut(&Path),
+) -> anyhow::Result<()> {
+    let mut f = BufWriter::with_capacity(WRITE_BUFFER_SIZE, File::create(dest)?);
+    let zst_encoder = zstd::Encoder::new(&mut f, compression_level as i32)?;
+    let mut tar_encoder = Builder::new(zst_encoder);
+    append_dir(&mut tar_encoder, src.as_ref(), progress)?;
+    let zst_encoder = tar_encoder.into_inner()?;
+    zst_encoder.finish()?;
+    f.flush()?;
+    Ok(())
+}
+
+pub fn from_tar_zst(src: impl AsRef<Path>, dest: impl AsRef<Path>) -> anyhow::Result<()> {
+    let f = File::open(&src)?;
+    let zst = zstd::Decoder::new(f)?;
+    let mut ar = Archive::new(zst);
+    create_dir_all(&dest)?;
+    ar.unpack(&dest)?;
+    Ok(())
+}
+
+/// The passphrase the snapshots are encrypted with. An AES-256-GCM key is derived from it with
+/// PBKDF2 and the random salt stored in the header of each encrypted file.
+#[derive(Clone)]
+pub struct EncryptionKey(String);
+
+impl EncryptionKey {
+    pub fn from_passphrase(passphrase: &str) -> Self {
+        Self(passphrase.to_string())
+    }
+
+    /// Reads the passphrase from the file at `path`, the surrounding whitespaces are ignored.
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let passphrase = fs::read_to_string(path)?;
+        Ok(Self::from_passphrase(passphrase.trim()))
+    }
+
+    fn cipher(&self, salt: &[u8]) -> Aes256Gcm {
+        let mut key = [0; 32];
+        pbkdf2::<Hmac<Sha256>>(self.0.as_bytes(), salt, KEY_DERIVATION_ROUNDS, &mut key);
+        Aes256Gcm::new(Key::from_slice(&key))
+    }
+}
+
+/// Encrypts everything written to it into the inner writer, by chunks so that the content never
+/// has to be held entirely. `finish` must be called once everything is written.
+pub struct EncryptWriter<W: Write> {
+    writer: W,
+    encryptor: EncryptorBE32<Aes256Gcm>,
+    chunk: Vec<u8>,
+}
+
+impl<W: Write> EncryptWriter<W> {
+    pub fn new(mut writer: W, key: &EncryptionKey) -> io::Result<Self> {
+        let mut salt = [0; SALT_SIZE];
+        let mut nonce_prefix = [0; NONCE_PREFIX_SIZE];
+        let mut rng = rand::thread_rng();
+        rng.fill(&mut salt);
+        rng.fill(&mut nonce_prefix);
+        writer.write_all(ENCRYPTION_MAGIC)?;
+        writer.write_all(&salt)?;
+        writer.write_all(&nonce_prefix)?;
+
+        let encryptor = EncryptorBE32::from_aead(key.cipher(&salt), nonce_prefix.as_ref().into());
+        Ok(Self {
+            writer,
+            encryptor,
+            chunk: Vec::with_capacity(ENCRYPTION_CHUNK_SIZE),
+        })
+    }
+
+    /// Encrypts the last chunk, marked as such so that a truncated file can't be decrypted, and
+    /// returns the inner writer.
+    pub fn finish(self) -> io::Result<W> {
+        let Self {
+            mut writer,
+            encryptor,
+            chunk,
+        } = self;
+        let encrypted = encryptor
+            .encrypt_last(chunk.as_slice())
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "could not encrypt the file"))?;
+        writer.write_all(&encrypted)?;
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Write for EncryptWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // A full chunk is only encrypted once more bytes are written, the last one is encrypted
+        // by `finish`.
+        if self.chunk.len() == ENCRYPTION_CHUNK_SIZE {
+            let encrypted = self
+                .encryptor
+                .encrypt_next(self.chunk.as_slice())
+                .map_err(|_| io::Error::new(io::ErrorKind::Other, "could not encrypt the file"))?;
+            self.writer.write_all(&encrypted)?;
+            self.chunk.clear();
+        }
+
+        let len = buf.len().min(ENCRYPTION_CHUNK_SIZE - self.chunk.len());
+        self.chunk.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Decrypts the content of a reader encrypted with an `EncryptWriter`, chunk by chunk. Reading
+/// fails if the key is not the one the content was encrypted with or if it was altered, what was
+/// read until then must be discarded.
+pub struct DecryptReader<R: Read> {
+    reader: R,
+    /// Taken once the last chunk is decrypted.
+    decryptor: Option<DecryptorBE32<Aes256Gcm>>,
+    /// The next encrypted chunk, read ahead to know whether it is the last one.
+    next_chunk: Vec<u8>,
+    next_len: usize,
+    following_chunk: Vec<u8>,
+    decrypted: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> DecryptReader<R> {
+    pub fn new(mut reader: R, key: &EncryptionKey) -> anyhow::Result<Self> {
+        let mut header = [0; ENCRYPTION_MAGIC.len() + SALT_SIZE + NONCE_PREFIX_SIZE];
+        if read_chunk(&mut reader, &mut header)? != header.len()
+            || &header[..ENCRYPTION_MAGIC.len()] != ENCRYPTION_MAGIC
+        {
+            bail!("the file is not encrypted");
+        }
+        let (salt, nonce_prefix) = header[ENCRYPTION_MAGIC.len()..].split_at(SALT_SIZE);
+        let decryptor = DecryptorBE32::from_aead(key.cipher(salt), nonce_prefix.into());
+
+        let mut next_chunk = vec![0; ENCRYPTION_CHUNK_SIZE + TAG_SIZE];
+        let next_len = read_chunk(&mut reader, &mut next_chunk)?;
+        Ok(Self {
+            reader,
+            decryptor: Some(decryptor),
+            next_chunk,
+            next_len,
+            following_chunk: vec![0; ENCRYPTION_CHUNK_SIZE + TAG_SIZE],
+            decrypted: Vec::new(),
+            position: 0,
+        })
+    }
+
+    fn decrypt_next_chunk(&mut self) -> io::Result<()> {
+        let mut decryptor = match self.decryptor.take() {
+            Some(decryptor) => decryptor,
+            None => return Ok(()),
+        };
+
+        let following_len = read_chunk(&mut self.reader, &mut self.following_chunk)?;
+        let chunk = &self.next_chunk[..self.next_len];
+        let decrypted = if following_len == 0 {
+            decryptor.decrypt_last(chunk)
+        } else {
+            let decrypted = decryptor.decrypt_next(chunk);
+            self.decryptor = Some(decryptor);
+            decrypted
+        };
+        self.decrypted = decrypted.map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid encryption key or corrupted file",
+            )
+        })?;
+        self.position = 0;
+
+        mem::swap(&mut self.next_chunk, &mut self.following_chunk);
+        self.next_len = following_len;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.decrypted.len() {
+            if self.decryptor.is_none() {
+                return Ok(0);
+            }
+            self.decrypt_next_chunk()?;
+        }
+
+        let len = buf.len().min(self.decrypted.len() - self.position);
+        buf[..len].copy_from_slice(&self.decrypted[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+/// Encrypts the `src` file into `dest`, the file is read and encrypted by chunks so that it never
+/// has to be loaded entirely.
+pub fn encrypt_file(
+    src: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    key: &EncryptionKey,
+) -> anyhow::Result<()> {
+    let mut reader = BufReader::new(File::open(src)?);
+    let writer = BufWriter::with_capacity(WRITE_BUFFER_SIZE, File::create(dest)?);
+    let mut writer = EncryptWriter::new(writer, key)?;
+    io::copy(&mut reader, &mut writer)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Decrypts the `src` file, encrypted with `encrypt_file`, into `dest`. This fails if the key is
+/// not the one the file was encrypted with or if the file was altered, the content written to
+/// `dest` must then be discarded.
+pub fn decrypt_file(
+    src: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    key: &EncryptionKey,
+) -> anyhow::Result<()> {
+    let mut reader = DecryptReader::new(BufReader::new(File::open(src)?), key)?;
+    let mut writer = BufWriter::with_capacity(WRITE_BUFFER_SIZE, File::create(dest)?);
+    io::copy(&mut reader, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Fills `buffer` from `reader`, returns the number of bytes read, which is only smaller than the
+/// buffer at the end of the reader.
+fn read_chunk(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buffer.len() {
+        match reader.read(&mut buffer[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(len)
+}
+
+/// Appends the entries of the `src` directory one by one, each file is copied to the tarball with
+/// a fixed size buffer instead of being read entirely.
+fn append_dir<W: Write>(
+    builder: &mut Builder<W>,
+    src: &Path,
+    mut progress: impl FnMut(&Path),
+) -> anyhow::Result<()> {
+    for entry in WalkDir::new(src).sort_by(|a, b| a.file_name().cmp(b.file_name())) {
+        let entry = entry?;
+        let path = entry.path().strip_prefix(src)?;
+        if path.as_os_str().is_empty() {
+            continue;
+        }
+
+        if entry.file_type().is_dir() {
+            builder.append_dir(path, entry.path())?;
+        } else {
+            let mut file = File::open(entry.path())?;
+            builder.append_file(path, &mut file)?;
+            progress(path);
+        }
+    }
+    Ok(())
+}
+
+fn list_tar_files(reader: impl Read) -> anyhow::Result<Vec<PathBuf>> {
+    let mut ar = Archive::new(reader);
+    let mut files = Vec::new();
+    for entry in ar.entries()? {
+        let mut entry = entry?;
+        if entry.header().entry_type().is_file() {
+            // the tarballs are built from the `.` directory, we only keep the normal components
+            let path = entry
+                .path()?
+                .components()
+                .filter(|c| matches!(c, Component::Normal(_)))
+                .collect();
+            files.push(path);
+        }
+        // make sure that the content of the entry can be read entirely
+        io::copy(&mut entry, &mut io::sink())?;
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::*;
+
+    fn roundtrip(compression_level: u32) {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let src = dir.path().join("src");
+        create_dir_all(src.join("indexes")).unwrap();
+        fs::write(src.join("indexes").join("data.mdb"), vec![42; 4096]).unwrap();
+
+        let archive = dir.path().join("archive.tar.gz");
+        to_tar_gz(&src, &archive, compression_level).unwrap();
+
+        let dest = dir.path().join("dest");
+        from_tar_gz(&archive, &dest).unwrap();
+
+        let content = fs::read(dest.join("indexes").join("data.mdb")).unwrap();
+        assert_eq!(content, vec![42; 4096]);
+    }
+
+    #[test]
+    fn roundtrip_no_compression() {
+        roundtrip(0);
+    }
+
+    #[test]
+    fn roundtrip_best_compression() {
+        roundtrip(9);
+    }
+
+    #[test]
+    fn progress_is_reported_for_each_file() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let src = dir.path().join("src");
+        let mut expected = Vec::new();
+        for i in 0..20 {
+            let index_dir = PathBuf::from("indexes").join(format!("index-{}", i));
+            create_dir_all(src.join(&index_dir)).unwrap();
+            for name in &["data.mdb", "lock.mdb"] {
+                fs::write(src.join(&index_dir).join(name), vec![i as u8; 1024]).unwrap();
+                expected.push(index_dir.join(name));
+            }
+        }
+
+        let archive = dir.path().join("archive.tar.gz");
+        let mut reported = Vec::new();
+        to_tar_gz_with_progress(&src, &archive, DEFAULT_COMPRESSION_LEVEL, |path| {
+            reported.push(path.to_owned())
+        })
+        .unwrap();
+
+        reported.sort();
+        expected.sort();
+        assert_eq!(reported, expected);
+
+        let mut files = CompressionFormat::Gzip.list_files(&archive).unwrap();
+        files.sort();
+        assert_eq!(files, expected);
+    }
+
+    #[test]
+    fn encryption_roundtrip() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        // several chunks and a partial last one
+        let content: Vec<u8> = (0..ENCRYPTION_CHUNK_SIZE * 3 + 42)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        let src = dir.path().join("snapshot");
+        fs::write(&src, &content).unwrap();
+
+        let key = EncryptionKey::from_passphrase("secret");
+        let encrypted = dir.path().join("snapshot.enc");
+        encrypt_file(&src, &encrypted, &key).unwrap();
+        assert_ne!(
+            fs::read(&encrypted).unwrap()[ENCRYPTION_MAGIC.len()..],
+            content[..]
+        );
+
+        let decrypted = dir.path().join("decrypted");
+        decrypt_file(&encrypted, &decrypted, &key).unwrap();
+        assert_eq!(fs::read(&decrypted).unwrap(), content);
+    }
+
+    #[test]
+    fn keys_are_derived_with_a_random_salt() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let src = dir.path().join("snapshot");
+        fs::write(&src, vec![42; 4096]).unwrap();
+
+        let key = EncryptionKey::from_passphrase("secret");
+        let first = dir.path().join("first.enc");
+        let second = dir.path().join("second.enc");
+        encrypt_file(&src, &first, &key).unwrap();
+        encrypt_file(&src, &second, &key).unwrap();
+
+        let (first, second) = (fs::read(&first).unwrap(), fs::read(&second).unwrap());
+        let salt_end = ENCRYPTION_MAGIC.len() + SALT_SIZE;
+        assert_ne!(
+            first[ENCRYPTION_MAGIC.len()..salt_end],
+            second[ENCRYPTION_MAGIC.len()..salt_end]
+        );
+        assert_ne!(first[salt_end..], second[salt_end..]);
+    }
+
+    #[test]
+    fn decryption_with_wrong_key() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let src = dir.path().join("snapshot");
+        fs::write(&src, vec![42; 4096]).unwrap();
+
+        let encrypted = dir.path().join("snapshot.enc");
+        encrypt_file(&src, &encrypted, &EncryptionKey::from_passphrase("secret")).unwrap();
+
+        let decrypted = dir.path().join("decrypted");
+        let wrong_key = EncryptionKey::from_passphrase("wrong");
+        let error = decrypt_file(&encrypted, &decrypted, &wrong_key).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid encryption key or corrupted file"
+        );
+
+        // a truncated file can't be decrypted either
+        let len = fs::metadata(&encrypted).unwrap().len();
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&encrypted)
+            .unwrap()
+            .set_len(len - 1)
+            .unwrap();
+        let key = EncryptionKey::from_passphrase("secret");
+        assert!(decrypt_file(&encrypted, &decrypted, &key).is_err());
+    }
+
+    #[test]
+    fn roundtrip_zstd() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let src = dir.path().join("src");
+        create_dir_all(src.join("indexes")).unwrap();
+        fs::write(src.join("indexes").join("data.mdb"), vec![42; 4096]).unwrap();
+
+        let archive = dir.path().join("archive.tar.zst");
+        to_tar_zst(&src, &archive, DEFAULT_COMPRESSION_LEVEL).unwrap();
+        assert_eq!(
+            CompressionFormat::from_path(&archive),
+            CompressionFormat::Zstd
+        );
+
+        let dest = dir.path().join("dest");
+        from_tar_zst(&archive, &dest).unwrap();
+
+        let content = fs::read(dest.join("indexes").join("data.mdb")).unwrap();
+        assert_eq!(content, vec![42; 4096]);
+    }
+}
@@ -0,0 +1,123 @@
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use anyhow::bail;
+use chrono::{SecondsFormat, Utc};
+use futures::future::{ok, Future, Ready};
+use log::{info, Record};
+use serde_json::json;
+
+use crate::helpers::RequestId;
+
+/// The target of the request logs, they are already formatted as json lines.
+pub const REQUEST_LOG_TARGET: &str = "meilisearch_http::request";
+
+/// The format of the logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => bail!("invalid log format: {}", other),
+        }
+    }
+}
+
+/// Formats a record as a json line, to be used with `env_logger::Builder::format`.
+pub fn format_json(buf: &mut impl Write, record: &Record) -> io::Result<()> {
+    if record.target() == REQUEST_LOG_TARGET {
+        return writeln!(buf, "{}", record.args());
+    }
+
+    let line = json!({
+        "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    });
+    writeln!(buf, "{}", line)
+}
+
+/// Logs a json line with the method, path, request id, status and latency of each request.
+#[derive(Clone, Copy)]
+pub struct RequestLogger;
+
+impl<S: 'static, B> Transform<S, ServiceRequest> for RequestLogger
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type InitError = ();
+    type Transform = RequestLoggerMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestLoggerMiddleware {
+            service: Rc::new(service),
+        })
+    }
+}
+
+pub struct RequestLoggerMiddleware<S> {
+    service: Rc<S>,
+}
+
+#[allow(clippy::type_complexity)]
+impl<S, B> Service<ServiceRequest> for RequestLoggerMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let start = Instant::now();
+        let method = req.method().to_string();
+        let path = req.path().to_string();
+        let request_id = req.extensions().get::<RequestId>().map(|id| id.0.clone());
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let result = fut.await;
+            // The errors of the inner services are turned into responses by actix afterward.
+            let status = match &result {
+                Ok(res) => res.status(),
+                Err(e) => e.as_response_error().status_code(),
+            };
+            let line = json!({
+                "timestamp": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                "level": "INFO",
+                "method": method,
+                "path": path,
+                "request_id": request_id,
+                "status": status.as_u16(),
+                "latency_ms": start.elapsed().as_secs_f64() * 1000.0,
+            });
+            info!(target: REQUEST_LOG_TARGET, "{}", line);
+            result
+        })
+    }
+}
@@ -0,0 +1,176 @@
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::web;
+use futures::future::{ok, Future, Ready};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+use crate::Data;
+
+/// The route label of the requests that didn't match any route.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// The prometheus metrics of the instance. The request metrics are recorded by the
+/// `RequestMetrics` middleware, the gauges of the indexes are computed when the metrics are
+/// rendered.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    http_requests_total: IntCounterVec,
+    http_request_duration_seconds: HistogramVec,
+    update_queue_depth: IntGaugeVec,
+    index_documents: IntGaugeVec,
+}
+
+impl Metrics {
+    pub fn new() -> prometheus::Result<Self> {
+        let http_requests_total = IntCounterVec::new(
+            Opts::new(
+                "meilisearch_http_requests_total",
+                "The number of http requests.",
+            ),
+            &["method", "route", "status"],
+        )?;
+        let http_request_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "meilisearch_http_request_duration_seconds",
+                "The latency of the http requests.",
+            ),
+            &["method", "route", "status"],
+        )?;
+        let update_queue_depth = IntGaugeVec::new(
+            Opts::new(
+                "meilisearch_update_queue_depth",
+                "The number of enqueued and processing updates of the index.",
+            ),
+            &["index"],
+        )?;
+        let index_documents = IntGaugeVec::new(
+            Opts::new(
+                "meilisearch_index_documents",
+                "The number of documents of the index.",
+            ),
+            &["index"],
+        )?;
+
+        let registry = Registry::new();
+        registry.register(Box::new(http_requests_total.clone()))?;
+        registry.register(Box::new(http_request_duration_seconds.clone()))?;
+        registry.register(Box::new(update_queue_depth.clone()))?;
+        registry.register(Box::new(index_documents.clone()))?;
+
+        Ok(Self {
+            registry,
+            http_requests_total,
+            http_request_duration_seconds,
+            update_queue_depth,
+            index_documents,
+        })
+    }
+
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, latency: Duration) {
+        let status = status.to_string();
+        let labels = [method, route, status.as_str()];
+        self.http_requests_total.with_label_values(&labels).inc();
+        self.http_request_duration_seconds
+            .with_label_values(&labels)
+            .observe(latency.as_secs_f64());
+    }
+
+    /// Refreshes the gauges of the indexes and renders all the metrics in the prometheus text
+    /// format.
+    pub async fn render(&self, data: &Data) -> anyhow::Result<String> {
+        // The deleted indexes must not be reported anymore.
+        self.update_queue_depth.reset();
+        self.index_documents.reset();
+
+        for index in data.list_indexes().await? {
+            let queue = data.get_update_queue_stats(index.uid.clone()).await?;
+            let stats = data.get_index_stats(index.uid.clone()).await?;
+            self.update_queue_depth
+                .with_label_values(&[&index.uid])
+                .set((queue.enqueued + queue.is_processing as u64) as i64);
+            self.index_documents
+                .with_label_values(&[&index.uid])
+                .set(stats.number_of_documents as i64);
+        }
+
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+/// Records the count and the latency of the requests by route and status.
+#[derive(Clone, Copy)]
+pub struct RequestMetrics;
+
+impl<S: 'static, B> Transform<S, ServiceRequest> for RequestMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type InitError = ();
+    type Transform = RequestMetricsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestMetricsMiddleware {
+            service: Rc::new(service),
+        })
+    }
+}
+
+pub struct RequestMetricsMiddleware<S> {
+    service: Rc<S>,
+}
+
+#[allow(clippy::type_complexity)]
+impl<S, B> Service<ServiceRequest> for RequestMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let start = Instant::now();
+        // This unwrap is left because this error should never appear. If that's the case, then
+        // it means that actix-web has an issue or someone changes the type `Data`.
+        let data = req.app_data::<web::Data<Data>>().unwrap().clone();
+        let method = req.method().to_string();
+        // The route pattern is used rather than the path, so the cardinality of the labels doesn't
+        // depend on the indexes and the documents.
+        let route = req
+            .request()
+            .match_pattern()
+            .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let result = fut.await;
+            let status = match &result {
+                Ok(res) => res.status(),
+                Err(e) => e.as_response_error().status_code(),
+            };
+            data.metrics()
+                .observe_request(&method, &route, status.as_u16(), start.elapsed());
+            result
+        })
+    }
+}
@@ -1,6 +1,11 @@
 pub mod authentication;
 pub mod compression;
 mod env;
+pub mod logging;
+pub mod metrics;
+pub mod object_storage;
+pub mod request_id;
 
-pub use authentication::Authentication;
+pub use authentication::{Authentication, Clock, EnforcedFilter};
 pub use env::EnvSizer;
+pub use request_id::RequestId;
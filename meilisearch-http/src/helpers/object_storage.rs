@@ -0,0 +1,216 @@
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context};
+use chrono::Utc;
+use hmac::{Hmac, Mac, NewMac};
+use log::{error, info};
+use reqwest::header::{AUTHORIZATION, CONTENT_LENGTH, HOST};
+use reqwest::{Client, Url};
+use sha2::{Digest, Sha256};
+use tokio::time::sleep;
+
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The credentials used to sign the requests sent to the object storage.
+#[derive(Clone)]
+pub struct S3Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl S3Credentials {
+    /// Reads the credentials from the `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+    /// `AWS_SESSION_TOKEN` environment variables.
+    pub fn from_env() -> anyhow::Result<Self> {
+        let var = |name| std::env::var(name).with_context(|| format!("missing {}", name));
+        Ok(Self {
+            access_key_id: var("AWS_ACCESS_KEY_ID")?,
+            secret_access_key: var("AWS_SECRET_ACCESS_KEY")?,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+        })
+    }
+}
+
+/// Uploads files to a bucket of an S3-compatible object storage, the requests are signed with the
+/// AWS signature version 4 and the objects are addressed with path-style urls.
+#[derive(Clone)]
+pub struct S3Uploader {
+    client: Client,
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    credentials: S3Credentials,
+    retries: u32,
+}
+
+impl S3Uploader {
+    pub fn new(
+        endpoint: &str,
+        bucket: String,
+        region: String,
+        credentials: S3Credentials,
+        retries: u32,
+    ) -> anyhow::Result<Self> {
+        let endpoint = Url::parse(endpoint)
+            .with_context(|| format!("invalid object storage endpoint {:?}", endpoint))?;
+        if endpoint.host_str().is_none() {
+            bail!("invalid object storage endpoint {:?}", endpoint.as_str());
+        }
+        Ok(Self {
+            client: Client::new(),
+            endpoint,
+            bucket,
+            region,
+            credentials,
+            retries,
+        })
+    }
+
+    /// Uploads the file at `path` as the `key` object, retrying up to the configured number of
+    /// times. The failures are logged and the last one is returned.
+    pub async fn upload_with_retries(&self, path: &Path, key: &str) -> anyhow::Result<()> {
+        let mut attempt = 0;
+        loop {
+            match self.upload(path, key).await {
+                Ok(()) => {
+                    info!("Uploaded {:?} to the {} bucket.", key, self.bucket);
+                    return Ok(());
+                }
+                Err(e) if attempt < self.retries => {
+                    attempt += 1;
+                    error!(
+                        "Could not upload {:?}, retrying ({}/{}): {}",
+                        key, attempt, self.retries, e
+                    );
+                    sleep(RETRY_DELAY).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn upload(&self, path: &Path, key: &str) -> anyhow::Result<()> {
+        let file = tokio::fs::File::open(path).await?;
+        let len = file.metadata().await?.len();
+
+        let object_path = format!(
+            "{}/{}/{}",
+            self.endpoint.path().trim_end_matches('/'),
+            uri_encode(&self.bucket),
+            key.split('/').map(uri_encode).collect::<Vec<_>>().join("/")
+        );
+        let mut url = self.endpoint.clone();
+        url.set_path(&object_path);
+
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => unreachable!("the endpoint host is checked at creation"),
+        };
+        let now = Utc::now();
+        let date = now.format("%Y%m%d").to_string();
+        let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+
+        let mut headers = vec![
+            ("host", host.clone()),
+            ("x-amz-content-sha256", UNSIGNED_PAYLOAD.to_string()),
+            ("x-amz-date", timestamp.clone()),
+        ];
+        if let Some(ref token) = self.credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let authorization = self.authorization(url.path(), &headers, &date, &timestamp);
+
+        let mut request = self
+            .client
+            .put(url)
+            .header(HOST, host)
+            .header(CONTENT_LENGTH, len)
+            .header(AUTHORIZATION, authorization);
+        for (name, value) in headers.into_iter().filter(|(name, _)| *name != "host") {
+            request = request.header(name, value);
+        }
+
+        let response = request.body(file).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!(
+                "object storage responded with {}: {}",
+                status,
+                body
+            ));
+        }
+        Ok(())
+    }
+
+    /// Computes the signature version 4 authorization header of a `PUT` request on `path`, the
+    /// `headers` must be sorted by name.
+    fn authorization(
+        &self,
+        path: &str,
+        headers: &[(&str, String)],
+        date: &str,
+        timestamp: &str,
+    ) -> String {
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+            .collect();
+        let canonical_request = format!(
+            "PUT\n{}\n\n{}\n{}\n{}",
+            path, canonical_headers, signed_headers, UNSIGNED_PAYLOAD
+        );
+
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            timestamp,
+            scope,
+            hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let secret = format!("AWS4{}", self.credentials.secret_access_key);
+        let key = hmac(secret.as_bytes(), date.as_bytes());
+        let key = hmac(&key, self.region.as_bytes());
+        let key = hmac(&key, b"s3");
+        let key = hmac(&key, b"aws4_request");
+        let signature = hex(&hmac(&key, string_to_sign.as_bytes()));
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.credentials.access_key_id, scope, signed_headers, signature
+        )
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_varkey(key).expect("hmac accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Encodes a path segment as specified by the signature version 4.
+fn uri_encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
@@ -0,0 +1,94 @@
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use futures::future::{ok, Future, Ready};
+use uuid::Uuid;
+
+/// The header the id of a request is read from, and echoed in.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: String;
+}
+
+/// The id of a request, inserted in the request extensions. It is taken from the `X-Request-Id`
+/// header of the request, or generated when it is missing.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+    /// Returns the id of the request being handled, if any. It is only set while the request goes
+    /// through `RequestIdentifier`, so that the errors raised while handling it can carry it.
+    pub fn current() -> Option<String> {
+        CURRENT_REQUEST_ID.try_with(|id| id.clone()).ok()
+    }
+}
+
+/// Identifies each request and echoes its id in the `X-Request-Id` header of the response. The
+/// errors of the inner middlewares, such as the authentication ones, are turned into responses by
+/// actix afterward, they carry the id themselves.
+#[derive(Clone, Copy)]
+pub struct RequestIdentifier;
+
+impl<S: 'static, B> Transform<S, ServiceRequest> for RequestIdentifier
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type InitError = ();
+    type Transform = RequestIdentifierMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RequestIdentifierMiddleware {
+            service: Rc::new(service),
+        })
+    }
+}
+
+pub struct RequestIdentifierMiddleware<S> {
+    service: Rc<S>,
+}
+
+#[allow(clippy::type_complexity)]
+impl<S, B> Service<ServiceRequest> for RequestIdentifierMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| !id.is_empty())
+            .map(String::from)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        req.extensions_mut().insert(RequestId(id.clone()));
+
+        let service = self.service.clone();
+        Box::pin(CURRENT_REQUEST_ID.scope(id.clone(), async move {
+            let mut res = service.call(req).await?;
+            if let Ok(value) = HeaderValue::from_str(&id) {
+                res.headers_mut()
+                    .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+            }
+            Ok(res)
+        }))
+    }
+}
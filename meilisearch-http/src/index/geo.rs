@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+
+use anyhow::bail;
+use milli::update::UpdateFormat;
+use serde_json::{Map, Value};
+
+/// The attribute holding the location of the documents, as a `{ "lat": _, "lng": _ }` object.
+pub const GEO_FIELD: &str = "_geo";
+/// The facet type of the `_geo` attribute, it can't be faceted with any other type.
+pub const GEO_FACET_TYPE: &str = "geo";
+
+/// The mean radius of the earth, in meters.
+const EARTH_RADIUS: f64 = 6_371_000.0;
+
+/// A `_geoRadius(lat, lng, meters)` filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoRadius {
+    pub point: (f64, f64),
+    pub meters: f64,
+}
+
+impl GeoRadius {
+    /// Returns whether the location of a document, given as its `_geo` value, is in the radius.
+    pub fn contains(&self, geo: &Value) -> bool {
+        match geo_point(geo) {
+            Some(point) => distance(self.point, point) <= self.meters,
+            None => false,
+        }
+    }
+}
+
+/// Returns the latitude and longitude of a `_geo` value, if it is valid. The coordinates can be
+/// numbers or strings, as in CSV documents.
+pub fn geo_point(geo: &Value) -> Option<(f64, f64)> {
+    fn coordinate(value: Option<&Value>) -> Option<f64> {
+        match value? {
+            Value::Number(number) => number.as_f64(),
+            Value::String(string) => string.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    let lat = coordinate(geo.get("lat"))?;
+    let lng = coordinate(geo.get("lng"))?;
+    if (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lng) {
+        Some((lat, lng))
+    } else {
+        None
+    }
+}
+
+/// Returns the distance in meters between two points, with the haversine formula.
+pub fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat_a, lng_a) = (a.0.to_radians(), a.1.to_radians());
+    let (lat_b, lng_b) = (b.0.to_radians(), b.1.to_radians());
+    let h = ((lat_b - lat_a) / 2.0).sin().powi(2)
+        + lat_a.cos() * lat_b.cos() * ((lng_b - lng_a) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().asin()
+}
+
+/// Parses the `lat, lng` coordinates given to `_geoRadius` and `_geoPoint`.
+fn parse_coordinates(expression: &str, params: &str, expected: usize) -> anyhow::Result<Vec<f64>> {
+    let values = params
+        .split(',')
+        .map(|param| param.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>();
+    match values {
+        Ok(values) if values.len() == expected => {
+            if !(-90.0..=90.0).contains(&values[0]) || !(-180.0..=180.0).contains(&values[1]) {
+                bail!("Invalid coordinates in {:?}.", expression);
+            }
+            Ok(values)
+        }
+        _ => bail!("Invalid geo expression {:?}.", expression),
+    }
+}
+
+/// Parses a `_geoPoint(lat, lng)` sort attribute, returns `None` for the other attributes.
+pub fn parse_geo_point(field: &str) -> Option<anyhow::Result<(f64, f64)>> {
+    let params = field.strip_prefix("_geoPoint(")?.strip_suffix(')')?;
+    Some(parse_coordinates(field, params, 2).map(|values| (values[0], values[1])))
+}
+
+/// Extracts the `_geoRadius` expressions from `filter`, they are applied on top of the rest of the
+/// filter, which is returned for the engine. The expressions must be combined with the rest of
+/// the filter with `AND` to be extracted.
+pub fn extract_geo_radius(filter: &str) -> anyhow::Result<(Option<String>, Vec<GeoRadius>)> {
+    if !filter.contains("_geoRadius") {
+        return Ok((Some(filter.to_string()), Vec::new()));
+    }
+
+    let (parts, has_or) = split_and(filter);
+    let mut remaining = Vec::new();
+    let mut radiuses = Vec::new();
+    for part in parts {
+        let params = part
+            .strip_prefix("_geoRadius(")
+            .and_then(|rest| rest.strip_suffix(')'));
+        match params {
+            Some(params) => {
+                let values = parse_coordinates(part, params, 3)?;
+                radiuses.push(GeoRadius {
+                    point: (values[0], values[1]),
+                    meters: values[2],
+                });
+            }
+            None if part.contains("_geoRadius") => {
+                bail!("`_geoRadius` can only be combined with the other filters with `AND`.")
+            }
+            None => remaining.push(part),
+        }
+    }
+    if has_or {
+        bail!("`_geoRadius` can only be combined with the other filters with `AND`.");
+    }
+
+    let remaining = Some(remaining.join(" AND ")).filter(|filter| !filter.is_empty());
+    Ok((remaining, radiuses))
+}
+
+/// Splits `filter` on its `AND` operators that aren't in parentheses or quotes, and returns
+/// whether it also contains such an `OR` operator.
+fn split_and(filter: &str) -> (Vec<&str>, bool) {
+    let bytes = filter.as_bytes();
+    let is_operator_at = |i: usize, operator: &[u8]| {
+        let end = i + operator.len();
+        i > 0
+            && bytes[i - 1].is_ascii_whitespace()
+            && bytes.get(end).map_or(false, u8::is_ascii_whitespace)
+            && bytes[i..end].eq_ignore_ascii_case(operator)
+    };
+
+    let mut parts = Vec::new();
+    let mut has_or = false;
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match (quote, bytes[i]) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => (),
+            (None, c @ b'"') | (None, c @ b'\'') => quote = Some(c),
+            (None, b'(') => depth += 1,
+            (None, b')') => depth = depth.saturating_sub(1),
+            (None, _) if depth == 0 && is_operator_at(i, b"AND") => {
+                parts.push(filter[start..i].trim());
+                i += 3;
+                start = i;
+                continue;
+            }
+            (None, _) if depth == 0 && is_operator_at(i, b"OR") => has_or = true,
+            _ => (),
+        }
+        i += 1;
+    }
+    parts.push(filter[start..].trim());
+
+    (parts, has_or)
+}
+
+/// Checks that the `_geo` attribute is faceted with the `geo` type, and that no other attribute is.
+pub fn check_geo_facet_type(facet_types: &HashMap<String, String>) -> anyhow::Result<()> {
+    for (attribute, facet_type) in facet_types {
+        match (attribute == GEO_FIELD, facet_type == GEO_FACET_TYPE) {
+            (true, false) => bail!(
+                "The `_geo` attribute must be faceted with the {:?} type, not {:?}.",
+                GEO_FACET_TYPE,
+                facet_type
+            ),
+            (false, true) => bail!(
+                "Only the `_geo` attribute can be faceted with the {:?} type, not {:?}.",
+                GEO_FACET_TYPE,
+                attribute
+            ),
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the `_geo` attribute of the documents of `content` is a valid location, when it is
+/// set. The CSV documents can't hold objects, their `_geo` attribute isn't checked.
+pub fn validate_geo_documents(format: UpdateFormat, content: &[u8]) -> anyhow::Result<()> {
+    fn validate(document: &Map<String, Value>) -> anyhow::Result<()> {
+        match document.get(GEO_FIELD) {
+            None | Some(Value::Null) => Ok(()),
+            Some(geo) if geo_point(geo).is_some() => Ok(()),
+            Some(geo) => bail!(
+                "Invalid `_geo` attribute {}, expected `{{ \"lat\": _, \"lng\": _ }}`.",
+                geo
+            ),
+        }
+    }
+
+    match format {
+        UpdateFormat::Json => {
+            for document in serde_json::from_slice::<Vec<Map<String, Value>>>(content)? {
+                validate(&document)?;
+            }
+        }
+        UpdateFormat::JsonStream => {
+            for document in
+                serde_json::Deserializer::from_slice(content).into_iter::<Map<String, Value>>()
+            {
+                validate(&document?)?;
+            }
+        }
+        UpdateFormat::Csv => (),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn geo_facet_type_is_required() {
+        let facet_types = |geo: &str| {
+            let mut facet_types = HashMap::new();
+            facet_types.insert("kind".to_string(), "string".to_string());
+            facet_types.insert(GEO_FIELD.to_string(), geo.to_string());
+            facet_types
+        };
+        assert!(check_geo_facet_type(&facet_types("geo")).is_ok());
+        assert!(check_geo_facet_type(&facet_types("string")).is_err());
+
+        let mut facet_types = facet_types("geo");
+        facet_types.insert("location".to_string(), "geo".to_string());
+        assert!(check_geo_facet_type(&facet_types).is_err());
+    }
+
+    #[test]
+    fn distance_between_cities() {
+        let paris = (48.8566, 2.3522);
+        let london = (51.5074, -0.1278);
+        let meters = distance(paris, london);
+        assert!((meters - 343_500.0).abs() < 2_000.0, "{}", meters);
+        assert_eq!(distance(paris, paris), 0.0);
+    }
+
+    #[test]
+    fn extract_radius_from_filter() {
+        let (filter, radiuses) =
+            extract_geo_radius("_geoRadius(45.5, 9.1, 2000) AND genre = \"sci AND fi\"").unwrap();
+        assert_eq!(filter.as_deref(), Some("genre = \"sci AND fi\""));
+        assert_eq!(
+            radiuses,
+            vec![GeoRadius {
+                point: (45.5, 9.1),
+                meters: 2000.0
+            }]
+        );
+
+        let (filter, radiuses) = extract_geo_radius("_geoRadius(45.5, 9.1, 2000)").unwrap();
+        assert_eq!(filter, None);
+        assert_eq!(radiuses.len(), 1);
+
+        let (filter, radiuses) = extract_geo_radius("genre = drama OR genre = action").unwrap();
+        assert_eq!(filter.as_deref(), Some("genre = drama OR genre = action"));
+        assert!(radiuses.is_empty());
+
+        assert!(extract_geo_radius("genre = drama OR _geoRadius(45.5, 9.1, 2000)").is_err());
+        assert!(extract_geo_radius("(_geoRadius(45.5, 9.1, 2000)) AND genre = drama").is_err());
+        assert!(extract_geo_radius("_geoRadius(45.5, 2000)").is_err());
+        assert!(extract_geo_radius("_geoRadius(95.5, 9.1, 2000)").is_err());
+    }
+
+    #[test]
+    fn parse_geo_point_sort() {
+        assert_eq!(
+            parse_geo_point("_geoPoint(45.5, 9.1)").unwrap().unwrap(),
+            (45.5, 9.1)
+        );
+        assert!(parse_geo_point("_geoPoint(45.5)").unwrap().is_err());
+        assert!(parse_geo_point("price").is_none());
+    }
+}
@@ -1,20 +1,42 @@
-use std::collections::{BTreeSet, HashSet};
-use std::ops::Deref;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::ops::{Bound, Deref};
+use std::path::Path;
 use std::sync::Arc;
 
 use anyhow::{bail, Context};
-use milli::obkv_to_json;
+use chrono::{DateTime, Utc};
+use heed::types::{SerdeJson, Str};
+use milli::{obkv_to_json, DocumentId, BEU32};
 use serde_json::{Map, Value};
 
-pub use search::{SearchQuery, SearchResult, DEFAULT_SEARCH_LIMIT};
-pub use updates::{Facets, Settings, UpdateResult};
+pub use search::{
+    SearchQuery, SearchResult, DEFAULT_HIGHLIGHT_POST_TAG, DEFAULT_HIGHLIGHT_PRE_TAG,
+    DEFAULT_SEARCH_LIMIT, MAX_TYPOS,
+};
+pub use updates::{Facets, Pagination, Settings, SettingsValidation, TypoTolerance, UpdateResult};
 use crate::helpers::EnvSizer;
 
+mod geo;
 mod search;
 mod updates;
 
 pub type Document = Map<String, Value>;
 
+/// The key of the typo tolerance in the main database of the index.
+const TYPO_TOLERANCE_KEY: &str = "typo-tolerance";
+/// The key of the pagination in the main database of the index.
+const PAGINATION_KEY: &str = "pagination";
+/// The key of the split and concatenation of the query words in the main database of the index.
+const SPLIT_JOIN_WORDS_KEY: &str = "split-join-words";
+/// The key of the facet type of the `_geo` attribute, when it is faceted.
+const GEO_FACET_TYPE_KEY: &str = "geo-facet-type";
+/// The key milli reads the update date of the index from.
+const UPDATED_AT_KEY: &str = "updated-at";
+/// The number of documents whose values are read to infer the facet type of an attribute.
+const FACET_TYPE_SAMPLE_SIZE: usize = 1000;
+
 #[derive(Clone)]
 pub struct Index(pub Arc<milli::Index>);
 
@@ -40,11 +62,14 @@ impl Index {
             .map(|fields| fields.into_iter().map(String::from).collect())
             .unwrap_or_else(|| vec!["*".to_string()]);
 
-        let faceted_attributes = self
+        let mut faceted_attributes: HashMap<_, _> = self
             .faceted_fields(&txn)?
             .into_iter()
             .map(|(k, v)| (k, v.to_string()))
             .collect();
+        if let Some(geo_facet_type) = self.geo_facet_type(&txn)? {
+            faceted_attributes.insert(geo::GEO_FIELD.to_string(), geo_facet_type);
+        }
 
         let criteria = self
             .criteria(&txn)?
@@ -60,21 +85,141 @@ impl Index {
             .transpose()?
             .unwrap_or_else(BTreeSet::new);
 
+        let distinct_attribute = self.distinct_attribute(&txn)?.map(String::from);
+
+        // The synonyms are stored as normalized word sequences, they are joined back into strings.
+        let synonyms = self
+            .synonyms(&txn)?
+            .into_iter()
+            .map(|(word, synonyms)| {
+                let synonyms = synonyms.into_iter().map(|s| s.join(" ")).collect();
+                (word.join(" "), synonyms)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let typo_tolerance = self.typo_tolerance(&txn)?;
+        let pagination = self.pagination(&txn)?;
+        let split_join_words = self.split_join_words(&txn)?;
+
         Ok(Settings {
             displayed_attributes: Some(Some(displayed_attributes)),
             searchable_attributes: Some(Some(searchable_attributes)),
             attributes_for_faceting: Some(Some(faceted_attributes)),
             ranking_rules: Some(Some(criteria)),
             stop_words: Some(Some(stop_words)),
+            distinct_attribute: Some(distinct_attribute),
+            synonyms: Some(Some(synonyms)),
+            typo_tolerance: Some(Some(typo_tolerance)),
+            pagination: Some(Some(pagination)),
+            split_join_words: Some(Some(split_join_words)),
         })
     }
 
+    pub fn typo_tolerance(&self, txn: &heed::RoTxn) -> anyhow::Result<TypoTolerance> {
+        let typo_tolerance = self
+            .main
+            .get::<_, Str, SerdeJson<TypoTolerance>>(txn, TYPO_TOLERANCE_KEY)?
+            .unwrap_or_default();
+        Ok(typo_tolerance)
+    }
+
+    fn put_typo_tolerance(
+        &self,
+        txn: &mut heed::RwTxn,
+        typo_tolerance: &TypoTolerance,
+    ) -> anyhow::Result<()> {
+        self.main.put::<_, Str, SerdeJson<TypoTolerance>>(
+            txn,
+            TYPO_TOLERANCE_KEY,
+            typo_tolerance,
+        )?;
+        Ok(())
+    }
+
+    fn delete_typo_tolerance(&self, txn: &mut heed::RwTxn) -> anyhow::Result<()> {
+        self.main.delete::<_, Str>(txn, TYPO_TOLERANCE_KEY)?;
+        Ok(())
+    }
+
+    pub fn pagination(&self, txn: &heed::RoTxn) -> anyhow::Result<Pagination> {
+        let pagination = self
+            .main
+            .get::<_, Str, SerdeJson<Pagination>>(txn, PAGINATION_KEY)?
+            .unwrap_or_default();
+        Ok(pagination)
+    }
+
+    fn put_pagination(&self, txn: &mut heed::RwTxn, pagination: &Pagination) -> anyhow::Result<()> {
+        self.main
+            .put::<_, Str, SerdeJson<Pagination>>(txn, PAGINATION_KEY, pagination)?;
+        Ok(())
+    }
+
+    fn delete_pagination(&self, txn: &mut heed::RwTxn) -> anyhow::Result<()> {
+        self.main.delete::<_, Str>(txn, PAGINATION_KEY)?;
+        Ok(())
+    }
+
+    /// Returns whether the words of the queries can match once split in two words, or joined with
+    /// the next words of the query, as `iphone` and `i phone`. It is enabled by default.
+    pub fn split_join_words(&self, txn: &heed::RoTxn) -> anyhow::Result<bool> {
+        let split_join_words = self
+            .main
+            .get::<_, Str, SerdeJson<bool>>(txn, SPLIT_JOIN_WORDS_KEY)?
+            .unwrap_or(true);
+        Ok(split_join_words)
+    }
+
+    fn put_split_join_words(&self, txn: &mut heed::RwTxn, enabled: bool) -> anyhow::Result<()> {
+        self.main
+            .put::<_, Str, SerdeJson<bool>>(txn, SPLIT_JOIN_WORDS_KEY, &enabled)?;
+        Ok(())
+    }
+
+    fn delete_split_join_words(&self, txn: &mut heed::RwTxn) -> anyhow::Result<()> {
+        self.main.delete::<_, Str>(txn, SPLIT_JOIN_WORDS_KEY)?;
+        Ok(())
+    }
+
+    /// Returns the facet type given to the `_geo` attribute, if it is faceted. The documents can
+    /// then be filtered and sorted by location.
+    pub fn geo_facet_type(&self, txn: &heed::RoTxn) -> anyhow::Result<Option<String>> {
+        let geo_facet_type = self
+            .main
+            .get::<_, Str, SerdeJson<String>>(txn, GEO_FACET_TYPE_KEY)?;
+        Ok(geo_facet_type)
+    }
+
+    fn put_geo_facet_type(
+        &self,
+        txn: &mut heed::RwTxn,
+        geo_facet_type: &str,
+    ) -> anyhow::Result<()> {
+        self.main
+            .put::<_, Str, SerdeJson<&str>>(txn, GEO_FACET_TYPE_KEY, &geo_facet_type)?;
+        Ok(())
+    }
+
+    fn delete_geo_facet_type(&self, txn: &mut heed::RwTxn) -> anyhow::Result<()> {
+        self.main.delete::<_, Str>(txn, GEO_FACET_TYPE_KEY)?;
+        Ok(())
+    }
+
+    /// Sets the update date of the index to now, it must be called by every update, including the
+    /// ones only touching the settings stored outside of milli.
+    fn bump_updated_at(&self, txn: &mut heed::RwTxn) -> heed::Result<()> {
+        self.main
+            .put::<_, Str, SerdeJson<DateTime<Utc>>>(txn, UPDATED_AT_KEY, &Utc::now())
+    }
+
+    /// Returns the documents of the page along with the number of documents of the index, read in
+    /// the same transaction.
     pub fn retrieve_documents<S: AsRef<str>>(
         &self,
         offset: usize,
         limit: usize,
         attributes_to_retrieve: Option<Vec<S>>,
-    ) -> anyhow::Result<Vec<Map<String, Value>>> {
+    ) -> anyhow::Result<(Vec<Map<String, Value>>, u64)> {
         let txn = self.read_txn()?;
 
         let fields_ids_map = self.fields_ids_map(&txn)?;
@@ -91,7 +236,72 @@ impl Index {
             documents.push(object);
         }
 
-        Ok(documents)
+        Ok((documents, self.number_of_documents(&txn)?))
+    }
+
+    /// Reads at most `page_size` documents following the document `after`, or from the first one,
+    /// and returns them as JSON lines along with the id of the last one read, or `None` once all
+    /// the documents are read. Each page is read in its own transaction, so that exporting many
+    /// documents doesn't hold a transaction for long.
+    pub fn export_documents_page<S: AsRef<str>>(
+        &self,
+        attributes_to_retrieve: Option<Vec<S>>,
+        after: Option<DocumentId>,
+        page_size: usize,
+    ) -> anyhow::Result<(Vec<Vec<u8>>, Option<DocumentId>)> {
+        let txn = self.read_txn()?;
+
+        let fields_ids_map = self.fields_ids_map(&txn)?;
+        let fields_to_display =
+            self.fields_to_display(&txn, attributes_to_retrieve, &fields_ids_map)?;
+
+        let start = match after {
+            Some(id) => Bound::Excluded(BEU32::new(id)),
+            None => Bound::Unbounded,
+        };
+
+        let mut lines = Vec::new();
+        let mut last_id = None;
+        for entry in self
+            .documents
+            .range(&txn, &(start, Bound::Unbounded))?
+            .take(page_size)
+        {
+            let (id, obkv) = entry?;
+            let object = obkv_to_json(&fields_to_display, &fields_ids_map, obkv)?;
+            let mut line = serde_json::to_vec(&object)?;
+            line.push(b'\n');
+            lines.push(line);
+            last_id = Some(id.get());
+        }
+
+        if lines.len() < page_size {
+            last_id = None;
+        }
+
+        Ok((lines, last_id))
+    }
+
+    /// Writes the settings of the index in `settings.json` and all its documents, with all their
+    /// fields and one JSON line each, in `documents.jsonl` at `path`.
+    pub fn dump(&self, path: &Path) -> anyhow::Result<()> {
+        let settings = self.settings()?;
+        serde_json::to_writer(File::create(path.join("settings.json"))?, &settings)?;
+
+        let txn = self.read_txn()?;
+        let fields_ids_map = self.fields_ids_map(&txn)?;
+        let all_fields: Vec<_> = fields_ids_map.iter().map(|(id, _)| id).collect();
+
+        let mut file = BufWriter::new(File::create(path.join("documents.jsonl"))?);
+        for entry in self.documents.range(&txn, &(..))? {
+            let (_id, obkv) = entry?;
+            let document = obkv_to_json(&all_fields, &fields_ids_map, obkv)?;
+            serde_json::to_writer(&mut file, &document)?;
+            writeln!(file)?;
+        }
+        file.flush()?;
+
+        Ok(())
     }
 
     pub fn retrieve_document<S: AsRef<str>>(
@@ -123,6 +333,99 @@ impl Index {
         }
     }
 
+    /// Returns the facet type of each attribute, so that they can be made filterable without giving
+    /// their type. The attributes that are already faceted keep their type, the type of the others
+    /// is deduced from the values of the first documents. The attributes without any value in them,
+    /// like all the attributes of an empty index, have no type.
+    pub fn infer_facet_types(
+        &self,
+        attributes: Vec<String>,
+    ) -> anyhow::Result<HashMap<String, String>> {
+        let txn = self.read_txn()?;
+        let faceted_fields = self.faceted_fields(&txn)?;
+        let fields_ids_map = self.fields_ids_map(&txn)?;
+
+        let mut facet_types = HashMap::new();
+        let mut to_infer = Vec::new();
+        for attribute in attributes {
+            match faceted_fields.get(&attribute) {
+                Some(facet_type) => {
+                    facet_types.insert(attribute, facet_type.to_string());
+                }
+                None if attribute == geo::GEO_FIELD => {
+                    facet_types.insert(attribute, geo::GEO_FACET_TYPE.to_string());
+                }
+                None => to_infer.push((attribute, ValueKinds::default())),
+            }
+        }
+
+        if !to_infer.is_empty() {
+            for entry in self
+                .documents
+                .range(&txn, &(..))?
+                .take(FACET_TYPE_SAMPLE_SIZE)
+            {
+                let (_id, obkv) = entry?;
+                for (attribute, kinds) in to_infer.iter_mut() {
+                    let value = fields_ids_map.id(attribute).and_then(|fid| obkv.get(fid));
+                    if let Some(bytes) = value {
+                        kinds.add(&serde_json::from_slice(bytes)?);
+                    }
+                }
+            }
+        }
+
+        let inferred = to_infer.into_iter().filter_map(|(attribute, kinds)| {
+            kinds
+                .facet_type()
+                .map(|facet_type| (attribute, facet_type.to_string()))
+        });
+        facet_types.extend(inferred);
+        Ok(facet_types)
+    }
+
+    /// Checks whether a document exists using the external ids only, the document isn't read.
+    pub fn document_exists(&self, doc_id: &str) -> anyhow::Result<bool> {
+        let txn = self.read_txn()?;
+        Ok(self
+            .external_documents_ids(&txn)?
+            .get(doc_id.as_bytes())
+            .is_some())
+    }
+
+    /// Retrieves the documents with the given external ids, in the same order, the documents that
+    /// don't exist are returned as `None`.
+    pub fn retrieve_documents_by_ids<S: AsRef<str>>(
+        &self,
+        doc_ids: Vec<String>,
+        attributes_to_retrieve: Option<Vec<S>>,
+    ) -> anyhow::Result<Vec<Option<Map<String, Value>>>> {
+        let txn = self.read_txn()?;
+
+        let fields_ids_map = self.fields_ids_map(&txn)?;
+
+        let fields_to_display =
+            self.fields_to_display(&txn, attributes_to_retrieve, &fields_ids_map)?;
+
+        let external_documents_ids = self.external_documents_ids(&txn)?;
+
+        let mut documents = Vec::with_capacity(doc_ids.len());
+        for doc_id in doc_ids {
+            let document = match external_documents_ids.get(doc_id.as_bytes()) {
+                Some(internal_id) => self
+                    .documents(&txn, std::iter::once(internal_id))?
+                    .into_iter()
+                    .next()
+                    .map(|(_, d)| obkv_to_json(&fields_to_display, &fields_ids_map, d))
+                    .transpose()?,
+                None => None,
+            };
+            documents.push(document);
+        }
+
+        Ok(documents)
+    }
+
     pub fn size(&self) -> u64 {
         self.env.size()
     }
@@ -150,3 +453,34 @@ impl Index {
         Ok(displayed_fields_ids)
     }
 }
+
+/// The kinds of the values found for an attribute, used to deduce its facet type.
+#[derive(Default)]
+struct ValueKinds {
+    integers: bool,
+    floats: bool,
+    others: bool,
+}
+
+impl ValueKinds {
+    fn add(&mut self, value: &Value) {
+        match value {
+            Value::Null => (),
+            Value::Number(n) if n.is_f64() => self.floats = true,
+            Value::Number(_) => self.integers = true,
+            Value::Array(values) => values.iter().for_each(|value| self.add(value)),
+            _ => self.others = true,
+        }
+    }
+
+    /// Numbers are faceted as numbers only if all the values are numbers, there is no type without
+    /// any value.
+    fn facet_type(&self) -> Option<&'static str> {
+        match (self.integers, self.floats, self.others) {
+            (false, false, false) => None,
+            (_, true, false) => Some("float"),
+            (true, false, false) => Some("integer"),
+            _ => Some("string"),
+        }
+    }
+}
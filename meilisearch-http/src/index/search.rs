@@ -1,125 +1,1156 @@
-use std::collections::{BTreeMap, HashSet};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet};
 use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 use std::time::Instant;
 
 use anyhow::bail;
 use either::Either;
 use heed::RoTxn;
 use meilisearch_tokenizer::{Analyzer, AnalyzerConfig};
-use milli::{facet::FacetValue, FacetCondition, MatchingWords};
+use milli::facet::{FacetType, FacetValue};
+use milli::{DocumentId, FacetCondition, FieldsIdsMap, MatchingWords};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
-use super::Index;
+use super::geo::{self, GeoRadius, GEO_FIELD};
+use super::{Index, TypoTolerance};
 
+/// The number of hits returned when neither the query nor the index sets a limit.
 pub const DEFAULT_SEARCH_LIMIT: usize = 20;
 
-const fn default_search_limit() -> usize {
-    DEFAULT_SEARCH_LIMIT
+/// The number of words kept around the first match when cropping an attribute.
+pub const DEFAULT_CROP_LENGTH: usize = 10;
+
+/// The ranking rules computed by milli, which can't be reordered at query time.
+pub(super) const RELEVANCY_RULES: &[&str] = &[
+    "words",
+    "typo",
+    "proximity",
+    "attribute",
+    "wordsPosition",
+    "exactness",
+];
+
+/// The most typos the engine allows on a word of a query.
+pub const MAX_TYPOS: usize = 2;
+
+pub const DEFAULT_HIGHLIGHT_PRE_TAG: &str = "<em>";
+pub const DEFAULT_HIGHLIGHT_POST_TAG: &str = "</em>";
+
+fn default_highlight_pre_tag() -> String {
+    DEFAULT_HIGHLIGHT_PRE_TAG.to_string()
 }
 
-#[derive(Deserialize)]
+fn default_highlight_post_tag() -> String {
+    DEFAULT_HIGHLIGHT_POST_TAG.to_string()
+}
+
+#[derive(Deserialize, Serialize)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct SearchQuery {
     pub q: Option<String>,
     pub offset: Option<usize>,
-    #[serde(default = "default_search_limit")]
-    pub limit: usize,
+    /// Defaults to the `pagination.defaultLimit` setting of the index, then to the
+    /// `--default-search-limit` of the server.
+    pub limit: Option<usize>,
     pub attributes_to_retrieve: Option<Vec<String>>,
+    /// Restricts the matching of the query to these searchable attributes for this search only.
+    pub attributes_to_search_on: Option<Vec<String>>,
     pub attributes_to_crop: Option<Vec<String>>,
     pub crop_length: Option<usize>,
     pub attributes_to_highlight: Option<HashSet<String>>,
-    pub filters: Option<String>,
+    #[serde(default = "default_highlight_pre_tag")]
+    pub highlight_pre_tag: String,
+    #[serde(default = "default_highlight_post_tag")]
+    pub highlight_post_tag: String,
+    pub filter: Option<String>,
     pub matches: Option<bool>,
     pub facet_filters: Option<Value>,
-    pub facet_distributions: Option<Vec<String>>,
+    pub facets_distribution: Option<Vec<String>>,
+    pub sort: Option<Vec<String>>,
+    /// Overrides the ranking rules of the index for this search only, see `parse_ranking_rules`.
+    pub ranking_rules: Option<Vec<String>>,
+    /// Caps the typos allowed on each word of the query on top of the typo tolerance of the
+    /// index, `0` only matches the exact words. It is clamped to `MAX_TYPOS` by the index actor.
+    pub max_typos: Option<usize>,
+    /// Counts the hits exactly instead of letting the engine estimate them. All the matching
+    /// documents are ranked to do so, which is more expensive on large result sets.
+    #[serde(default)]
+    pub exhaustive_nb_hits: bool,
+    /// Adds the byte offsets of the matched words to each hit, in `_matchesPosition`.
+    #[serde(default)]
+    pub show_matches_position: bool,
+    /// Only returns the primary key of the hits, the other attributes of the documents aren't
+    /// read. The attributes to retrieve, crop and highlight are ignored.
+    #[serde(default)]
+    pub ids_only: bool,
+    /// The filter of the API key of the search, it can't be set by the client and is always
+    /// combined with the other filters. It is still serialized, as part of the key of the search
+    /// in the cache.
+    #[serde(skip_deserializing)]
+    pub enforced_filter: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchResult {
     pub hits: Vec<Map<String, Value>>,
     pub nb_hits: u64,
+    pub estimated_total_hits: u64,
+    /// Whether the hits were counted exactly, they are estimated by the engine otherwise.
     pub exhaustive_nb_hits: bool,
     pub query: String,
     pub limit: usize,
     pub offset: usize,
     pub processing_time_ms: u128,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub facet_distributions: Option<BTreeMap<String, BTreeMap<FacetValue, u64>>>,
+    pub facets_distribution: Option<BTreeMap<String, BTreeMap<FacetValue, u64>>>,
+}
+
+/// Returns an error once the search is `cancelled`, it is checked between the steps of the search
+/// so that a search that timed out stops early and releases its read transaction.
+fn check_cancelled(cancelled: &AtomicBool) -> anyhow::Result<()> {
+    if cancelled.load(AtomicOrdering::Relaxed) {
+        bail!("The search was cancelled.");
+    }
+    Ok(())
 }
 
 impl Index {
-    pub fn perform_search(&self, query: SearchQuery) -> anyhow::Result<SearchResult> {
+    pub fn perform_search(
+        &self,
+        query: SearchQuery,
+        cancelled: &AtomicBool,
+    ) -> anyhow::Result<SearchResult> {
         let before_search = Instant::now();
         let rtxn = self.read_txn()?;
+        // The default limit of the index and of the server is resolved by the index actor.
+        let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
 
         let mut search = self.search(&rtxn);
 
-        if let Some(ref query) = query.q {
-            search.query(query);
+        // An empty query is a placeholder search, returning all the documents matching the filters
+        // in the order of the sort, or in their natural order.
+        let q = query.q.as_deref().filter(|q| !q.trim().is_empty());
+        if let Some(q) = q {
+            search.query(q);
+        }
+
+        let typo_tolerance = self.typo_tolerance(&rtxn)?;
+        let allowed_typos = AllowedTypos {
+            typo_tolerance: &typo_tolerance,
+            max_typos: query.max_typos,
+        };
+        let query_words = q.map(query_words).unwrap_or_default();
+        let authorize_typos = allowed_typos.any_allowed(&query_words);
+        search.authorize_typos(authorize_typos);
+        // The documents only matching thanks to more typos than allowed on a word are discarded
+        // after the search.
+        let restrict_word_typos = authorize_typos && allowed_typos.restricts(&query_words);
+        // The documents only matching thanks to a typo in an attribute where typos are disabled
+        // are discarded after the search.
+        let restrict_typos =
+            authorize_typos && q.is_some() && !typo_tolerance.disable_on_attributes.is_empty();
+        // The engine always splits and joins the words of the query, the documents only matching
+        // this way are discarded when it is disabled on the index.
+        let restrict_split_join = q.is_some() && !self.split_join_words(&rtxn)?;
+        // The documents matched by the engine are then restricted to the ones containing the quoted
+        // phrases of the query, with their words in order.
+        let phrases = q.map(query_phrases).unwrap_or_default();
+        // The documents only matching outside of the attributes to search on are discarded too.
+        let attributes_to_search_on = match query.attributes_to_search_on {
+            Some(ref attributes) if q.is_some() => {
+                self.attributes_to_search_on(&rtxn, attributes)?
+            }
+            _ => None,
+        };
+
+        let faceted_fields = self.faceted_fields(&rtxn)?;
+        let geo_faceted = self.geo_facet_type(&rtxn)?.is_some();
+        let mut sort_criteria = match query.sort {
+            Some(ref sort) => Some(parse_sort(sort, &faceted_fields, geo_faceted)?),
+            None => None,
+        };
+
+        // The custom rules of the override are applied after the sort, if any.
+        if let Some(ref rules) = query.ranking_rules {
+            let ranking_criteria = parse_ranking_rules(rules, &self.criteria(&rtxn)?)?;
+            if !ranking_criteria.is_empty() {
+                sort_criteria
+                    .get_or_insert_with(Vec::new)
+                    .extend(ranking_criteria);
+            }
         }
 
-        search.limit(query.limit);
-        search.offset(query.offset.unwrap_or_default());
+        // The geo filters are applied on the documents returned by the engine.
+        let (filter, geo_radiuses) = match query.filter {
+            Some(ref filter) => geo::extract_geo_radius(filter)?,
+            None => (None, Vec::new()),
+        };
+        if !geo_radiuses.is_empty() && !geo_faceted {
+            bail!("The `_geo` attribute must be faceted to filter the documents by location.");
+        }
+
+        // All the matching documents must be ranked to be sorted or counted exactly, the window of
+        // documents to return is selected afterwards.
+        let rank_all = sort_criteria.is_some() || query.exhaustive_nb_hits;
+        // The documents returned by the engine are then restricted by these filters, the engine is
+        // asked for more documents until the window is filled.
+        let post_filter = restrict_typos
+            || restrict_word_typos
+            || restrict_split_join
+            || !phrases.is_empty()
+            || attributes_to_search_on.is_some()
+            || !geo_radiuses.is_empty();
+        let offset = query.offset.unwrap_or_default();
+        let window = offset.saturating_add(limit);
+
+        let facet_condition = match query.facet_filters {
+            Some(ref facets) => parse_facets(facets, self, &rtxn)?,
+            None => None,
+        };
+
+        let filter_condition = match filter {
+            Some(ref filter) => Some(FacetCondition::from_str(&rtxn, &self.0, filter)?),
+            None => None,
+        };
+
+        let condition = match (facet_condition, filter_condition) {
+            (Some(facets), Some(filter)) => {
+                Some(FacetCondition::And(Box::new(facets), Box::new(filter)))
+            }
+            (facets, filter) => facets.or(filter),
+        };
+
+        let enforced_condition = match query.enforced_filter {
+            Some(ref filter) => Some(FacetCondition::from_str(&rtxn, &self.0, filter)?),
+            None => None,
+        };
 
-        if let Some(ref facets) = query.facet_filters {
-            if let Some(facets) = parse_facets(facets, self, &rtxn)? {
-                search.facet_condition(facets);
+        let condition = match (enforced_condition, condition) {
+            (Some(enforced), Some(condition)) => {
+                Some(FacetCondition::And(Box::new(enforced), Box::new(condition)))
             }
+            (enforced, condition) => enforced.or(condition),
+        };
+
+        if let Some(condition) = condition {
+            search.facet_condition(condition);
         }
 
+        let mut requested = if rank_all {
+            self.number_of_documents(&rtxn)? as usize
+        } else if post_filter {
+            window.max(1)
+        } else {
+            limit
+        };
+        search.limit(requested);
+        search.offset(if rank_all || post_filter { 0 } else { offset });
+
         let milli::SearchResult {
             documents_ids,
             matching_words,
-            candidates,
+            mut candidates,
             ..
         } = search.execute()?;
+        check_cancelled(cancelled)?;
         let mut documents = Vec::new();
         let fields_ids_map = self.fields_ids_map(&rtxn).unwrap();
 
-        let fields_to_display =
-            self.fields_to_display(&rtxn, query.attributes_to_retrieve, &fields_ids_map)?;
+        // Returns the documents returned by the engine passing all the filters, in the same order.
+        let filter_documents = |documents_ids: Vec<DocumentId>| -> anyhow::Result<Vec<_>> {
+            let q = q.unwrap_or_default();
+            let documents_ids = if restrict_typos {
+                self.discard_disabled_typos(
+                    &rtxn,
+                    q,
+                    &typo_tolerance.disable_on_attributes,
+                    &fields_ids_map,
+                    &matching_words,
+                    documents_ids,
+                )?
+            } else {
+                documents_ids
+            };
+
+            let documents_ids = if restrict_word_typos {
+                self.discard_excess_typos(
+                    &rtxn,
+                    &fields_ids_map,
+                    &query_words,
+                    allowed_typos,
+                    documents_ids,
+                )?
+            } else {
+                documents_ids
+            };
+
+            let documents_ids = if restrict_split_join {
+                self.discard_split_join_words(
+                    &rtxn,
+                    &fields_ids_map,
+                    &query_words,
+                    allowed_typos,
+                    documents_ids,
+                )?
+            } else {
+                documents_ids
+            };
+
+            let documents_ids = match attributes_to_search_on {
+                Some(ref attributes) => self.discard_outside_attributes(
+                    &rtxn,
+                    &fields_ids_map,
+                    attributes,
+                    &matching_words,
+                    documents_ids,
+                )?,
+                None => documents_ids,
+            };
+
+            let documents_ids = if phrases.is_empty() {
+                documents_ids
+            } else {
+                self.discard_missing_phrases(
+                    &rtxn,
+                    &fields_ids_map,
+                    &phrases,
+                    attributes_to_search_on.as_ref(),
+                    documents_ids,
+                )?
+            };
+
+            if geo_radiuses.is_empty() {
+                Ok(documents_ids)
+            } else {
+                self.filter_geo_radius(&rtxn, &fields_ids_map, documents_ids, &geo_radiuses)
+            }
+        };
+
+        // The engine went through all the matching documents once they are all ranked or when
+        // they don't fill the requested batch.
+        let (documents_ids, exhausted) = if post_filter {
+            let mut kept = Vec::new();
+            let mut batch = documents_ids;
+            let mut engine_offset = 0;
+            loop {
+                let returned = batch.len();
+                // The documents discarded by any of the filters aren't counted in the facets
+                // distribution nor in the estimated number of hits.
+                for &id in &batch {
+                    candidates.remove(id);
+                }
+                let batch_kept = filter_documents(batch)?;
+                for &id in &batch_kept {
+                    candidates.insert(id);
+                }
+                kept.extend(batch_kept);
+
+                if rank_all || returned < requested {
+                    break (kept, true);
+                } else if kept.len() >= window {
+                    break (kept, false);
+                }
+
+                engine_offset += returned;
+                requested = requested.saturating_mul(2);
+                search.offset(engine_offset);
+                search.limit(requested);
+                batch = search.execute()?.documents_ids;
+                check_cancelled(cancelled)?;
+            }
+        } else {
+            let exhausted = rank_all || documents_ids.len() < limit;
+            (documents_ids, exhausted)
+        };
+
+        // The candidates of the engine are exact once it went through all the matching documents,
+        // the filtered documents are only counted exactly in that case as well.
+        let exhaustive_nb_hits = exhausted;
+        let nb_hits = if exhausted && (rank_all || post_filter) {
+            documents_ids.len() as u64
+        } else {
+            candidates.len()
+        };
+
+        let documents_ids = match sort_criteria {
+            Some(ref sort_criteria) => {
+                let sorted = self.sort_documents(
+                    &rtxn,
+                    &fields_ids_map,
+                    documents_ids,
+                    sort_criteria,
+                    window,
+                    cancelled,
+                )?;
+                sorted.into_iter().skip(offset).take(limit).collect()
+            }
+            None if rank_all || post_filter => {
+                documents_ids.into_iter().skip(offset).take(limit).collect()
+            }
+            None => documents_ids,
+        };
+
+        let fields_to_display = if query.ids_only {
+            self.primary_key(&rtxn)?
+                .and_then(|primary_key| fields_ids_map.id(primary_key))
+                .into_iter()
+                .collect()
+        } else {
+            self.fields_to_display(&rtxn, query.attributes_to_retrieve, &fields_ids_map)?
+        };
 
         let stop_words = fst::Set::default();
-        let highlighter = Highlighter::new(&stop_words);
+        let highlighter = Highlighter::new(
+            &stop_words,
+            &query.highlight_pre_tag,
+            &query.highlight_post_tag,
+        );
+
+        let crop_length = query.crop_length.unwrap_or(DEFAULT_CROP_LENGTH);
 
         for (_id, obkv) in self.documents(&rtxn, documents_ids)? {
             let mut object =
                 milli::obkv_to_json(&fields_to_display, &fields_ids_map, obkv).unwrap();
-            if let Some(ref attributes_to_highlight) = query.attributes_to_highlight {
-                highlighter.highlight_record(&mut object, &matching_words, attributes_to_highlight);
+            if query.ids_only {
+                documents.push(object);
+                continue;
+            }
+            if query.attributes_to_highlight.is_some() || query.attributes_to_crop.is_some() {
+                let mut formatted = object.clone();
+                // Cropping is done first so that the highlight tags don't count as words.
+                if let Some(ref attributes_to_crop) = query.attributes_to_crop {
+                    highlighter.crop_record(
+                        &mut formatted,
+                        &matching_words,
+                        attributes_to_crop,
+                        crop_length,
+                    );
+                }
+                if let Some(ref attributes_to_highlight) = query.attributes_to_highlight {
+                    highlighter.highlight_record(
+                        &mut formatted,
+                        &matching_words,
+                        attributes_to_highlight,
+                    );
+                }
+                object.insert("_formatted".to_string(), Value::Object(formatted));
+            }
+            if query.show_matches_position {
+                let positions = highlighter.matches_position(&object, &matching_words);
+                object.insert(
+                    "_matchesPosition".to_string(),
+                    serde_json::to_value(positions)?,
+                );
             }
             documents.push(object);
         }
 
-        let nb_hits = candidates.len();
-
-        let facet_distributions = match query.facet_distributions {
+        let facets_distribution = match query.facets_distribution {
             Some(ref fields) => {
+                let fields = if fields.iter().any(|f| f == "*") {
+                    faceted_fields.keys().cloned().collect()
+                } else {
+                    if let Some(field) = fields.iter().find(|f| !faceted_fields.contains_key(*f)) {
+                        bail!("Attribute {:?} is not faceted.", field);
+                    }
+                    fields.clone()
+                };
+
                 let mut facet_distribution = self.facets_distribution(&rtxn);
-                if fields.iter().all(|f| f != "*") {
-                    facet_distribution.facets(fields);
+                facet_distribution.facets(&fields);
+                let mut distribution = facet_distribution.candidates(candidates).execute()?;
+                // Facets without any matching documents are returned as empty maps.
+                for field in fields {
+                    distribution.entry(field).or_default();
                 }
-                Some(facet_distribution.candidates(candidates).execute()?)
+                Some(distribution)
             }
             None => None,
         };
 
         let result = SearchResult {
-            exhaustive_nb_hits: false, // not implemented yet
+            exhaustive_nb_hits,
             hits: documents,
             nb_hits,
+            estimated_total_hits: nb_hits,
             query: query.q.clone().unwrap_or_default(),
-            limit: query.limit,
-            offset: query.offset.unwrap_or_default(),
+            limit,
+            offset,
             processing_time_ms: before_search.elapsed().as_millis(),
-            facet_distributions,
+            facets_distribution,
         };
         Ok(result)
     }
 }
 
+/// The typos allowed on the words of a query, by the typo tolerance of the index and the
+/// `maxTypos` of the search. The engine allows one typo on words of 5 characters and two typos on
+/// words of 9 characters, a word can't be allowed more than that.
+#[derive(Clone, Copy)]
+struct AllowedTypos<'a> {
+    typo_tolerance: &'a TypoTolerance,
+    max_typos: Option<usize>,
+}
+
+impl AllowedTypos<'_> {
+    fn engine(word: &str) -> usize {
+        TypoTolerance::default().allowed_typos(word) as usize
+    }
+
+    /// The number of typos allowed on `word`.
+    fn get(&self, word: &str) -> usize {
+        let allowed_typos =
+            (self.typo_tolerance.allowed_typos(word) as usize).min(Self::engine(word));
+        self.max_typos
+            .map_or(allowed_typos, |max_typos| allowed_typos.min(max_typos))
+    }
+
+    /// Whether a typo is allowed on one of the `words`, the engine's typo tolerance is disabled
+    /// otherwise.
+    fn any_allowed(&self, words: &[String]) -> bool {
+        words.iter().any(|word| self.get(word) > 0)
+    }
+
+    /// Whether the engine allows more typos than this on one of the `words`.
+    fn restricts(&self, words: &[String]) -> bool {
+        words.iter().any(|word| self.get(word) < Self::engine(word))
+    }
+}
+
+/// Returns the words of `query`, as they are analyzed in the documents.
+fn query_words(query: &str) -> Vec<String> {
+    let stop_words = fst::Set::default();
+    let mut config = AnalyzerConfig::default();
+    config.stop_words(&stop_words);
+    let analyzer = Analyzer::new(config);
+    value_words(&analyzer, &Value::String(query.to_string()))
+}
+
+impl Index {
+    /// Keeps the `documents_ids` matching `query` without typos, or with a typo in one of the
+    /// attributes outside of `disabled_attributes`, in the same order.
+    fn discard_disabled_typos(
+        &self,
+        rtxn: &RoTxn,
+        query: &str,
+        disabled_attributes: &BTreeSet<String>,
+        fields_ids_map: &FieldsIdsMap,
+        matching_words: &MatchingWords,
+        documents_ids: Vec<DocumentId>,
+    ) -> anyhow::Result<Vec<DocumentId>> {
+        let mut exact_search = self.search(rtxn);
+        exact_search.query(query);
+        exact_search.authorize_typos(false);
+        exact_search.limit(self.number_of_documents(rtxn)? as usize);
+        let exact = exact_search.execute()?;
+        let exact_ids: HashSet<_> = exact.documents_ids.into_iter().collect();
+
+        let stop_words = fst::Set::default();
+        let mut config = AnalyzerConfig::default();
+        config.stop_words(&stop_words);
+        let analyzer = Analyzer::new(config);
+        // A word matched by the typo tolerant search but not by the exact one contains a typo.
+        let has_typo = |value: &Value| {
+            value_words(&analyzer, value)
+                .iter()
+                .any(|word| matching_words.matches(word) && !exact.matching_words.matches(word))
+        };
+
+        let mut matching_ids = Vec::with_capacity(documents_ids.len());
+        for (id, obkv) in self.documents(rtxn, documents_ids)? {
+            let matches = exact_ids.contains(&id)
+                || obkv.iter().any(|(field_id, value)| {
+                    let tolerant = fields_ids_map
+                        .name(field_id)
+                        .map_or(false, |name| !disabled_attributes.contains(name));
+                    tolerant
+                        && serde_json::from_slice::<Value>(value)
+                            .map_or(false, |value| has_typo(&value))
+                });
+            if matches {
+                matching_ids.push(id);
+            }
+        }
+
+        Ok(matching_ids)
+    }
+
+    /// Keeps the `documents_ids` of the documents with a searchable word matching one of the
+    /// `query_words` within the typos allowed on it, the last one being a prefix, in the same
+    /// order.
+    fn discard_excess_typos(
+        &self,
+        rtxn: &RoTxn,
+        fields_ids_map: &FieldsIdsMap,
+        query_words: &[String],
+        allowed_typos: AllowedTypos,
+        documents_ids: Vec<DocumentId>,
+    ) -> anyhow::Result<Vec<DocumentId>> {
+        let stop_words = fst::Set::default();
+        let mut config = AnalyzerConfig::default();
+        config.stop_words(&stop_words);
+        let analyzer = Analyzer::new(config);
+
+        let query_words: Vec<_> = query_words
+            .iter()
+            .map(|word| (word.as_str(), allowed_typos.get(word)))
+            .collect();
+        let last = query_words.len().saturating_sub(1);
+        let matches_query_word = |word: &String| {
+            query_words
+                .iter()
+                .enumerate()
+                .any(|(i, &(query_word, allowed_typos))| {
+                    (i == last && word.starts_with(query_word))
+                        || levenshtein(word, query_word) <= allowed_typos
+                })
+        };
+
+        let searchable_fields = self.searchable_fields(rtxn)?;
+        let is_searchable = |name: &str| {
+            searchable_fields
+                .as_ref()
+                .map_or(true, |fields| fields.contains(&name))
+        };
+
+        let mut matching_ids = Vec::with_capacity(documents_ids.len());
+        for (id, obkv) in self.documents(rtxn, documents_ids)? {
+            let matches = obkv.iter().any(|(field_id, value)| {
+                fields_ids_map.name(field_id).map_or(false, is_searchable)
+                    && serde_json::from_slice::<Value>(value).map_or(false, |value| {
+                        value_words(&analyzer, &value)
+                            .iter()
+                            .any(matches_query_word)
+                    })
+            });
+            if matches {
+                matching_ids.push(id);
+            }
+        }
+
+        Ok(matching_ids)
+    }
+
+    /// Keeps the `documents_ids` of the documents matching each of the `query_words` as it is, or
+    /// with the typos allowed on it, in the same order. The engine drops the last words of the
+    /// query first, a document is only discarded if it has a word of the query split in two of
+    /// its words, or joined with the previous or the next word of the query, but not the word
+    /// itself.
+    fn discard_split_join_words(
+        &self,
+        rtxn: &RoTxn,
+        fields_ids_map: &FieldsIdsMap,
+        query_words: &[String],
+        allowed_typos: AllowedTypos,
+        documents_ids: Vec<DocumentId>,
+    ) -> anyhow::Result<Vec<DocumentId>> {
+        let stop_words = fst::Set::default();
+        let mut config = AnalyzerConfig::default();
+        config.stop_words(&stop_words);
+        let analyzer = Analyzer::new(config);
+
+        let query_words: Vec<_> = query_words
+            .iter()
+            .map(|word| (word.as_str(), allowed_typos.get(word)))
+            .collect();
+        // The last word of the query is a prefix.
+        let last = query_words.len().saturating_sub(1);
+
+        let searchable_fields = self.searchable_fields(rtxn)?;
+        let is_searchable = |name: &str| {
+            searchable_fields
+                .as_ref()
+                .map_or(true, |fields| fields.contains(&name))
+        };
+
+        let mut matching_ids = Vec::with_capacity(documents_ids.len());
+        for (id, obkv) in self.documents(rtxn, documents_ids)? {
+            let mut words = HashSet::new();
+            for (field_id, value) in obkv.iter() {
+                if fields_ids_map.name(field_id).map_or(false, is_searchable) {
+                    if let Ok(value) = serde_json::from_slice::<Value>(value) {
+                        words.extend(value_words(&analyzer, &value));
+                    }
+                }
+            }
+
+            let matches_word = |i: usize| {
+                let (query_word, allowed_typos) = query_words[i];
+                words.iter().any(|word| {
+                    (i == last && word.starts_with(query_word))
+                        || levenshtein(word, query_word) <= allowed_typos
+                })
+            };
+            let is_split = |i: usize| {
+                let (query_word, _) = query_words[i];
+                query_word.char_indices().skip(1).any(|(at, _)| {
+                    words.contains(&query_word[..at]) && words.contains(&query_word[at..])
+                })
+            };
+            let is_joined = |i: usize, j: usize| {
+                let joined = format!("{}{}", query_words[i].0, query_words[j].0);
+                words.contains(&joined)
+            };
+
+            let matches = (0..query_words.len()).all(|i| {
+                matches_word(i)
+                    || !(is_split(i)
+                        || (i > 0 && is_joined(i - 1, i))
+                        || (i < last && is_joined(i, i + 1)))
+            });
+            if matches {
+                matching_ids.push(id);
+            }
+        }
+
+        Ok(matching_ids)
+    }
+
+    /// Checks that the `attributes` are searchable, and returns them unless they include all the
+    /// searchable attributes with `*`. All the attributes of the documents are searchable when the
+    /// searchable attributes are not set.
+    fn attributes_to_search_on(
+        &self,
+        rtxn: &RoTxn,
+        attributes: &[String],
+    ) -> anyhow::Result<Option<HashSet<String>>> {
+        if attributes.iter().any(|attribute| attribute == "*") {
+            return Ok(None);
+        }
+        let searchable_fields = self.searchable_fields(rtxn)?;
+        let fields_ids_map = self.fields_ids_map(rtxn)?;
+        let is_searchable = |attribute: &str| match searchable_fields {
+            Some(ref searchable_fields) => searchable_fields.contains(&attribute),
+            None => fields_ids_map.id(attribute).is_some(),
+        };
+        if let Some(attribute) = attributes
+            .iter()
+            .find(|attribute| !is_searchable(attribute))
+        {
+            bail!("Attribute {:?} is not searchable.", attribute);
+        }
+        Ok(Some(attributes.iter().cloned().collect()))
+    }
+
+    /// Keeps the `documents_ids` of the documents with a word of the query in one of the
+    /// `attributes`, in the same order.
+    fn discard_outside_attributes(
+        &self,
+        rtxn: &RoTxn,
+        fields_ids_map: &FieldsIdsMap,
+        attributes: &HashSet<String>,
+        matching_words: &MatchingWords,
+        documents_ids: Vec<DocumentId>,
+    ) -> anyhow::Result<Vec<DocumentId>> {
+        let stop_words = fst::Set::default();
+        let mut config = AnalyzerConfig::default();
+        config.stop_words(&stop_words);
+        let analyzer = Analyzer::new(config);
+
+        let mut matching_ids = Vec::with_capacity(documents_ids.len());
+        for (id, obkv) in self.documents(rtxn, documents_ids)? {
+            let matches = obkv.iter().any(|(field_id, value)| {
+                let searched = fields_ids_map
+                    .name(field_id)
+                    .map_or(false, |name| attributes.contains(name));
+                searched
+                    && serde_json::from_slice::<Value>(value).map_or(false, |value| {
+                        value_words(&analyzer, &value)
+                            .iter()
+                            .any(|word| matching_words.matches(word))
+                    })
+            });
+            if matches {
+                matching_ids.push(id);
+            }
+        }
+
+        Ok(matching_ids)
+    }
+
+    /// Keeps the `documents_ids` of the documents containing all the `phrases` in one of their
+    /// searchable attributes, or of the `attributes_to_search_on` if any. The order of the
+    /// documents is kept.
+    fn discard_missing_phrases(
+        &self,
+        rtxn: &RoTxn,
+        fields_ids_map: &FieldsIdsMap,
+        phrases: &[Vec<String>],
+        attributes_to_search_on: Option<&HashSet<String>>,
+        documents_ids: Vec<DocumentId>,
+    ) -> anyhow::Result<Vec<DocumentId>> {
+        let searchable_fields = self.searchable_fields(rtxn)?;
+        let is_searchable = |name: &str| match attributes_to_search_on {
+            Some(attributes) => attributes.contains(name),
+            None => searchable_fields
+                .as_ref()
+                .map_or(true, |fields| fields.contains(&name)),
+        };
+
+        let stop_words = fst::Set::default();
+        let mut config = AnalyzerConfig::default();
+        config.stop_words(&stop_words);
+        let analyzer = Analyzer::new(config);
+
+        let mut matching_ids = Vec::with_capacity(documents_ids.len());
+        for (id, obkv) in self.documents(rtxn, documents_ids)? {
+            let mut values = Vec::new();
+            for (field_id, value) in obkv.iter() {
+                if fields_ids_map.name(field_id).map_or(false, is_searchable) {
+                    values.push(serde_json::from_slice::<Value>(value)?);
+                }
+            }
+            let matches = phrases.iter().all(|phrase| {
+                values
+                    .iter()
+                    .any(|value| contains_phrase(&analyzer, value, phrase))
+            });
+            if matches {
+                matching_ids.push(id);
+            }
+        }
+
+        Ok(matching_ids)
+    }
+
+    /// Returns the `documents_ids` located in all the `geo_radiuses`, in the same order.
+    fn filter_geo_radius(
+        &self,
+        rtxn: &RoTxn,
+        fields_ids_map: &FieldsIdsMap,
+        documents_ids: Vec<DocumentId>,
+        geo_radiuses: &[GeoRadius],
+    ) -> anyhow::Result<Vec<DocumentId>> {
+        let geo_field_id = match fields_ids_map.id(GEO_FIELD) {
+            Some(field_id) => field_id,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut kept = Vec::new();
+        for (id, obkv) in self.documents(rtxn, documents_ids)? {
+            let geo = match obkv.get(geo_field_id) {
+                Some(bytes) => serde_json::from_slice::<Value>(bytes)?,
+                None => Value::Null,
+            };
+            if geo_radiuses.iter().all(|radius| radius.contains(&geo)) {
+                kept.push(id);
+            }
+        }
+        Ok(kept)
+    }
+
+    /// Returns the `window` first `documents_ids` according to the `sort_criteria`, documents that
+    /// are equal for all the criteria keep their relevancy order. Documents in which a sort
+    /// attribute is missing are placed after the others. The documents sorted by geo point are
+    /// ordered by their distance to the point.
+    ///
+    /// Only the sorted attributes of the documents are deserialized, and only the `window` best
+    /// documents seen so far are kept.
+    fn sort_documents(
+        &self,
+        rtxn: &RoTxn,
+        fields_ids_map: &FieldsIdsMap,
+        documents_ids: Vec<DocumentId>,
+        sort_criteria: &[SortCriterion],
+        window: usize,
+        cancelled: &AtomicBool,
+    ) -> anyhow::Result<Vec<DocumentId>> {
+        if window == 0 {
+            return Ok(Vec::new());
+        }
+
+        let fields_ids = sort_criteria
+            .iter()
+            .map(|criterion| fields_ids_map.id(&criterion.field))
+            .collect::<Vec<_>>();
+
+        // The worst of the best documents is on top of the heap, it is replaced by any better one.
+        let mut best = BinaryHeap::with_capacity(window.min(documents_ids.len()) + 1);
+        let mut rank = 0;
+        for ids in documents_ids.chunks(SORT_BATCH_SIZE) {
+            check_cancelled(cancelled)?;
+            for (id, obkv) in self.documents(rtxn, ids.iter().copied())? {
+                let mut values = Vec::with_capacity(fields_ids.len());
+                for (criterion, field_id) in sort_criteria.iter().zip(&fields_ids) {
+                    let value = match field_id.and_then(|fid| obkv.get(fid)) {
+                        Some(bytes) => Some(serde_json::from_slice::<Value>(bytes)?),
+                        None => None,
+                    };
+                    let value = match criterion.geo_point {
+                        Some(point) => value
+                            .as_ref()
+                            .and_then(geo::geo_point)
+                            .map(|location| Value::from(geo::distance(point, location))),
+                        None => value,
+                    };
+                    values.push(value);
+                }
+
+                best.push(SortedDocument {
+                    criteria: sort_criteria,
+                    values,
+                    rank,
+                    id,
+                });
+                if best.len() > window {
+                    best.pop();
+                }
+                rank += 1;
+            }
+        }
+
+        Ok(best
+            .into_sorted_vec()
+            .into_iter()
+            .map(|document| document.id)
+            .collect())
+    }
+}
+
+/// The number of documents read at once when they are sorted.
+const SORT_BATCH_SIZE: usize = 1000;
+
+/// A document ordered by the values of its sorted attributes, then by its relevancy `rank`.
+struct SortedDocument<'a> {
+    criteria: &'a [SortCriterion],
+    values: Vec<Option<Value>>,
+    rank: usize,
+    id: DocumentId,
+}
+
+impl Ord for SortedDocument<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.criteria
+            .iter()
+            .zip(self.values.iter().zip(&other.values))
+            .map(|(criterion, (a, b))| match (a, b) {
+                (Some(a), Some(b)) if criterion.ascending => compare_values(a, b),
+                (Some(a), Some(b)) => compare_values(b, a),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            })
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+            .then(self.rank.cmp(&other.rank))
+    }
+}
+
+impl PartialOrd for SortedDocument<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for SortedDocument<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortedDocument<'_> {}
+
+struct SortCriterion {
+    field: String,
+    ascending: bool,
+    /// Set when the documents are sorted by their distance to this point, `field` is `_geo`.
+    geo_point: Option<(f64, f64)>,
+}
+
+/// Parses sort criteria of the form `attribute:asc` or `attribute:desc`, only faceted attributes
+/// can be sorted. The documents are sorted by their distance to a point with
+/// `_geoPoint(lat, lng):asc`, which requires `_geo` to be faceted.
+fn parse_sort(
+    sort: &[String],
+    faceted_fields: &HashMap<String, FacetType>,
+    geo_faceted: bool,
+) -> anyhow::Result<Vec<SortCriterion>> {
+    sort.iter()
+        .map(|criterion| {
+            let mut parts = criterion.rsplitn(2, ':');
+            let (order, field) = match (parts.next(), parts.next()) {
+                (Some(order), Some(field)) => (order, field),
+                _ => bail!(
+                    "Invalid sort criterion {:?}, expected `attribute:asc` or `attribute:desc`",
+                    criterion
+                ),
+            };
+            let ascending = match order {
+                "asc" => true,
+                "desc" => false,
+                _ => bail!("Invalid sort order {:?}, expected `asc` or `desc`", order),
+            };
+            let (field, geo_point) = match geo::parse_geo_point(field) {
+                Some(_) if !geo_faceted => {
+                    bail!("The `_geo` attribute must be faceted to sort the documents by location.")
+                }
+                Some(point) => (GEO_FIELD, Some(point?)),
+                None => (field, None),
+            };
+            if geo_point.is_none() && !faceted_fields.contains_key(field) {
+                bail!(
+                    "Attribute {:?} is not sortable, only faceted attributes can be sorted.",
+                    field
+                );
+            }
+            Ok(SortCriterion {
+                field: field.to_string(),
+                ascending,
+                geo_point,
+            })
+        })
+        .collect()
+}
+
+/// Parses a query-time override of the ranking rules. Only the custom `asc(attribute)` and
+/// `desc(attribute)` rules can be changed: they must be placed first and are applied on top of the
+/// relevancy computed by milli. The relevancy rules are optional, but when they are given they must
+/// be in the order configured for the index.
+fn parse_ranking_rules(
+    rules: &[String],
+    index_criteria: &[milli::Criterion],
+) -> anyhow::Result<Vec<SortCriterion>> {
+    let mut criteria = Vec::new();
+    let mut relevancy_rules = Vec::new();
+    for rule in rules {
+        if RELEVANCY_RULES.contains(&rule.as_str()) {
+            relevancy_rules.push(rule.clone());
+            continue;
+        }
+
+        let custom_field = |prefix: &str| {
+            rule.strip_prefix(prefix)
+                .and_then(|rest| rest.strip_suffix(')'))
+                .filter(|field| !field.is_empty())
+        };
+        let (field, ascending) = match (custom_field("asc("), custom_field("desc(")) {
+            (Some(field), _) => (field.to_string(), true),
+            (_, Some(field)) => (field.to_string(), false),
+            _ => bail!("Invalid ranking rule {:?}", rule),
+        };
+        if !relevancy_rules.is_empty() {
+            bail!(
+                "Ranking rule {:?} must be placed before the relevancy rules to be applied at query time",
+                rule
+            );
+        }
+        criteria.push(SortCriterion {
+            field,
+            ascending,
+            geo_point: None,
+        });
+    }
+
+    if !relevancy_rules.is_empty() {
+        let configured: Vec<_> = index_criteria
+            .iter()
+            .map(|criterion| criterion.to_string())
+            .filter(|criterion| RELEVANCY_RULES.contains(&criterion.as_str()))
+            .collect();
+        if relevancy_rules != configured {
+            bail!(
+                "The relevancy rules can't be reordered at query time, expected {:?}",
+                configured
+            );
+        }
+    }
+
+    Ok(criteria)
+}
+
+/// Returns the words of the quoted phrases of `query`, an unterminated quote runs until the end
+/// of the query.
+fn query_phrases(query: &str) -> Vec<Vec<String>> {
+    let stop_words = fst::Set::default();
+    let mut config = AnalyzerConfig::default();
+    config.stop_words(&stop_words);
+    let analyzer = Analyzer::new(config);
+
+    query
+        .split('"')
+        .skip(1)
+        .step_by(2)
+        .map(|phrase| value_words(&analyzer, &Value::String(phrase.to_string())))
+        .filter(|words| !words.is_empty())
+        .collect()
+}
+
+/// Returns whether one of the strings contained in `value` has the words of `phrase` in order.
+fn contains_phrase<A: AsRef<[u8]>>(
+    analyzer: &Analyzer<A>,
+    value: &Value,
+    phrase: &[String],
+) -> bool {
+    match value {
+        Value::String(_) => value_words(analyzer, value)
+            .windows(phrase.len())
+            .any(|words| words == phrase),
+        Value::Array(values) => values
+            .iter()
+            .any(|value| contains_phrase(analyzer, value, phrase)),
+        Value::Object(object) => object
+            .values()
+            .any(|value| contains_phrase(analyzer, value, phrase)),
+        _ => false,
+    }
+}
+
+/// Returns the words of the strings contained in `value`.
+fn value_words<A: AsRef<[u8]>>(analyzer: &Analyzer<A>, value: &Value) -> Vec<String> {
+    match value {
+        Value::String(string) => analyzer
+            .analyze(string)
+            .tokens()
+            .filter(|token| token.is_word())
+            .map(|token| token.text().to_string())
+            .collect(),
+        Value::Array(values) => values
+            .iter()
+            .flat_map(|value| value_words(analyzer, value))
+            .collect(),
+        Value::Object(object) => object
+            .values()
+            .flat_map(|value| value_words(analyzer, value))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the number of characters to insert, delete or substitute to turn `a` into `b`.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut distances: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut previous = distances[0];
+        distances[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous + (a != *b) as usize;
+            previous = distances[j + 1];
+            distances[j + 1] = substitution.min(previous + 1).min(distances[j] + 1);
+        }
+    }
+    distances[b.len()]
+}
+
+/// Orders numbers before strings, and other values last.
+fn compare_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Number(_), _) => Ordering::Less,
+        (_, Value::Number(_)) => Ordering::Greater,
+        (Value::String(_), _) => Ordering::Less,
+        (_, Value::String(_)) => Ordering::Greater,
+        _ => Ordering::Equal,
+    }
+}
+
 fn parse_facets_array(
     txn: &RoTxn,
     index: &Index,
@@ -149,18 +1180,35 @@ fn parse_facets_array(
     FacetCondition::from_array(txn, &index.0, ands)
 }
 
+/// The position of a matched word in a string attribute. The offsets are in bytes, not in
+/// characters, so that they can be used to slice the UTF-8 string.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MatchPosition {
+    pub start: usize,
+    pub length: usize,
+    /// The position of the string in the attribute, when the attribute is an array.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+}
+
 pub struct Highlighter<'a, A> {
     analyzer: Analyzer<'a, A>,
+    pre_tag: &'a str,
+    post_tag: &'a str,
 }
 
 impl<'a, A: AsRef<[u8]>> Highlighter<'a, A> {
-    pub fn new(stop_words: &'a fst::Set<A>) -> Self {
+    pub fn new(stop_words: &'a fst::Set<A>, pre_tag: &'a str, post_tag: &'a str) -> Self {
         let mut config = AnalyzerConfig::default();
         config.stop_words(stop_words);
 
         let analyzer = Analyzer::new(config);
 
-        Self { analyzer }
+        Self {
+            analyzer,
+            pre_tag,
+            post_tag,
+        }
     }
 
     pub fn highlight_value(&self, value: Value, words_to_highlight: &MatchingWords) -> Value {
@@ -175,11 +1223,11 @@ impl<'a, A: AsRef<[u8]>> Highlighter<'a, A> {
                     if token.is_word() {
                         let to_highlight = words_to_highlight.matches(token.text());
                         if to_highlight {
-                            string.push_str("<mark>")
+                            string.push_str(self.pre_tag)
                         }
                         string.push_str(word);
                         if to_highlight {
-                            string.push_str("</mark>")
+                            string.push_str(self.post_tag)
                         }
                     } else {
                         string.push_str(word);
@@ -202,6 +1250,124 @@ impl<'a, A: AsRef<[u8]>> Highlighter<'a, A> {
         }
     }
 
+    /// Crops the strings of `value` to a window of `crop_length` words, centered on the first
+    /// word matching the query, or starting at the beginning of the string if there is no match.
+    pub fn crop_value(
+        &self,
+        value: Value,
+        matching_words: &MatchingWords,
+        crop_length: usize,
+    ) -> Value {
+        match value {
+            Value::String(old_string) => {
+                let analyzed = self.analyzer.analyze(&old_string);
+                let tokens = analyzed.reconstruct().collect::<Vec<_>>();
+                let words = tokens
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (_, token))| token.is_word())
+                    .map(|(i, _)| i)
+                    .collect::<Vec<_>>();
+
+                if words.len() <= crop_length {
+                    return Value::String(old_string);
+                } else if crop_length == 0 {
+                    return Value::String(String::new());
+                }
+
+                let first_match = words
+                    .iter()
+                    .position(|&i| matching_words.matches(tokens[i].1.text()))
+                    .unwrap_or(0);
+                let start = first_match
+                    .saturating_sub(crop_length / 2)
+                    .min(words.len() - crop_length);
+                let end = start + crop_length;
+
+                // keep the separators between the words of the window, but not around it
+                let string = tokens[words[start]..=words[end - 1]]
+                    .iter()
+                    .map(|(word, _)| *word)
+                    .collect::<String>();
+                Value::String(string)
+            }
+            Value::Array(values) => Value::Array(
+                values
+                    .into_iter()
+                    .map(|v| self.crop_value(v, matching_words, crop_length))
+                    .collect(),
+            ),
+            value => value,
+        }
+    }
+
+    /// Returns the positions of the words of `text` matching the query.
+    fn string_matches_position(
+        &self,
+        text: &str,
+        matching_words: &MatchingWords,
+        index: Option<usize>,
+    ) -> Vec<MatchPosition> {
+        let analyzed = self.analyzer.analyze(text);
+        let mut positions = Vec::new();
+        // The words are slices of the original text, their lengths are the offsets in bytes.
+        let mut start = 0;
+        for (word, token) in analyzed.reconstruct() {
+            if token.is_word() && matching_words.matches(token.text()) {
+                positions.push(MatchPosition {
+                    start,
+                    length: word.len(),
+                    index,
+                });
+            }
+            start += word.len();
+        }
+        positions
+    }
+
+    /// Returns the positions of the matched words of each attribute of `object`, the attributes
+    /// without any match are omitted. Only the strings, and the strings of arrays, are considered.
+    pub fn matches_position(
+        &self,
+        object: &Map<String, Value>,
+        matching_words: &MatchingWords,
+    ) -> BTreeMap<String, Vec<MatchPosition>> {
+        let mut matches_position = BTreeMap::new();
+        for (key, value) in object {
+            let positions = match value {
+                Value::String(text) => self.string_matches_position(text, matching_words, None),
+                Value::Array(values) => values
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, value)| value.as_str().map(|text| (i, text)))
+                    .flat_map(|(i, text)| {
+                        self.string_matches_position(text, matching_words, Some(i))
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            if !positions.is_empty() {
+                matches_position.insert(key.clone(), positions);
+            }
+        }
+        matches_position
+    }
+
+    pub fn crop_record(
+        &self,
+        object: &mut Map<String, Value>,
+        matching_words: &MatchingWords,
+        attributes_to_crop: &[String],
+        crop_length: usize,
+    ) {
+        for (key, value) in object.iter_mut() {
+            if attributes_to_crop.contains(key) {
+                let old_value = mem::take(value);
+                *value = self.crop_value(old_value, matching_words, crop_length);
+            }
+        }
+    }
+
     pub fn highlight_record(
         &self,
         object: &mut Map<String, Value>,
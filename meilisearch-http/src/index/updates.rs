@@ -1,21 +1,36 @@
-use std::collections::{BTreeSet, HashMap};
-use std::io;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::{self, Read};
 use std::num::NonZeroUsize;
 
+use anyhow::bail;
 use flate2::read::GzDecoder;
+use heed::RoTxn;
 use log::info;
-use milli::update::{DocumentAdditionResult, IndexDocumentsMethod, UpdateBuilder, UpdateFormat};
+use milli::update::{IndexDocumentsMethod, UpdateBuilder, UpdateFormat, UpdateIndexingStep};
+use milli::{FacetCondition, FieldsDistribution};
 use serde::{de::Deserializer, Deserialize, Serialize};
+use serde_json::{Map, Value};
 
+use super::geo::{check_geo_facet_type, validate_geo_documents, GEO_FIELD};
+use super::search::RELEVANCY_RULES;
 use super::Index;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum UpdateResult {
-    DocumentsAddition(DocumentAdditionResult),
+    DocumentsAddition(DocumentsAdditionResult),
     DocumentDeletion { deleted: u64 },
     Other,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentsAdditionResult {
+    pub nb_documents: usize,
+    /// The primary key of the index once the documents are added, it is inferred from the
+    /// documents when the addition doesn't give one to an index without primary key.
+    #[serde(default)]
+    pub primary_key: Option<String>,
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
@@ -27,6 +42,8 @@ pub struct Settings {
     )]
     pub displayed_attributes: Option<Option<Vec<String>>>,
 
+    /// The attributes are given to milli in this order, the first ones are the most important for
+    /// the `attribute` ranking rule.
     #[serde(
         default,
         deserialize_with = "deserialize_some",
@@ -50,6 +67,41 @@ pub struct Settings {
         skip_serializing_if = "Option::is_none"
     )]
     pub stop_words: Option<Option<BTreeSet<String>>>,
+
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub distinct_attribute: Option<Option<String>>,
+
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub synonyms: Option<Option<BTreeMap<String, Vec<String>>>>,
+
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub typo_tolerance: Option<Option<TypoTolerance>>,
+
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pagination: Option<Option<Pagination>>,
+
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub split_join_words: Option<Option<bool>>,
 }
 
 impl Settings {
@@ -60,10 +112,73 @@ impl Settings {
             attributes_for_faceting: Some(None),
             ranking_rules: Some(None),
             stop_words: Some(None),
+            distinct_attribute: Some(None),
+            synonyms: Some(None),
+            typo_tolerance: Some(None),
+            pagination: Some(None),
+            split_join_words: Some(None),
         }
     }
 }
 
+/// The typo tolerance of an index, the fields missing from an update are set to their default.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+#[serde(rename_all = "camelCase")]
+pub struct TypoTolerance {
+    pub enabled: bool,
+    pub min_word_size_for_one_typo: usize,
+    pub min_word_size_for_two_typos: usize,
+    /// The attributes in which only the exact words of the query match.
+    pub disable_on_attributes: BTreeSet<String>,
+}
+
+impl Default for TypoTolerance {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_word_size_for_one_typo: 5,
+            min_word_size_for_two_typos: 9,
+            disable_on_attributes: BTreeSet::new(),
+        }
+    }
+}
+
+impl TypoTolerance {
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.min_word_size_for_one_typo > self.min_word_size_for_two_typos {
+            bail!(
+                "minWordSizeForOneTypo ({}) can't be greater than minWordSizeForTwoTypos ({}).",
+                self.min_word_size_for_one_typo,
+                self.min_word_size_for_two_typos
+            );
+        }
+        Ok(())
+    }
+
+    /// The number of typos allowed on a word of the query.
+    pub fn allowed_typos(&self, word: &str) -> u8 {
+        let len = word.chars().count();
+        if !self.enabled || len < self.min_word_size_for_one_typo {
+            0
+        } else if len < self.min_word_size_for_two_typos {
+            1
+        } else {
+            2
+        }
+    }
+}
+
+/// The pagination of the search results of an index.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+#[serde(rename_all = "camelCase")]
+pub struct Pagination {
+    /// The number of hits returned by the searches that don't set a limit, defaults to the
+    /// `--default-search-limit` of the server.
+    pub default_limit: Option<usize>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
@@ -72,6 +187,15 @@ pub struct Facets {
     pub min_level_size: Option<NonZeroUsize>,
 }
 
+/// The outcome of the validation of a settings update against the current schema of an index.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SettingsValidation {
+    /// The problems that would make the update fail.
+    pub errors: Vec<String>,
+    /// The settings that would be applied, but are likely mistakes.
+    pub warnings: Vec<String>,
+}
+
 fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
 where
     T: Deserialize<'de>,
@@ -81,6 +205,8 @@ where
 }
 
 impl Index {
+    /// Adds the documents of `content`, the steps of the indexing are reported to `progress`.
+    #[allow(clippy::too_many_arguments)]
     pub fn update_documents(
         &self,
         format: UpdateFormat,
@@ -88,6 +214,9 @@ impl Index {
         content: impl io::Read,
         update_builder: UpdateBuilder,
         primary_key: Option<&str>,
+        strict: bool,
+        csv_delimiter: Option<u8>,
+        progress: impl Fn(UpdateIndexingStep) + Sync,
     ) -> anyhow::Result<UpdateResult> {
         info!("performing document addition");
         // We must use the write transaction of the update here.
@@ -98,6 +227,33 @@ impl Index {
             self.put_primary_key(&mut wtxn, primary_key)?;
         }
 
+        // The indexer only reads comma separated CSV, other delimiters are converted beforehand.
+        let content = match (format, csv_delimiter) {
+            (UpdateFormat::Csv, Some(delimiter)) if delimiter != b',' => {
+                Box::new(io::Cursor::new(transcode_csv(content, delimiter)?)) as Box<dyn io::Read>
+            }
+            _ => Box::new(content),
+        };
+
+        // In strict mode the content is read upfront to make sure that none of the documents
+        // already exists before indexing anything. The locations of the documents are checked
+        // beforehand as well when `_geo` is faceted.
+        let validate_geo = self.geo_facet_type(&wtxn)?.is_some();
+        let content = if strict || validate_geo {
+            let mut content = content;
+            let mut buffer = Vec::new();
+            content.read_to_end(&mut buffer)?;
+            if strict {
+                self.ensure_new_documents(&wtxn, format, &buffer)?;
+            }
+            if validate_geo {
+                validate_geo_documents(format, &buffer)?;
+            }
+            Box::new(io::Cursor::new(buffer)) as Box<dyn io::Read>
+        } else {
+            Box::new(content)
+        };
+
         let mut builder = update_builder.index_documents(&mut wtxn, self);
         builder.update_format(format);
         builder.index_documents_method(method);
@@ -110,26 +266,79 @@ impl Index {
         };
 
         let result = builder.execute(reader, |indexing_step, update_id| {
-            info!("update {}: {:?}", update_id, indexing_step)
+            info!("update {}: {:?}", update_id, indexing_step);
+            progress(indexing_step);
         });
 
         info!("document addition done: {:?}", result);
 
-        result.and_then(|addition_result| {
-            wtxn.commit()
-                .and(Ok(UpdateResult::DocumentsAddition(addition_result)))
-                .map_err(Into::into)
-        })
+        let addition_result = result?;
+        let primary_key = self.primary_key(&wtxn)?.map(String::from);
+        self.bump_updated_at(&mut wtxn)?;
+        wtxn.commit()?;
+        Ok(UpdateResult::DocumentsAddition(DocumentsAdditionResult {
+            nb_documents: addition_result.nb_documents,
+            primary_key,
+        }))
+    }
+
+    /// Returns an error listing the ids of the documents of `content` that already exist in the
+    /// index, or that appear several times in `content`, since the last occurrence would replace
+    /// the previous ones. The documents are only checked once the primary key is known.
+    fn ensure_new_documents(
+        &self,
+        txn: &RoTxn,
+        format: UpdateFormat,
+        content: &[u8],
+    ) -> anyhow::Result<()> {
+        // Without a primary key the index can't contain any document yet.
+        let primary_key = match self.primary_key(txn)? {
+            Some(primary_key) => primary_key.to_string(),
+            None => return Ok(()),
+        };
+
+        let ids = document_ids(format, content, &primary_key)?;
+        let external_documents_ids = self.external_documents_ids(txn)?;
+        let colliding_ids: Vec<_> = ids
+            .iter()
+            .filter(|id| external_documents_ids.get(id.as_bytes()).is_some())
+            .collect();
+
+        if !colliding_ids.is_empty() {
+            bail!("Documents with ids {:?} already exist.", colliding_ids);
+        }
+
+        let mut seen_ids = HashSet::with_capacity(ids.len());
+        let mut duplicate_ids = BTreeSet::new();
+        for id in &ids {
+            if !seen_ids.insert(id) {
+                duplicate_ids.insert(id);
+            }
+        }
+
+        if !duplicate_ids.is_empty() {
+            let duplicate_ids: Vec<_> = duplicate_ids.into_iter().collect();
+            bail!(
+                "Documents with ids {:?} appear several times in the payload.",
+                duplicate_ids
+            );
+        }
+
+        Ok(())
     }
 
+    /// Removes all the documents and resets the fields distribution, the primary key and the
+    /// settings of the index are kept.
     pub fn clear_documents(&self, update_builder: UpdateBuilder) -> anyhow::Result<UpdateResult> {
         // We must use the write transaction of the update here.
         let mut wtxn = self.write_txn()?;
         let builder = update_builder.clear_documents(&mut wtxn, self);
 
         match builder.execute() {
-            Ok(_count) => wtxn
-                .commit()
+            Ok(_count) => self
+                .put_fields_distribution(&mut wtxn, &FieldsDistribution::default())
+                .and_then(|()| self.bump_updated_at(&mut wtxn))
+                .and_then(|()| wtxn.commit())
                 .and(Ok(UpdateResult::Other))
                 .map_err(Into::into),
             Err(e) => Err(e),
@@ -142,6 +351,14 @@ impl Index {
         update_builder: UpdateBuilder,
     ) -> anyhow::Result<UpdateResult> {
         // We must use the write transaction of the update here.
+        // The typo tolerance and the facet types are checked before any setting is applied.
+        if let Some(Some(typo_tolerance)) = &settings.typo_tolerance {
+            typo_tolerance.check()?;
+        }
+        if let Some(Some(facet_types)) = &settings.attributes_for_faceting {
+            check_geo_facet_type(facet_types)?;
+        }
+
         let mut wtxn = self.write_txn()?;
         let mut builder = update_builder.settings(&mut wtxn, self);
 
@@ -162,8 +379,11 @@ impl Index {
         }
 
         // We transpose the settings JSON struct into a real setting update.
+        // The `_geo` attribute is faceted outside of milli, which can't facet objects.
+        let mut geo_facet_type = None;
         if let Some(ref facet_types) = settings.attributes_for_faceting {
-            let facet_types = facet_types.clone().unwrap_or_else(HashMap::new);
+            let mut facet_types = facet_types.clone().unwrap_or_else(HashMap::new);
+            geo_facet_type = Some(facet_types.remove(GEO_FIELD));
             builder.set_faceted_fields(facet_types);
         }
 
@@ -183,18 +403,166 @@ impl Index {
             }
         }
 
+        // We transpose the settings JSON struct into a real setting update.
+        if let Some(ref distinct_attribute) = settings.distinct_attribute {
+            match distinct_attribute {
+                Some(attribute) => builder.set_distinct_attribute(attribute.clone()),
+                None => builder.reset_distinct_attribute(),
+            }
+        }
+
+        // We transpose the settings JSON struct into a real setting update.
+        if let Some(ref synonyms) = settings.synonyms {
+            match synonyms {
+                Some(synonyms) => builder.set_synonyms(synonyms.clone().into_iter().collect()),
+                None => builder.reset_synonyms(),
+            }
+        }
+
         let result = builder
             .execute(|indexing_step, update_id| info!("update {}: {:?}", update_id, indexing_step));
 
+        // The typo tolerance isn't a setting of milli, it is applied at search time.
+        if let (Ok(()), Some(typo_tolerance)) = (&result, &settings.typo_tolerance) {
+            match typo_tolerance {
+                Some(typo_tolerance) => self.put_typo_tolerance(&mut wtxn, typo_tolerance)?,
+                None => self.delete_typo_tolerance(&mut wtxn)?,
+            }
+        }
+
+        // The pagination is applied at search time too.
+        if let (Ok(()), Some(pagination)) = (&result, &settings.pagination) {
+            match pagination {
+                Some(pagination) => self.put_pagination(&mut wtxn, pagination)?,
+                None => self.delete_pagination(&mut wtxn)?,
+            }
+        }
+
+        // The split and joined words are also discarded at search time, when they are disabled.
+        if let (Ok(()), Some(split_join_words)) = (&result, settings.split_join_words) {
+            match split_join_words {
+                Some(enabled) => self.put_split_join_words(&mut wtxn, enabled)?,
+                None => self.delete_split_join_words(&mut wtxn)?,
+            }
+        }
+
+        if let (Ok(()), Some(geo_facet_type)) = (&result, geo_facet_type) {
+            match geo_facet_type {
+                Some(geo_facet_type) => self.put_geo_facet_type(&mut wtxn, &geo_facet_type)?,
+                None => self.delete_geo_facet_type(&mut wtxn)?,
+            }
+        }
+
         match result {
-            Ok(()) => wtxn
-                .commit()
+            Ok(()) => self
+                .bump_updated_at(&mut wtxn)
+                .and_then(|()| wtxn.commit())
                 .and(Ok(UpdateResult::Other))
                 .map_err(Into::into),
             Err(e) => Err(e),
         }
     }
 
+    /// Checks the settings against the current schema of the index, without applying them.
+    pub fn validate_settings(&self, settings: &Settings) -> anyhow::Result<SettingsValidation> {
+        let txn = self.read_txn()?;
+        let fields_ids_map = self.fields_ids_map(&txn)?;
+        let mut validation = SettingsValidation::default();
+
+        let mut check_attribute = |setting: &str, attribute: &str| {
+            if attribute != "*" && fields_ids_map.id(attribute).is_none() {
+                validation.warnings.push(format!(
+                    "The attribute {:?} of {} is not present in any document.",
+                    attribute, setting
+                ));
+            }
+        };
+
+        if let Some(Some(ref names)) = settings.searchable_attributes {
+            names
+                .iter()
+                .for_each(|name| check_attribute("searchableAttributes", name));
+        }
+        if let Some(Some(ref names)) = settings.displayed_attributes {
+            names
+                .iter()
+                .for_each(|name| check_attribute("displayedAttributes", name));
+        }
+        if let Some(Some(ref attribute)) = settings.distinct_attribute {
+            check_attribute("distinctAttribute", attribute);
+        }
+        if let Some(Some(ref typo_tolerance)) = settings.typo_tolerance {
+            typo_tolerance
+                .disable_on_attributes
+                .iter()
+                .for_each(|name| check_attribute("typoTolerance", name));
+        }
+
+        // The ranking rules are checked against the faceted attributes set by the update, if any.
+        let faceted_fields: BTreeSet<String> = match settings.attributes_for_faceting {
+            Some(ref facet_types) => facet_types
+                .iter()
+                .flat_map(|facet_types| facet_types.keys().cloned())
+                .collect(),
+            None => self
+                .faceted_fields(&txn)?
+                .into_iter()
+                .map(|(k, _)| k)
+                .collect(),
+        };
+        if settings.attributes_for_faceting.is_some() {
+            faceted_fields
+                .iter()
+                .for_each(|name| check_attribute("attributesForFaceting", name));
+        }
+
+        if let Some(Some(ref rules)) = settings.ranking_rules {
+            for rule in rules {
+                if RELEVANCY_RULES.contains(&rule.as_str()) {
+                    continue;
+                }
+
+                let field = rule
+                    .strip_prefix("asc(")
+                    .or_else(|| rule.strip_prefix("desc("))
+                    .and_then(|rest| rest.strip_suffix(')'))
+                    .filter(|field| !field.is_empty());
+                match field {
+                    Some(field) if !faceted_fields.contains(field) => {
+                        validation.errors.push(format!(
+                            "The ranking rule {:?} is applied on {:?}, which is not a faceted attribute.",
+                            rule, field
+                        ))
+                    }
+                    Some(_) => (),
+                    None => validation
+                        .errors
+                        .push(format!("Invalid ranking rule {:?}.", rule)),
+                }
+            }
+        }
+
+        if let Some(Some(ref synonyms)) = settings.synonyms {
+            if synonyms.keys().any(|word| word.trim().is_empty()) {
+                validation
+                    .errors
+                    .push("The synonyms can't be defined for an empty word.".to_string());
+            }
+        }
+        if let Some(Some(ref typo_tolerance)) = settings.typo_tolerance {
+            if let Err(e) = typo_tolerance.check() {
+                validation.errors.push(e.to_string());
+            }
+        }
+        if let Some(Some(ref facet_types)) = settings.attributes_for_faceting {
+            if let Err(e) = check_geo_facet_type(facet_types) {
+                validation.errors.push(e.to_string());
+            }
+        }
+
+        Ok(validation)
+    }
+
     pub fn update_facets(
         &self,
         levels: &Facets,
@@ -210,8 +578,9 @@ impl Index {
             builder.min_level_size(value);
         }
         match builder.execute() {
-            Ok(()) => wtxn
-                .commit()
+            Ok(()) => self
+                .bump_updated_at(&mut wtxn)
+                .and_then(|()| wtxn.commit())
                 .and(Ok(UpdateResult::Other))
                 .map_err(Into::into),
             Err(e) => Err(e),
@@ -233,11 +602,105 @@ impl Index {
         });
 
         match builder.execute() {
-            Ok(deleted) => txn
-                .commit()
+            Ok(deleted) => self
+                .bump_updated_at(&mut txn)
+                .and_then(|()| txn.commit())
+                .and(Ok(UpdateResult::DocumentDeletion { deleted }))
+                .map_err(Into::into),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Deletes all the documents matching `filter` in a single transaction.
+    pub fn delete_documents_by_filter(
+        &self,
+        filter: &str,
+        update_builder: UpdateBuilder,
+    ) -> anyhow::Result<UpdateResult> {
+        let mut txn = self.write_txn()?;
+
+        // The documents are resolved in the transaction of the update, so that the matching
+        // documents can't change before being deleted.
+        let condition = FacetCondition::from_str(&txn, self, filter)?;
+        let documents_ids = condition.evaluate(&txn, self)?;
+
+        let mut builder = update_builder.delete_documents(&mut txn, self)?;
+        documents_ids.iter().for_each(|id| {
+            builder.delete_document(id);
+        });
+
+        match builder.execute() {
+            Ok(deleted) => self
+                .bump_updated_at(&mut txn)
+                .and_then(|()| txn.commit())
                 .and(Ok(UpdateResult::DocumentDeletion { deleted }))
                 .map_err(Into::into),
             Err(e) => Err(e),
         }
     }
 }
+
+/// Extracts the external ids of the documents of a payload, the documents missing the primary key
+/// are ignored, they will be rejected by the indexer.
+fn document_ids(
+    format: UpdateFormat,
+    content: &[u8],
+    primary_key: &str,
+) -> anyhow::Result<Vec<String>> {
+    fn external_id(value: &Value) -> String {
+        match value {
+            Value::String(id) => id.clone(),
+            value => value.to_string(),
+        }
+    }
+
+    let ids = match format {
+        UpdateFormat::Json => serde_json::from_slice::<Vec<Map<String, Value>>>(content)?
+            .iter()
+            .filter_map(|document| document.get(primary_key).map(external_id))
+            .collect(),
+        UpdateFormat::JsonStream => {
+            let mut ids = Vec::new();
+            for document in
+                serde_json::Deserializer::from_slice(content).into_iter::<Map<String, Value>>()
+            {
+                if let Some(id) = document?.get(primary_key) {
+                    ids.push(external_id(id));
+                }
+            }
+            ids
+        }
+        UpdateFormat::Csv => {
+            let mut reader = csv::Reader::from_reader(content);
+            let position = match reader.headers()?.iter().position(|h| h == primary_key) {
+                Some(position) => position,
+                None => return Ok(Vec::new()),
+            };
+            let mut ids = Vec::new();
+            for record in reader.records() {
+                if let Some(id) = record?.get(position) {
+                    ids.push(id.to_string());
+                }
+            }
+            ids
+        }
+    };
+
+    Ok(ids)
+}
+
+/// Rewrites a CSV payload separated by `delimiter` into a comma separated one.
+fn transcode_csv(content: impl io::Read, delimiter: u8) -> anyhow::Result<Vec<u8>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .from_reader(content);
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    for record in reader.byte_records() {
+        writer.write_byte_record(&record?)?;
+    }
+
+    let content = writer.into_inner().map_err(|e| e.into_error())?;
+    Ok(content)
+}
@@ -0,0 +1,119 @@
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::time::timeout;
+
+use super::index_actor::IndexActorHandle;
+use super::update_actor::UpdateActorHandle;
+use super::uuid_resolver::UuidResolverHandle;
+
+/// Pings every actor and returns the names of the ones that failed to answer within `timeout`.
+pub async fn unresponsive_actors(
+    uuid_resolver: &impl UuidResolverHandle,
+    update_handle: &impl UpdateActorHandle,
+    index_handle: &impl IndexActorHandle,
+    timeout: Duration,
+) -> Vec<&'static str> {
+    let (uuid_resolver, update_actor, index_actor) = tokio::join!(
+        responds(uuid_resolver.ping(), timeout),
+        responds(update_handle.ping(), timeout),
+        responds(index_handle.ping(), timeout),
+    );
+
+    [
+        ("uuid resolver", uuid_resolver),
+        ("update actor", update_actor),
+        ("index actor", index_actor),
+    ]
+    .iter()
+    .filter(|(_, responds)| !responds)
+    .map(|(name, _)| *name)
+    .collect()
+}
+
+async fn responds<T, E>(ping: impl Future<Output = Result<T, E>>, duration: Duration) -> bool {
+    matches!(timeout(duration, ping).await, Ok(Ok(_)))
+}
+
+#[cfg(test)]
+mod test {
+    use futures::future::{err, ok, pending};
+
+    use super::*;
+    use crate::index_controller::index_actor::MockIndexActorHandle;
+    use crate::index_controller::update_actor::MockUpdateActorHandle;
+    use crate::index_controller::uuid_resolver::{MockUuidResolverHandle, UuidError};
+    use crate::index_controller::ActorError;
+
+    fn healthy_actors() -> (
+        MockUuidResolverHandle,
+        MockUpdateActorHandle,
+        MockIndexActorHandle,
+    ) {
+        let mut uuid_resolver = MockUuidResolverHandle::new();
+        uuid_resolver.expect_ping().returning(|| Box::pin(ok(())));
+
+        let mut update_handle = MockUpdateActorHandle::new();
+        update_handle.expect_ping().returning(|| Box::pin(ok(())));
+
+        let mut index_handle = MockIndexActorHandle::new();
+        index_handle.expect_ping().returning(|| Box::pin(ok(())));
+
+        (uuid_resolver, update_handle, index_handle)
+    }
+
+    #[actix_rt::test]
+    async fn all_actors_respond() {
+        let (uuid_resolver, update_handle, index_handle) = healthy_actors();
+
+        let unresponsive = unresponsive_actors(
+            &uuid_resolver,
+            &update_handle,
+            &index_handle,
+            Duration::from_millis(100),
+        )
+        .await;
+
+        assert!(unresponsive.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn closed_actor_is_reported() {
+        let (_, update_handle, index_handle) = healthy_actors();
+        let mut uuid_resolver = MockUuidResolverHandle::new();
+        uuid_resolver.expect_ping().returning(|| {
+            Box::pin(err(UuidError::from(ActorError::Unavailable(
+                "uuid resolver",
+            ))))
+        });
+
+        let unresponsive = unresponsive_actors(
+            &uuid_resolver,
+            &update_handle,
+            &index_handle,
+            Duration::from_millis(100),
+        )
+        .await;
+
+        assert_eq!(unresponsive, vec!["uuid resolver"]);
+    }
+
+    #[actix_rt::test]
+    async fn stuck_actor_is_reported() {
+        let (uuid_resolver, _, index_handle) = healthy_actors();
+        let mut update_handle = MockUpdateActorHandle::new();
+        update_handle
+            .expect_ping()
+            .returning(|| Box::pin(pending()));
+
+        let unresponsive = unresponsive_actors(
+            &uuid_resolver,
+            &update_handle,
+            &index_handle,
+            Duration::from_millis(100),
+        )
+        .await;
+
+        assert_eq!(unresponsive, vec!["update actor"]);
+    }
+}
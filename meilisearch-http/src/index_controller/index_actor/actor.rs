@@ -1,25 +1,37 @@
+use std::collections::HashMap;
 use std::fs::File;
-use std::future::Future;
+use std::io::{Seek, SeekFrom};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use async_stream::stream;
-use futures::pin_mut;
-use futures::stream::StreamExt;
 use heed::CompactionOption;
-use log::debug;
-use tokio::sync::{mpsc, RwLock};
+use log::{debug, error, info, warn};
+use tokio::sync::{mpsc, Mutex, RwLock};
 use tokio::task::spawn_blocking;
+use tokio::time::timeout;
 use uuid::Uuid;
 
-use crate::index::{Document, SearchQuery, SearchResult, Settings};
+use crate::index::{Document, SearchQuery, SearchResult, Settings, SettingsValidation, MAX_TYPOS};
+use crate::index_controller::shutdown::Shutdown;
 use crate::index_controller::update_handler::UpdateHandler;
 use crate::index_controller::{
-    get_arc_ownership_blocking, updates::Processing, IndexStats, UpdateMeta,
+    get_arc_ownership_blocking, updates::Processing, IndexStats, SearchTimeout, UpdateMeta,
 };
 use crate::option::IndexerOpts;
 
-use super::{IndexError, IndexMeta, IndexMsg, IndexSettings, IndexStore, Result, UpdateResult};
+use super::{
+    IndexError, IndexMeta, IndexMsg, IndexSettings, IndexStore, Result, SearchCache, UpdateResult,
+    MAP_SIZE_FILE,
+};
+
+/// The number of exported documents buffered ahead of the response.
+const EXPORT_CHANNEL_SIZE: usize = 100;
+/// The number of documents read in each transaction of an export.
+const EXPORT_PAGE_SIZE: usize = 100;
+/// How many bytes of map size are added to a full index per byte of update payload.
+const INDEX_GROWTH_FACTOR: usize = 10;
 
 pub struct IndexActor<S> {
     read_receiver: Option<mpsc::Receiver<IndexMsg>>,
@@ -27,13 +39,29 @@ pub struct IndexActor<S> {
     update_handler: Arc<UpdateHandler>,
     processing: RwLock<Option<Uuid>>,
     store: S,
+    /// The size the environments of the indexes can grow to when they are full.
+    max_index_size: usize,
+    max_search_limit: usize,
+    default_search_limit: usize,
+    read_concurrency: usize,
+    search_timeout: Duration,
+    search_cache: SearchCache,
+    shutdown: Shutdown,
 }
 
-impl<S: IndexStore + Sync + Send> IndexActor<S> {
+impl<S: IndexStore + Sync + Send + 'static> IndexActor<S> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         read_receiver: mpsc::Receiver<IndexMsg>,
         write_receiver: mpsc::Receiver<IndexMsg>,
         store: S,
+        max_index_size: usize,
+        max_search_limit: usize,
+        default_search_limit: usize,
+        read_concurrency: usize,
+        search_timeout: Duration,
+        search_cache: SearchCache,
+        shutdown: Shutdown,
     ) -> Result<Self> {
         let options = IndexerOpts::default();
         let update_handler = UpdateHandler::new(&options).map_err(IndexError::Error)?;
@@ -46,51 +74,60 @@ impl<S: IndexStore + Sync + Send> IndexActor<S> {
             update_handler,
             processing: RwLock::new(None),
             store,
+            max_index_size,
+            max_search_limit,
+            default_search_limit,
+            read_concurrency,
+            search_timeout,
+            search_cache,
+            shutdown,
         })
     }
 
-    /// `run` poll the write_receiver and read_receiver concurrently, but while messages send
-    /// through the read channel are processed concurrently, the messages sent through the write
-    /// channel are processed one at a time.
+    /// `run` spawns a pool of `read_concurrency` readers and a single writer. The readers take the
+    /// messages sent through the read channel one at a time, each of them on its own read
+    /// transaction, while the writer processes the messages sent through the write channel. The
+    /// readers never wait for the writer.
+    ///
+    /// Once the shutdown is triggered no new message is received, but the messages being processed
+    /// are run to completion.
     pub async fn run(mut self) {
-        let mut read_receiver = self
+        let read_receiver = self
             .read_receiver
             .take()
             .expect("Index Actor must have a inbox at this point.");
-
-        let read_stream = stream! {
-            loop {
-                match read_receiver.recv().await {
-                    Some(msg) => yield msg,
-                    None => break,
-                }
-            }
-        };
-
-        let mut write_receiver = self
+        let write_receiver = self
             .write_receiver
             .take()
             .expect("Index Actor must have a inbox at this point.");
 
-        let write_stream = stream! {
-            loop {
-                match write_receiver.recv().await {
-                    Some(msg) => yield msg,
-                    None => break,
-                }
-            }
-        };
-
-        pin_mut!(write_stream);
-        pin_mut!(read_stream);
-
-        let fut1 = read_stream.for_each_concurrent(Some(10), |msg| self.handle_message(msg));
-        let fut2 = write_stream.for_each_concurrent(Some(1), |msg| self.handle_message(msg));
-
-        let fut1: Box<dyn Future<Output = ()> + Unpin + Send> = Box::new(fut1);
-        let fut2: Box<dyn Future<Output = ()> + Unpin + Send> = Box::new(fut2);
+        let actor = Arc::new(self);
+        let read_receiver = Arc::new(Mutex::new(read_receiver));
+        let readers = (0..actor.read_concurrency).map(|_| {
+            let actor = actor.clone();
+            let receiver = read_receiver.clone();
+            tokio::task::spawn(async move { actor.run_worker(&receiver).await })
+        });
+        let readers = futures::future::join_all(readers);
+
+        let write_receiver = Mutex::new(write_receiver);
+        tokio::join!(readers, actor.run_worker(&write_receiver));
+    }
 
-        tokio::join!(fut1, fut2);
+    /// Handles the messages of `receiver`, one at a time, until the shutdown. The idle workers
+    /// sharing a receiver wait for their turn to receive a message.
+    async fn run_worker(&self, receiver: &Mutex<mpsc::Receiver<IndexMsg>>) {
+        let mut shutdown = self.shutdown.clone();
+        loop {
+            let msg = tokio::select! {
+                msg = async { receiver.lock().await.recv().await } => msg,
+                _ = shutdown.wait() => None,
+            };
+            match msg {
+                Some(msg) => self.handle_message(msg).await,
+                None => break,
+            }
+        }
     }
 
     async fn handle_message(&self, msg: IndexMsg) {
@@ -99,9 +136,10 @@ impl<S: IndexStore + Sync + Send> IndexActor<S> {
             CreateIndex {
                 uuid,
                 primary_key,
+                map_size,
                 ret,
             } => {
-                let _ = ret.send(self.handle_create_index(uuid, primary_key).await);
+                let _ = ret.send(self.handle_create_index(uuid, primary_key, map_size).await);
             }
             Update { ret, meta, data } => {
                 let _ = ret.send(self.handle_update(meta, data).await);
@@ -112,6 +150,20 @@ impl<S: IndexStore + Sync + Send> IndexActor<S> {
             Settings { ret, uuid } => {
                 let _ = ret.send(self.handle_settings(uuid).await);
             }
+            ValidateSettings {
+                ret,
+                uuid,
+                settings,
+            } => {
+                let _ = ret.send(self.handle_validate_settings(uuid, settings).await);
+            }
+            InferFacetTypes {
+                ret,
+                uuid,
+                attributes,
+            } => {
+                let _ = ret.send(self.handle_infer_facet_types(uuid, attributes).await);
+            }
             Documents {
                 ret,
                 uuid,
@@ -124,6 +176,16 @@ impl<S: IndexStore + Sync + Send> IndexActor<S> {
                         .await,
                 );
             }
+            ExportDocuments {
+                uuid,
+                attributes_to_retrieve,
+                ret,
+            } => {
+                let _ = ret.send(
+                    self.handle_export_documents(uuid, attributes_to_retrieve)
+                        .await,
+                );
+            }
             Document {
                 uuid,
                 attributes_to_retrieve,
@@ -135,6 +197,20 @@ impl<S: IndexStore + Sync + Send> IndexActor<S> {
                         .await,
                 );
             }
+            DocumentExists { uuid, doc_id, ret } => {
+                let _ = ret.send(self.handle_document_exists(uuid, doc_id).await);
+            }
+            DocumentsByIds {
+                uuid,
+                attributes_to_retrieve,
+                doc_ids,
+                ret,
+            } => {
+                let _ = ret.send(
+                    self.handle_fetch_documents_by_ids(uuid, doc_ids, attributes_to_retrieve)
+                        .await,
+                );
+            }
             Delete { uuid, ret } => {
                 let _ = ret.send(self.handle_delete(uuid).await);
             }
@@ -151,27 +227,90 @@ impl<S: IndexStore + Sync + Send> IndexActor<S> {
             Snapshot { uuid, path, ret } => {
                 let _ = ret.send(self.handle_snapshot(uuid, path).await);
             }
+            Dump { uuid, path, ret } => {
+                let _ = ret.send(self.handle_dump(uuid, path).await);
+            }
             GetStats { uuid, ret } => {
                 let _ = ret.send(self.handle_get_stats(uuid).await);
             }
+            DocumentCount { uuid, ret } => {
+                let _ = ret.send(self.handle_document_count(uuid).await);
+            }
+            Ping { ret } => {
+                let _ = ret.send(Ok(()));
+            }
         }
     }
 
-    async fn handle_search(&self, uuid: Uuid, query: SearchQuery) -> anyhow::Result<SearchResult> {
+    /// Checks that a page of `limit` documents starting at `offset` can be served.
+    fn check_pagination(&self, offset: usize, limit: usize) -> Result<()> {
+        check_pagination(self.max_search_limit, offset, limit)
+    }
+
+    async fn handle_search(
+        &self,
+        uuid: Uuid,
+        mut query: SearchQuery,
+    ) -> anyhow::Result<SearchResult> {
+        if let Some(limit) = query.limit {
+            self.check_pagination(query.offset.unwrap_or_default(), limit)?;
+        }
+        if let Some(max_typos) = query.max_typos.filter(|&max_typos| max_typos > MAX_TYPOS) {
+            warn!(
+                "The search allows {} typos, it is clamped to the {} typos the engine supports.",
+                max_typos, MAX_TYPOS
+            );
+            query.max_typos = Some(MAX_TYPOS);
+        }
+        // The key is made before the search, a result read before a write isn't cached after it.
+        let key = self.search_cache.key(uuid, &query);
+        if let Some(result) = self.search_cache.get(&key) {
+            return Ok(result);
+        }
         let index = self
             .store
             .get(uuid)
             .await?
             .ok_or(IndexError::UnexistingIndex)?;
-        spawn_blocking(move || index.perform_search(query)).await?
+
+        let max_search_limit = self.max_search_limit;
+        let default_search_limit = self.default_search_limit;
+        // A blocking task can't be interrupted: on timeout the reader is freed right away and the
+        // search is cancelled, it stops at its next step and releases its read transaction.
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let search_cancelled = cancelled.clone();
+        let search = spawn_blocking(move || {
+            // Without an explicit limit, the default of the index takes precedence over the one
+            // of the server.
+            if query.limit.is_none() {
+                let txn = index.read_txn()?;
+                let limit = index
+                    .pagination(&txn)?
+                    .default_limit
+                    .unwrap_or(default_search_limit);
+                check_pagination(max_search_limit, query.offset.unwrap_or_default(), limit)?;
+                query.limit = Some(limit);
+            }
+            index.perform_search(query, &search_cancelled)
+        });
+        let result = match timeout(self.search_timeout, search).await {
+            Ok(result) => result??,
+            Err(_) => {
+                cancelled.store(true, Ordering::Relaxed);
+                return Err(SearchTimeout(self.search_timeout.as_millis()).into());
+            }
+        };
+        self.search_cache.insert(key, &result);
+        Ok(result)
     }
 
     async fn handle_create_index(
         &self,
         uuid: Uuid,
         primary_key: Option<String>,
+        map_size: Option<usize>,
     ) -> Result<IndexMeta> {
-        let index = self.store.create(uuid, primary_key).await?;
+        let index = self.store.create(uuid, primary_key, map_size).await?;
         let meta = spawn_blocking(move || IndexMeta::new(&index))
             .await
             .map_err(|e| IndexError::Error(e.into()))??;
@@ -183,13 +322,17 @@ impl<S: IndexStore + Sync + Send> IndexActor<S> {
         meta: Processing<UpdateMeta>,
         data: File,
     ) -> Result<UpdateResult> {
-        async fn get_result<S: IndexStore>(actor: &IndexActor<S>, meta: Processing<UpdateMeta>, data: File) -> Result<UpdateResult> {
+        async fn get_result<S: IndexStore>(
+            actor: &IndexActor<S>,
+            meta: Processing<UpdateMeta>,
+            data: File,
+        ) -> Result<UpdateResult> {
             debug!("Processing update {}", meta.id());
             let uuid = *meta.index_uuid();
             let update_handler = actor.update_handler.clone();
             let index = match actor.store.get(uuid).await? {
                 Some(index) => index,
-                None => actor.store.create(uuid, None).await?,
+                None => actor.store.create(uuid, None, None).await?,
             };
 
             spawn_blocking(move || update_handler.handle_update(meta, data, index))
@@ -197,9 +340,51 @@ impl<S: IndexStore + Sync + Send> IndexActor<S> {
                 .map_err(|e| IndexError::Error(e.into()))
         }
 
-        *self.processing.write().await = Some(meta.index_uuid().clone());
-        let result = get_result(self, meta, data).await;
+        let uuid = *meta.index_uuid();
+        *self.processing.write().await = Some(uuid);
+        // A full index is grown once to a size estimated from the update payload, capped to the
+        // maximum size, before processing the update again.
+        let result: Result<UpdateResult> = async {
+            let mut retry_data = data.try_clone().map_err(|e| IndexError::Error(e.into()))?;
+            let payload_size = data
+                .metadata()
+                .map_err(|e| IndexError::Error(e.into()))?
+                .len() as usize;
+            match get_result(self, meta.clone(), data).await? {
+                Err(failed) if failed.is_map_full() => {
+                    let map_size = self.store.map_size(uuid).await?;
+                    if map_size < self.max_index_size {
+                        let map_size = map_size
+                            .saturating_add(payload_size.saturating_mul(INDEX_GROWTH_FACTOR))
+                            .checked_next_power_of_two()
+                            .unwrap_or(usize::MAX)
+                            .min(self.max_index_size);
+                        info!("Index {} is full, growing it to {} bytes.", uuid, map_size);
+                        self.store.resize(uuid, map_size).await?;
+                        retry_data
+                            .seek(SeekFrom::Start(0))
+                            .map_err(|e| IndexError::Error(e.into()))?;
+                        match get_result(self, meta.clone(), retry_data).await? {
+                            Err(failed) if failed.is_map_full() => (),
+                            result => return Ok(result),
+                        }
+                    }
+                    let error = if self.store.map_size(uuid).await? >= self.max_index_size {
+                        format!(
+                            "The index reached the maximum size of {} bytes.",
+                            self.max_index_size
+                        )
+                    } else {
+                        "The index is full.".to_string()
+                    };
+                    Ok(Err(meta.fail(error)))
+                }
+                result => Ok(result),
+            }
+        }
+        .await;
         *self.processing.write().await = None;
+        self.search_cache.invalidate(uuid);
 
         result
     }
@@ -215,13 +400,52 @@ impl<S: IndexStore + Sync + Send> IndexActor<S> {
             .map_err(|e| IndexError::Error(e.into()))?
     }
 
+    async fn handle_validate_settings(
+        &self,
+        uuid: Uuid,
+        settings: Settings,
+    ) -> Result<SettingsValidation> {
+        let index = self
+            .store
+            .get(uuid)
+            .await?
+            .ok_or(IndexError::UnexistingIndex)?;
+        spawn_blocking(move || {
+            index
+                .validate_settings(&settings)
+                .map_err(IndexError::Error)
+        })
+        .await
+        .map_err(|e| IndexError::Error(e.into()))?
+    }
+
+    async fn handle_infer_facet_types(
+        &self,
+        uuid: Uuid,
+        attributes: Vec<String>,
+    ) -> Result<HashMap<String, String>> {
+        let index = self
+            .store
+            .get(uuid)
+            .await?
+            .ok_or(IndexError::UnexistingIndex)?;
+        spawn_blocking(move || {
+            index
+                .infer_facet_types(attributes)
+                .map_err(IndexError::Error)
+        })
+        .await
+        .map_err(|e| IndexError::Error(e.into()))?
+    }
+
     async fn handle_fetch_documents(
         &self,
         uuid: Uuid,
         offset: usize,
         limit: usize,
         attributes_to_retrieve: Option<Vec<String>>,
-    ) -> Result<Vec<Document>> {
+    ) -> Result<(Vec<Document>, u64)> {
+        self.check_pagination(offset, limit)?;
         let index = self
             .store
             .get(uuid)
@@ -236,6 +460,56 @@ impl<S: IndexStore + Sync + Send> IndexActor<S> {
         .map_err(|e| IndexError::Error(e.into()))?
     }
 
+    async fn handle_export_documents(
+        &self,
+        uuid: Uuid,
+        attributes_to_retrieve: Option<Vec<String>>,
+    ) -> Result<mpsc::Receiver<anyhow::Result<Vec<u8>>>> {
+        let index = self
+            .store
+            .get(uuid)
+            .await?
+            .ok_or(IndexError::UnexistingIndex)?;
+        let (sender, receiver) = mpsc::channel(EXPORT_CHANNEL_SIZE);
+        // The export is not awaited, the pages are read as the receiver consumes them. An error
+        // is sent in place of the remaining documents so that the response can be interrupted.
+        tokio::task::spawn(async move {
+            let mut after = None;
+            loop {
+                let index = index.clone();
+                let attributes_to_retrieve = attributes_to_retrieve.clone();
+                let read = spawn_blocking(move || {
+                    index.export_documents_page(attributes_to_retrieve, after, EXPORT_PAGE_SIZE)
+                })
+                .await;
+                let (lines, next) = match read {
+                    Ok(Ok(page)) => page,
+                    Ok(Err(e)) => {
+                        error!("Could not export the documents: {}", e);
+                        let _ = sender.send(Err(e)).await;
+                        return;
+                    }
+                    Err(e) => {
+                        error!("Could not export the documents: {}", e);
+                        let _ = sender.send(Err(e.into())).await;
+                        return;
+                    }
+                };
+                for line in lines {
+                    // The receiver was dropped, the response was interrupted.
+                    if sender.send(Ok(line)).await.is_err() {
+                        return;
+                    }
+                }
+                match next {
+                    Some(id) => after = Some(id),
+                    None => return,
+                }
+            }
+        });
+        Ok(receiver)
+    }
+
     async fn handle_fetch_document(
         &self,
         uuid: Uuid,
@@ -256,8 +530,41 @@ impl<S: IndexStore + Sync + Send> IndexActor<S> {
         .map_err(|e| IndexError::Error(e.into()))?
     }
 
+    async fn handle_document_exists(&self, uuid: Uuid, doc_id: String) -> Result<bool> {
+        let index = self
+            .store
+            .get(uuid)
+            .await?
+            .ok_or(IndexError::UnexistingIndex)?;
+        spawn_blocking(move || index.document_exists(&doc_id).map_err(IndexError::Error))
+            .await
+            .map_err(|e| IndexError::Error(e.into()))?
+    }
+
+    async fn handle_fetch_documents_by_ids(
+        &self,
+        uuid: Uuid,
+        doc_ids: Vec<String>,
+        attributes_to_retrieve: Option<Vec<String>>,
+    ) -> Result<Vec<Option<Document>>> {
+        self.check_pagination(0, doc_ids.len())?;
+        let index = self
+            .store
+            .get(uuid)
+            .await?
+            .ok_or(IndexError::UnexistingIndex)?;
+        spawn_blocking(move || {
+            index
+                .retrieve_documents_by_ids(doc_ids, attributes_to_retrieve)
+                .map_err(IndexError::Error)
+        })
+        .await
+        .map_err(|e| IndexError::Error(e.into()))?
+    }
+
     async fn handle_delete(&self, uuid: Uuid) -> Result<()> {
         let index = self.store.delete(uuid).await?;
+        self.search_cache.invalidate(uuid);
 
         if let Some(index) = index {
             tokio::task::spawn(async move {
@@ -276,9 +583,11 @@ impl<S: IndexStore + Sync + Send> IndexActor<S> {
     async fn handle_get_meta(&self, uuid: Uuid) -> Result<IndexMeta> {
         match self.store.get(uuid).await? {
             Some(index) => {
-                let meta = spawn_blocking(move || IndexMeta::new(&index))
-                    .await
-                    .map_err(|e| IndexError::Error(e.into()))??;
+                let index_size = self.store.map_size(uuid).await?;
+                let meta =
+                    spawn_blocking(move || IndexMeta::new(&index)?.with_size(&index, index_size))
+                        .await
+                        .map_err(|e| IndexError::Error(e.into()))??;
                 Ok(meta)
             }
             None => Err(IndexError::UnexistingIndex),
@@ -296,15 +605,22 @@ impl<S: IndexStore + Sync + Send> IndexActor<S> {
             .await?
             .ok_or(IndexError::UnexistingIndex)?;
 
+        let search_cache = self.search_cache.clone();
         spawn_blocking(move || match index_settings.primary_key {
             Some(ref primary_key) => {
                 let mut txn = index.write_txn()?;
-                if index.primary_key(&txn)?.is_some() {
+                // The documents are resolved by their primary key, it can only be changed while
+                // the index is empty.
+                let changed = index
+                    .primary_key(&txn)?
+                    .map_or(false, |current| current != primary_key);
+                if changed && index.number_of_documents(&txn)? > 0 {
                     return Err(IndexError::ExistingPrimaryKey);
                 }
                 index.put_primary_key(&mut txn, primary_key)?;
                 let meta = IndexMeta::new_txn(&index, &txn)?;
                 txn.commit()?;
+                search_cache.invalidate(uuid);
                 Ok(meta)
             }
             None => {
@@ -329,6 +645,13 @@ impl<S: IndexStore + Sync + Send> IndexActor<S> {
             create_dir_all(&index_path)
                 .await
                 .map_err(|e| IndexError::Error(e.into()))?;
+            // The map size the index was created with is needed to reopen it.
+            let map_size_path = index.env.path().join(MAP_SIZE_FILE);
+            if map_size_path.exists() {
+                tokio::fs::copy(map_size_path, index_path.join(MAP_SIZE_FILE))
+                    .await
+                    .map_err(|e| IndexError::Error(e.into()))?;
+            }
             index_path.push("data.mdb");
             spawn_blocking(move || -> anyhow::Result<()> {
                 // Get write txn to wait for ongoing write transaction before snapshot.
@@ -346,6 +669,17 @@ impl<S: IndexStore + Sync + Send> IndexActor<S> {
         Ok(())
     }
 
+    async fn handle_dump(&self, uuid: Uuid, path: PathBuf) -> Result<()> {
+        let index = self
+            .store
+            .get(uuid)
+            .await?
+            .ok_or(IndexError::UnexistingIndex)?;
+        spawn_blocking(move || index.dump(&path).map_err(IndexError::Error))
+            .await
+            .map_err(|e| IndexError::Error(e.into()))?
+    }
+
     async fn handle_get_stats(&self, uuid: Uuid) -> Result<IndexStats> {
         let index = self
             .store
@@ -369,4 +703,31 @@ impl<S: IndexStore + Sync + Send> IndexActor<S> {
         .await
         .map_err(|e| IndexError::Error(e.into()))?
     }
+
+    async fn handle_document_count(&self, uuid: Uuid) -> Result<u64> {
+        let index = self
+            .store
+            .get(uuid)
+            .await?
+            .ok_or(IndexError::UnexistingIndex)?;
+
+        spawn_blocking(move || {
+            let rtxn = index.read_txn().map_err(|e| IndexError::Error(e.into()))?;
+            Ok(index.number_of_documents(&rtxn)?)
+        })
+        .await
+        .map_err(|e| IndexError::Error(e.into()))?
+    }
+}
+
+/// Checks that a page of `limit` documents starting at `offset` can be served, when at most
+/// `max_search_limit` documents can be returned.
+fn check_pagination(max_search_limit: usize, offset: usize, limit: usize) -> Result<()> {
+    if limit > max_search_limit {
+        return Err(IndexError::MaxLimitExceeded(limit, max_search_limit));
+    }
+    if offset.checked_add(limit).is_none() {
+        return Err(IndexError::OffsetOverflow(offset));
+    }
+    Ok(())
 }
@@ -1,14 +1,18 @@
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use tokio::sync::{mpsc, oneshot};
 use uuid::Uuid;
 
-use crate::index::{Document, SearchQuery, SearchResult, Settings};
+use crate::index::{Document, SearchQuery, SearchResult, Settings, SettingsValidation};
+use crate::index_controller::shutdown::Shutdown;
 use crate::index_controller::{updates::Processing, UpdateMeta};
-use crate::index_controller::{IndexSettings, IndexStats};
+use crate::index_controller::{ActorError, IndexSettings, IndexStats};
 
 use super::{
-    IndexActor, IndexActorHandle, IndexMeta, IndexMsg, MapIndexStore, Result, UpdateResult,
+    IndexActor, IndexActorHandle, IndexMeta, IndexMsg, IndexStore, IndexStoreBackend,
+    MapIndexStore, Result, SearchCache, UpdateResult,
 };
 
 #[derive(Clone)]
@@ -19,15 +23,23 @@ pub struct IndexActorHandleImpl {
 
 #[async_trait::async_trait]
 impl IndexActorHandle for IndexActorHandleImpl {
-    async fn create_index(&self, uuid: Uuid, primary_key: Option<String>) -> Result<IndexMeta> {
+    async fn create_index(
+        &self,
+        uuid: Uuid,
+        primary_key: Option<String>,
+        map_size: Option<usize>,
+    ) -> Result<IndexMeta> {
         let (ret, receiver) = oneshot::channel();
         let msg = IndexMsg::CreateIndex {
             ret,
             uuid,
             primary_key,
+            map_size,
         };
         let _ = self.read_sender.send(msg).await;
-        receiver.await.expect("IndexActor has been killed")
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?
     }
 
     async fn update(
@@ -37,22 +49,63 @@ impl IndexActorHandle for IndexActorHandleImpl {
     ) -> anyhow::Result<UpdateResult> {
         let (ret, receiver) = oneshot::channel();
         let msg = IndexMsg::Update { ret, meta, data };
-        let _ = self.read_sender.send(msg).await;
-        Ok(receiver.await.expect("IndexActor has been killed")?)
+        // Updates go through their own mailbox so they can't starve the read requests.
+        let _ = self.write_sender.send(msg).await;
+        Ok(receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))??)
     }
 
     async fn search(&self, uuid: Uuid, query: SearchQuery) -> Result<SearchResult> {
         let (ret, receiver) = oneshot::channel();
         let msg = IndexMsg::Search { uuid, query, ret };
         let _ = self.read_sender.send(msg).await;
-        Ok(receiver.await.expect("IndexActor has been killed")?)
+        Ok(receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))??)
     }
 
     async fn settings(&self, uuid: Uuid) -> Result<Settings> {
         let (ret, receiver) = oneshot::channel();
         let msg = IndexMsg::Settings { uuid, ret };
         let _ = self.read_sender.send(msg).await;
-        Ok(receiver.await.expect("IndexActor has been killed")?)
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?
+    }
+
+    async fn validate_settings(
+        &self,
+        uuid: Uuid,
+        settings: Settings,
+    ) -> Result<SettingsValidation> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = IndexMsg::ValidateSettings {
+            uuid,
+            settings,
+            ret,
+        };
+        let _ = self.read_sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?
+    }
+
+    async fn infer_facet_types(
+        &self,
+        uuid: Uuid,
+        attributes: Vec<String>,
+    ) -> Result<HashMap<String, String>> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = IndexMsg::InferFacetTypes {
+            uuid,
+            attributes,
+            ret,
+        };
+        let _ = self.read_sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?
     }
 
     async fn documents(
@@ -61,7 +114,7 @@ impl IndexActorHandle for IndexActorHandleImpl {
         offset: usize,
         limit: usize,
         attributes_to_retrieve: Option<Vec<String>>,
-    ) -> Result<Vec<Document>> {
+    ) -> Result<(Vec<Document>, u64)> {
         let (ret, receiver) = oneshot::channel();
         let msg = IndexMsg::Documents {
             uuid,
@@ -71,7 +124,26 @@ impl IndexActorHandle for IndexActorHandleImpl {
             limit,
         };
         let _ = self.read_sender.send(msg).await;
-        Ok(receiver.await.expect("IndexActor has been killed")?)
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?
+    }
+
+    async fn export_documents(
+        &self,
+        uuid: Uuid,
+        attributes_to_retrieve: Option<Vec<String>>,
+    ) -> Result<mpsc::Receiver<anyhow::Result<Vec<u8>>>> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = IndexMsg::ExportDocuments {
+            uuid,
+            attributes_to_retrieve,
+            ret,
+        };
+        let _ = self.read_sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?
     }
 
     async fn document(
@@ -88,21 +160,55 @@ impl IndexActorHandle for IndexActorHandleImpl {
             attributes_to_retrieve,
         };
         let _ = self.read_sender.send(msg).await;
-        Ok(receiver.await.expect("IndexActor has been killed")?)
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?
+    }
+
+    async fn documents_by_ids(
+        &self,
+        uuid: Uuid,
+        doc_ids: Vec<String>,
+        attributes_to_retrieve: Option<Vec<String>>,
+    ) -> Result<Vec<Option<Document>>> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = IndexMsg::DocumentsByIds {
+            uuid,
+            ret,
+            doc_ids,
+            attributes_to_retrieve,
+        };
+        let _ = self.read_sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?
+    }
+
+    async fn document_exists(&self, uuid: Uuid, doc_id: String) -> Result<bool> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = IndexMsg::DocumentExists { uuid, doc_id, ret };
+        let _ = self.read_sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?
     }
 
     async fn delete(&self, uuid: Uuid) -> Result<()> {
         let (ret, receiver) = oneshot::channel();
         let msg = IndexMsg::Delete { uuid, ret };
         let _ = self.read_sender.send(msg).await;
-        Ok(receiver.await.expect("IndexActor has been killed")?)
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?
     }
 
     async fn get_index_meta(&self, uuid: Uuid) -> Result<IndexMeta> {
         let (ret, receiver) = oneshot::channel();
         let msg = IndexMsg::GetMeta { uuid, ret };
         let _ = self.read_sender.send(msg).await;
-        Ok(receiver.await.expect("IndexActor has been killed")?)
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?
     }
 
     async fn update_index(&self, uuid: Uuid, index_settings: IndexSettings) -> Result<IndexMeta> {
@@ -113,31 +219,95 @@ impl IndexActorHandle for IndexActorHandleImpl {
             ret,
         };
         let _ = self.read_sender.send(msg).await;
-        Ok(receiver.await.expect("IndexActor has been killed")?)
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?
     }
 
     async fn snapshot(&self, uuid: Uuid, path: PathBuf) -> Result<()> {
         let (ret, receiver) = oneshot::channel();
         let msg = IndexMsg::Snapshot { uuid, path, ret };
         let _ = self.read_sender.send(msg).await;
-        Ok(receiver.await.expect("IndexActor has been killed")?)
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?
+    }
+
+    async fn dump(&self, uuid: Uuid, path: PathBuf) -> Result<()> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = IndexMsg::Dump { uuid, path, ret };
+        let _ = self.read_sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?
     }
 
     async fn get_index_stats(&self, uuid: Uuid) -> Result<IndexStats> {
         let (ret, receiver) = oneshot::channel();
         let msg = IndexMsg::GetStats { uuid, ret };
         let _ = self.read_sender.send(msg).await;
-        Ok(receiver.await.expect("IndexActor has been killed")?)
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?
+    }
+
+    async fn document_count(&self, uuid: Uuid) -> Result<u64> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = IndexMsg::DocumentCount { uuid, ret };
+        let _ = self.read_sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?
+    }
+
+    async fn ping(&self) -> Result<()> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = IndexMsg::Ping { ret };
+        self.read_sender
+            .send(msg)
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("index actor"))?
     }
 }
 
 impl IndexActorHandleImpl {
-    pub fn new(path: impl AsRef<Path>, index_size: usize) -> anyhow::Result<Self> {
-        let (read_sender, read_receiver) = mpsc::channel(100);
-        let (write_sender, write_receiver) = mpsc::channel(100);
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        path: impl AsRef<Path>,
+        backend: IndexStoreBackend,
+        index_size: usize,
+        max_index_size: usize,
+        max_search_limit: usize,
+        default_search_limit: usize,
+        read_mailbox_size: usize,
+        write_mailbox_size: usize,
+        read_concurrency: usize,
+        search_timeout: Duration,
+        search_cache: SearchCache,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<Self> {
+        let (read_sender, read_receiver) = mpsc::channel(read_mailbox_size);
+        let (write_sender, write_receiver) = mpsc::channel(write_mailbox_size);
 
-        let store = MapIndexStore::new(path, index_size);
-        let actor = IndexActor::new(read_receiver, write_receiver, store)?;
+        let store: Box<dyn IndexStore + Sync + Send> = match backend {
+            IndexStoreBackend::Disk => Box::new(MapIndexStore::new(path, index_size)),
+            IndexStoreBackend::Memory => Box::new(MapIndexStore::in_memory(index_size)?),
+        };
+        let actor = IndexActor::new(
+            read_receiver,
+            write_receiver,
+            store,
+            max_index_size,
+            max_search_limit,
+            default_search_limit,
+            read_concurrency,
+            search_timeout,
+            search_cache,
+            shutdown,
+        )?;
         tokio::task::spawn(actor.run());
         Ok(Self {
             read_sender,
@@ -145,3 +315,409 @@ impl IndexActorHandleImpl {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::io::{Seek, SeekFrom};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    use milli::update::{IndexDocumentsMethod, UpdateFormat};
+    use tokio::time::timeout;
+
+    use super::*;
+    use crate::index::DEFAULT_SEARCH_LIMIT;
+    use crate::index_controller::index_actor::IndexError;
+    use crate::index_controller::shutdown;
+    use crate::index_controller::updates::Enqueued;
+    use crate::index_controller::SearchTimeout;
+
+    const INDEX_SIZE: usize = 104_857_600; // 100MiB
+    const MAX_SEARCH_LIMIT: usize = 1000;
+    const MAILBOX_SIZE: usize = 100;
+    const READ_CONCURRENCY: usize = 10;
+    const SEARCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[actix_rt::test]
+    async fn get_index_stats() {
+        let path = tempfile::tempdir_in(".").unwrap();
+        let handle = IndexActorHandleImpl::new(
+            path.path(),
+            IndexStoreBackend::Disk,
+            INDEX_SIZE,
+            INDEX_SIZE,
+            MAX_SEARCH_LIMIT,
+            DEFAULT_SEARCH_LIMIT,
+            MAILBOX_SIZE,
+            MAILBOX_SIZE,
+            READ_CONCURRENCY,
+            SEARCH_TIMEOUT,
+            SearchCache::new(0),
+            shutdown::channel().1,
+        )
+        .unwrap();
+        let uuid = Uuid::new_v4();
+
+        handle
+            .create_index(uuid, Some("id".to_string()), None)
+            .await
+            .unwrap();
+
+        let stats = handle.get_index_stats(uuid).await.unwrap();
+        assert_eq!(stats.number_of_documents, 0);
+        assert!(!stats.is_indexing);
+        assert!(stats.fields_distribution.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn get_index_stats_unexisting_index() {
+        let path = tempfile::tempdir_in(".").unwrap();
+        let handle = IndexActorHandleImpl::new(
+            path.path(),
+            IndexStoreBackend::Disk,
+            INDEX_SIZE,
+            INDEX_SIZE,
+            MAX_SEARCH_LIMIT,
+            DEFAULT_SEARCH_LIMIT,
+            MAILBOX_SIZE,
+            MAILBOX_SIZE,
+            READ_CONCURRENCY,
+            SEARCH_TIMEOUT,
+            SearchCache::new(0),
+            shutdown::channel().1,
+        )
+        .unwrap();
+
+        let result = handle.get_index_stats(Uuid::new_v4()).await;
+        assert!(matches!(result, Err(IndexError::UnexistingIndex)));
+    }
+
+    #[actix_rt::test]
+    async fn document_count_is_sent_on_the_read_channel() {
+        let (read_sender, mut read_receiver) = mpsc::channel(100);
+        let (write_sender, mut write_receiver) = mpsc::channel(100);
+        let handle = IndexActorHandleImpl {
+            read_sender,
+            write_sender,
+        };
+
+        // A reader answering the document count requests in place of the actor.
+        tokio::task::spawn(async move {
+            while let Some(msg) = read_receiver.recv().await {
+                if let IndexMsg::DocumentCount { ret, .. } = msg {
+                    let _ = ret.send(Ok(42));
+                }
+            }
+        });
+
+        let count = handle.document_count(Uuid::new_v4()).await.unwrap();
+        assert_eq!(count, 42);
+        assert!(write_receiver.try_recv().is_err());
+    }
+
+    #[actix_rt::test]
+    async fn killed_actor_is_unavailable() {
+        let (read_sender, read_receiver) = mpsc::channel(100);
+        let (write_sender, _) = mpsc::channel(100);
+        let handle = IndexActorHandleImpl {
+            read_sender,
+            write_sender,
+        };
+
+        // The actor dies while handling the message, without answering.
+        tokio::task::spawn(async move {
+            let mut read_receiver = read_receiver;
+            let _ = read_receiver.recv().await;
+        });
+        let result = handle.get_index_stats(Uuid::new_v4()).await;
+        assert!(matches!(
+            result,
+            Err(IndexError::Actor(ActorError::Unavailable(_)))
+        ));
+
+        // The actor is gone, the messages can't be sent anymore.
+        let result = handle.get_index_meta(Uuid::new_v4()).await;
+        assert!(matches!(
+            result,
+            Err(IndexError::Actor(ActorError::Unavailable(_)))
+        ));
+        assert!(handle.ping().await.is_err());
+    }
+
+    #[actix_rt::test]
+    async fn reads_are_not_blocked_by_writes() {
+        const WRITES: usize = 20;
+        const READS: usize = 1000;
+
+        let path = tempfile::tempdir_in(".").unwrap();
+        let handle = IndexActorHandleImpl::new(
+            path.path(),
+            IndexStoreBackend::Disk,
+            INDEX_SIZE,
+            INDEX_SIZE,
+            MAX_SEARCH_LIMIT,
+            DEFAULT_SEARCH_LIMIT,
+            MAILBOX_SIZE,
+            1,
+            READ_CONCURRENCY,
+            SEARCH_TIMEOUT,
+            SearchCache::new(0),
+            shutdown::channel().1,
+        )
+        .unwrap();
+        let uuid = Uuid::new_v4();
+        handle
+            .create_index(uuid, Some("id".to_string()), None)
+            .await
+            .unwrap();
+
+        // Fill the write mailbox with document additions that take a while to be processed.
+        let finished_writes = Arc::new(AtomicUsize::new(0));
+        let mut writes = Vec::new();
+        for update_id in 0..WRITES {
+            let documents: Vec<_> = (0..5000)
+                .map(|id| serde_json::json!({ "id": id, "content": format!("document {}", id) }))
+                .collect();
+            let mut file = tempfile::tempfile().unwrap();
+            serde_json::to_writer(&mut file, &documents).unwrap();
+            file.seek(SeekFrom::Start(0)).unwrap();
+
+            let meta = UpdateMeta::DocumentsAddition {
+                method: IndexDocumentsMethod::ReplaceDocuments,
+                format: UpdateFormat::Json,
+                primary_key: None,
+                strict: false,
+                csv_delimiter: None,
+            };
+            let meta = Enqueued::new(meta, update_id as u64, uuid).processing();
+
+            let handle = handle.clone();
+            let finished_writes = finished_writes.clone();
+            writes.push(tokio::task::spawn(async move {
+                let result = handle.update(meta, file).await;
+                finished_writes.fetch_add(1, Ordering::SeqCst);
+                result
+            }));
+        }
+
+        let start = Instant::now();
+        let reads = (0..READS).map(|_| handle.get_index_stats(uuid));
+        let results = timeout(Duration::from_secs(10), futures::future::join_all(reads))
+            .await
+            .expect("the reads were blocked by the writes");
+        let elapsed = start.elapsed();
+
+        assert!(results.iter().all(Result::is_ok));
+        assert!(
+            finished_writes.load(Ordering::SeqCst) < WRITES,
+            "the {} reads took {:?}, all the writes were processed first",
+            READS,
+            elapsed
+        );
+
+        for write in writes {
+            assert!(write.await.unwrap().is_ok());
+        }
+    }
+
+    #[actix_rt::test]
+    async fn searches_are_answered_during_update() {
+        const SEARCHES: usize = 50;
+
+        let path = tempfile::tempdir_in(".").unwrap();
+        let handle = IndexActorHandleImpl::new(
+            path.path(),
+            IndexStoreBackend::Disk,
+            INDEX_SIZE,
+            INDEX_SIZE,
+            MAX_SEARCH_LIMIT,
+            DEFAULT_SEARCH_LIMIT,
+            MAILBOX_SIZE,
+            MAILBOX_SIZE,
+            READ_CONCURRENCY,
+            SEARCH_TIMEOUT,
+            SearchCache::new(0),
+            shutdown::channel().1,
+        )
+        .unwrap();
+        let uuid = Uuid::new_v4();
+        handle
+            .create_index(uuid, Some("id".to_string()), None)
+            .await
+            .unwrap();
+
+        let documents: Vec<_> = (0..100_000)
+            .map(|id| serde_json::json!({ "id": id, "content": format!("document {}", id) }))
+            .collect();
+        let mut file = tempfile::tempfile().unwrap();
+        serde_json::to_writer(&mut file, &documents).unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let meta = UpdateMeta::DocumentsAddition {
+            method: IndexDocumentsMethod::ReplaceDocuments,
+            format: UpdateFormat::Json,
+            primary_key: None,
+            strict: false,
+            csv_delimiter: None,
+        };
+        let meta = Enqueued::new(meta, 0, uuid).processing();
+
+        let update = {
+            let handle = handle.clone();
+            tokio::task::spawn(async move { handle.update(meta, file).await })
+        };
+
+        let searches = (0..SEARCHES).map(|_| {
+            let query = serde_json::from_value(serde_json::json!({ "q": "document" })).unwrap();
+            let handle = handle.clone();
+            async move {
+                let start = Instant::now();
+                let result = handle.search(uuid, query).await;
+                (result, start.elapsed())
+            }
+        });
+        let results = timeout(Duration::from_secs(5), futures::future::join_all(searches))
+            .await
+            .expect("the searches waited for the update");
+
+        for (result, elapsed) in results {
+            assert!(result.is_ok());
+            assert!(
+                elapsed < Duration::from_secs(1),
+                "a search took {:?}",
+                elapsed
+            );
+        }
+
+        assert!(update.await.unwrap().is_ok());
+    }
+
+    #[actix_rt::test]
+    async fn search_times_out() {
+        let path = tempfile::tempdir_in(".").unwrap();
+        let handle = IndexActorHandleImpl::new(
+            path.path(),
+            IndexStoreBackend::Disk,
+            INDEX_SIZE,
+            INDEX_SIZE,
+            MAX_SEARCH_LIMIT,
+            DEFAULT_SEARCH_LIMIT,
+            MAILBOX_SIZE,
+            MAILBOX_SIZE,
+            1,
+            Duration::from_millis(1),
+            SearchCache::new(0),
+            shutdown::channel().1,
+        )
+        .unwrap();
+        let uuid = Uuid::new_v4();
+        handle
+            .create_index(uuid, Some("id".to_string()), None)
+            .await
+            .unwrap();
+
+        let documents: Vec<_> = (0..50_000)
+            .map(|id| {
+                serde_json::json!({
+                    "id": id,
+                    "content": format!("document {} with some words in common", id),
+                })
+            })
+            .collect();
+        let mut file = tempfile::tempfile().unwrap();
+        serde_json::to_writer(&mut file, &documents).unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let meta = UpdateMeta::DocumentsAddition {
+            method: IndexDocumentsMethod::ReplaceDocuments,
+            format: UpdateFormat::Json,
+            primary_key: None,
+            strict: false,
+            csv_delimiter: None,
+        };
+        let meta = Enqueued::new(meta, 0, uuid).processing();
+        assert!(handle.update(meta, file).await.unwrap().is_ok());
+
+        // Matching many words with typos against all the documents is expensive.
+        let query = serde_json::from_value(serde_json::json!({
+            "q": "documnt wth some wrds in comon",
+            "limit": MAX_SEARCH_LIMIT,
+        }))
+        .unwrap();
+        let result = handle.search(uuid, query).await;
+        assert!(
+            matches!(result, Err(IndexError::Error(ref e)) if e.is::<SearchTimeout>()),
+            "the search didn't time out"
+        );
+
+        // The only reader is freed without waiting for the search to stop.
+        let count = timeout(Duration::from_secs(1), handle.document_count(uuid))
+            .await
+            .expect("the reader is still busy with the search");
+        assert_eq!(count.unwrap(), 50_000);
+    }
+
+    #[actix_rt::test]
+    async fn identical_searches_are_cached_until_a_write() {
+        fn documents_addition(
+            uuid: Uuid,
+            update_id: u64,
+            documents: serde_json::Value,
+        ) -> (Processing<UpdateMeta>, std::fs::File) {
+            let mut file = tempfile::tempfile().unwrap();
+            serde_json::to_writer(&mut file, &documents).unwrap();
+            file.seek(SeekFrom::Start(0)).unwrap();
+            let meta = UpdateMeta::DocumentsAddition {
+                method: IndexDocumentsMethod::ReplaceDocuments,
+                format: UpdateFormat::Json,
+                primary_key: None,
+                strict: false,
+                csv_delimiter: None,
+            };
+            (Enqueued::new(meta, update_id, uuid).processing(), file)
+        }
+
+        let path = tempfile::tempdir_in(".").unwrap();
+        let search_cache = SearchCache::new(10);
+        let handle = IndexActorHandleImpl::new(
+            path.path(),
+            IndexStoreBackend::Disk,
+            INDEX_SIZE,
+            INDEX_SIZE,
+            MAX_SEARCH_LIMIT,
+            DEFAULT_SEARCH_LIMIT,
+            MAILBOX_SIZE,
+            MAILBOX_SIZE,
+            READ_CONCURRENCY,
+            SEARCH_TIMEOUT,
+            search_cache.clone(),
+            shutdown::channel().1,
+        )
+        .unwrap();
+        let uuid = Uuid::new_v4();
+        handle
+            .create_index(uuid, Some("id".to_string()), None)
+            .await
+            .unwrap();
+        let (meta, file) =
+            documents_addition(uuid, 0, serde_json::json!([{ "id": 1, "title": "hello" }]));
+        assert!(handle.update(meta, file).await.unwrap().is_ok());
+
+        let search = || {
+            let query = serde_json::from_value(serde_json::json!({ "q": "hello" })).unwrap();
+            handle.search(uuid, query)
+        };
+        assert_eq!(search().await.unwrap().nb_hits, 1);
+        assert_eq!(search_cache.hits(), 0);
+        assert_eq!(search().await.unwrap().nb_hits, 1);
+        assert_eq!(search_cache.hits(), 1);
+
+        // The document addition evicts the result, the next search sees the new document.
+        let (meta, file) =
+            documents_addition(uuid, 1, serde_json::json!([{ "id": 2, "title": "hello" }]));
+        assert!(handle.update(meta, file).await.unwrap().is_ok());
+        assert_eq!(search().await.unwrap().nb_hits, 2);
+        assert_eq!(search_cache.hits(), 1);
+        assert_eq!(search().await.unwrap().nb_hits, 2);
+        assert_eq!(search_cache.hits(), 2);
+    }
+}
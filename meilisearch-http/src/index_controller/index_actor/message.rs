@@ -1,9 +1,10 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 
-use tokio::sync::oneshot;
+use tokio::sync::{mpsc, oneshot};
 use uuid::Uuid;
 
-use crate::index::{Document, SearchQuery, SearchResult, Settings};
+use crate::index::{Document, SearchQuery, SearchResult, Settings, SettingsValidation};
 use crate::index_controller::{updates::Processing, IndexStats, UpdateMeta};
 
 use super::{IndexMeta, IndexSettings, Result, UpdateResult};
@@ -12,6 +13,7 @@ pub enum IndexMsg {
     CreateIndex {
         uuid: Uuid,
         primary_key: Option<String>,
+        map_size: Option<usize>,
         ret: oneshot::Sender<Result<IndexMeta>>,
     },
     Update {
@@ -28,12 +30,27 @@ pub enum IndexMsg {
         uuid: Uuid,
         ret: oneshot::Sender<Result<Settings>>,
     },
+    ValidateSettings {
+        uuid: Uuid,
+        settings: Settings,
+        ret: oneshot::Sender<Result<SettingsValidation>>,
+    },
+    InferFacetTypes {
+        uuid: Uuid,
+        attributes: Vec<String>,
+        ret: oneshot::Sender<Result<HashMap<String, String>>>,
+    },
     Documents {
         uuid: Uuid,
         attributes_to_retrieve: Option<Vec<String>>,
         offset: usize,
         limit: usize,
-        ret: oneshot::Sender<Result<Vec<Document>>>,
+        ret: oneshot::Sender<Result<(Vec<Document>, u64)>>,
+    },
+    ExportDocuments {
+        uuid: Uuid,
+        attributes_to_retrieve: Option<Vec<String>>,
+        ret: oneshot::Sender<Result<mpsc::Receiver<anyhow::Result<Vec<u8>>>>>,
     },
     Document {
         uuid: Uuid,
@@ -41,6 +58,17 @@ pub enum IndexMsg {
         doc_id: String,
         ret: oneshot::Sender<Result<Document>>,
     },
+    DocumentsByIds {
+        uuid: Uuid,
+        attributes_to_retrieve: Option<Vec<String>>,
+        doc_ids: Vec<String>,
+        ret: oneshot::Sender<Result<Vec<Option<Document>>>>,
+    },
+    DocumentExists {
+        uuid: Uuid,
+        doc_id: String,
+        ret: oneshot::Sender<Result<bool>>,
+    },
     Delete {
         uuid: Uuid,
         ret: oneshot::Sender<Result<()>>,
@@ -59,8 +87,20 @@ pub enum IndexMsg {
         path: PathBuf,
         ret: oneshot::Sender<Result<()>>,
     },
+    Dump {
+        uuid: Uuid,
+        path: PathBuf,
+        ret: oneshot::Sender<Result<()>>,
+    },
     GetStats {
         uuid: Uuid,
         ret: oneshot::Sender<Result<IndexStats>>,
     },
+    DocumentCount {
+        uuid: Uuid,
+        ret: oneshot::Sender<Result<u64>>,
+    },
+    Ping {
+        ret: oneshot::Sender<Result<()>>,
+    },
 }
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 use chrono::{DateTime, Utc};
@@ -5,18 +6,21 @@ use chrono::{DateTime, Utc};
 use mockall::automock;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use tokio::sync::mpsc;
 use uuid::Uuid;
 
 use actor::IndexActor;
 pub use handle_impl::IndexActorHandleImpl;
 use message::IndexMsg;
-use store::{IndexStore, MapIndexStore};
+pub use search_cache::SearchCache;
+pub use store::IndexStoreBackend;
+use store::{IndexStore, MapIndexStore, MAP_SIZE_FILE};
 
 use crate::index::UpdateResult as UResult;
-use crate::index::{Document, Index, SearchQuery, SearchResult, Settings};
+use crate::index::{Document, Index, SearchQuery, SearchResult, Settings, SettingsValidation};
 use crate::index_controller::{
     updates::{Failed, Processed, Processing},
-    IndexStats, UpdateMeta,
+    ActorError, IndexStats, UpdateMeta,
 };
 
 use super::IndexSettings;
@@ -24,6 +28,7 @@ use super::IndexSettings;
 mod actor;
 mod handle_impl;
 mod message;
+mod search_cache;
 mod store;
 
 pub type Result<T> = std::result::Result<T, IndexError>;
@@ -32,9 +37,20 @@ type UpdateResult = std::result::Result<Processed<UpdateMeta, UResult>, Failed<U
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct IndexMeta {
-    created_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
-    primary_key: Option<String>,
+    pub primary_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<IndexSize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexSize {
+    /// The size in bytes of the data file of the index environment.
+    pub used: u64,
+    /// The size in bytes the index environment is allowed to map.
+    pub mapped: u64,
 }
 
 impl IndexMeta {
@@ -51,8 +67,27 @@ impl IndexMeta {
             primary_key,
             updated_at,
             created_at,
+            size: None,
         })
     }
+
+    /// Adds the size of the index environment to the meta. Only the data file metadata are read,
+    /// which is much cheaper than computing the size of the whole index directory.
+    fn with_size(mut self, index: &Index, mapped: usize) -> Result<Self> {
+        let used = std::fs::metadata(index.env.path().join("data.mdb"))
+            .map_err(|e| IndexError::Error(e.into()))?
+            .len();
+        self.size = Some(IndexSize {
+            used,
+            mapped: mapped as u64,
+        });
+        Ok(self)
+    }
+
+    /// The size the environment of the index maps, if the size of the index was read.
+    pub fn map_size(&self) -> Option<usize> {
+        self.size.map(|size| size.mapped as usize)
+    }
 }
 
 #[derive(Error, Debug)]
@@ -65,14 +100,27 @@ pub enum IndexError {
     UnexistingIndex,
     #[error("Heed error: {0}")]
     HeedError(#[from] heed::Error),
-    #[error("Existing primary key")]
+    #[error("The primary key can't be changed, the index must be empty first")]
     ExistingPrimaryKey,
+    #[error("Limit {0} exceeds the maximum limit of {1}")]
+    MaxLimitExceeded(usize, usize),
+    #[error("Offset {0} is too large")]
+    OffsetOverflow(usize),
+    #[error("{0}")]
+    Actor(#[from] ActorError),
 }
 
 #[async_trait::async_trait]
 #[cfg_attr(test, automock)]
 pub trait IndexActorHandle {
-    async fn create_index(&self, uuid: Uuid, primary_key: Option<String>) -> Result<IndexMeta>;
+    /// Creates the index, its environment maps `map_size` bytes instead of the default size when
+    /// it is set.
+    async fn create_index(
+        &self,
+        uuid: Uuid,
+        primary_key: Option<String>,
+        map_size: Option<usize>,
+    ) -> Result<IndexMeta>;
     async fn update(
         &self,
         meta: Processing<UpdateMeta>,
@@ -80,23 +128,54 @@ pub trait IndexActorHandle {
     ) -> anyhow::Result<UpdateResult>;
     async fn search(&self, uuid: Uuid, query: SearchQuery) -> Result<SearchResult>;
     async fn settings(&self, uuid: Uuid) -> Result<Settings>;
+    /// Checks the settings against the index, without enqueuing an update.
+    async fn validate_settings(&self, uuid: Uuid, settings: Settings)
+        -> Result<SettingsValidation>;
+    /// Returns the facet types to give to the attributes to make them filterable.
+    async fn infer_facet_types(
+        &self,
+        uuid: Uuid,
+        attributes: Vec<String>,
+    ) -> Result<HashMap<String, String>>;
 
+    /// Returns a page of the documents of the index along with the number of documents of the
+    /// index.
     async fn documents(
         &self,
         uuid: Uuid,
         offset: usize,
         limit: usize,
         attributes_to_retrieve: Option<Vec<String>>,
-    ) -> Result<Vec<Document>>;
+    ) -> Result<(Vec<Document>, u64)>;
+    /// Streams all the documents of the index as JSON lines, they are read as the receiver
+    /// consumes them.
+    async fn export_documents(
+        &self,
+        uuid: Uuid,
+        attributes_to_retrieve: Option<Vec<String>>,
+    ) -> Result<mpsc::Receiver<anyhow::Result<Vec<u8>>>>;
     async fn document(
         &self,
         uuid: Uuid,
         doc_id: String,
         attributes_to_retrieve: Option<Vec<String>>,
     ) -> Result<Document>;
+    async fn documents_by_ids(
+        &self,
+        uuid: Uuid,
+        doc_ids: Vec<String>,
+        attributes_to_retrieve: Option<Vec<String>>,
+    ) -> Result<Vec<Option<Document>>>;
+    async fn document_exists(&self, uuid: Uuid, doc_id: String) -> Result<bool>;
     async fn delete(&self, uuid: Uuid) -> Result<()>;
     async fn get_index_meta(&self, uuid: Uuid) -> Result<IndexMeta>;
     async fn update_index(&self, uuid: Uuid, index_settings: IndexSettings) -> Result<IndexMeta>;
     async fn snapshot(&self, uuid: Uuid, path: PathBuf) -> Result<()>;
+    /// Writes the settings and the documents of the index in portable files at `path`.
+    async fn dump(&self, uuid: Uuid, path: PathBuf) -> Result<()>;
     async fn get_index_stats(&self, uuid: Uuid) -> Result<IndexStats>;
+    /// Returns the number of documents of the index, without reading them.
+    async fn document_count(&self, uuid: Uuid) -> Result<u64>;
+    /// Checks that the actor is processing its messages.
+    async fn ping(&self) -> Result<()>;
 }
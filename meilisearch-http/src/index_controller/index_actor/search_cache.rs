@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::index::{SearchQuery, SearchResult};
+
+/// A least recently used cache of the search results, shared by the readers of the index actor.
+/// The entries are tied to the generation of their index, which is bumped on every write so that
+/// the results computed before the write are never served afterward.
+#[derive(Clone)]
+pub struct SearchCache {
+    inner: Option<Arc<Inner>>,
+}
+
+struct Inner {
+    capacity: usize,
+    state: Mutex<State>,
+    hits: AtomicU64,
+}
+
+#[derive(Default)]
+struct State {
+    generations: HashMap<Uuid, u64>,
+    /// The entries from the least to the most recently used.
+    entries: IndexMap<CacheKey, SearchResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    uuid: Uuid,
+    generation: u64,
+    query: String,
+}
+
+/// The key of a search in the cache, along with the generation of the index it was computed for.
+pub struct SearchKey(Option<CacheKey>);
+
+impl SearchCache {
+    /// Creates a cache holding up to `capacity` results, it is disabled when `capacity` is `0`.
+    pub fn new(capacity: usize) -> Self {
+        let inner = (capacity > 0).then(|| {
+            Arc::new(Inner {
+                capacity,
+                state: Mutex::new(State::default()),
+                hits: AtomicU64::new(0),
+            })
+        });
+        Self { inner }
+    }
+
+    /// Returns the key of `query` on the index `uuid` at its current generation.
+    pub fn key(&self, uuid: Uuid, query: &SearchQuery) -> SearchKey {
+        let key = self.inner.as_ref().and_then(|inner| {
+            let query = normalize(query)?;
+            let generation = inner.state.lock().generation(uuid);
+            Some(CacheKey {
+                uuid,
+                generation,
+                query,
+            })
+        });
+        SearchKey(key)
+    }
+
+    /// Returns the cached result of a search, and marks it as the most recently used.
+    pub fn get(&self, key: &SearchKey) -> Option<SearchResult> {
+        let (inner, key) = self.inner.as_ref().zip(key.0.as_ref())?;
+        let mut state = inner.state.lock();
+        let (_, key, result) = state.entries.shift_remove_full(key)?;
+        state.entries.insert(key, result.clone());
+        inner.hits.fetch_add(1, Ordering::Relaxed);
+        Some(result)
+    }
+
+    /// Caches the result of a search, unless its index was written to since the key was made.
+    pub fn insert(&self, key: SearchKey, result: &SearchResult) {
+        if let Some((inner, key)) = self.inner.as_ref().zip(key.0) {
+            let mut state = inner.state.lock();
+            if state.generation(key.uuid) != key.generation {
+                return;
+            }
+            state.entries.insert(key, result.clone());
+            if state.entries.len() > inner.capacity {
+                state.entries.shift_remove_index(0);
+            }
+        }
+    }
+
+    /// Bumps the generation of the index `uuid` and evicts its results, it must be called once the
+    /// index has been written to.
+    pub fn invalidate(&self, uuid: Uuid) {
+        if let Some(inner) = self.inner.as_ref() {
+            let mut state = inner.state.lock();
+            *state.generations.entry(uuid).or_default() += 1;
+            state.entries.retain(|key, _| key.uuid != uuid);
+        }
+    }
+
+    /// The number of searches served from the cache.
+    pub fn hits(&self) -> u64 {
+        self.inner
+            .as_ref()
+            .map_or(0, |inner| inner.hits.load(Ordering::Relaxed))
+    }
+}
+
+impl State {
+    fn generation(&self, uuid: Uuid) -> u64 {
+        self.generations.get(&uuid).copied().unwrap_or_default()
+    }
+}
+
+/// Serializes `query` so that the equivalent queries have the same key, the attributes to
+/// highlight are a set and are sorted.
+fn normalize(query: &SearchQuery) -> Option<String> {
+    let mut value = serde_json::to_value(query).ok()?;
+    if let Some(Value::Array(attributes)) = value.get_mut("attributesToHighlight") {
+        attributes.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+    }
+    serde_json::to_string(&value).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn query(q: &str) -> SearchQuery {
+        serde_json::from_value(json!({ "q": q })).unwrap()
+    }
+
+    fn result(query: &str) -> SearchResult {
+        SearchResult {
+            hits: Vec::new(),
+            nb_hits: 0,
+            estimated_total_hits: 0,
+            exhaustive_nb_hits: false,
+            query: query.to_string(),
+            limit: 20,
+            offset: 0,
+            processing_time_ms: 0,
+            facets_distribution: None,
+        }
+    }
+
+    #[test]
+    fn least_recently_used_result_is_evicted() {
+        let cache = SearchCache::new(2);
+        let uuid = Uuid::new_v4();
+        for q in &["a", "b"] {
+            cache.insert(cache.key(uuid, &query(q)), &result(q));
+        }
+        assert!(cache.get(&cache.key(uuid, &query("a"))).is_some());
+        cache.insert(cache.key(uuid, &query("c")), &result("c"));
+
+        assert!(cache.get(&cache.key(uuid, &query("b"))).is_none());
+        assert!(cache.get(&cache.key(uuid, &query("a"))).is_some());
+        assert!(cache.get(&cache.key(uuid, &query("c"))).is_some());
+        assert_eq!(cache.hits(), 3);
+    }
+
+    #[test]
+    fn result_read_before_a_write_is_not_cached() {
+        let cache = SearchCache::new(2);
+        let uuid = Uuid::new_v4();
+        let key = cache.key(uuid, &query("a"));
+        cache.invalidate(uuid);
+        cache.insert(key, &result("a"));
+        assert!(cache.get(&cache.key(uuid, &query("a"))).is_none());
+    }
+
+    #[test]
+    fn highlighted_attributes_are_normalized() {
+        let cache = SearchCache::new(2);
+        let uuid = Uuid::new_v4();
+        let highlight = |attributes: Value| -> SearchQuery {
+            serde_json::from_value(json!({ "q": "a", "attributesToHighlight": attributes }))
+                .unwrap()
+        };
+        let key = cache.key(uuid, &highlight(json!(["title", "genre", "overview"])));
+        cache.insert(key, &result("a"));
+        let key = cache.key(uuid, &highlight(json!(["overview", "title", "genre"])));
+        assert!(cache.get(&key).is_some());
+    }
+}
@@ -3,6 +3,7 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use heed::EnvOpenOptions;
+use tempfile::TempDir;
 use tokio::fs;
 use tokio::sync::RwLock;
 use tokio::task::spawn_blocking;
@@ -13,17 +14,54 @@ use crate::index::Index;
 
 type AsyncMap<K, V> = Arc<RwLock<HashMap<K, V>>>;
 
+/// The file of the index directory holding the map size given to the index at its creation, if
+/// any. The index is reopened with it instead of the default size.
+pub const MAP_SIZE_FILE: &str = "map-size";
+
+/// The directory the environments of the in-memory indexes are put in, when it exists. It is a
+/// tmpfs on most linux systems.
+const SHARED_MEMORY_DIR: &str = "/dev/shm";
+
+/// The store backing the indexes of the index actor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexStoreBackend {
+    /// The indexes are stored in the `indexes` directory of the database.
+    Disk,
+    /// The indexes are lost when the server stops, see `MapIndexStore::in_memory`. It is meant for
+    /// the tests, which don't need to persist the indexes.
+    Memory,
+}
+
+impl Default for IndexStoreBackend {
+    fn default() -> Self {
+        Self::Disk
+    }
+}
+
+/// Opens, creates and deletes the indexes of the index actor, which doesn't know where they are
+/// stored.
 #[async_trait::async_trait]
 pub trait IndexStore {
-    async fn create(&self, uuid: Uuid, primary_key: Option<String>) -> Result<Index>;
+    async fn create(
+        &self,
+        uuid: Uuid,
+        primary_key: Option<String>,
+        map_size: Option<usize>,
+    ) -> Result<Index>;
     async fn get(&self, uuid: Uuid) -> Result<Option<Index>>;
     async fn delete(&self, uuid: Uuid) -> Result<Option<Index>>;
+    /// Returns the size the environment of the index maps.
+    async fn map_size(&self, uuid: Uuid) -> Result<usize>;
+    /// Closes the index, it is reopened with `map_size` afterwards.
+    async fn resize(&self, uuid: Uuid, map_size: usize) -> Result<()>;
 }
 
 pub struct MapIndexStore {
     index_store: AsyncMap<Uuid, Index>,
     path: PathBuf,
     index_size: usize,
+    /// The directory of the in-memory indexes, removed along with the store.
+    _temp_dir: Option<TempDir>,
 }
 
 impl MapIndexStore {
@@ -34,21 +72,47 @@ impl MapIndexStore {
             index_store,
             path,
             index_size,
+            _temp_dir: None,
         }
     }
+
+    /// Keeps the indexes for the lifetime of the store only. As LMDB maps its environments from
+    /// files, they are put in a temporary directory in shared memory when possible, and on disk
+    /// otherwise.
+    pub fn in_memory(index_size: usize) -> anyhow::Result<Self> {
+        let mut builder = tempfile::Builder::new();
+        builder.prefix("meilisearch-indexes-");
+        let dir = if Path::new(SHARED_MEMORY_DIR).is_dir() {
+            builder.tempdir_in(SHARED_MEMORY_DIR)?
+        } else {
+            builder.tempdir()?
+        };
+        let mut store = Self::new(dir.path(), index_size);
+        store._temp_dir = Some(dir);
+        Ok(store)
+    }
 }
 
 #[async_trait::async_trait]
 impl IndexStore for MapIndexStore {
-    async fn create(&self, uuid: Uuid, primary_key: Option<String>) -> Result<Index> {
+    async fn create(
+        &self,
+        uuid: Uuid,
+        primary_key: Option<String>,
+        map_size: Option<usize>,
+    ) -> Result<Index> {
         let path = self.path.join(format!("index-{}", uuid));
         if path.exists() {
             return Err(IndexError::IndexAlreadyExists);
         }
 
-        let index_size = self.index_size;
+        let index_size = map_size.unwrap_or(self.index_size);
         let index = spawn_blocking(move || -> Result<Index> {
             let index = open_index(&path, index_size)?;
+            if let Some(map_size) = map_size {
+                std::fs::write(path.join(MAP_SIZE_FILE), map_size.to_string())
+                    .map_err(|e| IndexError::Error(e.into()))?;
+            }
             if let Some(primary_key) = primary_key {
                 let mut txn = index.write_txn()?;
                 index.put_primary_key(&mut txn, &primary_key)?;
@@ -77,9 +141,12 @@ impl IndexStore for MapIndexStore {
                 }
 
                 let index_size = self.index_size;
-                let index = spawn_blocking(move || open_index(path, index_size))
-                    .await
-                    .map_err(|e| IndexError::Error(e.into()))??;
+                let index = spawn_blocking(move || {
+                    let index_size = read_map_size(&path)?.unwrap_or(index_size);
+                    open_index(path, index_size)
+                })
+                .await
+                .map_err(|e| IndexError::Error(e.into()))??;
                 self.index_store.write().await.insert(uuid, index.clone());
                 Ok(Some(index))
             }
@@ -94,12 +161,104 @@ impl IndexStore for MapIndexStore {
         let index = self.index_store.write().await.remove(&uuid);
         Ok(index)
     }
+
+    async fn map_size(&self, uuid: Uuid) -> Result<usize> {
+        let path = self.path.join(format!("index-{}", uuid));
+        let map_size = read_map_size(path)?;
+        Ok(map_size.unwrap_or(self.index_size))
+    }
+
+    async fn resize(&self, uuid: Uuid, map_size: usize) -> Result<()> {
+        let path = self.path.join(format!("index-{}", uuid));
+        fs::write(path.join(MAP_SIZE_FILE), map_size.to_string())
+            .await
+            .map_err(|e| IndexError::Error(e.into()))?;
+
+        // The environment is marked as closing while the store is locked, so that it can't be
+        // reopened with the former size, but the store is unlocked before waiting for the other
+        // users of the index to release it.
+        let closing = self
+            .index_store
+            .write()
+            .await
+            .remove(&uuid)
+            .map(|index| index.env.clone().prepare_for_closing());
+        if let Some(closing) = closing {
+            spawn_blocking(move || closing.wait())
+                .await
+                .map_err(|e| IndexError::Error(e.into()))?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: IndexStore + Sync + Send + ?Sized> IndexStore for Box<S> {
+    async fn create(
+        &self,
+        uuid: Uuid,
+        primary_key: Option<String>,
+        map_size: Option<usize>,
+    ) -> Result<Index> {
+        (**self).create(uuid, primary_key, map_size).await
+    }
+
+    async fn get(&self, uuid: Uuid) -> Result<Option<Index>> {
+        (**self).get(uuid).await
+    }
+
+    async fn delete(&self, uuid: Uuid) -> Result<Option<Index>> {
+        (**self).delete(uuid).await
+    }
+
+    async fn map_size(&self, uuid: Uuid) -> Result<usize> {
+        (**self).map_size(uuid).await
+    }
+
+    async fn resize(&self, uuid: Uuid, map_size: usize) -> Result<()> {
+        (**self).resize(uuid, map_size).await
+    }
 }
 
 fn open_index(path: impl AsRef<Path>, size: usize) -> Result<Index> {
     std::fs::create_dir_all(&path).map_err(|e| IndexError::Error(e.into()))?;
     let mut options = EnvOpenOptions::new();
     options.map_size(size);
-    let index = milli::Index::new(options, &path).map_err(IndexError::Error)?;
+    let index = match milli::Index::new(options.clone(), &path) {
+        // The environment of a resized index is closed once it is released by its users, it is
+        // then reopened with its new size.
+        Err(e)
+            if matches!(
+                e.downcast_ref::<heed::Error>(),
+                Some(heed::Error::DatabaseClosing)
+            ) =>
+        {
+            let path = path
+                .as_ref()
+                .canonicalize()
+                .map_err(|e| IndexError::Error(e.into()))?;
+            if let Some(closing) = heed::env_closing_event(&path) {
+                closing.wait();
+            }
+            milli::Index::new(options, &path)
+        }
+        result => result,
+    }
+    .map_err(IndexError::Error)?;
     Ok(Index(Arc::new(index)))
 }
+
+/// Returns the map size stored in the directory of an index, if it was created with one.
+fn read_map_size(path: impl AsRef<Path>) -> Result<Option<usize>> {
+    match std::fs::read_to_string(path.as_ref().join(MAP_SIZE_FILE)) {
+        Ok(map_size) => {
+            let map_size = map_size
+                .trim()
+                .parse()
+                .map_err(|e: std::num::ParseIntError| IndexError::Error(e.into()))?;
+            Ok(Some(map_size))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(IndexError::Error(e.into())),
+    }
+}
@@ -1,32 +1,45 @@
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
 use actix_web::web::{Bytes, Payload};
-use anyhow::bail;
+use futures::future::join_all;
 use futures::stream::StreamExt;
-use log::info;
+use log::{error, info};
 use milli::update::{IndexDocumentsMethod, UpdateFormat};
 use milli::FieldsDistribution;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 use tokio::sync::mpsc;
 use tokio::time::sleep;
 use uuid::Uuid;
 
 use index_actor::IndexActorHandle;
+pub use index_actor::IndexStoreBackend;
+use shutdown::ShutdownSignal;
 use snapshot::load_snapshot;
 use snapshot::SnapshotService;
+use stats::index_stats;
+use trash::TrashPurger;
 use update_actor::UpdateActorHandle;
-pub use updates::{Failed, Processed, Processing};
-use uuid_resolver::UuidError;
+pub use updates::{Failed, Priority, Processed, Processing, PruneBefore, QueueStats, UpdateState};
 use uuid_resolver::UuidResolverHandle;
+use uuid_resolver::{RenameHook, UuidError};
 
+use crate::data::KeyStore;
+use crate::helpers::Clock;
 use crate::index::{Document, SearchQuery, SearchResult};
-use crate::index::{Facets, Settings, UpdateResult};
+use crate::index::{Facets, Settings, SettingsValidation, UpdateResult};
 use crate::option::Opt;
 
+mod health;
 mod index_actor;
+mod shutdown;
 mod snapshot;
+mod stats;
+mod trash;
 mod update_actor;
 mod update_handler;
 mod updates;
@@ -34,6 +47,71 @@ mod uuid_resolver;
 
 pub type UpdateStatus = updates::UpdateStatus<UpdateMeta, UpdateResult, String>;
 
+/// Error returned by the actor handles when their actor can't be reached anymore.
+#[derive(Debug, Error)]
+pub enum ActorError {
+    #[error("The {0} is unavailable.")]
+    Unavailable(&'static str),
+}
+
+impl ActorError {
+    /// Returns whether `error` was caused by an actor that can't be reached.
+    pub fn is_cause_of(error: &anyhow::Error) -> bool {
+        error.chain().any(|cause| cause.is::<ActorError>())
+    }
+}
+
+/// Error returned when a search doesn't complete within the search timeout.
+#[derive(Debug, Error)]
+#[error("The search didn't complete within {0}ms.")]
+pub struct SearchTimeout(pub u128);
+
+impl SearchTimeout {
+    /// Returns whether `error` was caused by a search that timed out.
+    pub fn is_cause_of(error: &anyhow::Error) -> bool {
+        error.chain().any(|cause| cause.is::<SearchTimeout>())
+    }
+}
+
+/// Error returned when a documents payload exceeds the payload size limit.
+#[derive(Debug, Error)]
+#[error("The payload exceeds the limit of {0} bytes.")]
+pub struct PayloadTooLarge(pub usize);
+
+impl PayloadTooLarge {
+    /// Returns whether `error` was caused by a payload that was too large.
+    pub fn is_cause_of(error: &anyhow::Error) -> bool {
+        error.chain().any(|cause| cause.is::<PayloadTooLarge>())
+    }
+}
+
+/// Error returned when an index is to be modified while the server is read-only.
+#[derive(Debug, Error)]
+#[error("The server is read-only, the indexes can't be modified.")]
+pub struct ReadOnly;
+
+impl ReadOnly {
+    /// Returns whether `error` was caused by a modification refused by a read-only server.
+    pub fn is_cause_of(error: &anyhow::Error) -> bool {
+        error.chain().any(|cause| cause.is::<ReadOnly>())
+    }
+}
+
+/// The order in which the indexes are listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IndexesSort {
+    Uid,
+    /// From the oldest to the newest index, the indexes created at the same time are sorted by uid.
+    CreatedAt,
+}
+
+impl Default for IndexesSort {
+    fn default() -> Self {
+        Self::Uid
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct IndexMetadata {
@@ -50,9 +128,19 @@ pub enum UpdateMeta {
         method: IndexDocumentsMethod,
         format: UpdateFormat,
         primary_key: Option<String>,
+        /// When set, the update fails if any of the documents already exists.
+        #[serde(default)]
+        strict: bool,
+        /// The delimiter of the CSV payloads, defaults to a comma.
+        #[serde(default)]
+        csv_delimiter: Option<u8>,
     },
     ClearDocuments,
     DeleteDocuments,
+    /// Deletes the documents matching a filter expression, evaluated when the update is processed.
+    DeleteDocumentsByFilter {
+        filter: String,
+    },
     Settings(Settings),
     Facets(Facets),
 }
@@ -61,6 +149,9 @@ pub enum UpdateMeta {
 pub struct IndexSettings {
     pub uid: Option<String>,
     pub primary_key: Option<String>,
+    /// The size in bytes the environment of the index maps instead of `--max-mdb-size`, it can
+    /// only be set at the creation of the index.
+    pub map_size: Option<usize>,
 }
 
 #[derive(Clone, Debug)]
@@ -75,6 +166,21 @@ pub struct IndexController {
     uuid_resolver: uuid_resolver::UuidResolverHandleImpl,
     index_handle: index_actor::IndexActorHandleImpl,
     update_handle: update_actor::UpdateActorHandleImpl<Bytes>,
+    /// Opened once the snapshot is imported, so that its keys are restored as well.
+    key_store: KeyStore,
+    shutdown: ShutdownSignal,
+    payload_size_limit: usize,
+    max_index_map_size: usize,
+    /// Set with `--read-only`, the updates are refused and the write channels are never fed.
+    read_only: bool,
+    /// Set when the deleted indexes are moved to the trash.
+    trash_purger: Option<
+        TrashPurger<
+            uuid_resolver::UuidResolverHandleImpl,
+            update_actor::UpdateActorHandleImpl<Bytes>,
+            index_actor::IndexActorHandleImpl,
+        >,
+    >,
 }
 
 impl IndexController {
@@ -82,6 +188,8 @@ impl IndexController {
         let index_size = options.max_mdb_size.get_bytes() as usize;
         let update_store_size = options.max_udb_size.get_bytes() as usize;
 
+        let snapshot_encryption_key = options.snapshot_encryption_key()?;
+
         if let Some(ref path) = options.import_snapshot {
             info!("Loading from snapshot {:?}", path);
             load_snapshot(
@@ -89,23 +197,73 @@ impl IndexController {
                 path,
                 options.ignore_snapshot_if_db_exists,
                 options.ignore_missing_snapshot,
+                snapshot_encryption_key.as_ref(),
+                options.snapshot_temp_dir.as_deref(),
             )?;
         }
 
         std::fs::create_dir_all(&path)?;
+        let key_store = KeyStore::new(&path)?;
 
-        let uuid_resolver = uuid_resolver::UuidResolverHandleImpl::new(&path)?;
-        let index_handle = index_actor::IndexActorHandleImpl::new(&path, index_size)?;
+        let mailbox_size = options.actor_mailbox_size.get();
+        let index_read_mailbox_size = options
+            .index_read_mailbox_size
+            .map_or(mailbox_size, NonZeroUsize::get);
+        let index_write_mailbox_size = options
+            .index_write_mailbox_size
+            .map_or(mailbox_size, NonZeroUsize::get);
+
+        let (shutdown, shutdown_listener) = shutdown::channel();
+
+        let uuid_resolver = uuid_resolver::UuidResolverHandleImpl::new(
+            &path,
+            options.cascade_aliases_deletion,
+            mailbox_size,
+            shutdown_listener.clone(),
+        )?;
+        let index_handle = index_actor::IndexActorHandleImpl::new(
+            &path,
+            options.index_store,
+            index_size,
+            options.max_index_map_size.get_bytes() as usize,
+            options.max_search_limit,
+            options.default_search_limit,
+            index_read_mailbox_size,
+            index_write_mailbox_size,
+            options.index_read_threads.get(),
+            Duration::from_millis(options.search_timeout_ms),
+            index_actor::SearchCache::new(options.search_cache_size),
+            shutdown_listener.clone(),
+        )?;
+        // The pending updates of the trashed indexes are resumed once the index is restored.
+        let trashed = {
+            let uuid_resolver = uuid_resolver.clone();
+            async move {
+                match uuid_resolver.trashed().await {
+                    Ok(uuids) => uuids.into_iter().collect(),
+                    Err(e) => {
+                        error!("Could not list the trashed indexes: {}", e);
+                        HashSet::new()
+                    }
+                }
+            }
+        };
         let update_handle = update_actor::UpdateActorHandleImpl::new(
             index_handle.clone(),
             &path,
             update_store_size,
+            mailbox_size,
+            shutdown_listener,
+            update_actor::RetryPolicy::new(options.update_max_retries),
+            options.read_only,
+            trashed,
         )?;
 
         if options.schedule_snapshot {
             let snapshot_service = SnapshotService::new(
                 uuid_resolver.clone(),
                 update_handle.clone(),
+                Some(key_store.clone()),
                 Duration::from_secs(options.snapshot_interval_sec),
                 options.snapshot_dir.clone(),
                 options
@@ -113,18 +271,59 @@ impl IndexController {
                     .file_name()
                     .map(|n| n.to_owned().into_string().expect("invalid path"))
                     .unwrap_or_else(|| String::from("data.ms")),
+                options.snapshot_compression_level,
+                options.snapshot_format,
+                snapshot_encryption_key,
+                options.snapshot_uploader()?,
+                options.snapshot_retention,
+                options.snapshot_temp_dir.clone(),
             );
 
             tokio::task::spawn(snapshot_service.run());
         }
 
+        // The trash isn't purged by a read-only server.
+        let trash_purger = options
+            .index_trash_retention_sec
+            .filter(|_| !options.read_only)
+            .map(|retention| {
+                TrashPurger::new(
+                    uuid_resolver.clone(),
+                    update_handle.clone(),
+                    index_handle.clone(),
+                    Duration::from_secs(retention),
+                )
+            });
+        if let Some(ref trash_purger) = trash_purger {
+            tokio::task::spawn(trash_purger.clone().run());
+        }
+
         Ok(Self {
             uuid_resolver,
             index_handle,
             update_handle,
+            key_store,
+            shutdown,
+            payload_size_limit: options.http_payload_size_limit.get_bytes() as usize,
+            max_index_map_size: options.max_index_map_size.get_bytes() as usize,
+            read_only: options.read_only,
+            trash_purger,
         })
     }
 
+    /// Stops the actors, the update being processed, if any, is run to completion. Returns
+    /// `false` if the actors didn't stop within `timeout`.
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        self.shutdown.trigger(timeout).await
+    }
+
+    pub fn key_store(&self) -> &KeyStore {
+        &self.key_store
+    }
+
+    /// Registers a documents addition, the index is created if it doesn't exist yet. Returns the
+    /// enqueued update and whether the index was created.
+    #[allow(clippy::too_many_arguments)]
     pub async fn add_documents(
         &self,
         uid: String,
@@ -132,52 +331,105 @@ impl IndexController {
         format: milli::update::UpdateFormat,
         payload: Payload,
         primary_key: Option<String>,
-    ) -> anyhow::Result<UpdateStatus> {
+        strict: bool,
+        csv_delimiter: Option<u8>,
+        priority: Priority,
+    ) -> anyhow::Result<(UpdateStatus, bool)> {
+        self.check_writable()?;
         let perform_update = |uuid| async move {
             let meta = UpdateMeta::DocumentsAddition {
                 method,
                 format,
                 primary_key,
+                strict,
+                csv_delimiter,
             };
             let (sender, receiver) = mpsc::channel(10);
+            let limit = self.payload_size_limit;
 
             // It is necessary to spawn a local task to send the payload to the update handle to
             // prevent dead_locking between the update_handle::update that waits for the update to be
             // registered and the update_actor that waits for the the payload to be sent to it.
             tokio::task::spawn_local(async move {
-                payload
-                    .map(|bytes| {
-                        bytes.map_err(|e| {
-                            Box::new(e) as Box<dyn std::error::Error + Sync + Send + 'static>
-                        })
-                    })
-                    .for_each(|r| async {
-                        let _ = sender.send(r).await;
-                    })
-                    .await
+                let mut payload = payload;
+                let mut size = 0;
+                while let Some(bytes) = payload.next().await {
+                    let bytes = match bytes {
+                        Ok(bytes) if size + bytes.len() > limit => {
+                            Err(Box::new(PayloadTooLarge(limit))
+                                as Box<dyn std::error::Error + Sync + Send + 'static>)
+                        }
+                        Ok(bytes) => {
+                            size += bytes.len();
+                            Ok(bytes)
+                        }
+                        Err(e) => Err(Box::new(e) as _),
+                    };
+                    // The rest of the payload is not read once it is known to be invalid.
+                    let failed = bytes.is_err();
+                    if sender.send(bytes).await.is_err() || failed {
+                        break;
+                    }
+                }
             });
 
             // This must be done *AFTER* spawning the task.
-            self.update_handle.update(meta, receiver, uuid).await
+            self.update_handle
+                .update(meta, receiver, uuid, priority)
+                .await
         };
 
         match self.uuid_resolver.get(uid).await {
-            Ok(uuid) => Ok(perform_update(uuid).await?),
+            Ok(uuid) => Ok((perform_update(uuid).await?, false)),
             Err(UuidError::UnexistingIndex(name)) => {
                 let uuid = Uuid::new_v4();
                 let status = perform_update(uuid).await?;
                 self.uuid_resolver.insert(name, uuid).await?;
-                Ok(status)
+                Ok((status, true))
             }
             Err(e) => Err(e.into()),
         }
     }
 
+    /// Registers the addition of `documents`, given as JSON lines, the documents that already exist
+    /// in the index are updated with the fields of the new ones.
+    pub async fn merge_documents(
+        &self,
+        uid: String,
+        documents: Vec<u8>,
+        primary_key: Option<String>,
+    ) -> anyhow::Result<UpdateStatus> {
+        self.check_writable()?;
+        let uuid = self.uuid_resolver.get(uid).await?;
+        let meta = UpdateMeta::DocumentsAddition {
+            method: IndexDocumentsMethod::UpdateDocuments,
+            format: UpdateFormat::JsonStream,
+            primary_key,
+            strict: false,
+            csv_delimiter: None,
+        };
+        let (sender, receiver) = mpsc::channel(1);
+
+        tokio::task::spawn(async move {
+            let _ = sender.send(Ok(Bytes::from(documents))).await;
+        });
+
+        let status = self
+            .update_handle
+            .update(meta, receiver, uuid, Priority::Normal)
+            .await?;
+        Ok(status)
+    }
+
     pub async fn clear_documents(&self, uid: String) -> anyhow::Result<UpdateStatus> {
+        self.check_writable()?;
         let uuid = self.uuid_resolver.get(uid).await?;
         let meta = UpdateMeta::ClearDocuments;
         let (_, receiver) = mpsc::channel(1);
-        let status = self.update_handle.update(meta, receiver, uuid).await?;
+        let status = self
+            .update_handle
+            .update(meta, receiver, uuid, Priority::Normal)
+            .await?;
         Ok(status)
     }
 
@@ -186,6 +438,7 @@ impl IndexController {
         uid: String,
         document_ids: Vec<String>,
     ) -> anyhow::Result<UpdateStatus> {
+        self.check_writable()?;
         let uuid = self.uuid_resolver.get(uid).await?;
         let meta = UpdateMeta::DeleteDocuments;
         let (sender, receiver) = mpsc::channel(10);
@@ -196,7 +449,26 @@ impl IndexController {
             let _ = sender.send(Ok(bytes)).await;
         });
 
-        let status = self.update_handle.update(meta, receiver, uuid).await?;
+        let status = self
+            .update_handle
+            .update(meta, receiver, uuid, Priority::Normal)
+            .await?;
+        Ok(status)
+    }
+
+    pub async fn delete_documents_by_filter(
+        &self,
+        uid: String,
+        filter: String,
+    ) -> anyhow::Result<UpdateStatus> {
+        self.check_writable()?;
+        let uuid = self.uuid_resolver.get(uid).await?;
+        let meta = UpdateMeta::DeleteDocumentsByFilter { filter };
+        let (_, receiver) = mpsc::channel(1);
+        let status = self
+            .update_handle
+            .update(meta, receiver, uuid, Priority::Normal)
+            .await?;
         Ok(status)
     }
 
@@ -205,12 +477,16 @@ impl IndexController {
         uid: String,
         settings: Settings,
         create: bool,
+        priority: Priority,
     ) -> anyhow::Result<UpdateStatus> {
+        self.check_writable()?;
         let perform_udpate = |uuid| async move {
             let meta = UpdateMeta::Settings(settings);
             // Nothing so send, drop the sender right away, as not to block the update actor.
             let (_, receiver) = mpsc::channel(1);
-            self.update_handle.update(meta, receiver, uuid).await
+            self.update_handle
+                .update(meta, receiver, uuid, priority)
+                .await
         };
 
         match self.uuid_resolver.get(uid).await {
@@ -229,10 +505,19 @@ impl IndexController {
         &self,
         index_settings: IndexSettings,
     ) -> anyhow::Result<IndexMetadata> {
-        let IndexSettings { uid, primary_key } = index_settings;
+        self.check_writable()?;
+        let IndexSettings {
+            uid,
+            primary_key,
+            map_size,
+        } = index_settings;
         let uid = uid.ok_or_else(|| anyhow::anyhow!("Can't create an index without a uid."))?;
+        self.check_map_size(map_size)?;
         let uuid = self.uuid_resolver.create(uid.clone()).await?;
-        let meta = self.index_handle.create_index(uuid, primary_key).await?;
+        let meta = self
+            .index_handle
+            .create_index(uuid, primary_key, map_size)
+            .await?;
         let _ = self.update_handle.create(uuid).await?;
         let meta = IndexMetadata {
             name: uid.clone(),
@@ -243,22 +528,198 @@ impl IndexController {
         Ok(meta)
     }
 
+    /// Creates several indexes, the failure to create one of them doesn't prevent the creation of
+    /// the others. The outcomes are returned in the order of the given settings.
+    pub async fn create_indexes(
+        &self,
+        indexes_settings: Vec<IndexSettings>,
+    ) -> Vec<anyhow::Result<IndexMetadata>> {
+        // The indexes with an invalid map size aren't created with the others.
+        let checks: Vec<_> = indexes_settings
+            .iter()
+            .map(|settings| {
+                self.check_writable()?;
+                self.check_map_size(settings.map_size)
+            })
+            .collect();
+        let uids = indexes_settings
+            .iter()
+            .zip(&checks)
+            .filter(|(_, check)| check.is_ok())
+            .map(|(settings, _)| settings.uid.clone().unwrap_or_default())
+            .collect();
+        let mut uuids = self.uuid_resolver.create_batch(uids).await.into_iter();
+
+        let mut results = Vec::with_capacity(indexes_settings.len());
+        for (settings, check) in indexes_settings.into_iter().zip(checks) {
+            let result = async {
+                check?;
+                let uuid = uuids.next().expect("missing uuid for a checked index")?;
+                let uid = settings.uid.unwrap_or_default();
+                let meta = self
+                    .index_handle
+                    .create_index(uuid, settings.primary_key, settings.map_size)
+                    .await?;
+                let _ = self.update_handle.create(uuid).await?;
+                Ok(IndexMetadata {
+                    name: uid.clone(),
+                    uid,
+                    meta,
+                })
+            };
+            results.push(result.await);
+        }
+
+        results
+    }
+
+    /// Refuses the modifications of the indexes when the server is read-only.
+    fn check_writable(&self) -> anyhow::Result<()> {
+        if self.read_only {
+            return Err(ReadOnly.into());
+        }
+        Ok(())
+    }
+
+    /// Checks that the map size requested for a new index doesn't exceed `--max-index-map-size`.
+    fn check_map_size(&self, map_size: Option<usize>) -> anyhow::Result<()> {
+        match map_size {
+            Some(map_size) if map_size > self.max_index_map_size => anyhow::bail!(
+                "The map size of {} bytes exceeds the maximum map size of {} bytes.",
+                map_size,
+                self.max_index_map_size
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// Deletes an index, it is moved to the trash instead when the deleted indexes are retained.
     pub async fn delete_index(&self, uid: String) -> anyhow::Result<()> {
+        self.check_writable()?;
+        if self.trash_purger.is_some() {
+            self.uuid_resolver.trash(uid, chrono::Utc::now()).await?;
+            return Ok(());
+        }
         let uuid = self.uuid_resolver.delete(uid).await?;
         self.update_handle.delete(uuid).await?;
         self.index_handle.delete(uuid).await?;
         Ok(())
     }
 
+    /// Deletes several indexes concurrently, the results are returned in the order of `uids`.
+    pub async fn delete_indexes(&self, uids: Vec<String>) -> Vec<anyhow::Result<()>> {
+        join_all(uids.into_iter().map(|uid| self.delete_index(uid))).await
+    }
+
+    /// Restores the last index deleted under `uid`, if it is still in the trash.
+    pub async fn restore_index(&self, uid: String) -> anyhow::Result<IndexMetadata> {
+        self.check_writable()?;
+        let uuid = self.uuid_resolver.restore(uid.clone()).await?;
+        // The update store is opened so that the updates left pending while the index was in the
+        // trash are processed.
+        self.update_handle.create(uuid).await?;
+        let meta = self.index_handle.get_index_meta(uuid).await?;
+        let meta = IndexMetadata {
+            name: uid.clone(),
+            uid,
+            meta,
+        };
+        Ok(meta)
+    }
+
+    /// Purges the indexes trashed for longer than the retention at the time given by `clock`,
+    /// returns how many indexes were purged.
+    pub async fn purge_index_trash(&self, clock: &Clock) -> anyhow::Result<usize> {
+        match self.trash_purger {
+            Some(ref trash_purger) => trash_purger.purge(clock).await,
+            None => Ok(0),
+        }
+    }
+
+    /// Swaps two indexes, each uid points to the index of the other one afterward.
+    pub async fn swap_indexes(&self, first: String, second: String) -> anyhow::Result<()> {
+        self.check_writable()?;
+        self.uuid_resolver.swap(first, second).await?;
+        Ok(())
+    }
+
+    pub async fn create_alias(&self, alias: String, uid: String) -> anyhow::Result<()> {
+        self.check_writable()?;
+        self.uuid_resolver.create_alias(alias, uid).await?;
+        Ok(())
+    }
+
+    pub async fn delete_alias(&self, alias: String) -> anyhow::Result<()> {
+        self.check_writable()?;
+        self.uuid_resolver.delete_alias(alias).await?;
+        Ok(())
+    }
+
     pub async fn update_status(&self, uid: String, id: u64) -> anyhow::Result<UpdateStatus> {
         let uuid = self.uuid_resolver.get(uid).await?;
         let result = self.update_handle.update_status(uuid, id).await?;
         Ok(result)
     }
 
-    pub async fn all_update_status(&self, uid: String) -> anyhow::Result<Vec<UpdateStatus>> {
+    pub async fn abort_update(&self, uid: String, id: u64) -> anyhow::Result<UpdateStatus> {
+        self.check_writable()?;
+        let uuid = self.uuid_resolver.get(uid).await?;
+        let result = self.update_handle.abort(uuid, id).await?;
+        Ok(result)
+    }
+
+    /// Removes the finished updates of an index below `before`, in the state `state` if any, and
+    /// returns how many were removed.
+    pub async fn prune_updates(
+        &self,
+        uid: String,
+        before: PruneBefore,
+        state: Option<UpdateState>,
+    ) -> anyhow::Result<u64> {
+        self.check_writable()?;
+        let uuid = self.uuid_resolver.get(uid).await?;
+        let count = self.update_handle.prune(uuid, before, state).await?;
+        Ok(count)
+    }
+
+    pub async fn watch_update(
+        &self,
+        uid: String,
+        id: u64,
+    ) -> anyhow::Result<mpsc::Receiver<UpdateStatus>> {
+        let uuid = self.uuid_resolver.get(uid).await?;
+        let result = self.update_handle.watch(uuid, id).await?;
+        Ok(result)
+    }
+
+    pub async fn all_update_status(
+        &self,
+        uid: String,
+        offset: usize,
+        limit: Option<usize>,
+        state: Option<UpdateState>,
+    ) -> anyhow::Result<Vec<UpdateStatus>> {
         let uuid = self.uuid_resolver.get(uid).await?;
-        let result = self.update_handle.get_all_updates_status(uuid).await?;
+        let result = self
+            .update_handle
+            .list_updates(uuid, offset, limit, state)
+            .await?;
+        Ok(result)
+    }
+
+    /// Streams the statuses of the updates of an index as JSON lines.
+    pub async fn stream_update_status(
+        &self,
+        uid: String,
+        offset: usize,
+        limit: Option<usize>,
+        state: Option<UpdateState>,
+    ) -> anyhow::Result<mpsc::Receiver<Vec<u8>>> {
+        let uuid = self.uuid_resolver.get(uid).await?;
+        let result = self
+            .update_handle
+            .stream_updates(uuid, offset, limit, state)
+            .await?;
         Ok(result)
     }
 
@@ -280,19 +741,78 @@ impl IndexController {
         Ok(ret)
     }
 
+    /// Lists the `limit` indexes following the `offset` first ones in the `sort` order. When sorted
+    /// by uid, only the metadata of the returned indexes are read.
+    pub async fn list_indexes_page(
+        &self,
+        offset: usize,
+        limit: Option<usize>,
+        sort: IndexesSort,
+    ) -> anyhow::Result<Vec<IndexMetadata>> {
+        let mut uuids = self.uuid_resolver.list().await?;
+        uuids.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        let limit = limit.unwrap_or(usize::MAX);
+
+        let uuids: Vec<_> = match sort {
+            IndexesSort::Uid => uuids.into_iter().skip(offset).take(limit).collect(),
+            IndexesSort::CreatedAt => uuids,
+        };
+
+        let mut ret = Vec::with_capacity(uuids.len());
+        for (uid, uuid) in uuids {
+            let meta = self.index_handle.get_index_meta(uuid).await?;
+            ret.push(IndexMetadata {
+                name: uid.clone(),
+                uid,
+                meta,
+            });
+        }
+
+        if sort == IndexesSort::CreatedAt {
+            // The sort is stable, the indexes created at the same time stay sorted by uid.
+            ret.sort_by_key(|index| index.meta.created_at);
+            ret = ret.into_iter().skip(offset).take(limit).collect();
+        }
+
+        Ok(ret)
+    }
+
     pub async fn settings(&self, uid: String) -> anyhow::Result<Settings> {
         let uuid = self.uuid_resolver.get(uid.clone()).await?;
         let settings = self.index_handle.settings(uuid).await?;
         Ok(settings)
     }
 
+    pub async fn validate_settings(
+        &self,
+        uid: String,
+        settings: Settings,
+    ) -> anyhow::Result<SettingsValidation> {
+        let uuid = self.uuid_resolver.get(uid).await?;
+        let validation = self.index_handle.validate_settings(uuid, settings).await?;
+        Ok(validation)
+    }
+
+    pub async fn infer_facet_types(
+        &self,
+        uid: String,
+        attributes: Vec<String>,
+    ) -> anyhow::Result<HashMap<String, String>> {
+        let uuid = self.uuid_resolver.get(uid).await?;
+        let facet_types = self
+            .index_handle
+            .infer_facet_types(uuid, attributes)
+            .await?;
+        Ok(facet_types)
+    }
+
     pub async fn documents(
         &self,
         uid: String,
         offset: usize,
         limit: usize,
         attributes_to_retrieve: Option<Vec<String>>,
-    ) -> anyhow::Result<Vec<Document>> {
+    ) -> anyhow::Result<(Vec<Document>, u64)> {
         let uuid = self.uuid_resolver.get(uid.clone()).await?;
         let documents = self
             .index_handle
@@ -301,6 +821,20 @@ impl IndexController {
         Ok(documents)
     }
 
+    /// Streams all the documents of the index as JSON lines.
+    pub async fn export_documents(
+        &self,
+        uid: String,
+        attributes_to_retrieve: Option<Vec<String>>,
+    ) -> anyhow::Result<mpsc::Receiver<anyhow::Result<Vec<u8>>>> {
+        let uuid = self.uuid_resolver.get(uid).await?;
+        let documents = self
+            .index_handle
+            .export_documents(uuid, attributes_to_retrieve)
+            .await?;
+        Ok(documents)
+    }
+
     pub async fn document(
         &self,
         uid: String,
@@ -315,17 +849,55 @@ impl IndexController {
         Ok(document)
     }
 
+    pub async fn document_exists(&self, uid: String, doc_id: String) -> anyhow::Result<bool> {
+        let uuid = self.uuid_resolver.get(uid).await?;
+        let exists = self.index_handle.document_exists(uuid, doc_id).await?;
+        Ok(exists)
+    }
+
+    pub async fn documents_by_ids(
+        &self,
+        uid: String,
+        doc_ids: Vec<String>,
+        attributes_to_retrieve: Option<Vec<String>>,
+    ) -> anyhow::Result<Vec<Option<Document>>> {
+        let uuid = self.uuid_resolver.get(uid).await?;
+        let documents = self
+            .index_handle
+            .documents_by_ids(uuid, doc_ids, attributes_to_retrieve)
+            .await?;
+        Ok(documents)
+    }
+
     pub async fn update_index(
         &self,
         uid: String,
         index_settings: IndexSettings,
     ) -> anyhow::Result<IndexMetadata> {
-        if index_settings.uid.is_some() {
-            bail!("Can't change the index uid.")
-        }
-
-        let uuid = self.uuid_resolver.get(uid.clone()).await?;
-        let meta = self.index_handle.update_index(uuid, index_settings).await?;
+        self.check_writable()?;
+        // The index keeps its uuid, and thus its update store and its pending updates, when it is
+        // renamed. Its other settings are updated before the rename is committed, so that both
+        // are applied or none.
+        let (uid, meta) = match index_settings.uid.clone() {
+            Some(new_uid) if new_uid != uid => {
+                let index_handle = self.index_handle.clone();
+                let hook: RenameHook = Box::new(move |uuid| {
+                    futures::executor::block_on(index_handle.update_index(uuid, index_settings))?;
+                    Ok(())
+                });
+                let uuid = self
+                    .uuid_resolver
+                    .rename(uid, new_uid.clone(), hook)
+                    .await?;
+                let meta = self.index_handle.get_index_meta(uuid).await?;
+                (new_uid, meta)
+            }
+            _ => {
+                let uuid = self.uuid_resolver.get(uid.clone()).await?;
+                let meta = self.index_handle.update_index(uuid, index_settings).await?;
+                (uid, meta)
+            }
+        };
         let meta = IndexMetadata {
             name: uid.clone(),
             uid,
@@ -334,6 +906,12 @@ impl IndexController {
         Ok(meta)
     }
 
+    pub async fn document_count(&self, uid: String) -> anyhow::Result<u64> {
+        let uuid = self.uuid_resolver.get(uid).await?;
+        let count = self.index_handle.document_count(uuid).await?;
+        Ok(count)
+    }
+
     pub async fn search(&self, uid: String, query: SearchQuery) -> anyhow::Result<SearchResult> {
         let uuid = self.uuid_resolver.get(uid).await?;
         let result = self.index_handle.search(uuid, query).await?;
@@ -351,10 +929,40 @@ impl IndexController {
         Ok(meta)
     }
 
-    pub async fn get_stats(&self, uid: String) -> anyhow::Result<IndexStats> {
+    /// Dumps the settings, documents and updates of every index in a sub-directory of `path` named
+    /// after its uid, through the same actors as the snapshots. Returns the dumped indexes.
+    pub async fn dump(&self, path: PathBuf) -> anyhow::Result<Vec<IndexMetadata>> {
+        let indexes = self.uuid_resolver.list().await?;
+
+        let mut ret = Vec::with_capacity(indexes.len());
+        for (uid, uuid) in indexes {
+            self.update_handle.dump(uuid, path.join(&uid)).await?;
+            let meta = self.index_handle.get_index_meta(uuid).await?;
+            ret.push(IndexMetadata {
+                name: uid.clone(),
+                uid,
+                meta,
+            });
+        }
+
+        Ok(ret)
+    }
+
+    /// Dumps the settings and the documents of the index `uid` at `path`, without its updates.
+    pub async fn dump_index(&self, uid: String, path: PathBuf) -> anyhow::Result<IndexMetadata> {
         let uuid = self.uuid_resolver.get(uid.clone()).await?;
+        self.index_handle.dump(uuid, path).await?;
+        let meta = self.index_handle.get_index_meta(uuid).await?;
+        Ok(IndexMetadata {
+            name: uid.clone(),
+            uid,
+            meta,
+        })
+    }
 
-        Ok(self.index_handle.get_index_stats(uuid).await?)
+    pub async fn get_stats(&self, uid: String) -> anyhow::Result<IndexStats> {
+        let uuid = self.uuid_resolver.get(uid.clone()).await?;
+        index_stats(&self.update_handle, &self.index_handle, uuid).await
     }
 
     pub async fn get_updates_size(&self, uid: String) -> anyhow::Result<u64> {
@@ -363,9 +971,26 @@ impl IndexController {
         Ok(self.update_handle.get_size(uuid).await?)
     }
 
+    pub async fn get_queue_stats(&self, uid: String) -> anyhow::Result<QueueStats> {
+        let uuid = self.uuid_resolver.get(uid).await?;
+
+        Ok(self.update_handle.queue_stats(uuid).await?)
+    }
+
     pub async fn get_uuids_size(&self) -> anyhow::Result<u64> {
         Ok(self.uuid_resolver.get_size().await?)
     }
+
+    /// Returns the names of the actors that didn't answer a ping within `timeout`.
+    pub async fn unresponsive_actors(&self, timeout: Duration) -> Vec<&'static str> {
+        health::unresponsive_actors(
+            &self.uuid_resolver,
+            &self.update_handle,
+            &self.index_handle,
+            timeout,
+        )
+        .await
+    }
 }
 
 pub async fn get_arc_ownership_blocking<T>(mut item: Arc<T>) -> T {
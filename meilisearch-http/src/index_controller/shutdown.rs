@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use tokio::sync::{mpsc, watch, Mutex};
+
+/// Creates the signal used to shut the actors down, and the listener to hand to each of them.
+pub fn channel() -> (ShutdownSignal, Shutdown) {
+    let (sender, receiver) = watch::channel(false);
+    let (complete_sender, complete_receiver) = mpsc::channel(1);
+
+    let signal = ShutdownSignal {
+        sender,
+        complete: Mutex::new(complete_receiver),
+    };
+    let shutdown = Shutdown {
+        receiver,
+        _complete: complete_sender,
+    };
+
+    (signal, shutdown)
+}
+
+pub struct ShutdownSignal {
+    sender: watch::Sender<bool>,
+    complete: Mutex<mpsc::Receiver<()>>,
+}
+
+impl ShutdownSignal {
+    /// Broadcasts the shutdown to all the listeners and waits for all of them to be dropped.
+    /// Returns `false` if some listeners are still alive after `timeout`.
+    pub async fn trigger(&self, timeout: Duration) -> bool {
+        let _ = self.sender.send(true);
+        let mut complete = self.complete.lock().await;
+        tokio::time::timeout(timeout, complete.recv()).await.is_ok()
+    }
+}
+
+/// Listens for the shutdown signal. An actor must hold its listener until it is done with its
+/// work, the shutdown is complete once every listener has been dropped.
+#[derive(Clone)]
+pub struct Shutdown {
+    receiver: watch::Receiver<bool>,
+    _complete: mpsc::Sender<()>,
+}
+
+impl Shutdown {
+    /// Resolves once the shutdown has been triggered. It never resolves if the signal is dropped
+    /// without being triggered.
+    pub async fn wait(&mut self) {
+        while !*self.receiver.borrow() {
+            if self.receiver.changed().await.is_err() {
+                futures::future::pending::<()>().await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[actix_rt::test]
+    async fn trigger_waits_for_the_listeners() {
+        let (signal, shutdown) = channel();
+
+        let mut listener = shutdown.clone();
+        let (done_sender, mut done_receiver) = mpsc::channel(1);
+        tokio::task::spawn(async move {
+            listener.wait().await;
+            let _ = done_sender.send(()).await;
+        });
+        drop(shutdown);
+
+        assert!(signal.trigger(Duration::from_secs(1)).await);
+        assert!(done_receiver.recv().await.is_some());
+    }
+
+    #[actix_rt::test]
+    async fn trigger_times_out_on_busy_listeners() {
+        let (signal, shutdown) = channel();
+
+        assert!(!signal.trigger(Duration::from_millis(100)).await);
+        drop(shutdown);
+    }
+}
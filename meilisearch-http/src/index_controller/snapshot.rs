@@ -1,22 +1,41 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use anyhow::bail;
-use log::{error, info};
+use chrono::Utc;
+use log::{debug, error, info};
+use tempfile::NamedTempFile;
 use tokio::fs;
-use tokio::task::spawn_blocking;
+use tokio::task::{spawn_blocking, JoinHandle};
 use tokio::time::sleep;
+use walkdir::WalkDir;
 
 use super::update_actor::UpdateActorHandle;
 use super::uuid_resolver::UuidResolverHandle;
-use crate::helpers::compression;
+use crate::data::KeyStore;
+use crate::helpers::compression::{
+    self, CompressionFormat, DecryptReader, EncryptWriter, EncryptionKey,
+};
+use crate::helpers::object_storage::S3Uploader;
 
 pub struct SnapshotService<U, R> {
     uuid_resolver_handle: R,
     update_handle: U,
+    /// The scoped keys are snapshotted along with the indexes if set.
+    key_store: Option<KeyStore>,
     snapshot_period: Duration,
     snapshot_path: PathBuf,
     db_name: String,
+    compression_level: u32,
+    format: CompressionFormat,
+    encryption_key: Option<EncryptionKey>,
+    uploader: Option<S3Uploader>,
+    retention: Option<usize>,
+    /// Where the snapshots are prepared, in the snapshot directory if unset.
+    temp_dir: Option<PathBuf>,
 }
 
 impl<U, R> SnapshotService<U, R>
@@ -24,19 +43,34 @@ where
     U: UpdateActorHandle,
     R: UuidResolverHandle,
 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         uuid_resolver_handle: R,
         update_handle: U,
+        key_store: Option<KeyStore>,
         snapshot_period: Duration,
         snapshot_path: PathBuf,
         db_name: String,
+        compression_level: u32,
+        format: CompressionFormat,
+        encryption_key: Option<EncryptionKey>,
+        uploader: Option<S3Uploader>,
+        retention: Option<usize>,
+        temp_dir: Option<PathBuf>,
     ) -> Self {
         Self {
             uuid_resolver_handle,
             update_handle,
+            key_store,
             snapshot_period,
             snapshot_path,
             db_name,
+            compression_level,
+            format,
+            encryption_key,
+            uploader,
+            retention,
+            temp_dir,
         }
     }
 
@@ -46,20 +80,34 @@ where
             self.snapshot_period.as_secs()
         );
         loop {
-            if let Err(e) = self.perform_snapshot().await {
-                error!("{}", e);
+            match self.perform_snapshot().await {
+                // The upload of a snapshot runs while the next ones are performed.
+                Ok(Some(snapshot_path)) if self.uploader.is_some() => {
+                    if let Err(e) = self.upload_snapshot(snapshot_path) {
+                        error!("{}", e);
+                    }
+                }
+                Ok(_) => (),
+                Err(e) => error!("{}", e),
             }
             sleep(self.snapshot_period).await;
         }
     }
 
-    async fn perform_snapshot(&self) -> anyhow::Result<()> {
+    /// Performs a snapshot and returns its path, or `None` if there was nothing to snapshot.
+    async fn perform_snapshot(&self) -> anyhow::Result<Option<PathBuf>> {
         info!("Performing snapshot.");
 
-        let snapshot_dir = self.snapshot_path.clone();
-        fs::create_dir_all(&snapshot_dir).await?;
-        let temp_snapshot_dir =
-            spawn_blocking(move || tempfile::tempdir_in(snapshot_dir)).await??;
+        fs::create_dir_all(&self.snapshot_path).await?;
+        let temp_dir = self
+            .temp_dir
+            .clone()
+            .unwrap_or_else(|| self.snapshot_path.clone());
+        fs::create_dir_all(&temp_dir).await?;
+        let temp_snapshot_dir = {
+            let temp_dir = temp_dir.clone();
+            spawn_blocking(move || tempfile::tempdir_in(temp_dir)).await??
+        };
         let temp_snapshot_path = temp_snapshot_dir.path().to_owned();
 
         let uuids = self
@@ -68,7 +116,7 @@ where
             .await?;
 
         if uuids.is_empty() {
-            return Ok(());
+            return Ok(None);
         }
 
         let tasks = uuids
@@ -81,23 +129,257 @@ where
 
         futures::future::try_join_all(tasks).await?;
 
-        let snapshot_dir = self.snapshot_path.clone();
-        let snapshot_path = self
-            .snapshot_path
-            .join(format!("{}.snapshot", self.db_name));
+        if let Some(ref key_store) = self.key_store {
+            let key_store = key_store.clone();
+            let path = temp_snapshot_path.clone();
+            spawn_blocking(move || key_store.snapshot(path)).await??;
+        }
+
+        // The snapshots are timestamped when they are retained, otherwise the last one is
+        // overwritten.
+        let snapshot_stem = match self.retention {
+            Some(_) => format!(
+                "{}-{}",
+                self.db_name,
+                Utc::now().format(SNAPSHOT_TIMESTAMP_FORMAT)
+            ),
+            None => self.db_name.clone(),
+        };
+        let mut snapshot_name = match self.format {
+            CompressionFormat::Gzip => format!("{}.snapshot", snapshot_stem),
+            CompressionFormat::Zstd => format!("{}.snapshot.zst", snapshot_stem),
+        };
+        if self.encryption_key.is_some() {
+            snapshot_name.push_str(".enc");
+        }
+        let snapshot_path = self.snapshot_path.join(snapshot_name);
+        let compression_level = self.compression_level;
+        let format = self.format;
+        let encryption_key = self.encryption_key.clone();
         let snapshot_path = spawn_blocking(move || -> anyhow::Result<PathBuf> {
-            let temp_snapshot_file = tempfile::NamedTempFile::new_in(snapshot_dir)?;
-            let temp_snapshot_file_path = temp_snapshot_file.path().to_owned();
-            compression::to_tar_gz(temp_snapshot_path, temp_snapshot_file_path)?;
-            temp_snapshot_file.persist(&snapshot_path)?;
+            let temp_snapshot_file = tempfile::NamedTempFile::new_in(temp_dir)?;
+            let expected_files = list_snapshot_files(&temp_snapshot_path)?;
+            let mut compressed = 0;
+            let progress = |path: &Path| {
+                compressed += 1;
+                debug!(
+                    "Compressed {:?} ({}/{} files).",
+                    path,
+                    compressed,
+                    expected_files.len()
+                );
+            };
+            // The tarball is encrypted as it is compressed, it is never written in plaintext.
+            let writer = BufWriter::new(temp_snapshot_file.as_file());
+            let mut writer = match encryption_key {
+                Some(ref key) => {
+                    let writer = EncryptWriter::new(writer, key)?;
+                    format
+                        .compress_into(&temp_snapshot_path, writer, compression_level, progress)?
+                        .finish()?
+                }
+                None => format.compress_into(
+                    &temp_snapshot_path,
+                    writer,
+                    compression_level,
+                    progress,
+                )?,
+            };
+            writer.flush()?;
+            drop(writer);
+            persist_snapshot(
+                temp_snapshot_file,
+                &snapshot_path,
+                format,
+                &expected_files,
+                encryption_key.as_ref(),
+            )?;
             Ok(snapshot_path)
         })
         .await??;
 
         info!("Created snapshot in {:?}.", snapshot_path);
 
-        Ok(())
+        if let Some(retention) = self.retention {
+            let snapshot_dir = self.snapshot_path.clone();
+            let db_name = self.db_name.clone();
+            let current = snapshot_path.clone();
+            let pruned = spawn_blocking(move || {
+                prune_snapshots(&snapshot_dir, &db_name, retention, &current)
+            })
+            .await?;
+            if let Err(e) = pruned {
+                error!("Could not prune the old snapshots: {}", e);
+            }
+        }
+
+        Ok(Some(snapshot_path))
+    }
+
+    /// Uploads the snapshot at `snapshot_path` in the background, named after its file. A failed
+    /// upload doesn't fail the snapshot, it is kept on the local disk.
+    fn upload_snapshot(&self, snapshot_path: PathBuf) -> anyhow::Result<JoinHandle<()>> {
+        let uploader = match self.uploader {
+            Some(ref uploader) => uploader.clone(),
+            None => bail!(
+                "no object storage to upload snapshot {:?} to",
+                snapshot_path
+            ),
+        };
+        let key = match snapshot_path.file_name().and_then(|name| name.to_str()) {
+            Some(key) => key.to_string(),
+            None => bail!("invalid snapshot name {:?}", snapshot_path),
+        };
+
+        Ok(tokio::spawn(async move {
+            if let Err(e) = uploader.upload_with_retries(&snapshot_path, &key).await {
+                error!("Could not upload snapshot {:?}: {}", snapshot_path, e);
+            }
+        }))
+    }
+}
+
+const SNAPSHOT_TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S%6f";
+
+/// Returns whether `name` is the name of a timestamped snapshot of the `db_name` database.
+fn is_timestamped_snapshot(name: &str, db_name: &str) -> bool {
+    let timestamp_len = "YYYYmmdd-HHMMSSffffff".len();
+    match name.strip_prefix(db_name).and_then(|s| s.strip_prefix('-')) {
+        Some(rest) if rest.len() > timestamp_len && rest.is_char_boundary(timestamp_len) => {
+            let (timestamp, extension) = rest.split_at(timestamp_len);
+            let valid_timestamp = timestamp.bytes().enumerate().all(|(i, b)| match i {
+                8 => b == b'-',
+                _ => b.is_ascii_digit(),
+            });
+            let valid_extension = matches!(
+                extension,
+                ".snapshot" | ".snapshot.zst" | ".snapshot.enc" | ".snapshot.zst.enc"
+            );
+            valid_timestamp && valid_extension
+        }
+        _ => false,
+    }
+}
+
+/// Removes the oldest timestamped snapshots of `dir` so that only the `retention` most recent
+/// ones are kept. The `current` snapshot is always kept and the other files are left untouched.
+fn prune_snapshots(
+    dir: &Path,
+    db_name: &str,
+    retention: usize,
+    current: &Path,
+) -> anyhow::Result<()> {
+    let mut snapshots = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let is_snapshot = entry.file_type()?.is_file()
+            && entry
+                .file_name()
+                .to_str()
+                .map_or(false, |name| is_timestamped_snapshot(name, db_name));
+        if is_snapshot && path != current {
+            snapshots.push(path);
+        }
+    }
+
+    // The timestamps sort chronologically, the current snapshot counts as the most recent one.
+    snapshots.sort_unstable_by(|a, b| b.cmp(a));
+    for path in snapshots.into_iter().skip(retention.saturating_sub(1)) {
+        info!("Removing old snapshot {:?}.", path);
+        std::fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the paths, relative to `dir`, of all the files written in the snapshot directory: the
+/// uuid store, the key store and the update store and index of every uuid.
+fn list_snapshot_files(dir: &Path) -> anyhow::Result<HashSet<PathBuf>> {
+    let mut files = HashSet::new();
+    for entry in WalkDir::new(dir) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            files.insert(entry.path().strip_prefix(dir)?.to_owned());
+        }
+    }
+    Ok(files)
+}
+
+/// Checks that the `temp_snapshot_file` tarball, encrypted with `encryption_key` if any, can be
+/// read entirely and contains all the `expected_files` before moving it to `snapshot_path`. The
+/// temporary file is removed if the verification fails.
+fn persist_snapshot(
+    temp_snapshot_file: NamedTempFile,
+    snapshot_path: &Path,
+    format: CompressionFormat,
+    expected_files: &HashSet<PathBuf>,
+    encryption_key: Option<&EncryptionKey>,
+) -> anyhow::Result<()> {
+    let reader = BufReader::new(File::open(temp_snapshot_file.path())?);
+    let files = match encryption_key {
+        Some(key) => format.list_files_from(DecryptReader::new(reader, key)?)?,
+        None => format.list_files_from(reader)?,
+    };
+    let files = files.into_iter().collect::<HashSet<_>>();
+    if let Some(missing) = expected_files.difference(&files).next() {
+        bail!("corrupted snapshot, missing file {:?}", missing);
     }
+
+    persist_file(temp_snapshot_file, snapshot_path)
+}
+
+/// The error returned by a rename across filesystems, `EXDEV`.
+const CROSS_DEVICE_LINK: i32 = 18;
+
+/// Moves `file` to `path` atomically. When they are on different filesystems, the file is copied
+/// next to `path` first, so that a partial snapshot is never visible at `path`.
+fn persist_file(file: NamedTempFile, path: &Path) -> anyhow::Result<()> {
+    let file = match file.persist(path) {
+        Ok(_) => return Ok(()),
+        Err(e) if e.error.raw_os_error() == Some(CROSS_DEVICE_LINK) => e.file,
+        Err(e) => return Err(e.error.into()),
+    };
+
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let copy = NamedTempFile::new_in(dir)?;
+    std::fs::copy(file.path(), copy.path())?;
+    copy.persist(path)?;
+    Ok(())
+}
+
+/// Decrypts the snapshot at `snapshot_path` if it is encrypted, in `temp_dir` if set, and next to
+/// the `db_path` it is loaded into otherwise. The returned file must be used in place of the
+/// snapshot, it is removed once dropped.
+fn decrypt_snapshot(
+    snapshot_path: &Path,
+    db_path: &Path,
+    encryption_key: Option<&EncryptionKey>,
+    temp_dir: Option<&Path>,
+) -> anyhow::Result<Option<NamedTempFile>> {
+    if snapshot_path.extension().map_or(true, |ext| ext != "enc") {
+        return Ok(None);
+    }
+
+    let key = match encryption_key {
+        Some(key) => key,
+        None => bail!(
+            "snapshot {:?} is encrypted, the encryption key must be provided",
+            snapshot_path
+        ),
+    };
+
+    let dir = match temp_dir {
+        Some(temp_dir) => {
+            std::fs::create_dir_all(temp_dir)?;
+            temp_dir
+        }
+        None => db_path.parent().unwrap_or_else(|| Path::new(".")),
+    };
+    let decrypted_file = NamedTempFile::new_in(dir)?;
+    compression::decrypt_file(snapshot_path, decrypted_file.path(), key)
+        .map_err(|e| anyhow::anyhow!("could not decrypt snapshot {:?}: {}", snapshot_path, e))?;
+    Ok(Some(decrypted_file))
 }
 
 pub fn load_snapshot(
@@ -105,9 +387,29 @@ pub fn load_snapshot(
     snapshot_path: impl AsRef<Path>,
     ignore_snapshot_if_db_exists: bool,
     ignore_missing_snapshot: bool,
+    encryption_key: Option<&EncryptionKey>,
+    temp_dir: Option<&Path>,
 ) -> anyhow::Result<()> {
     if !db_path.as_ref().exists() && snapshot_path.as_ref().exists() {
-        match compression::from_tar_gz(snapshot_path, &db_path) {
+        // The snapshot is entirely decrypted and authenticated before being extracted, so that
+        // nothing is extracted with a wrong key.
+        let decrypted_file = decrypt_snapshot(
+            snapshot_path.as_ref(),
+            db_path.as_ref(),
+            encryption_key,
+            temp_dir,
+        )?;
+        let (snapshot_path, format) = match decrypted_file {
+            Some(ref file) => (
+                file.path().to_owned(),
+                CompressionFormat::from_path(snapshot_path.as_ref().with_extension("")),
+            ),
+            None => (
+                snapshot_path.as_ref().to_owned(),
+                CompressionFormat::from_path(&snapshot_path),
+            ),
+        };
+        match format.decompress(snapshot_path, &db_path) {
             Ok(()) => Ok(()),
             Err(e) => {
                 // clean created db folder
@@ -144,6 +446,7 @@ mod test {
     use uuid::Uuid;
 
     use super::*;
+    use crate::data::{ApiKeys, KeyAction};
     use crate::index_controller::update_actor::{MockUpdateActorHandle, UpdateError};
     use crate::index_controller::uuid_resolver::{MockUuidResolverHandle, UuidError};
 
@@ -172,9 +475,16 @@ mod test {
         let snapshot_service = SnapshotService::new(
             uuid_resolver,
             update_handle,
+            None,
             Duration::from_millis(100),
             snapshot_path.path().to_owned(),
             "data.ms".to_string(),
+            compression::DEFAULT_COMPRESSION_LEVEL,
+            CompressionFormat::Gzip,
+            None,
+            None,
+            None,
+            None,
         );
 
         snapshot_service.perform_snapshot().await.unwrap();
@@ -195,9 +505,16 @@ mod test {
         let snapshot_service = SnapshotService::new(
             uuid_resolver,
             update_handle,
+            None,
             Duration::from_millis(100),
             snapshot_path.path().to_owned(),
             "data.ms".to_string(),
+            compression::DEFAULT_COMPRESSION_LEVEL,
+            CompressionFormat::Gzip,
+            None,
+            None,
+            None,
+            None,
         );
 
         assert!(snapshot_service.perform_snapshot().await.is_err());
@@ -224,9 +541,16 @@ mod test {
         let snapshot_service = SnapshotService::new(
             uuid_resolver,
             update_handle,
+            None,
             Duration::from_millis(100),
             snapshot_path.path().to_owned(),
             "data.ms".to_string(),
+            compression::DEFAULT_COMPRESSION_LEVEL,
+            CompressionFormat::Gzip,
+            None,
+            None,
+            None,
+            None,
         );
 
         assert!(snapshot_service.perform_snapshot().await.is_err());
@@ -234,6 +558,546 @@ mod test {
         assert!(!snapshot_path.path().join("data.ms.snapshot").exists());
     }
 
+    #[test]
+    fn load_snapshot_roundtrip() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        std::fs::write(src.join("data.mdb"), b"hello").unwrap();
+
+        let snapshot_path = dir.path().join("data.ms.snapshot");
+        compression::to_tar_gz(&src, &snapshot_path, compression::DEFAULT_COMPRESSION_LEVEL)
+            .unwrap();
+
+        let db_path = dir.path().join("data.ms");
+        load_snapshot(&db_path, &snapshot_path, false, false, None, None).unwrap();
+
+        assert_eq!(std::fs::read(db_path.join("data.mdb")).unwrap(), b"hello");
+    }
+
+    #[actix_rt::test]
+    async fn zstd_snapshot_roundtrip() {
+        let uuid = Uuid::new_v4();
+        let mut uuid_resolver = MockUuidResolverHandle::new();
+        uuid_resolver
+            .expect_snapshot()
+            .times(1)
+            .returning(move |path| {
+                std::fs::write(path.join("uuids"), b"hello").unwrap();
+                Box::pin(ok(vec![uuid]))
+            });
+
+        let mut update_handle = MockUpdateActorHandle::new();
+        update_handle
+            .expect_snapshot()
+            .times(1)
+            .returning(|_, _| Box::pin(ok(())));
+
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let snapshot_service = SnapshotService::new(
+            uuid_resolver,
+            update_handle,
+            None,
+            Duration::from_millis(100),
+            dir.path().join("snapshots"),
+            "data.ms".to_string(),
+            compression::DEFAULT_COMPRESSION_LEVEL,
+            CompressionFormat::Zstd,
+            None,
+            None,
+            None,
+            None,
+        );
+
+        snapshot_service.perform_snapshot().await.unwrap();
+
+        let snapshot_path = dir.path().join("snapshots").join("data.ms.snapshot.zst");
+        assert!(snapshot_path.exists());
+
+        let db_path = dir.path().join("data.ms");
+        load_snapshot(&db_path, &snapshot_path, false, false, None, None).unwrap();
+
+        assert_eq!(std::fs::read(db_path.join("uuids")).unwrap(), b"hello");
+    }
+
+    #[actix_rt::test]
+    async fn snapshot_key_store() {
+        let uuid = Uuid::new_v4();
+        let mut uuid_resolver = MockUuidResolverHandle::new();
+        uuid_resolver
+            .expect_snapshot()
+            .times(1)
+            .returning(move |_| Box::pin(ok(vec![uuid])));
+
+        let mut update_handle = MockUpdateActorHandle::new();
+        update_handle
+            .expect_snapshot()
+            .times(1)
+            .returning(|_, _| Box::pin(ok(())));
+
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let key_store = KeyStore::new(dir.path().join("db")).unwrap();
+        key_store.rotate(&ApiKeys::new(Some("rotated"))).unwrap();
+        let (key, _) = key_store
+            .create(
+                vec!["movies".to_string()],
+                vec![KeyAction::Search],
+                None,
+                None,
+            )
+            .unwrap();
+
+        let snapshot_service = SnapshotService::new(
+            uuid_resolver,
+            update_handle,
+            Some(key_store),
+            Duration::from_millis(100),
+            dir.path().join("snapshots"),
+            "data.ms".to_string(),
+            compression::DEFAULT_COMPRESSION_LEVEL,
+            CompressionFormat::Gzip,
+            None,
+            None,
+            None,
+            None,
+        );
+
+        snapshot_service.perform_snapshot().await.unwrap();
+
+        let snapshot_path = dir.path().join("snapshots").join("data.ms.snapshot");
+        let db_path = dir.path().join("data.ms");
+        load_snapshot(&db_path, &snapshot_path, false, false, None, None).unwrap();
+
+        let key_store = KeyStore::new(&db_path).unwrap();
+        assert!(key_store.get(&key).unwrap().is_some());
+        assert!(key_store.rotated_keys().unwrap().is_none());
+    }
+
+    #[actix_rt::test]
+    async fn snapshot_prepared_in_temp_dir() {
+        let uuid = Uuid::new_v4();
+        let mut uuid_resolver = MockUuidResolverHandle::new();
+        uuid_resolver
+            .expect_snapshot()
+            .times(1)
+            .returning(move |path| {
+                std::fs::write(path.join("uuids"), b"hello").unwrap();
+                Box::pin(ok(vec![uuid]))
+            });
+
+        let mut update_handle = MockUpdateActorHandle::new();
+        update_handle
+            .expect_snapshot()
+            .times(1)
+            .returning(|_, _| Box::pin(ok(())));
+
+        // The snapshot is prepared on another filesystem than the snapshots when there is one.
+        #[cfg(unix)]
+        let temp_dir = temp_dir_on_other_filesystem();
+        #[cfg(not(unix))]
+        let temp_dir = None;
+        let temp_dir = temp_dir.unwrap_or_else(|| tempfile::tempdir().unwrap());
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let snapshot_service = SnapshotService::new(
+            uuid_resolver,
+            update_handle,
+            None,
+            Duration::from_millis(100),
+            dir.path().join("snapshots"),
+            "data.ms".to_string(),
+            compression::DEFAULT_COMPRESSION_LEVEL,
+            CompressionFormat::Gzip,
+            None,
+            None,
+            None,
+            Some(temp_dir.path().join("scratch")),
+        );
+
+        snapshot_service.perform_snapshot().await.unwrap();
+
+        let snapshots: Vec<_> = std::fs::read_dir(dir.path().join("snapshots"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(snapshots, vec!["data.ms.snapshot"]);
+        // Nothing is left in the temporary directory.
+        let scratch = temp_dir.path().join("scratch");
+        assert_eq!(std::fs::read_dir(scratch).unwrap().count(), 0);
+
+        let db_path = dir.path().join("data.ms");
+        let snapshot_path = dir.path().join("snapshots/data.ms.snapshot");
+        load_snapshot(&db_path, &snapshot_path, false, false, None, None).unwrap();
+        assert_eq!(std::fs::read(db_path.join("uuids")).unwrap(), b"hello");
+    }
+
+    #[actix_rt::test]
+    async fn encrypted_snapshot_roundtrip() {
+        let uuid = Uuid::new_v4();
+        let mut uuid_resolver = MockUuidResolverHandle::new();
+        uuid_resolver
+            .expect_snapshot()
+            .times(1)
+            .returning(move |path| {
+                std::fs::write(path.join("uuids"), b"hello").unwrap();
+                Box::pin(ok(vec![uuid]))
+            });
+
+        let mut update_handle = MockUpdateActorHandle::new();
+        update_handle
+            .expect_snapshot()
+            .times(1)
+            .returning(|_, _| Box::pin(ok(())));
+
+        let key = EncryptionKey::from_passphrase("my secret key");
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let snapshot_service = SnapshotService::new(
+            uuid_resolver,
+            update_handle,
+            None,
+            Duration::from_millis(100),
+            dir.path().join("snapshots"),
+            "data.ms".to_string(),
+            compression::DEFAULT_COMPRESSION_LEVEL,
+            CompressionFormat::Gzip,
+            Some(key.clone()),
+            None,
+            None,
+            None,
+        );
+
+        snapshot_service.perform_snapshot().await.unwrap();
+
+        let snapshot_path = dir.path().join("snapshots").join("data.ms.snapshot.enc");
+        assert!(snapshot_path.exists());
+
+        let db_path = dir.path().join("data.ms");
+        let wrong_key = EncryptionKey::from_passphrase("not my secret key");
+        assert!(load_snapshot(&db_path, &snapshot_path, false, false, None, None).is_err());
+        assert!(load_snapshot(
+            &db_path,
+            &snapshot_path,
+            false,
+            false,
+            Some(&wrong_key),
+            None
+        )
+        .is_err());
+        // Nothing was extracted with a missing or wrong key
+        assert!(!db_path.exists());
+
+        let scratch = dir.path().join("scratch");
+        load_snapshot(
+            &db_path,
+            &snapshot_path,
+            false,
+            false,
+            Some(&key),
+            Some(&scratch),
+        )
+        .unwrap();
+        // The decrypted snapshot was removed from the temporary directory.
+        assert_eq!(std::fs::read_dir(scratch).unwrap().count(), 0);
+
+        assert_eq!(std::fs::read(db_path.join("uuids")).unwrap(), b"hello");
+    }
+
+    #[actix_rt::test]
+    async fn snapshot_is_uploaded() {
+        use std::sync::{Arc, Mutex};
+
+        use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+
+        use crate::helpers::object_storage::S3Credentials;
+
+        let uuid = Uuid::new_v4();
+        let mut uuid_resolver = MockUuidResolverHandle::new();
+        uuid_resolver
+            .expect_snapshot()
+            .times(1)
+            .returning(move |path| {
+                std::fs::write(path.join("uuids"), b"hello").unwrap();
+                Box::pin(ok(vec![uuid]))
+            });
+
+        let mut update_handle = MockUpdateActorHandle::new();
+        update_handle
+            .expect_snapshot()
+            .times(1)
+            .returning(|_, _| Box::pin(ok(())));
+
+        // A fake object storage recording the requests it receives.
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let requests_clone = requests.clone();
+        let server = HttpServer::new(move || {
+            let requests = requests_clone.clone();
+            App::new().default_service(web::to(move |req: HttpRequest, body: web::Bytes| {
+                let requests = requests.clone();
+                async move {
+                    let signed = req
+                        .headers()
+                        .get("authorization")
+                        .and_then(|value| value.to_str().ok())
+                        .map_or(false, |value| {
+                            value.starts_with("AWS4-HMAC-SHA256 Credential=access/")
+                        });
+                    requests.lock().unwrap().push((
+                        req.method().to_string(),
+                        req.path().to_string(),
+                        body.to_vec(),
+                        signed,
+                    ));
+                    HttpResponse::Ok().finish()
+                }
+            }))
+        })
+        .workers(1)
+        .disable_signals()
+        .bind("127.0.0.1:0")
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_rt::spawn(server.run());
+
+        let credentials = S3Credentials {
+            access_key_id: "access".to_string(),
+            secret_access_key: "secret".to_string(),
+            session_token: None,
+        };
+        let uploader = S3Uploader::new(
+            &format!("http://{}", addr),
+            "snapshots".to_string(),
+            "us-east-1".to_string(),
+            credentials,
+            0,
+        )
+        .unwrap();
+
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let snapshot_service = SnapshotService::new(
+            uuid_resolver,
+            update_handle,
+            None,
+            Duration::from_millis(100),
+            dir.path().join("snapshots"),
+            "data.ms".to_string(),
+            compression::DEFAULT_COMPRESSION_LEVEL,
+            CompressionFormat::Gzip,
+            None,
+            Some(uploader),
+            None,
+            None,
+        );
+
+        let snapshot_path = snapshot_service.perform_snapshot().await.unwrap().unwrap();
+        snapshot_service
+            .upload_snapshot(snapshot_path)
+            .unwrap()
+            .await
+            .unwrap();
+
+        let snapshot =
+            std::fs::read(dir.path().join("snapshots").join("data.ms.snapshot")).unwrap();
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let (method, path, body, signed) = &requests[0];
+        assert_eq!(method, "PUT");
+        assert_eq!(path, "/snapshots/data.ms.snapshot");
+        assert_eq!(body, &snapshot);
+        assert!(signed);
+    }
+
+    #[actix_rt::test]
+    async fn old_snapshots_are_pruned() {
+        let uuid = Uuid::new_v4();
+        let mut uuid_resolver = MockUuidResolverHandle::new();
+        uuid_resolver
+            .expect_snapshot()
+            .times(3)
+            .returning(move |path| {
+                std::fs::write(path.join("uuids"), b"hello").unwrap();
+                Box::pin(ok(vec![uuid]))
+            });
+
+        let mut update_handle = MockUpdateActorHandle::new();
+        update_handle
+            .expect_snapshot()
+            .times(3)
+            .returning(|_, _| Box::pin(ok(())));
+
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let snapshot_dir = dir.path().join("snapshots");
+        std::fs::create_dir_all(&snapshot_dir).unwrap();
+        // Files that are not timestamped snapshots of the database are left untouched.
+        let unrelated = vec![
+            "data.ms.snapshot",
+            "other.ms-20000101-000000000000.snapshot",
+            "notes",
+        ];
+        for name in &unrelated {
+            std::fs::write(snapshot_dir.join(name), b"unrelated").unwrap();
+        }
+
+        let snapshot_service = SnapshotService::new(
+            uuid_resolver,
+            update_handle,
+            None,
+            Duration::from_millis(100),
+            snapshot_dir.clone(),
+            "data.ms".to_string(),
+            compression::DEFAULT_COMPRESSION_LEVEL,
+            CompressionFormat::Gzip,
+            None,
+            None,
+            Some(2),
+            None,
+        );
+
+        for _ in 0..3 {
+            snapshot_service.perform_snapshot().await.unwrap();
+        }
+
+        let mut snapshots = std::fs::read_dir(&snapshot_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| !unrelated.contains(&name.as_str()))
+            .collect::<Vec<_>>();
+        snapshots.sort();
+        assert_eq!(snapshots.len(), 2);
+        assert!(snapshots
+            .iter()
+            .all(|name| is_timestamped_snapshot(name, "data.ms")));
+        for name in &unrelated {
+            assert!(snapshot_dir.join(name).exists());
+        }
+
+        // The most recent snapshot can be loaded.
+        let db_path = dir.path().join("data.ms");
+        load_snapshot(
+            &db_path,
+            snapshot_dir.join(&snapshots[1]),
+            false,
+            false,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(std::fs::read(db_path.join("uuids")).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn persist_truncated_snapshot() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(src.join("index_uuids")).unwrap();
+        std::fs::write(src.join("index_uuids").join("data.mdb"), vec![42; 4096]).unwrap();
+        let expected_files = list_snapshot_files(&src).unwrap();
+
+        let temp_snapshot_file = NamedTempFile::new_in(dir.path()).unwrap();
+        compression::to_tar_gz(
+            &src,
+            temp_snapshot_file.path(),
+            compression::DEFAULT_COMPRESSION_LEVEL,
+        )
+        .unwrap();
+        let len = temp_snapshot_file.as_file().metadata().unwrap().len();
+        temp_snapshot_file.as_file().set_len(len / 2).unwrap();
+
+        let snapshot_path = dir.path().join("data.ms.snapshot");
+        let result = persist_snapshot(
+            temp_snapshot_file,
+            &snapshot_path,
+            CompressionFormat::Gzip,
+            &expected_files,
+            None,
+        );
+
+        assert!(result.is_err());
+        // Nothing was written to the file
+        assert!(!snapshot_path.exists());
+    }
+
+    /// Returns a temporary directory on another filesystem than the current directory, if there
+    /// is one.
+    #[cfg(unix)]
+    fn temp_dir_on_other_filesystem() -> Option<tempfile::TempDir> {
+        use std::os::unix::fs::MetadataExt;
+
+        let device = std::fs::metadata(".").unwrap().dev();
+        let candidates = [PathBuf::from("/dev/shm"), std::env::temp_dir()];
+        candidates
+            .iter()
+            .filter(|dir| std::fs::metadata(dir).map_or(false, |meta| meta.dev() != device))
+            .find_map(|dir| tempfile::tempdir_in(dir).ok())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn persist_file_across_filesystems() {
+        let other = match temp_dir_on_other_filesystem() {
+            Some(other) => other,
+            None => {
+                eprintln!("skipping persist_file_across_filesystems: no other filesystem");
+                return;
+            }
+        };
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let file = NamedTempFile::new_in(other.path()).unwrap();
+        std::fs::write(file.path(), b"snapshot").unwrap();
+
+        let path = dir.path().join("data.ms.snapshot");
+        persist_file(file, &path).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"snapshot");
+        // Only the snapshot is left in its directory.
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn persist_file_only_copies_across_filesystems() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let file = NamedTempFile::new_in(dir.path()).unwrap();
+
+        // The rename fails as the directory of the snapshot is missing, the file isn't copied.
+        let path = dir.path().join("missing").join("data.ms.snapshot");
+        assert!(persist_file(file, &path).is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn load_snapshot_existing_db() {
+        let db_path = tempfile::tempdir_in(".").unwrap();
+        let snapshot_path = tempfile::NamedTempFile::new_in(".").unwrap();
+
+        assert!(load_snapshot(
+            db_path.path(),
+            snapshot_path.path(),
+            false,
+            false,
+            None,
+            None
+        )
+        .is_err());
+        assert!(load_snapshot(
+            db_path.path(),
+            snapshot_path.path(),
+            true,
+            false,
+            None,
+            None
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn load_snapshot_missing_snapshot() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let db_path = dir.path().join("data.ms");
+        let snapshot_path = dir.path().join("data.ms.snapshot");
+
+        assert!(load_snapshot(&db_path, &snapshot_path, false, false, None, None).is_err());
+        assert!(load_snapshot(&db_path, &snapshot_path, false, true, None, None).is_ok());
+        // Nothing was created in place of the database
+        assert!(!db_path.exists());
+    }
+
     #[actix_rt::test]
     async fn test_loop() {
         let mut uuid_resolver = MockUuidResolverHandle::new();
@@ -250,11 +1114,52 @@ mod test {
         let snapshot_service = SnapshotService::new(
             uuid_resolver,
             update_handle,
+            None,
+            Duration::from_millis(100),
+            snapshot_path.path().to_owned(),
+            "data.ms".to_string(),
+            compression::DEFAULT_COMPRESSION_LEVEL,
+            CompressionFormat::Gzip,
+            None,
+            None,
+            None,
+            None,
+        );
+
+        let _ = timeout(Duration::from_millis(300), snapshot_service.run()).await;
+    }
+
+    #[actix_rt::test]
+    async fn test_loop_creates_snapshot() {
+        let uuid = Uuid::new_v4();
+        let mut uuid_resolver = MockUuidResolverHandle::new();
+        uuid_resolver
+            .expect_snapshot()
+            .returning(move |_| Box::pin(ok(vec![uuid])));
+
+        let mut update_handle = MockUpdateActorHandle::new();
+        update_handle
+            .expect_snapshot()
+            .returning(|_, _| Box::pin(ok(())));
+
+        let snapshot_path = tempfile::tempdir_in(".").unwrap();
+        let snapshot_service = SnapshotService::new(
+            uuid_resolver,
+            update_handle,
+            None,
             Duration::from_millis(100),
             snapshot_path.path().to_owned(),
             "data.ms".to_string(),
+            compression::DEFAULT_COMPRESSION_LEVEL,
+            CompressionFormat::Gzip,
+            None,
+            None,
+            None,
+            None,
         );
 
         let _ = timeout(Duration::from_millis(300), snapshot_service.run()).await;
+
+        assert!(snapshot_path.path().join("data.ms.snapshot").exists());
     }
 }
@@ -0,0 +1,129 @@
+use uuid::Uuid;
+
+use super::index_actor::IndexActorHandle;
+use super::update_actor::UpdateActorHandle;
+use super::{IndexStats, UpdateStatus};
+
+/// Returns the stats of the index `uuid`. The index actor only knows about the update it is
+/// currently processing, an index with pending updates is considered as indexing as well.
+pub async fn index_stats(
+    update_handle: &impl UpdateActorHandle,
+    index_handle: &impl IndexActorHandle,
+    uuid: Uuid,
+) -> anyhow::Result<IndexStats> {
+    let mut stats = index_handle.get_index_stats(uuid).await?;
+
+    if !stats.is_indexing {
+        let updates = update_handle.list_updates(uuid, 0, None, None).await?;
+        stats.is_indexing = updates.iter().any(|update| {
+            matches!(
+                update,
+                UpdateStatus::Enqueued(_) | UpdateStatus::Processing(_)
+            )
+        });
+    }
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod test {
+    use futures::future::{err, ok};
+    use milli::FieldsDistribution;
+
+    use super::*;
+    use crate::index_controller::index_actor::{IndexError, MockIndexActorHandle};
+    use crate::index_controller::update_actor::MockUpdateActorHandle;
+    use crate::index_controller::updates::Enqueued;
+    use crate::index_controller::UpdateMeta;
+
+    fn stats(is_indexing: bool) -> IndexStats {
+        let mut fields_distribution = FieldsDistribution::new();
+        fields_distribution.insert("id".to_string(), 2);
+        fields_distribution.insert("title".to_string(), 1);
+        IndexStats {
+            size: 4096,
+            number_of_documents: 2,
+            is_indexing,
+            fields_distribution,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn stats_of_the_index_actor() {
+        let uuid = Uuid::new_v4();
+        let mut index_handle = MockIndexActorHandle::new();
+        index_handle
+            .expect_get_index_stats()
+            .withf(move |id| *id == uuid)
+            .times(1)
+            .returning(|_| Box::pin(ok(stats(false))));
+        let mut update_handle = MockUpdateActorHandle::new();
+        update_handle
+            .expect_list_updates()
+            .times(1)
+            .returning(move |uuid, _, _, _| {
+                let finished = Enqueued::new(UpdateMeta::ClearDocuments, 0, uuid).abort();
+                Box::pin(ok(vec![UpdateStatus::Aborted(finished)]))
+            });
+
+        let result = index_stats(&update_handle, &index_handle, uuid)
+            .await
+            .unwrap();
+        assert_eq!(result.number_of_documents, 2);
+        assert_eq!(result.fields_distribution, stats(false).fields_distribution);
+        assert!(!result.is_indexing);
+    }
+
+    #[actix_rt::test]
+    async fn index_with_pending_updates_is_indexing() {
+        let uuid = Uuid::new_v4();
+        let mut index_handle = MockIndexActorHandle::new();
+        index_handle
+            .expect_get_index_stats()
+            .times(1)
+            .returning(|_| Box::pin(ok(stats(false))));
+        let mut update_handle = MockUpdateActorHandle::new();
+        update_handle
+            .expect_list_updates()
+            .times(1)
+            .returning(move |uuid, _, _, _| {
+                let pending = Enqueued::new(UpdateMeta::ClearDocuments, 0, uuid);
+                Box::pin(ok(vec![UpdateStatus::Enqueued(pending)]))
+            });
+
+        let result = index_stats(&update_handle, &index_handle, uuid)
+            .await
+            .unwrap();
+        assert!(result.is_indexing);
+    }
+
+    #[actix_rt::test]
+    async fn processing_index_does_not_list_the_updates() {
+        let mut index_handle = MockIndexActorHandle::new();
+        index_handle
+            .expect_get_index_stats()
+            .times(1)
+            .returning(|_| Box::pin(ok(stats(true))));
+        // Any call to the update actor fails the test.
+        let update_handle = MockUpdateActorHandle::new();
+
+        let result = index_stats(&update_handle, &index_handle, Uuid::new_v4())
+            .await
+            .unwrap();
+        assert!(result.is_indexing);
+    }
+
+    #[actix_rt::test]
+    async fn error_getting_the_stats() {
+        let mut index_handle = MockIndexActorHandle::new();
+        index_handle
+            .expect_get_index_stats()
+            .times(1)
+            .returning(|_| Box::pin(err(IndexError::UnexistingIndex)));
+        let update_handle = MockUpdateActorHandle::new();
+
+        let result = index_stats(&update_handle, &index_handle, Uuid::new_v4()).await;
+        assert!(result.is_err());
+    }
+}
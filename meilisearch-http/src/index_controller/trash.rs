@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use log::{error, info};
+use tokio::time::sleep;
+
+use super::index_actor::IndexActorHandle;
+use super::update_actor::UpdateActorHandle;
+use super::uuid_resolver::UuidResolverHandle;
+use crate::helpers::Clock;
+
+/// The longest time between two purges of the trash.
+const MAX_PURGE_PERIOD: Duration = Duration::from_secs(60);
+
+/// Purges the indexes that have been in the trash for longer than the retention.
+#[derive(Clone)]
+pub struct TrashPurger<R, U, I> {
+    uuid_resolver_handle: R,
+    update_handle: U,
+    index_handle: I,
+    retention: Duration,
+}
+
+impl<R, U, I> TrashPurger<R, U, I>
+where
+    R: UuidResolverHandle,
+    U: UpdateActorHandle,
+    I: IndexActorHandle,
+{
+    pub fn new(
+        uuid_resolver_handle: R,
+        update_handle: U,
+        index_handle: I,
+        retention: Duration,
+    ) -> Self {
+        Self {
+            uuid_resolver_handle,
+            update_handle,
+            index_handle,
+            retention,
+        }
+    }
+
+    pub async fn run(self) {
+        let period = self.retention.min(MAX_PURGE_PERIOD);
+        info!(
+            "Deleted indexes are purged after {}s.",
+            self.retention.as_secs()
+        );
+        let clock = Clock::system();
+        loop {
+            sleep(period).await;
+            if let Err(e) = self.purge(&clock).await {
+                error!("Error while purging the trash: {}", e);
+            }
+        }
+    }
+
+    /// Deletes the data of the indexes trashed for longer than the retention at the time given by
+    /// `clock`, and returns how many indexes were purged.
+    pub async fn purge(&self, clock: &Clock) -> anyhow::Result<usize> {
+        let retention = chrono::Duration::from_std(self.retention)?;
+        let uuids = self
+            .uuid_resolver_handle
+            .purge_trash(clock.now() - retention)
+            .await?;
+        for &uuid in &uuids {
+            self.update_handle.delete(uuid).await?;
+            self.index_handle.delete(uuid).await?;
+        }
+        if !uuids.is_empty() {
+            info!("Purged {} deleted indexes.", uuids.len());
+        }
+        Ok(uuids.len())
+    }
+}
@@ -1,23 +1,36 @@
+use std::collections::HashSet;
 use std::io::SeekFrom;
 use std::path::{Path, PathBuf};
 
-use log::info;
+use futures::future::Future;
+use log::{error, info};
+use milli::update::UpdateFormat;
 use oxidized_json_checker::JsonChecker;
 use tokio::fs;
 use tokio::io::{AsyncSeekExt, AsyncWriteExt};
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc};
 use uuid::Uuid;
 
 use crate::index_controller::index_actor::IndexActorHandle;
-use crate::index_controller::{get_arc_ownership_blocking, UpdateMeta, UpdateStatus};
+use crate::index_controller::shutdown::Shutdown;
+use crate::index_controller::{
+    get_arc_ownership_blocking, Priority, PruneBefore, QueueStats, UpdateMeta, UpdateState,
+    UpdateStatus,
+};
 
-use super::{PayloadData, Result, UpdateError, UpdateMsg, UpdateStoreStore};
+use super::{PayloadData, Result, UpdateError, UpdateMsg, UpdateStore, UpdateStoreStore};
+
+/// The number of update statuses buffered ahead of the response, when they are streamed.
+const UPDATES_CHANNEL_SIZE: usize = 100;
+/// The number of updates read in a single transaction when they are listed.
+const UPDATES_PAGE_SIZE: usize = 100;
 
 pub struct UpdateActor<D, S, I> {
     path: PathBuf,
     store: S,
     inbox: mpsc::Receiver<UpdateMsg<D>>,
     index_handle: I,
+    shutdown: Shutdown,
 }
 
 impl<D, S, I> UpdateActor<D, S, I>
@@ -31,6 +44,7 @@ where
         inbox: mpsc::Receiver<UpdateMsg<D>>,
         path: impl AsRef<Path>,
         index_handle: I,
+        shutdown: Shutdown,
     ) -> anyhow::Result<Self> {
         let path = path.as_ref().to_owned();
         std::fs::create_dir_all(path.join("update_files"))?;
@@ -40,30 +54,69 @@ where
             inbox,
             path,
             index_handle,
+            shutdown,
         })
     }
 
-    pub async fn run(mut self) {
+    /// Processes the messages of the actor, once the pending updates of the indexes that are not
+    /// in the `trashed` ones are resumed.
+    pub async fn run(mut self, trashed: impl Future<Output = HashSet<Uuid>>) {
         use UpdateMsg::*;
 
         info!("Started update actor.");
 
+        self.resume_pending_updates(trashed.await).await;
+
         loop {
-            match self.inbox.recv().await {
+            let msg = tokio::select! {
+                msg = self.inbox.recv() => msg,
+                _ = self.shutdown.wait() => None,
+            };
+            match msg {
                 Some(Update {
                     uuid,
                     meta,
                     data,
+                    priority,
                     ret,
                 }) => {
-                    let _ = ret.send(self.handle_update(uuid, meta, data).await);
+                    let _ = ret.send(self.handle_update(uuid, meta, data, priority).await);
                 }
-                Some(ListUpdates { uuid, ret }) => {
-                    let _ = ret.send(self.handle_list_updates(uuid).await);
+                Some(ListUpdates {
+                    uuid,
+                    offset,
+                    limit,
+                    state,
+                    ret,
+                }) => {
+                    let _ = ret.send(self.handle_list_updates(uuid, offset, limit, state).await);
+                }
+                Some(StreamUpdates {
+                    uuid,
+                    offset,
+                    limit,
+                    state,
+                    ret,
+                }) => {
+                    let _ = ret.send(self.handle_stream_updates(uuid, offset, limit, state).await);
                 }
                 Some(GetUpdate { uuid, ret, id }) => {
                     let _ = ret.send(self.handle_get_update(uuid, id).await);
                 }
+                Some(Watch { uuid, ret, id }) => {
+                    let _ = ret.send(self.handle_watch(uuid, id).await);
+                }
+                Some(Abort { uuid, ret, id }) => {
+                    let _ = ret.send(self.handle_abort(uuid, id).await);
+                }
+                Some(Prune {
+                    uuid,
+                    before,
+                    state,
+                    ret,
+                }) => {
+                    let _ = ret.send(self.handle_prune(uuid, before, state).await);
+                }
                 Some(Delete { uuid, ret }) => {
                     let _ = ret.send(self.handle_delete(uuid).await);
                 }
@@ -73,12 +126,51 @@ where
                 Some(Snapshot { uuid, path, ret }) => {
                     let _ = ret.send(self.handle_snapshot(uuid, path).await);
                 }
+                Some(Dump { uuid, path, ret }) => {
+                    let _ = ret.send(self.handle_dump(uuid, path).await);
+                }
                 Some(GetSize { uuid, ret }) => {
                     let _ = ret.send(self.handle_get_size(uuid).await);
                 }
+                Some(GetQueueStats { uuid, ret }) => {
+                    let _ = ret.send(self.handle_queue_stats(uuid).await);
+                }
+                Some(Ping { ret }) => {
+                    let _ = ret.send(Ok(()));
+                }
                 None => break,
             }
         }
+
+        self.handle_shutdown().await;
+        info!("Update actor stopped.");
+    }
+
+    /// Opens all the update stores found on disk so that the updates that were enqueued, or being
+    /// processed, when the instance stopped are processed without waiting for their index to be
+    /// accessed. An interrupted update is processed again from scratch. The updates of the
+    /// `trashed` indexes are left pending until the index is restored.
+    async fn resume_pending_updates(&self, trashed: HashSet<Uuid>) {
+        let mut entries = match fs::read_dir(&self.path).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                error!("Could not list the update stores: {}", e);
+                return;
+            }
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let uuid = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix("updates-"))
+                .and_then(|uuid| Uuid::parse_str(uuid).ok());
+            if let Some(uuid) = uuid.filter(|uuid| !trashed.contains(uuid)) {
+                if let Err(e) = self.store.get(uuid).await {
+                    error!("Could not open the update store of index {}: {}", uuid, e);
+                }
+            }
+        }
     }
 
     async fn handle_update(
@@ -86,6 +178,7 @@ where
         uuid: Uuid,
         meta: UpdateMeta,
         mut payload: mpsc::Receiver<PayloadData<D>>,
+        priority: Priority,
     ) -> Result<UpdateStatus> {
         let update_store = self.store.get_or_create(uuid).await?;
         let update_file_id = uuid::Uuid::new_v4();
@@ -108,6 +201,8 @@ where
                         .map_err(|e| UpdateError::Error(Box::new(e)))?;
                 }
                 Err(e) => {
+                    // The payload is never registered, its file is of no use.
+                    let _ = fs::remove_file(&path).await;
                     return Err(UpdateError::Error(e));
                 }
             }
@@ -116,6 +211,11 @@ where
         file.flush()
             .await
             .map_err(|e| UpdateError::Error(Box::new(e)))?;
+        // The payload must be on disk before the update is registered, so that it is not lost if
+        // the instance stops before processing it.
+        file.sync_all()
+            .await
+            .map_err(|e| UpdateError::Error(Box::new(e)))?;
 
         file.seek(SeekFrom::Start(0))
             .await
@@ -123,15 +223,28 @@ where
 
         let mut file = file.into_std().await;
 
+        // Only the payloads made of a single json value can be checked upfront.
+        let is_json = !matches!(
+            meta,
+            UpdateMeta::DocumentsAddition {
+                format: UpdateFormat::JsonStream,
+                ..
+            } | UpdateMeta::DocumentsAddition {
+                format: UpdateFormat::Csv,
+                ..
+            }
+        );
+
         tokio::task::spawn_blocking(move || {
             use std::io::{copy, sink, BufReader, Seek};
 
             // If the payload is empty, ignore the check.
-            if file
-                .metadata()
-                .map_err(|e| UpdateError::Error(Box::new(e)))?
-                .len()
-                > 0
+            if is_json
+                && file
+                    .metadata()
+                    .map_err(|e| UpdateError::Error(Box::new(e)))?
+                    .len()
+                    > 0
             {
                 // Check that the json payload is valid:
                 let reader = BufReader::new(&mut file);
@@ -148,7 +261,7 @@ where
 
             // The payload is valid, we can register it to the update store.
             update_store
-                .register_update(meta, path, uuid)
+                .register_update(meta, path, uuid, priority)
                 .map(UpdateStatus::Enqueued)
                 .map_err(|e| UpdateError::Error(Box::new(e)))
         })
@@ -156,19 +269,87 @@ where
         .map_err(|e| UpdateError::Error(Box::new(e)))?
     }
 
-    async fn handle_list_updates(&self, uuid: Uuid) -> Result<Vec<UpdateStatus>> {
-        let update_store = self.store.get(uuid).await?;
+    async fn handle_list_updates(
+        &self,
+        uuid: Uuid,
+        offset: usize,
+        limit: Option<usize>,
+        state: Option<UpdateState>,
+    ) -> Result<Vec<UpdateStatus>> {
+        let update_store = self
+            .store
+            .get(uuid)
+            .await?
+            .ok_or(UpdateError::UnexistingIndex(uuid))?;
         tokio::task::spawn_blocking(move || {
-            let result = update_store
-                .ok_or(UpdateError::UnexistingIndex(uuid))?
-                .list()
-                .map_err(|e| UpdateError::Error(e.into()))?;
-            Ok(result)
+            let mut updates = Vec::new();
+            for_each_update(&update_store, offset, limit, state, |update| {
+                updates.push(update);
+                true
+            })
+            .map_err(|e| UpdateError::Error(e.into()))?;
+            Ok(updates)
         })
         .await
         .map_err(|e| UpdateError::Error(Box::new(e)))?
     }
 
+    async fn handle_stream_updates(
+        &self,
+        uuid: Uuid,
+        offset: usize,
+        limit: Option<usize>,
+        state: Option<UpdateState>,
+    ) -> Result<mpsc::Receiver<Vec<u8>>> {
+        let update_store = self
+            .store
+            .get(uuid)
+            .await?
+            .ok_or(UpdateError::UnexistingIndex(uuid))?;
+        let (sender, receiver) = mpsc::channel(UPDATES_CHANNEL_SIZE);
+        // The stream is not awaited, the pages are read as the receiver consumes them.
+        tokio::task::spawn(async move {
+            let mut cursor = UpdatesCursor::new(offset, limit, state);
+            while !cursor.is_done() {
+                let update_store = update_store.clone();
+                let read = tokio::task::spawn_blocking(move || {
+                    let page = cursor.next_page(&update_store);
+                    (cursor, page)
+                })
+                .await;
+                let page = match read {
+                    Ok((next, Ok(page))) => {
+                        cursor = next;
+                        page
+                    }
+                    Ok((_, Err(e))) => {
+                        error!("Could not stream the updates: {}", e);
+                        break;
+                    }
+                    Err(e) => {
+                        error!("Could not stream the updates: {}", e);
+                        break;
+                    }
+                };
+                for update in page {
+                    let mut line = match serde_json::to_vec(&update) {
+                        Ok(line) => line,
+                        Err(e) => {
+                            error!("Could not serialize update status: {}", e);
+                            return;
+                        }
+                    };
+                    line.push(b'\n');
+                    // The receiver was dropped, the response was interrupted.
+                    if sender.send(line).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(receiver)
+    }
+
     async fn handle_get_update(&self, uuid: Uuid, id: u64) -> Result<UpdateStatus> {
         let store = self
             .store
@@ -182,6 +363,105 @@ where
         Ok(result)
     }
 
+    async fn handle_watch(&self, uuid: Uuid, id: u64) -> Result<mpsc::Receiver<UpdateStatus>> {
+        let store = self
+            .store
+            .get(uuid)
+            .await?
+            .ok_or(UpdateError::UnexistingIndex(uuid))?;
+
+        // We subscribe before reading the current status so no transition can be missed.
+        let mut transitions = store.subscribe();
+        let current = store
+            .meta(id)
+            .map_err(|e| UpdateError::Error(Box::new(e)))?
+            .ok_or(UpdateError::UnexistingUpdate(id))?;
+
+        let (sender, receiver) = mpsc::channel(10);
+        tokio::task::spawn(async move {
+            let mut finished = current.is_finished();
+            let mut state = current.state();
+            if sender.send(current).await.is_err() {
+                return;
+            }
+
+            while !finished {
+                let status = match transitions.recv().await {
+                    Ok(status) if status.id() == id => status,
+                    Ok(_) => continue,
+                    // The missed transitions may include the ones of the update, its current
+                    // status is read again. The stream is closed if it can't be read anymore.
+                    Err(broadcast::error::RecvError::Lagged(_)) => match store.meta(id) {
+                        Ok(Some(status)) if status.state() != state => status,
+                        Ok(Some(_)) => continue,
+                        Ok(None) | Err(_) => break,
+                    },
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                finished = status.is_finished();
+                state = status.state();
+                if sender.send(status).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(receiver)
+    }
+
+    async fn handle_abort(&self, uuid: Uuid, id: u64) -> Result<UpdateStatus> {
+        let store = self
+            .store
+            .get(uuid)
+            .await?
+            .ok_or(UpdateError::UnexistingIndex(uuid))?;
+        tokio::task::spawn_blocking(move || {
+            if let Some(aborted) = store
+                .abort_update(id)
+                .map_err(|e| UpdateError::Error(Box::new(e)))?
+            {
+                return Ok(UpdateStatus::Aborted(aborted));
+            }
+
+            // The update couldn't be aborted, we report its current state.
+            let state = match store
+                .meta(id)
+                .map_err(|e| UpdateError::Error(Box::new(e)))?
+                .ok_or(UpdateError::UnexistingUpdate(id))?
+            {
+                // The first enqueued update is about to be processed.
+                UpdateStatus::Enqueued(_) | UpdateStatus::Processing(_) => "processing",
+                UpdateStatus::Processed(_) => "processed",
+                UpdateStatus::Aborted(_) => "aborted",
+                UpdateStatus::Failed(_) => "failed",
+            };
+            Err(UpdateError::NotAbortable(id, state))
+        })
+        .await
+        .map_err(|e| UpdateError::Error(Box::new(e)))?
+    }
+
+    async fn handle_prune(
+        &self,
+        uuid: Uuid,
+        before: PruneBefore,
+        state: Option<UpdateState>,
+    ) -> Result<u64> {
+        let store = self
+            .store
+            .get(uuid)
+            .await?
+            .ok_or(UpdateError::UnexistingIndex(uuid))?;
+        let count = tokio::task::spawn_blocking(move || store.prune(before, state))
+            .await
+            .map_err(|e| UpdateError::Error(e.into()))?
+            .map_err(|e| UpdateError::Error(e.into()))?;
+        if count > 0 {
+            info!("Pruned {} updates of index {}.", count, uuid);
+        }
+        Ok(count)
+    }
+
     async fn handle_delete(&self, uuid: Uuid) -> Result<()> {
         let store = self.store.delete(uuid).await?;
 
@@ -228,6 +508,58 @@ where
         Ok(())
     }
 
+    async fn handle_dump(&self, uuid: Uuid, path: PathBuf) -> Result<()> {
+        use std::io::{BufWriter, Write};
+
+        let index_handle = self.index_handle.clone();
+        let update_store = self
+            .store
+            .get(uuid)
+            .await?
+            .ok_or(UpdateError::UnexistingIndex(uuid))?;
+        tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+            // The updates are not processed during the dump, as for the snapshots, so that the
+            // dumped documents match the dumped updates.
+            let _lock = update_store.update_lock.lock();
+
+            std::fs::create_dir_all(&path)?;
+            let mut updates = Vec::new();
+            for_each_update(&update_store, 0, None, None, |update| {
+                updates.push(update);
+                true
+            })?;
+            // The updates are listed from the most recent one, they are dumped in chronological
+            // order.
+            let mut file = BufWriter::new(std::fs::File::create(path.join("updates.jsonl"))?);
+            for update in updates.iter().rev() {
+                serde_json::to_writer(&mut file, update)?;
+                writeln!(file)?;
+            }
+            file.flush()?;
+
+            futures::executor::block_on(async move { index_handle.dump(uuid, path).await })?;
+            Ok(())
+        })
+        .await
+        .map_err(|e| UpdateError::Error(e.into()))?
+        .map_err(|e| UpdateError::Error(e.into()))?;
+
+        Ok(())
+    }
+
+    /// Stops processing the pending updates and closes the update stores once the update they are
+    /// processing, if any, is finished.
+    async fn handle_shutdown(&self) {
+        for store in self.store.drain().await {
+            store.stop_processing();
+            let store = get_arc_ownership_blocking(store).await;
+            let closed = tokio::task::spawn_blocking(move || store.prepare_for_closing().wait());
+            if let Err(e) = closed.await {
+                error!("Could not close an update store: {}", e);
+            }
+        }
+    }
+
     async fn handle_get_size(&self, uuid: Uuid) -> Result<u64> {
         let size = match self.store.get(uuid).await? {
             Some(update_store) => tokio::task::spawn_blocking(move || -> anyhow::Result<u64> {
@@ -243,4 +575,100 @@ where
 
         Ok(size)
     }
+
+    async fn handle_queue_stats(&self, uuid: Uuid) -> Result<QueueStats> {
+        let stats = match self.store.get(uuid).await? {
+            Some(update_store) => {
+                tokio::task::spawn_blocking(move || -> anyhow::Result<QueueStats> {
+                    let txn = update_store.env.read_txn()?;
+
+                    Ok(update_store.queue_stats(&txn)?)
+                })
+                .await
+                .map_err(|e| UpdateError::Error(e.into()))?
+                .map_err(|e| UpdateError::Error(e.into()))?
+            }
+            None => QueueStats::default(),
+        };
+
+        Ok(stats)
+    }
+}
+
+/// Reads the updates of a store in the state `state`, if any, by descending id, skipping the first
+/// `offset` updates and stopping after `limit` of them. The updates are read by pages, each in its
+/// own transaction, so that listing many updates doesn't hold a transaction for long.
+struct UpdatesCursor {
+    state: Option<UpdateState>,
+    offset: usize,
+    remaining: usize,
+    /// The id of the last update read, the next page starts below it.
+    before: Option<u64>,
+    exhausted: bool,
+}
+
+impl UpdatesCursor {
+    fn new(offset: usize, limit: Option<usize>, state: Option<UpdateState>) -> Self {
+        Self {
+            state,
+            offset,
+            remaining: limit.unwrap_or(usize::MAX),
+            before: None,
+            exhausted: false,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.exhausted || self.remaining == 0
+    }
+
+    /// Reads at most `UPDATES_PAGE_SIZE` updates and returns the ones that are listed, the page
+    /// can be empty when none of the updates read are listed.
+    fn next_page(&mut self, update_store: &UpdateStore) -> anyhow::Result<Vec<UpdateStatus>> {
+        let mut page = Vec::new();
+        if self.is_done() {
+            return Ok(page);
+        }
+
+        let mut read = 0;
+        let mut last_id = None;
+        update_store.for_each_rev(self.before, |update| {
+            read += 1;
+            last_id = Some(update.id());
+            if self.state.map_or(true, |state| update.state() == state) {
+                if self.offset > 0 {
+                    self.offset -= 1;
+                } else {
+                    self.remaining -= 1;
+                    page.push(update);
+                }
+            }
+            read < UPDATES_PAGE_SIZE && self.remaining > 0
+        })?;
+
+        self.before = last_id;
+        self.exhausted = read < UPDATES_PAGE_SIZE && self.remaining > 0;
+        Ok(page)
+    }
+}
+
+/// Calls `f` on the updates of `update_store` in the state `state`, if any, by descending id,
+/// skipping the first `offset` updates and stopping after `limit` of them or once `f` returns
+/// `false`.
+fn for_each_update(
+    update_store: &UpdateStore,
+    offset: usize,
+    limit: Option<usize>,
+    state: Option<UpdateState>,
+    mut f: impl FnMut(UpdateStatus) -> bool,
+) -> anyhow::Result<()> {
+    let mut cursor = UpdatesCursor::new(offset, limit, state);
+    while !cursor.is_done() {
+        for update in cursor.next_page(update_store)? {
+            if !f(update) {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
 }
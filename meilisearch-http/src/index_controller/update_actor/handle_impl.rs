@@ -1,13 +1,16 @@
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
+use futures::future::Future;
 use tokio::sync::{mpsc, oneshot};
 use uuid::Uuid;
 
-use crate::index_controller::IndexActorHandle;
+use crate::index_controller::shutdown::Shutdown;
+use crate::index_controller::{ActorError, IndexActorHandle};
 
 use super::{
-    MapUpdateStoreStore, PayloadData, Result, UpdateActor, UpdateActorHandle, UpdateMeta,
-    UpdateMsg, UpdateStatus,
+    MapUpdateStoreStore, PayloadData, Priority, PruneBefore, QueueStats, Result, RetryPolicy,
+    UpdateActor, UpdateActorHandle, UpdateMeta, UpdateMsg, UpdateState, UpdateStatus,
 };
 
 #[derive(Clone)]
@@ -23,16 +26,27 @@ where
         index_handle: I,
         path: impl AsRef<Path>,
         update_store_size: usize,
+        mailbox_size: usize,
+        shutdown: Shutdown,
+        retry_policy: RetryPolicy,
+        read_only: bool,
+        trashed: impl Future<Output = HashSet<Uuid>> + Send + 'static,
     ) -> anyhow::Result<Self>
     where
         I: IndexActorHandle + Clone + Send + Sync + 'static,
     {
         let path = path.as_ref().to_owned().join("updates");
-        let (sender, receiver) = mpsc::channel(100);
-        let store = MapUpdateStoreStore::new(index_handle.clone(), &path, update_store_size);
-        let actor = UpdateActor::new(store, receiver, path, index_handle)?;
+        let (sender, receiver) = mpsc::channel(mailbox_size);
+        let store = MapUpdateStoreStore::new(
+            index_handle.clone(),
+            &path,
+            update_store_size,
+            retry_policy,
+            read_only,
+        );
+        let actor = UpdateActor::new(store, receiver, path, index_handle, shutdown)?;
 
-        tokio::task::spawn(actor.run());
+        tokio::task::spawn(actor.run(trashed));
 
         Ok(Self { sender })
     }
@@ -45,45 +59,156 @@ where
 {
     type Data = D;
 
-    async fn get_all_updates_status(&self, uuid: Uuid) -> Result<Vec<UpdateStatus>> {
+    async fn list_updates(
+        &self,
+        uuid: Uuid,
+        offset: usize,
+        limit: Option<usize>,
+        state: Option<UpdateState>,
+    ) -> Result<Vec<UpdateStatus>> {
         let (ret, receiver) = oneshot::channel();
-        let msg = UpdateMsg::ListUpdates { uuid, ret };
+        let msg = UpdateMsg::ListUpdates {
+            uuid,
+            offset,
+            limit,
+            state,
+            ret,
+        };
         let _ = self.sender.send(msg).await;
-        receiver.await.expect("update actor killed.")
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("update actor"))?
+    }
+    async fn stream_updates(
+        &self,
+        uuid: Uuid,
+        offset: usize,
+        limit: Option<usize>,
+        state: Option<UpdateState>,
+    ) -> Result<mpsc::Receiver<Vec<u8>>> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = UpdateMsg::StreamUpdates {
+            uuid,
+            offset,
+            limit,
+            state,
+            ret,
+        };
+        let _ = self.sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("update actor"))?
     }
     async fn update_status(&self, uuid: Uuid, id: u64) -> Result<UpdateStatus> {
         let (ret, receiver) = oneshot::channel();
         let msg = UpdateMsg::GetUpdate { uuid, id, ret };
         let _ = self.sender.send(msg).await;
-        receiver.await.expect("update actor killed.")
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("update actor"))?
+    }
+
+    async fn watch(&self, uuid: Uuid, id: u64) -> Result<mpsc::Receiver<UpdateStatus>> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = UpdateMsg::Watch { uuid, id, ret };
+        let _ = self.sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("update actor"))?
+    }
+
+    async fn abort(&self, uuid: Uuid, id: u64) -> Result<UpdateStatus> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = UpdateMsg::Abort { uuid, id, ret };
+        let _ = self.sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("update actor"))?
+    }
+
+    async fn prune(
+        &self,
+        uuid: Uuid,
+        before: PruneBefore,
+        state: Option<UpdateState>,
+    ) -> Result<u64> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = UpdateMsg::Prune {
+            uuid,
+            before,
+            state,
+            ret,
+        };
+        let _ = self.sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("update actor"))?
     }
 
     async fn delete(&self, uuid: Uuid) -> Result<()> {
         let (ret, receiver) = oneshot::channel();
         let msg = UpdateMsg::Delete { uuid, ret };
         let _ = self.sender.send(msg).await;
-        receiver.await.expect("update actor killed.")
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("update actor"))?
     }
 
     async fn create(&self, uuid: Uuid) -> Result<()> {
         let (ret, receiver) = oneshot::channel();
         let msg = UpdateMsg::Create { uuid, ret };
         let _ = self.sender.send(msg).await;
-        receiver.await.expect("update actor killed.")
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("update actor"))?
     }
 
     async fn snapshot(&self, uuid: Uuid, path: PathBuf) -> Result<()> {
         let (ret, receiver) = oneshot::channel();
         let msg = UpdateMsg::Snapshot { uuid, path, ret };
         let _ = self.sender.send(msg).await;
-        receiver.await.expect("update actor killed.")
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("update actor"))?
+    }
+
+    async fn dump(&self, uuid: Uuid, path: PathBuf) -> Result<()> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = UpdateMsg::Dump { uuid, path, ret };
+        let _ = self.sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("update actor"))?
     }
 
     async fn get_size(&self, uuid: Uuid) -> Result<u64> {
         let (ret, receiver) = oneshot::channel();
         let msg = UpdateMsg::GetSize { uuid, ret };
         let _ = self.sender.send(msg).await;
-        receiver.await.expect("update actor killed.")
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("update actor"))?
+    }
+
+    async fn queue_stats(&self, uuid: Uuid) -> Result<QueueStats> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = UpdateMsg::GetQueueStats { uuid, ret };
+        let _ = self.sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("update actor"))?
+    }
+
+    async fn ping(&self) -> Result<()> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = UpdateMsg::Ping { ret };
+        self.sender
+            .send(msg)
+            .await
+            .map_err(|_| ActorError::Unavailable("update actor"))?;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("update actor"))?
     }
 
     async fn update(
@@ -91,15 +216,19 @@ where
         meta: UpdateMeta,
         data: mpsc::Receiver<PayloadData<Self::Data>>,
         uuid: Uuid,
+        priority: Priority,
     ) -> Result<UpdateStatus> {
         let (ret, receiver) = oneshot::channel();
         let msg = UpdateMsg::Update {
             uuid,
             data,
             meta,
+            priority,
             ret,
         };
         let _ = self.sender.send(msg).await;
-        receiver.await.expect("update actor killed.")
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("update actor"))?
     }
 }
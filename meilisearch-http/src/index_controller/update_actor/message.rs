@@ -3,24 +3,53 @@ use std::path::PathBuf;
 use tokio::sync::{mpsc, oneshot};
 use uuid::Uuid;
 
-use super::{PayloadData, Result, UpdateMeta, UpdateStatus};
+use super::{
+    PayloadData, Priority, PruneBefore, QueueStats, Result, UpdateMeta, UpdateState, UpdateStatus,
+};
 
 pub enum UpdateMsg<D> {
     Update {
         uuid: Uuid,
         meta: UpdateMeta,
         data: mpsc::Receiver<PayloadData<D>>,
+        priority: Priority,
         ret: oneshot::Sender<Result<UpdateStatus>>,
     },
     ListUpdates {
         uuid: Uuid,
+        offset: usize,
+        limit: Option<usize>,
+        state: Option<UpdateState>,
         ret: oneshot::Sender<Result<Vec<UpdateStatus>>>,
     },
+    StreamUpdates {
+        uuid: Uuid,
+        offset: usize,
+        limit: Option<usize>,
+        state: Option<UpdateState>,
+        ret: oneshot::Sender<Result<mpsc::Receiver<Vec<u8>>>>,
+    },
     GetUpdate {
         uuid: Uuid,
         ret: oneshot::Sender<Result<UpdateStatus>>,
         id: u64,
     },
+    Watch {
+        uuid: Uuid,
+        ret: oneshot::Sender<Result<mpsc::Receiver<UpdateStatus>>>,
+        id: u64,
+    },
+    Abort {
+        uuid: Uuid,
+        ret: oneshot::Sender<Result<UpdateStatus>>,
+        id: u64,
+    },
+    Prune {
+        uuid: Uuid,
+        before: PruneBefore,
+        state: Option<UpdateState>,
+        ret: oneshot::Sender<Result<u64>>,
+    },
     Delete {
         uuid: Uuid,
         ret: oneshot::Sender<Result<()>>,
@@ -34,8 +63,20 @@ pub enum UpdateMsg<D> {
         path: PathBuf,
         ret: oneshot::Sender<Result<()>>,
     },
+    Dump {
+        uuid: Uuid,
+        path: PathBuf,
+        ret: oneshot::Sender<Result<()>>,
+    },
     GetSize {
         uuid: Uuid,
         ret: oneshot::Sender<Result<u64>>,
     },
+    GetQueueStats {
+        uuid: Uuid,
+        ret: oneshot::Sender<Result<QueueStats>>,
+    },
+    Ping {
+        ret: oneshot::Sender<Result<()>>,
+    },
 }
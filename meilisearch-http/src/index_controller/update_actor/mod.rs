@@ -11,13 +11,16 @@ use tokio::sync::mpsc;
 use uuid::Uuid;
 
 use crate::index::UpdateResult;
-use crate::index_controller::{UpdateMeta, UpdateStatus};
+use crate::index_controller::{
+    ActorError, Priority, PruneBefore, QueueStats, UpdateMeta, UpdateState, UpdateStatus,
+};
 
 use actor::UpdateActor;
 use message::UpdateMsg;
 use store::{MapUpdateStoreStore, UpdateStoreStore};
 
 pub use handle_impl::UpdateActorHandleImpl;
+pub use update_store::RetryPolicy;
 
 pub type Result<T> = std::result::Result<T, UpdateError>;
 type UpdateStore = update_store::UpdateStore<UpdateMeta, UpdateResult, String>;
@@ -29,11 +32,15 @@ use mockall::automock;
 #[derive(Debug, Error)]
 pub enum UpdateError {
     #[error("error with update: {0}")]
-    Error(Box<dyn std::error::Error + Sync + Send + 'static>),
+    Error(#[source] Box<dyn std::error::Error + Sync + Send + 'static>),
     #[error("Index {0} doesn't exist.")]
     UnexistingIndex(Uuid),
     #[error("Update {0} doesn't exist.")]
     UnexistingUpdate(u64),
+    #[error("Update {0} can't be aborted, it is {1}.")]
+    NotAbortable(u64, &'static str),
+    #[error("{0}")]
+    Actor(#[from] ActorError),
 }
 
 #[async_trait::async_trait]
@@ -41,16 +48,51 @@ pub enum UpdateError {
 pub trait UpdateActorHandle {
     type Data: AsRef<[u8]> + Sized + 'static + Sync + Send;
 
-    async fn get_all_updates_status(&self, uuid: Uuid) -> Result<Vec<UpdateStatus>>;
+    /// Lists the updates of an index by descending id, the updates can be filtered by state.
+    async fn list_updates(
+        &self,
+        uuid: Uuid,
+        offset: usize,
+        limit: Option<usize>,
+        state: Option<UpdateState>,
+    ) -> Result<Vec<UpdateStatus>>;
+    /// Same as `list_updates`, but the updates are streamed as JSON lines while they are read.
+    async fn stream_updates(
+        &self,
+        uuid: Uuid,
+        offset: usize,
+        limit: Option<usize>,
+        state: Option<UpdateState>,
+    ) -> Result<mpsc::Receiver<Vec<u8>>>;
     async fn update_status(&self, uuid: Uuid, id: u64) -> Result<UpdateStatus>;
+    async fn abort(&self, uuid: Uuid, id: u64) -> Result<UpdateStatus>;
+    /// Returns a receiver of the current status of an update followed by all its transitions, the
+    /// receiver is closed once the update is finished.
+    async fn watch(&self, uuid: Uuid, id: u64) -> Result<mpsc::Receiver<UpdateStatus>>;
+    /// Removes the finished updates of an index below the threshold, in the given state if any,
+    /// and returns how many were removed.
+    async fn prune(
+        &self,
+        uuid: Uuid,
+        before: PruneBefore,
+        state: Option<UpdateState>,
+    ) -> Result<u64>;
     async fn delete(&self, uuid: Uuid) -> Result<()>;
     async fn create(&self, uuid: Uuid) -> Result<()>;
     async fn snapshot(&self, uuid: Uuid, path: PathBuf) -> Result<()>;
+    /// Writes the updates of the index, then its settings and documents, in portable files at
+    /// `path`. The updates of the index are not processed in the meantime.
+    async fn dump(&self, uuid: Uuid, path: PathBuf) -> Result<()>;
     async fn get_size(&self, uuid: Uuid) -> Result<u64>;
+    /// Returns the number of enqueued updates of an index and its recent throughput.
+    async fn queue_stats(&self, uuid: Uuid) -> Result<QueueStats>;
+    /// Checks that the actor is processing its messages.
+    async fn ping(&self) -> Result<()>;
     async fn update(
         &self,
         meta: UpdateMeta,
         data: mpsc::Receiver<PayloadData<Self::Data>>,
         uuid: Uuid,
+        priority: Priority,
     ) -> Result<UpdateStatus>;
 }
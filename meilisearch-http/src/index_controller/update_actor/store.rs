@@ -7,7 +7,7 @@ use tokio::fs;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
-use super::{Result, UpdateError, UpdateStore};
+use super::{Result, RetryPolicy, UpdateError, UpdateStore};
 use crate::index_controller::IndexActorHandle;
 
 #[async_trait::async_trait]
@@ -15,6 +15,8 @@ pub trait UpdateStoreStore {
     async fn get_or_create(&self, uuid: Uuid) -> Result<Arc<UpdateStore>>;
     async fn delete(&self, uuid: Uuid) -> Result<Option<Arc<UpdateStore>>>;
     async fn get(&self, uuid: Uuid) -> Result<Option<Arc<UpdateStore>>>;
+    /// Removes all the opened update stores from the store.
+    async fn drain(&self) -> Vec<Arc<UpdateStore>>;
 }
 
 pub struct MapUpdateStoreStore<I> {
@@ -22,10 +24,19 @@ pub struct MapUpdateStoreStore<I> {
     index_handle: I,
     path: PathBuf,
     update_store_size: usize,
+    retry_policy: RetryPolicy,
+    /// The pending updates of the stores are not processed when they are read-only.
+    read_only: bool,
 }
 
 impl<I: IndexActorHandle> MapUpdateStoreStore<I> {
-    pub fn new(index_handle: I, path: impl AsRef<Path>, update_store_size: usize) -> Self {
+    pub fn new(
+        index_handle: I,
+        path: impl AsRef<Path>,
+        update_store_size: usize,
+        retry_policy: RetryPolicy,
+        read_only: bool,
+    ) -> Self {
         let db = Arc::new(RwLock::new(HashMap::new()));
         let path = path.as_ref().to_owned();
         Self {
@@ -33,6 +44,8 @@ impl<I: IndexActorHandle> MapUpdateStoreStore<I> {
             index_handle,
             path,
             update_store_size,
+            retry_policy,
+            read_only,
         }
     }
 }
@@ -51,9 +64,13 @@ where
                 let path = self.path.clone().join(format!("updates-{}", e.key()));
                 fs::create_dir_all(&path).await.unwrap();
                 let index_handle = self.index_handle.clone();
-                let store = UpdateStore::open(options, &path, move |meta, file| {
-                    futures::executor::block_on(index_handle.update(meta, file))
-                })
+                let store = UpdateStore::open(
+                    options,
+                    &path,
+                    move |meta, file| futures::executor::block_on(index_handle.update(meta, file)),
+                    self.retry_policy,
+                    self.read_only,
+                )
                 .map_err(|e| UpdateError::Error(e.into()))?;
                 let store = e.insert(store);
                 Ok(store.clone())
@@ -81,9 +98,15 @@ where
                             let mut options = heed::EnvOpenOptions::new();
                             let update_store_size = self.update_store_size;
                             options.map_size(update_store_size);
-                            let store = UpdateStore::open(options, &path, move |meta, file| {
-                                futures::executor::block_on(index_handle.update(meta, file))
-                            })
+                            let store = UpdateStore::open(
+                                options,
+                                &path,
+                                move |meta, file| {
+                                    futures::executor::block_on(index_handle.update(meta, file))
+                                },
+                                self.retry_policy,
+                                self.read_only,
+                            )
                             .map_err(|e| UpdateError::Error(e.into()))?;
                             let store = entry.insert(store);
                             Ok(Some(store.clone()))
@@ -100,6 +123,15 @@ where
         }
     }
 
+    async fn drain(&self) -> Vec<Arc<UpdateStore>> {
+        self.db
+            .write()
+            .await
+            .drain()
+            .map(|(_, store)| store)
+            .collect()
+    }
+
     async fn delete(&self, uuid: Uuid) -> Result<Option<Arc<UpdateStore>>> {
         let store = self.db.write().await.remove(&uuid);
         let path = self.path.clone().join(format!("updates-{}", uuid));
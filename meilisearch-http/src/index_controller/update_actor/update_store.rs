@@ -1,13 +1,19 @@
+use std::collections::VecDeque;
 use std::fs::File;
 use std::fs::{copy, create_dir_all, remove_file};
+use std::ops::Bound;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use heed::types::{DecodeIgnore, OwnedType, SerdeJson};
+use heed::types::{DecodeIgnore, OwnedType, SerdeJson, Str, Unit};
 use heed::{CompactionOption, Database, Env, EnvOpenOptions};
+use itertools::Itertools;
+use log::warn;
 use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc};
 use uuid::Uuid;
 
 use crate::helpers::EnvSizer;
@@ -15,19 +21,109 @@ use crate::index_controller::updates::*;
 
 type BEU64 = heed::zerocopy::U64<heed::byteorder::BE>;
 
+const NEXT_UPDATE_ID_KEY: &str = "next-update-id";
+const HIGH_PRIORITY_DB_NAME: &str = "high-priority";
+
+/// The delay before the first retry of an update, it doubles at each retry.
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(1);
+/// The longest delay between two retries of an update.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// How many times the updates failing transiently are retried before being marked as failed.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            base_delay: BASE_RETRY_DELAY,
+        }
+    }
+
+    /// Returns the delay to wait before the `retry`-th retry, starting at 1.
+    fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(MAX_RETRY_DELAY)
+    }
+}
+
+/// The next attempt of an update that failed transiently, it is back in the queue until then.
+#[derive(Debug, Clone, Copy)]
+struct Retry {
+    update_id: u64,
+    retries: u32,
+    at: Instant,
+}
+
+/// The window over which the throughput of the updates is averaged.
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// Keeps the instants at which the updates finished during the last `window`.
+struct Throughput {
+    window: Duration,
+    finished: VecDeque<Instant>,
+}
+
+impl Throughput {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            finished: VecDeque::new(),
+        }
+    }
+
+    fn record(&mut self, now: Instant) {
+        self.prune(now);
+        self.finished.push_back(now);
+    }
+
+    /// Returns the number of updates finished per minute, averaged over the window.
+    fn per_minute(&mut self, now: Instant) -> f64 {
+        self.prune(now);
+        self.finished.len() as f64 * 60.0 / self.window.as_secs_f64()
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while let Some(first) = self.finished.front() {
+            if now.duration_since(*first) <= self.window {
+                break;
+            }
+            self.finished.pop_front();
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct UpdateStore<M, N, E> {
     pub env: Env,
     pending_meta: Database<OwnedType<BEU64>, SerdeJson<Enqueued<M>>>,
     pending: Database<OwnedType<BEU64>, SerdeJson<PathBuf>>,
+    /// The ids of the pending updates of high priority, so that the next one is found without
+    /// going through all the pending updates.
+    high_priority: Database<OwnedType<BEU64>, Unit>,
     processed_meta: Database<OwnedType<BEU64>, SerdeJson<Processed<M, N>>>,
     failed_meta: Database<OwnedType<BEU64>, SerdeJson<Failed<M, E>>>,
     aborted_meta: Database<OwnedType<BEU64>, SerdeJson<Aborted<M>>>,
+    /// The id of the next update, kept as the updates below it can be pruned.
+    next_update_id: Database<Str, OwnedType<BEU64>>,
     processing: Arc<RwLock<Option<Processing<M>>>>,
+    /// The retry of the next pending update, if it failed transiently. Like the processing, it is
+    /// not persisted, the retries start over once the store is reopened.
+    retry: Arc<Mutex<Option<Retry>>>,
     notification_sender: mpsc::Sender<()>,
+    /// Every transition of an update is broadcasted to the subscribers.
+    status_sender: broadcast::Sender<UpdateStatus<M, N, E>>,
     /// A lock on the update loop. This is meant to prevent a snapshot to occur while an update is
     /// processing, while not preventing writes all together during an update
     pub update_lock: Arc<Mutex<()>>,
+    /// Once set, the pending updates are not processed anymore.
+    stopped: Arc<AtomicBool>,
+    throughput: Arc<Mutex<Throughput>>,
+    retry_policy: RetryPolicy,
 }
 
 pub trait HandleUpdate<M, N, E> {
@@ -54,44 +150,62 @@ where
 impl<M, N, E> UpdateStore<M, N, E>
 where
     M: for<'a> Deserialize<'a> + Serialize + 'static + Send + Sync + Clone,
-    N: for<'a> Deserialize<'a> + Serialize + 'static + Send + Sync,
-    E: for<'a> Deserialize<'a> + Serialize + 'static + Send + Sync,
+    N: for<'a> Deserialize<'a> + Serialize + 'static + Send + Sync + Clone,
+    E: for<'a> Deserialize<'a> + Serialize + 'static + Send + Sync + Clone,
 {
     pub fn open<P, U>(
         mut options: EnvOpenOptions,
         path: P,
         update_handler: U,
+        retry_policy: RetryPolicy,
+        read_only: bool,
     ) -> heed::Result<Arc<Self>>
     where
         P: AsRef<Path>,
         U: HandleUpdate<M, N, E> + Sync + Clone + Send + 'static,
     {
-        options.max_dbs(5);
+        options.max_dbs(7);
 
         let env = options.open(path)?;
         let pending_meta = env.create_database(Some("pending-meta"))?;
         let pending = env.create_database(Some("pending"))?;
+        let high_priority = match env.open_database(Some(HIGH_PRIORITY_DB_NAME))? {
+            Some(high_priority) => high_priority,
+            None => index_high_priority(&env, pending_meta)?,
+        };
         let processed_meta = env.create_database(Some("processed-meta"))?;
         let aborted_meta = env.create_database(Some("aborted-meta"))?;
         let failed_meta = env.create_database(Some("failed-meta"))?;
+        let next_update_id = env.create_database(Some("next-update-id"))?;
         let processing = Arc::new(RwLock::new(None));
 
         let (notification_sender, mut notification_receiver) = mpsc::channel(10);
-        // Send a first notification to trigger the process.
-        let _ = notification_sender.send(());
+        // Send a first notification to trigger the process of the updates that were pending when
+        // the store was closed, unless the store is read-only: they are left pending.
+        if !read_only {
+            let _ = notification_sender.try_send(());
+        }
 
         let update_lock = Arc::new(Mutex::new(()));
+        let (status_sender, _) = broadcast::channel(100);
 
         let update_store = Arc::new(UpdateStore {
             env,
             pending,
             pending_meta,
+            high_priority,
             processed_meta,
             aborted_meta,
             notification_sender,
             failed_meta,
+            next_update_id,
             processing,
+            retry: Arc::new(Mutex::new(None)),
             update_lock,
+            status_sender,
+            stopped: Arc::new(AtomicBool::new(false)),
+            throughput: Arc::new(Mutex::new(Throughput::new(THROUGHPUT_WINDOW))),
+            retry_policy,
         });
 
         // We need a weak reference so we can take ownership on the arc later when we
@@ -125,12 +239,23 @@ where
         Ok(update_store)
     }
 
+    /// Stops the processing of the pending updates, the update being processed is not interrupted.
+    pub fn stop_processing(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
     pub fn prepare_for_closing(self) -> heed::EnvClosingEvent {
         self.env.prepare_for_closing()
     }
 
-    /// Returns the new biggest id to use to store the new update.
+    /// Returns the new biggest id to use to store the new update. The ids are never reused: the
+    /// next id is persisted, the biggest id stored is used by the stores created without it.
     fn new_update_id(&self, txn: &heed::RoTxn) -> heed::Result<u64> {
+        let next_update_id = self
+            .next_update_id
+            .get(txn, NEXT_UPDATE_ID_KEY)?
+            .map(|id| id.get());
+
         let last_pending = self
             .pending_meta
             .remap_data_type::<DecodeIgnore>()
@@ -149,16 +274,20 @@ where
             .last(txn)?
             .map(|(k, _)| k.get());
 
-        let last_update_id = [last_pending, last_processed, last_aborted]
+        let last_failed = self
+            .failed_meta
+            .remap_data_type::<DecodeIgnore>()
+            .last(txn)?
+            .map(|(k, _)| k.get());
+
+        let last_update_id = [last_pending, last_processed, last_aborted, last_failed]
             .iter()
             .copied()
             .flatten()
             .max();
 
-        match last_update_id {
-            Some(last_id) => Ok(last_id + 1),
-            None => Ok(0),
-        }
+        let next_stored_id = last_update_id.map_or(0, |last_id| last_id + 1);
+        Ok(next_update_id.map_or(next_stored_id, |id| id.max(next_stored_id)))
     }
 
     /// Registers the update content in the pending store and the meta
@@ -168,6 +297,7 @@ where
         meta: M,
         content: impl AsRef<Path>,
         index_uuid: Uuid,
+        priority: Priority,
     ) -> heed::Result<Enqueued<M>> {
         let mut wtxn = self.env.write_txn()?;
 
@@ -177,11 +307,16 @@ where
         // will be forced to wait for a new write txn.
         let update_id = self.new_update_id(&wtxn)?;
         let update_key = BEU64::new(update_id);
+        self.next_update_id
+            .put(&mut wtxn, NEXT_UPDATE_ID_KEY, &BEU64::new(update_id + 1))?;
 
-        let meta = Enqueued::new(meta, update_id, index_uuid);
+        let meta = Enqueued::new(meta, update_id, index_uuid).with_priority(priority);
         self.pending_meta.put(&mut wtxn, &update_key, &meta)?;
         self.pending
             .put(&mut wtxn, &update_key, &content.as_ref().to_owned())?;
+        if meta.priority == Priority::High {
+            self.high_priority.put(&mut wtxn, &update_key, &())?;
+        }
 
         wtxn.commit()?;
 
@@ -190,7 +325,26 @@ where
             .expect("Update store loop exited.");
         Ok(meta)
     }
-    /// Executes the user provided function on the next pending update (the one with the lowest id).
+    /// Returns the next pending update to process: the high priority update with the lowest id,
+    /// or the update with the lowest id if there are none.
+    fn next_pending(&self, txn: &heed::RoTxn) -> heed::Result<Option<(BEU64, Enqueued<M>)>> {
+        if let Some((key, ())) = self.high_priority.first(txn)? {
+            if let Some(pending) = self.pending_meta.get(txn, &key)? {
+                return Ok(Some((key, pending)));
+            }
+        }
+        self.pending_meta.first(txn)
+    }
+
+    /// Removes the update of `key` from the pending stores.
+    fn delete_pending(&self, wtxn: &mut heed::RwTxn, key: &BEU64) -> heed::Result<()> {
+        self.pending_meta.delete(wtxn, key)?;
+        self.pending.delete(wtxn, key)?;
+        self.high_priority.delete(wtxn, key)?;
+        Ok(())
+    }
+
+    /// Executes the user provided function on the next pending update, see `next_pending`.
     /// This is asynchronous as it let the user process the update with a read-only txn and
     /// only writing the result meta to the processed-meta store *after* it has been processed.
     fn process_pending_update<U>(&self, mut handler: U) -> anyhow::Result<Option<()>>
@@ -198,14 +352,34 @@ where
         U: HandleUpdate<M, N, E>,
     {
         let _lock = self.update_lock.lock();
+        if self.stopped.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+
+        // The next update is selected and marked as processing at once, so it can't be aborted in
+        // between.
+        let mut processing_lock = self.processing.write();
+
         // Create a read transaction to be able to retrieve the pending update in order.
         let rtxn = self.env.read_txn()?;
-        let first_meta = self.pending_meta.first(&rtxn)?;
+        let next_meta = self.next_pending(&rtxn)?;
 
         // If there is a pending update we process and only keep
         // a reader while processing it, not a writer.
-        match first_meta {
+        match next_meta {
             Some((first_id, pending)) => {
+                // The updates are processed in order, none is processed until the next one can be
+                // retried.
+                let retries = match *self.retry.lock() {
+                    Some(retry) if retry.update_id == pending.id() => {
+                        if retry.at > Instant::now() {
+                            return Ok(None);
+                        }
+                        retry.retries
+                    }
+                    _ => 0,
+                };
+
                 let content_path = self
                     .pending
                     .get(&rtxn, &first_id)?
@@ -214,26 +388,73 @@ where
                 // we change the state of the update from pending to processing before we pass it
                 // to the update handler. Processing store is non persistent to be able recover
                 // from a failure
-                let processing = pending.processing();
-                self.processing.write().replace(processing.clone());
+                let processing = Processing {
+                    retries,
+                    ..pending.processing()
+                };
+                processing_lock.replace(processing.clone());
+                drop(processing_lock);
+                let _ = self
+                    .status_sender
+                    .send(UpdateStatus::Processing(processing.clone()));
                 let file = File::open(&content_path)?;
                 // Process the pending update using the provided user function.
-                let result = handler.handle_update(processing, file)?;
+                let result = handler.handle_update(processing.clone(), file)?;
                 drop(rtxn);
 
+                // The update is put back in the queue until its next attempt, which is scheduled
+                // once the update lock is released.
+                if let Err(ref failed) = result {
+                    if failed.is_retryable()
+                        && processing.retries < self.retry_policy.max_retries
+                        && !self.stopped.load(Ordering::SeqCst)
+                    {
+                        let delay = self.retry_policy.delay(processing.retries + 1);
+                        warn!(
+                            "Update {} failed transiently, retrying in {:?}.",
+                            processing.id(),
+                            delay
+                        );
+                        self.retry.lock().replace(Retry {
+                            update_id: processing.id(),
+                            retries: processing.retries + 1,
+                            at: Instant::now() + delay,
+                        });
+                        self.processing.write().take();
+                        let _ = self
+                            .status_sender
+                            .send(UpdateStatus::Enqueued(processing.from));
+
+                        let notification_sender = self.notification_sender.clone();
+                        tokio::spawn(async move {
+                            tokio::time::sleep(delay).await;
+                            let _ = notification_sender.send(()).await;
+                        });
+                        return Ok(None);
+                    }
+                }
+
                 // Once the pending update have been successfully processed
                 // we must remove the content from the pending and processing stores and
                 // write the *new* meta to the processed-meta store and commit.
                 let mut wtxn = self.env.write_txn()?;
                 self.processing.write().take();
-                self.pending_meta.delete(&mut wtxn, &first_id)?;
+                self.retry.lock().take();
+                self.delete_pending(&mut wtxn, &first_id)?;
                 remove_file(&content_path)?;
-                self.pending.delete(&mut wtxn, &first_id)?;
-                match result {
-                    Ok(processed) => self.processed_meta.put(&mut wtxn, &first_id, &processed)?,
-                    Err(failed) => self.failed_meta.put(&mut wtxn, &first_id, &failed)?,
-                }
+                let status = match result {
+                    Ok(processed) => {
+                        self.processed_meta.put(&mut wtxn, &first_id, &processed)?;
+                        UpdateStatus::Processed(processed)
+                    }
+                    Err(failed) => {
+                        self.failed_meta.put(&mut wtxn, &first_id, &failed)?;
+                        UpdateStatus::Failed(failed)
+                    }
+                };
+                self.throughput.lock().record(Instant::now());
                 wtxn.commit()?;
+                let _ = self.status_sender.send(status);
 
                 Ok(Some(()))
             }
@@ -241,55 +462,95 @@ where
         }
     }
 
-    pub fn list(&self) -> anyhow::Result<Vec<UpdateStatus<M, N, E>>> {
+    /// Calls `f` on the updates with an id lower than `before`, if any, by descending id, until it
+    /// returns `false`. The updates are read one at a time from the databases of each state, in a
+    /// single transaction.
+    pub fn for_each_rev(
+        &self,
+        before: Option<u64>,
+        mut f: impl FnMut(UpdateStatus<M, N, E>) -> bool,
+    ) -> anyhow::Result<()> {
         let rtxn = self.env.read_txn()?;
-        let mut updates = Vec::new();
-
-        let processing = self.processing.read();
-        if let Some(ref processing) = *processing {
-            let update = UpdateStatus::from(processing.clone());
-            updates.push(update);
-        }
-
+        let range = (
+            Bound::Unbounded,
+            before.map_or(Bound::Unbounded, |id| Bound::Excluded(BEU64::new(id))),
+        );
+
+        let processing = self
+            .processing
+            .read()
+            .clone()
+            .filter(|p| before.map_or(true, |id| p.id() < id));
+        let processing_id = processing.as_ref().map(Processing::id);
         let pending = self
             .pending_meta
-            .iter(&rtxn)?
+            .rev_range(&rtxn, &range)?
             .filter_map(Result::ok)
-            .filter_map(|(_, p)| (Some(p.id()) != processing.as_ref().map(|p| p.id())).then(|| p))
-            .map(UpdateStatus::from);
-
-        updates.extend(pending);
-
+            .filter(|(_, p)| Some(p.id()) != processing_id)
+            .map(|(_, p)| UpdateStatus::from(p));
         let aborted = self
             .aborted_meta
-            .iter(&rtxn)?
+            .rev_range(&rtxn, &range)?
             .filter_map(Result::ok)
-            .map(|(_, p)| p)
-            .map(UpdateStatus::from);
-
-        updates.extend(aborted);
-
+            .map(|(_, p)| UpdateStatus::from(p));
         let processed = self
             .processed_meta
-            .iter(&rtxn)?
+            .rev_range(&rtxn, &range)?
             .filter_map(Result::ok)
-            .map(|(_, p)| p)
-            .map(UpdateStatus::from);
-
-        updates.extend(processed);
-
+            .map(|(_, p)| UpdateStatus::from(p));
         let failed = self
             .failed_meta
-            .iter(&rtxn)?
+            .rev_range(&rtxn, &range)?
             .filter_map(Result::ok)
-            .map(|(_, p)| p)
-            .map(UpdateStatus::from);
+            .map(|(_, p)| UpdateStatus::from(p));
+
+        let states: Vec<Box<dyn Iterator<Item = UpdateStatus<M, N, E>> + '_>> = vec![
+            Box::new(processing.map(UpdateStatus::from).into_iter()),
+            Box::new(pending),
+            Box::new(aborted),
+            Box::new(processed),
+            Box::new(failed),
+        ];
+        for update in states.into_iter().kmerge_by(|a, b| a.id() > b.id()) {
+            if !f(update) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
 
-        updates.extend(failed);
+    /// Removes the finished updates below the `before` threshold, only in the state `state` if
+    /// any, and returns how many updates were removed. The enqueued and processing updates are
+    /// never removed.
+    pub fn prune(&self, before: PruneBefore, state: Option<UpdateState>) -> heed::Result<u64> {
+        let mut wtxn = self.env.write_txn()?;
+        let prunes = |pruned| state.map_or(true, |state| state == pruned);
 
-        updates.sort_by_key(|u| u.id());
+        let mut count = 0;
+        if prunes(UpdateState::Processed) {
+            count += prune_database(&mut wtxn, self.processed_meta, |p| {
+                before.includes(p.id(), p.finished_at())
+            })?;
+        }
+        if prunes(UpdateState::Aborted) {
+            count += prune_database(&mut wtxn, self.aborted_meta, |p| {
+                before.includes(p.id(), p.finished_at())
+            })?;
+        }
+        if prunes(UpdateState::Failed) {
+            count += prune_database(&mut wtxn, self.failed_meta, |p| {
+                before.includes(p.id(), p.finished_at())
+            })?;
+        }
+        wtxn.commit()?;
+
+        Ok(count)
+    }
 
-        Ok(updates)
+    /// Returns a receiver of the transitions of all the updates of this store happening from now.
+    pub fn subscribe(&self) -> broadcast::Receiver<UpdateStatus<M, N, E>> {
+        self.status_sender.subscribe()
     }
 
     /// Returns the update associated meta or `None` if the update doesn't exist.
@@ -328,13 +589,15 @@ where
     /// Trying to abort an update that is currently being processed, an update
     /// that as already been processed or which doesn't actually exist, will
     /// return `None`.
-    #[allow(dead_code)]
     pub fn abort_update(&self, update_id: u64) -> heed::Result<Option<Aborted<M>>> {
         let mut wtxn = self.env.write_txn()?;
         let key = BEU64::new(update_id);
 
-        // We cannot abort an update that is currently being processed.
-        if self.pending_meta.first(&wtxn)?.map(|(key, _)| key.get()) == Some(update_id) {
+        // We cannot abort an update that is currently being processed. The updates are selected
+        // and marked as processing under the lock, it is held until the update is aborted so it
+        // can't be selected in the meantime.
+        let processing = self.processing.read();
+        if processing.as_ref().map(Processing::id) == Some(update_id) {
             return Ok(None);
         }
 
@@ -344,12 +607,22 @@ where
         };
 
         let aborted = pending.abort();
+        let content_path = self.pending.get(&wtxn, &key)?;
 
         self.aborted_meta.put(&mut wtxn, &key, &aborted)?;
-        self.pending_meta.delete(&mut wtxn, &key)?;
-        self.pending.delete(&mut wtxn, &key)?;
+        self.delete_pending(&mut wtxn, &key)?;
 
         wtxn.commit()?;
+        drop(processing);
+
+        // The update will never be processed, its content can be removed.
+        if let Some(content_path) = content_path {
+            let _ = remove_file(content_path);
+        }
+
+        let _ = self
+            .status_sender
+            .send(UpdateStatus::Aborted(aborted.clone()));
 
         Ok(Some(aborted))
     }
@@ -361,21 +634,26 @@ where
         let mut wtxn = self.env.write_txn()?;
         let mut aborted_updates = Vec::new();
 
-        // We skip the first pending update as it is currently being processed.
-        for result in self.pending_meta.iter(&wtxn)?.skip(1) {
+        // We skip the update being processed, the lock is held so that none is selected until the
+        // others are aborted.
+        let processing = self.processing.read();
+        let processing_id = processing.as_ref().map(Processing::id);
+        for result in self.pending_meta.iter(&wtxn)? {
             let (key, pending) = result?;
             let id = key.get();
-            aborted_updates.push((id, pending.abort()));
+            if Some(id) != processing_id {
+                aborted_updates.push((id, pending.abort()));
+            }
         }
 
         for (id, aborted) in &aborted_updates {
             let key = BEU64::new(*id);
             self.aborted_meta.put(&mut wtxn, &key, &aborted)?;
-            self.pending_meta.delete(&mut wtxn, &key)?;
-            self.pending.delete(&mut wtxn, &key)?;
+            self.delete_pending(&mut wtxn, &key)?;
         }
 
         wtxn.commit()?;
+        drop(processing);
 
         Ok(aborted_updates)
     }
@@ -411,6 +689,18 @@ where
         Ok(())
     }
 
+    pub fn queue_stats(&self, txn: &heed::RoTxn) -> heed::Result<QueueStats> {
+        // The update being processed stays in the pending store until it is finished.
+        let pending = self.pending_meta.len(txn)? as u64;
+        let is_processing = self.processing.read().is_some();
+
+        Ok(QueueStats {
+            enqueued: pending.saturating_sub(is_processing as u64),
+            is_processing,
+            processed_per_minute: self.throughput.lock().per_minute(Instant::now()),
+        })
+    }
+
     pub fn get_size(&self, txn: &heed::RoTxn) -> anyhow::Result<u64> {
         let mut size = self.env.size();
 
@@ -425,3 +715,570 @@ where
         Ok(size)
     }
 }
+
+/// Creates the database of the ids of the high priority updates, and fills it with the pending
+/// updates of the stores created without it.
+fn index_high_priority<M>(
+    env: &Env,
+    pending_meta: Database<OwnedType<BEU64>, SerdeJson<Enqueued<M>>>,
+) -> heed::Result<Database<OwnedType<BEU64>, Unit>>
+where
+    M: for<'a> Deserialize<'a>,
+{
+    let mut wtxn = env.write_txn()?;
+    let high_priority = env.create_database_with_txn(Some(HIGH_PRIORITY_DB_NAME), &mut wtxn)?;
+    let keys = pending_meta
+        .iter(&wtxn)?
+        .filter_map(Result::ok)
+        .filter(|(_, pending)| pending.priority == Priority::High)
+        .map(|(key, _)| key)
+        .collect::<Vec<_>>();
+    for key in &keys {
+        high_priority.put(&mut wtxn, key, &())?;
+    }
+    wtxn.commit()?;
+    Ok(high_priority)
+}
+
+/// Deletes the entries of `db` whose value matches `prune`, and returns how many were deleted.
+fn prune_database<T>(
+    wtxn: &mut heed::RwTxn,
+    db: Database<OwnedType<BEU64>, SerdeJson<T>>,
+    prune: impl Fn(&T) -> bool,
+) -> heed::Result<u64>
+where
+    T: for<'a> Deserialize<'a>,
+{
+    let keys = db
+        .iter(wtxn)?
+        .filter_map(Result::ok)
+        .filter(|(_, value)| prune(value))
+        .map(|(key, _)| key)
+        .collect::<Vec<_>>();
+    for key in &keys {
+        db.delete(wtxn, key)?;
+    }
+    Ok(keys.len() as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::index_controller::get_arc_ownership_blocking;
+
+    type TestUpdateStore = UpdateStore<String, (), String>;
+    type HandlerResult = anyhow::Result<Result<Processed<String, ()>, Failed<String, String>>>;
+
+    fn register(store: &Arc<TestUpdateStore>, dir: &Path, name: &str) -> u64 {
+        register_with_priority(store, dir, name, Priority::Normal)
+    }
+
+    fn register_with_priority(
+        store: &Arc<TestUpdateStore>,
+        dir: &Path,
+        name: &str,
+        priority: Priority,
+    ) -> u64 {
+        let content_path = dir.join(name);
+        std::fs::write(&content_path, b"[]").unwrap();
+        store
+            .register_update(name.to_string(), content_path, Uuid::new_v4(), priority)
+            .unwrap()
+            .id()
+    }
+
+    #[actix_rt::test]
+    async fn abort_enqueued_update() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(4096 * 100);
+
+        // The updates are processed once a token is received.
+        let (sender, receiver) = crossbeam_channel::unbounded::<()>();
+        let handler = move |processing: Processing<String>, _content: File| -> HandlerResult {
+            receiver.recv().unwrap();
+            Ok(Ok(processing.process(())))
+        };
+        let store = TestUpdateStore::open(
+            options,
+            dir.path().join("store"),
+            handler,
+            RetryPolicy::new(0),
+            false,
+        )
+        .unwrap();
+
+        let (first, second, third) = {
+            let store = store.clone();
+            let dir = dir.path().to_owned();
+            tokio::task::spawn_blocking(move || {
+                let first = register(&store, &dir, "first");
+                let second = register(&store, &dir, "second");
+                let third = register(&store, &dir, "third");
+                (first, second, third)
+            })
+            .await
+            .unwrap()
+        };
+
+        // The first update is being processed and can't be aborted.
+        while !matches!(
+            store.meta(first).unwrap(),
+            Some(UpdateStatus::Processing(_))
+        ) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(store.abort_update(first).unwrap().is_none());
+        assert_eq!(store.abort_update(second).unwrap().unwrap().id(), second);
+        assert!(!dir.path().join("second").exists());
+        // An update can't be aborted twice.
+        assert!(store.abort_update(second).unwrap().is_none());
+
+        sender.send(()).unwrap();
+        sender.send(()).unwrap();
+
+        while !matches!(store.meta(third).unwrap(), Some(UpdateStatus::Processed(_))) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert!(matches!(
+            store.meta(first).unwrap(),
+            Some(UpdateStatus::Processed(_))
+        ));
+        assert!(matches!(
+            store.meta(second).unwrap(),
+            Some(UpdateStatus::Aborted(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn for_each_rev_below_an_id() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(4096 * 100);
+
+        let handler = |processing: Processing<String>, _content: File| -> HandlerResult {
+            Ok(Ok(processing.process(())))
+        };
+        let store = TestUpdateStore::open(
+            options,
+            dir.path().join("store"),
+            handler,
+            RetryPolicy::new(0),
+            false,
+        )
+        .unwrap();
+
+        let ids = {
+            let store = store.clone();
+            let dir = dir.path().to_owned();
+            tokio::task::spawn_blocking(move || {
+                (0..5)
+                    .map(|i| register(&store, &dir, &format!("update-{}", i)))
+                    .collect::<Vec<_>>()
+            })
+            .await
+            .unwrap()
+        };
+        while !matches!(
+            store.meta(ids[4]).unwrap(),
+            Some(UpdateStatus::Processed(_))
+        ) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let ids_before = |before| {
+            let mut ids = Vec::new();
+            store
+                .for_each_rev(before, |update| {
+                    ids.push(update.id());
+                    true
+                })
+                .unwrap();
+            ids
+        };
+        assert_eq!(ids_before(None), vec![4, 3, 2, 1, 0]);
+        assert_eq!(ids_before(Some(3)), vec![2, 1, 0]);
+        assert!(ids_before(Some(0)).is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn queue_stats_of_a_burst() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(4096 * 100);
+
+        let (sender, receiver) = crossbeam_channel::unbounded::<()>();
+        let handler = move |processing: Processing<String>, _content: File| -> HandlerResult {
+            receiver.recv().unwrap();
+            Ok(Ok(processing.process(())))
+        };
+        let store = TestUpdateStore::open(
+            options,
+            dir.path().join("store"),
+            handler,
+            RetryPolicy::new(0),
+            false,
+        )
+        .unwrap();
+
+        let ids = {
+            let store = store.clone();
+            let dir = dir.path().to_owned();
+            tokio::task::spawn_blocking(move || {
+                (0..10)
+                    .map(|i| register(&store, &dir, &format!("update-{}", i)))
+                    .collect::<Vec<_>>()
+            })
+            .await
+            .unwrap()
+        };
+
+        // Wait for the first update to be picked up by the update loop.
+        while !matches!(
+            store.meta(ids[0]).unwrap(),
+            Some(UpdateStatus::Processing(_))
+        ) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let stats = store.queue_stats(&store.env.read_txn().unwrap()).unwrap();
+        assert_eq!(stats.enqueued, 9);
+        assert!(stats.is_processing);
+        assert_eq!(stats.processed_per_minute, 0.0);
+
+        for _ in 0..ids.len() {
+            sender.send(()).unwrap();
+        }
+        while !matches!(
+            store.meta(ids[9]).unwrap(),
+            Some(UpdateStatus::Processed(_))
+        ) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let stats = store.queue_stats(&store.env.read_txn().unwrap()).unwrap();
+        assert_eq!(stats.enqueued, 0);
+        assert!(!stats.is_processing);
+        assert_eq!(stats.processed_per_minute, 10.0 / 5.0);
+    }
+
+    #[actix_rt::test]
+    async fn high_priority_updates_are_processed_first() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(4096 * 100);
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let (sender, receiver) = crossbeam_channel::unbounded::<()>();
+        let handler = {
+            let order = order.clone();
+            move |processing: Processing<String>, _content: File| -> HandlerResult {
+                receiver.recv().unwrap();
+                order.lock().push(processing.meta().clone());
+                Ok(Ok(processing.process(())))
+            }
+        };
+        let store = TestUpdateStore::open(
+            options,
+            dir.path().join("store"),
+            handler,
+            RetryPolicy::new(0),
+            false,
+        )
+        .unwrap();
+
+        let first = {
+            let store = store.clone();
+            let dir = dir.path().to_owned();
+            tokio::task::spawn_blocking(move || register(&store, &dir, "documents-1"))
+                .await
+                .unwrap()
+        };
+        while !matches!(
+            store.meta(first).unwrap(),
+            Some(UpdateStatus::Processing(_))
+        ) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // Both updates are enqueued while the first one is being processed.
+        let (second, third) = {
+            let store = store.clone();
+            let dir = dir.path().to_owned();
+            tokio::task::spawn_blocking(move || {
+                let second = register(&store, &dir, "documents-2");
+                let third = register_with_priority(&store, &dir, "settings", Priority::High);
+                (second, third)
+            })
+            .await
+            .unwrap()
+        };
+
+        for _ in 0..3 {
+            sender.send(()).unwrap();
+        }
+        while !matches!(
+            store.meta(second).unwrap(),
+            Some(UpdateStatus::Processed(_))
+        ) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert!(matches!(
+            store.meta(third).unwrap(),
+            Some(UpdateStatus::Processed(_))
+        ));
+        assert_eq!(
+            *order.lock(),
+            vec!["documents-1", "settings", "documents-2"]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn interrupted_updates_are_resumed_on_reopen() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let path = dir.path().join("store");
+        let options = || {
+            let mut options = EnvOpenOptions::new();
+            options.map_size(4096 * 100);
+            options
+        };
+
+        // The processing of the first update is interrupted, as if the instance stopped.
+        let (sender, receiver) = crossbeam_channel::unbounded::<()>();
+        let handler = move |_processing: Processing<String>, _content: File| -> HandlerResult {
+            receiver.recv()?;
+            unreachable!("the update is interrupted")
+        };
+        let store =
+            TestUpdateStore::open(options(), &path, handler, RetryPolicy::new(0), false).unwrap();
+        let (interrupted, enqueued) = {
+            let store = store.clone();
+            let dir = dir.path().to_owned();
+            tokio::task::spawn_blocking(move || {
+                let interrupted = register(&store, &dir, "interrupted");
+                let enqueued = register(&store, &dir, "enqueued");
+                (interrupted, enqueued)
+            })
+            .await
+            .unwrap()
+        };
+        while !matches!(
+            store.meta(interrupted).unwrap(),
+            Some(UpdateStatus::Processing(_))
+        ) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        store.stop_processing();
+        drop(sender);
+        let store = get_arc_ownership_blocking(store).await;
+        tokio::task::spawn_blocking(move || store.prepare_for_closing().wait())
+            .await
+            .unwrap();
+
+        let handler = move |processing: Processing<String>, _content: File| -> HandlerResult {
+            Ok(Ok(processing.process(())))
+        };
+        let store =
+            TestUpdateStore::open(options(), &path, handler, RetryPolicy::new(0), false).unwrap();
+        while !matches!(
+            store.meta(enqueued).unwrap(),
+            Some(UpdateStatus::Processed(_))
+        ) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(matches!(
+            store.meta(interrupted).unwrap(),
+            Some(UpdateStatus::Processed(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn pending_updates_are_not_resumed_when_read_only() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let path = dir.path().join("store");
+        let options = || {
+            let mut options = EnvOpenOptions::new();
+            options.map_size(4096 * 100);
+            options
+        };
+
+        // The update stays pending as the store is closed before it is processed.
+        let handler = move |_processing: Processing<String>, _content: File| -> HandlerResult {
+            unreachable!("the store is stopped")
+        };
+        let store =
+            TestUpdateStore::open(options(), &path, handler, RetryPolicy::new(0), false).unwrap();
+        store.stop_processing();
+        let pending = {
+            let store = store.clone();
+            let dir = dir.path().to_owned();
+            tokio::task::spawn_blocking(move || register(&store, &dir, "pending"))
+                .await
+                .unwrap()
+        };
+        let store = get_arc_ownership_blocking(store).await;
+        tokio::task::spawn_blocking(move || store.prepare_for_closing().wait())
+            .await
+            .unwrap();
+
+        let handler = move |_processing: Processing<String>, _content: File| -> HandlerResult {
+            unreachable!("the store is read-only")
+        };
+        let store =
+            TestUpdateStore::open(options(), &path, handler, RetryPolicy::new(0), true).unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(matches!(
+            store.meta(pending).unwrap(),
+            Some(UpdateStatus::Enqueued(_))
+        ));
+    }
+
+    #[test]
+    fn throughput_forgets_old_updates() {
+        let start = Instant::now();
+        let mut throughput = Throughput::new(Duration::from_secs(60));
+        throughput.record(start);
+        throughput.record(start + Duration::from_secs(30));
+        assert_eq!(throughput.per_minute(start + Duration::from_secs(45)), 2.0);
+        assert_eq!(throughput.per_minute(start + Duration::from_secs(75)), 1.0);
+        assert_eq!(throughput.per_minute(start + Duration::from_secs(120)), 0.0);
+    }
+
+    fn open_with_retries(
+        dir: &Path,
+        failures: Vec<bool>,
+        base_delay: Duration,
+    ) -> Arc<TestUpdateStore> {
+        let mut options = EnvOpenOptions::new();
+        options.map_size(4096 * 100);
+
+        // Each attempt fails with the next failure, transient or not, until there are none left.
+        let failures = Arc::new(Mutex::new(VecDeque::from(failures)));
+        let handler = move |processing: Processing<String>, _content: File| -> HandlerResult {
+            match failures.lock().pop_front() {
+                Some(true) => Ok(Err(processing.fail_transient("disk full".to_string()))),
+                Some(false) => Ok(Err(processing.fail("malformed".to_string()))),
+                None => Ok(Ok(processing.process(()))),
+            }
+        };
+        let policy = RetryPolicy {
+            max_retries: 3,
+            base_delay,
+        };
+        TestUpdateStore::open(options, dir.join("store"), handler, policy, false).unwrap()
+    }
+
+    async fn wait_finished(
+        store: &Arc<TestUpdateStore>,
+        id: u64,
+    ) -> UpdateStatus<String, (), String> {
+        loop {
+            match store.meta(id).unwrap() {
+                Some(status) if status.is_finished() => return status,
+                _ => tokio::time::sleep(Duration::from_millis(10)).await,
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn transient_failures_are_retried() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let store = open_with_retries(dir.path(), vec![true, true], Duration::from_millis(1));
+
+        let id = {
+            let store = store.clone();
+            let dir = dir.path().to_owned();
+            tokio::task::spawn_blocking(move || register(&store, &dir, "update"))
+                .await
+                .unwrap()
+        };
+
+        match wait_finished(&store, id).await {
+            UpdateStatus::Processed(processed) => assert_eq!(processed.from.retries, 2),
+            status => panic!("unexpected status: {:?}", status),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn permanent_failures_are_not_retried() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let store = open_with_retries(dir.path(), vec![true, false], Duration::from_millis(1));
+
+        let id = {
+            let store = store.clone();
+            let dir = dir.path().to_owned();
+            tokio::task::spawn_blocking(move || register(&store, &dir, "update"))
+                .await
+                .unwrap()
+        };
+
+        let status = wait_finished(&store, id).await;
+        assert!(matches!(status, UpdateStatus::Failed(_)), "{:?}", status);
+        let status = serde_json::to_value(&status).unwrap();
+        assert_eq!(status["retries"], 1);
+        assert_eq!(status["error"], "malformed");
+    }
+
+    #[actix_rt::test]
+    async fn updates_are_back_in_queue_until_retried() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let store = open_with_retries(dir.path(), vec![true], Duration::from_millis(500));
+
+        let id = {
+            let store = store.clone();
+            let dir = dir.path().to_owned();
+            tokio::task::spawn_blocking(move || register(&store, &dir, "update"))
+                .await
+                .unwrap()
+        };
+
+        // The update is pending again after its first attempt, nothing is locked until it is
+        // retried.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(matches!(
+            store.meta(id).unwrap(),
+            Some(UpdateStatus::Enqueued(_))
+        ));
+        assert!(store.update_lock.try_lock().is_some());
+
+        match wait_finished(&store, id).await {
+            UpdateStatus::Processed(processed) => assert_eq!(processed.from.retries, 1),
+            status => panic!("unexpected status: {:?}", status),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn abort_update_waiting_for_retry() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let store = open_with_retries(dir.path(), vec![true], Duration::from_secs(60));
+
+        let id = {
+            let store = store.clone();
+            let dir = dir.path().to_owned();
+            tokio::task::spawn_blocking(move || register(&store, &dir, "update"))
+                .await
+                .unwrap()
+        };
+
+        // The next pending update can be aborted as long as it isn't processing.
+        while store.retry.lock().is_none() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(store.abort_update(id).unwrap().unwrap().id(), id);
+        assert!(matches!(
+            store.meta(id).unwrap(),
+            Some(UpdateStatus::Aborted(_))
+        ));
+    }
+
+    #[test]
+    fn retry_delay_is_exponential() {
+        let policy = RetryPolicy::new(10);
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(2), Duration::from_secs(2));
+        assert_eq!(policy.delay(4), Duration::from_secs(8));
+        assert_eq!(policy.delay(40), MAX_RETRY_DELAY);
+    }
+}
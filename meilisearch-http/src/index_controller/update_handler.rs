@@ -1,4 +1,5 @@
 use std::fs::File;
+use std::io;
 
 use crate::index::Index;
 use anyhow::Result;
@@ -74,22 +75,65 @@ impl UpdateHandler {
                 method,
                 format,
                 primary_key,
+                strict,
+                csv_delimiter,
             } => index.update_documents(
                 *format,
                 *method,
                 content,
                 update_builder,
                 primary_key.as_deref(),
+                *strict,
+                *csv_delimiter,
+                |step| meta.progress.report(step),
             ),
             ClearDocuments => index.clear_documents(update_builder),
             DeleteDocuments => index.delete_documents(content, update_builder),
+            DeleteDocumentsByFilter { filter } => {
+                index.delete_documents_by_filter(filter, update_builder)
+            }
             Settings(settings) => index.update_settings(settings, update_builder),
             Facets(levels) => index.update_facets(levels, update_builder),
         };
 
         match result {
             Ok(result) => Ok(meta.process(result)),
+            Err(e) if is_map_full(&e) => Err(meta.fail_map_full(e.to_string())),
+            Err(e) if is_transient(&e) => Err(meta.fail_transient(e.to_string())),
             Err(e) => Err(meta.fail(e.to_string())),
         }
     }
 }
+
+/// Returns whether the error is caused by the environment of the index reaching its map size.
+fn is_map_full(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<heed::Error>(),
+            Some(heed::Error::Mdb(heed::MdbError::MapFull))
+        )
+    })
+}
+
+/// Returns whether the error is caused by the environment rather than by the content of the
+/// update, like an io error or a full database, so processing the update again may succeed.
+fn is_transient(error: &anyhow::Error) -> bool {
+    // The readers of the documents report the malformed payloads as io errors too.
+    let is_transient_io = |e: &io::Error| {
+        !matches!(
+            e.kind(),
+            io::ErrorKind::InvalidData | io::ErrorKind::InvalidInput | io::ErrorKind::UnexpectedEof
+        )
+    };
+
+    error.chain().any(|cause| {
+        if let Some(e) = cause.downcast_ref::<io::Error>() {
+            return is_transient_io(e);
+        }
+        match cause.downcast_ref::<heed::Error>() {
+            Some(heed::Error::Io(e)) => is_transient_io(e),
+            Some(heed::Error::Mdb(heed::MdbError::MapFull)) => true,
+            _ => false,
+        }
+    })
+}
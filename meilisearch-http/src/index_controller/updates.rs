@@ -1,7 +1,28 @@
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::sync::Arc;
+
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use milli::update::UpdateIndexingStep;
+use parking_lot::Mutex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use uuid::Uuid;
 
+/// The lane of an update in the queue of its index. The high priority updates are processed
+/// before the normal ones, the updates of a lane are processed in the order they were enqueued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    High,
+    Normal,
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Enqueued<M> {
@@ -9,6 +30,8 @@ pub struct Enqueued<M> {
     pub meta: M,
     pub enqueued_at: DateTime<Utc>,
     pub index_uuid: Uuid,
+    #[serde(default)]
+    pub priority: Priority,
 }
 
 impl<M> Enqueued<M> {
@@ -18,13 +41,20 @@ impl<M> Enqueued<M> {
             meta,
             update_id,
             index_uuid,
+            priority: Priority::default(),
         }
     }
 
+    pub fn with_priority(self, priority: Priority) -> Self {
+        Self { priority, ..self }
+    }
+
     pub fn processing(self) -> Processing<M> {
         Processing {
             from: self,
             started_processing_at: Utc::now(),
+            retries: 0,
+            progress: UpdateProgress::default(),
         }
     }
 
@@ -44,7 +74,23 @@ impl<M> Enqueued<M> {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone)]
+/// Returns the number of seconds elapsed between `start` and `end`.
+fn duration_secs(start: DateTime<Utc>, end: DateTime<Utc>) -> f64 {
+    (end - start).num_microseconds().unwrap_or(i64::MAX) as f64 / 1_000_000.0
+}
+
+/// The serialized form of a finished update, the `finishedAt` date and the `duration` of the
+/// processing are computed from the transition dates of the update.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FinishedRepr<T> {
+    #[serde(flatten)]
+    update: T,
+    finished_at: DateTime<Utc>,
+    duration: f64,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Processed<M, N> {
     pub success: N,
@@ -57,6 +103,34 @@ impl<M, N> Processed<M, N> {
     pub fn id(&self) -> u64 {
         self.from.id()
     }
+
+    pub fn finished_at(&self) -> DateTime<Utc> {
+        self.processed_at
+    }
+}
+
+impl<M: Serialize, N: Serialize> Serialize for Processed<M, N> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ProcessedRepr<'a, M, N> {
+            success: &'a N,
+            processed_at: DateTime<Utc>,
+            #[serde(flatten)]
+            from: &'a Processing<M>,
+        }
+
+        FinishedRepr {
+            update: ProcessedRepr {
+                success: &self.success,
+                processed_at: self.processed_at,
+                from: &self.from,
+            },
+            finished_at: self.processed_at,
+            duration: duration_secs(self.from.started_processing_at, self.processed_at),
+        }
+        .serialize(serializer)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone)]
@@ -65,6 +139,11 @@ pub struct Processing<M> {
     #[serde(flatten)]
     pub from: Enqueued<M>,
     pub started_processing_at: DateTime<Utc>,
+    /// The number of times the processing was retried after a transient failure.
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(default, skip_serializing_if = "UpdateProgress::is_unknown")]
+    pub progress: UpdateProgress,
 }
 
 impl<M> Processing<M> {
@@ -83,20 +162,149 @@ impl<M> Processing<M> {
     pub fn process<N>(self, meta: N) -> Processed<M, N> {
         Processed {
             success: meta,
-            from: self,
+            from: self.finish(),
             processed_at: Utc::now(),
         }
     }
 
     pub fn fail<E>(self, error: E) -> Failed<M, E> {
         Failed {
-            from: self,
+            from: self.finish(),
             error,
             failed_at: Utc::now(),
+            retryable: false,
+            map_full: false,
+        }
+    }
+
+    /// Fails the processing with an error that may not happen again, the update can be retried.
+    pub fn fail_transient<E>(self, error: E) -> Failed<M, E> {
+        Failed {
+            retryable: true,
+            ..self.fail(error)
+        }
+    }
+
+    /// Fails the processing because the environment of the index is full, the update can be
+    /// retried once the index is given a larger map size.
+    pub fn fail_map_full<E>(self, error: E) -> Failed<M, E> {
+        Failed {
+            map_full: true,
+            ..self.fail_transient(error)
+        }
+    }
+
+    /// Detaches the processing from the progress of the update, which isn't reported once it is
+    /// finished.
+    fn finish(self) -> Self {
+        Self {
+            progress: UpdateProgress::default(),
+            ..self
         }
     }
 }
 
+/// The progress of a documents addition, as reported by the indexer.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    pub processed_documents: usize,
+    /// Unknown until all the documents have been read.
+    pub total_documents: Option<usize>,
+    /// The progress of the whole update, over all the indexing steps.
+    pub percentage: f64,
+}
+
+/// The progress of the update being processed. It is shared by the clones of its `Processing`
+/// status, so that the status returned while the update runs holds the progress reported by the
+/// index actor. It is ignored when comparing the statuses.
+#[derive(Debug, Clone, Default)]
+pub struct UpdateProgress(Arc<Mutex<Option<Progress>>>);
+
+impl UpdateProgress {
+    pub fn get(&self) -> Option<Progress> {
+        *self.0.lock()
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        self.get().is_none()
+    }
+
+    /// Updates the progress from the step the indexer is at.
+    pub fn report(&self, step: UpdateIndexingStep) {
+        use UpdateIndexingStep::*;
+
+        // The steps are weighted equally.
+        let ratio = |seen: usize, total: usize| seen as f64 / total.max(1) as f64;
+        let mut progress = self.0.lock();
+        let (step, processed_documents, total_documents, done) = match step {
+            TransformFromUserIntoGenericFormat { documents_seen } => (0, documents_seen, None, 0.0),
+            ComputeIdsAndMergeDocuments {
+                documents_seen,
+                total_documents,
+            } => (
+                1,
+                documents_seen,
+                Some(total_documents),
+                ratio(documents_seen, total_documents),
+            ),
+            IndexDocuments {
+                documents_seen,
+                total_documents,
+            } => (
+                2,
+                documents_seen,
+                Some(total_documents),
+                ratio(documents_seen, total_documents),
+            ),
+            // All the documents have been indexed once the databases are merged.
+            MergeDataIntoFinalDatabase {
+                databases_seen,
+                total_databases,
+            } => {
+                let total_documents = progress.and_then(|progress| progress.total_documents);
+                (
+                    3,
+                    total_documents.unwrap_or_default(),
+                    total_documents,
+                    ratio(databases_seen, total_databases),
+                )
+            }
+        };
+        *progress = Some(Progress {
+            processed_documents,
+            total_documents,
+            percentage: ((step as f64 + done.min(1.0)) / 4.0 * 100.0).floor(),
+        });
+    }
+}
+
+impl PartialEq for UpdateProgress {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for UpdateProgress {}
+
+impl Hash for UpdateProgress {
+    fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
+impl Serialize for UpdateProgress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.get().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for UpdateProgress {
+    /// The progress is only known while the update is processed, it is never read back.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        serde::de::IgnoredAny::deserialize(deserializer)?;
+        Ok(Self::default())
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Aborted<M> {
@@ -109,24 +317,121 @@ impl<M> Aborted<M> {
     pub fn id(&self) -> u64 {
         self.from.id()
     }
+
+    pub fn finished_at(&self) -> DateTime<Utc> {
+        self.aborted_at
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Failed<M, E> {
     #[serde(flatten)]
     from: Processing<M>,
     error: E,
     failed_at: DateTime<Utc>,
+    /// Only known while the update is processed, the failures read from the store are final.
+    #[serde(skip)]
+    retryable: bool,
+    #[serde(skip)]
+    map_full: bool,
 }
 
 impl<M, E> Failed<M, E> {
     pub fn id(&self) -> u64 {
         self.from.id()
     }
+
+    pub fn finished_at(&self) -> DateTime<Utc> {
+        self.failed_at
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.retryable
+    }
+
+    pub fn is_map_full(&self) -> bool {
+        self.map_full
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Serialize)]
+impl<M: Serialize, E: Serialize> Serialize for Failed<M, E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct FailedRepr<'a, M, E> {
+            #[serde(flatten)]
+            from: &'a Processing<M>,
+            error: &'a E,
+            failed_at: DateTime<Utc>,
+        }
+
+        FinishedRepr {
+            update: FailedRepr {
+                from: &self.from,
+                error: &self.error,
+                failed_at: self.failed_at,
+            },
+            finished_at: self.failed_at,
+            duration: duration_secs(self.from.started_processing_at, self.failed_at),
+        }
+        .serialize(serializer)
+    }
+}
+
+/// The state of an update, without its content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UpdateState {
+    Processing,
+    Enqueued,
+    Processed,
+    Aborted,
+    Failed,
+}
+
+/// The threshold below which the finished updates are pruned: the updates with a lower id, or
+/// the updates finished before a date.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PruneBefore {
+    Id(u64),
+    Date(DateTime<Utc>),
+}
+
+impl PruneBefore {
+    /// Returns whether the update `id`, finished at `finished_at`, is below the threshold.
+    pub fn includes(&self, id: u64, finished_at: DateTime<Utc>) -> bool {
+        match *self {
+            PruneBefore::Id(before) => id < before,
+            PruneBefore::Date(before) => finished_at < before,
+        }
+    }
+}
+
+impl FromStr for PruneBefore {
+    type Err = chrono::ParseError;
+
+    /// Parses an update id, or an RFC 3339 date.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(id) => Ok(PruneBefore::Id(id)),
+            Err(_) => Ok(PruneBefore::Date(s.parse()?)),
+        }
+    }
+}
+
+/// The state of the update queue of an index.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueStats {
+    /// The number of updates waiting to be processed, without the one being processed.
+    pub enqueued: u64,
+    pub is_processing: bool,
+    /// The number of updates finished per minute, averaged over the last minutes.
+    pub processed_per_minute: f64,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Serialize, Clone)]
 #[serde(tag = "status", rename_all = "camelCase")]
 pub enum UpdateStatus<M, N, E> {
     Processing(Processing<M>),
@@ -147,6 +452,24 @@ impl<M, N, E> UpdateStatus<M, N, E> {
         }
     }
 
+    pub fn state(&self) -> UpdateState {
+        match self {
+            UpdateStatus::Processing(_) => UpdateState::Processing,
+            UpdateStatus::Enqueued(_) => UpdateState::Enqueued,
+            UpdateStatus::Processed(_) => UpdateState::Processed,
+            UpdateStatus::Aborted(_) => UpdateState::Aborted,
+            UpdateStatus::Failed(_) => UpdateState::Failed,
+        }
+    }
+
+    /// Returns whether the update reached a state it will never leave.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            UpdateStatus::Processed(_) | UpdateStatus::Aborted(_) | UpdateStatus::Failed(_)
+        )
+    }
+
     pub fn processed(&self) -> Option<&Processed<M, N>> {
         match self {
             UpdateStatus::Processed(p) => Some(p),
@@ -1,19 +1,26 @@
 use std::path::PathBuf;
 
+use chrono::{DateTime, Utc};
 use log::{info, warn};
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
-use super::{Result, UuidError, UuidResolveMsg, UuidStore};
+use super::{RenameHook, Result, UuidError, UuidResolveMsg, UuidStore};
+use crate::index_controller::shutdown::Shutdown;
 
 pub struct UuidResolverActor<S> {
     inbox: mpsc::Receiver<UuidResolveMsg>,
     store: S,
+    shutdown: Shutdown,
 }
 
 impl<S: UuidStore> UuidResolverActor<S> {
-    pub fn new(inbox: mpsc::Receiver<UuidResolveMsg>, store: S) -> Self {
-        Self { inbox, store }
+    pub fn new(inbox: mpsc::Receiver<UuidResolveMsg>, store: S, shutdown: Shutdown) -> Self {
+        Self {
+            inbox,
+            store,
+            shutdown,
+        }
     }
 
     pub async fn run(mut self) {
@@ -22,10 +29,17 @@ impl<S: UuidStore> UuidResolverActor<S> {
         info!("uuid resolver started");
 
         loop {
-            match self.inbox.recv().await {
+            let msg = tokio::select! {
+                msg = self.inbox.recv() => msg,
+                _ = self.shutdown.wait() => None,
+            };
+            match msg {
                 Some(Create { uid: name, ret }) => {
                     let _ = ret.send(self.handle_create(name).await);
                 }
+                Some(CreateBatch { uids, ret }) => {
+                    let _ = ret.send(self.handle_create_batch(uids).await);
+                }
                 Some(Get { uid: name, ret }) => {
                     let _ = ret.send(self.handle_get(name).await);
                 }
@@ -38,13 +52,52 @@ impl<S: UuidStore> UuidResolverActor<S> {
                 Some(Insert { ret, uuid, name }) => {
                     let _ = ret.send(self.handle_insert(name, uuid).await);
                 }
+                Some(Rename {
+                    uid,
+                    new_uid,
+                    hook,
+                    ret,
+                }) => {
+                    let _ = ret.send(self.handle_rename(uid, new_uid, hook).await);
+                }
+                Some(Swap { first, second, ret }) => {
+                    let _ = ret.send(self.handle_swap(first, second).await);
+                }
+                Some(CreateAlias { alias, uid, ret }) => {
+                    let _ = ret.send(self.handle_create_alias(alias, uid).await);
+                }
+                Some(DeleteAlias { alias, ret }) => {
+                    let _ = ret.send(self.handle_delete_alias(alias).await);
+                }
+                Some(Trash {
+                    uid,
+                    deleted_at,
+                    ret,
+                }) => {
+                    let _ = ret.send(self.handle_trash(uid, deleted_at).await);
+                }
+                Some(Restore { uid, ret }) => {
+                    let _ = ret.send(self.handle_restore(uid).await);
+                }
+                Some(Trashed { ret }) => {
+                    let _ = ret.send(self.handle_trashed().await);
+                }
+                Some(PurgeTrash {
+                    deleted_before,
+                    ret,
+                }) => {
+                    let _ = ret.send(self.handle_purge_trash(deleted_before).await);
+                }
                 Some(SnapshotRequest { path, ret }) => {
                     let _ = ret.send(self.handle_snapshot(path).await);
                 }
                 Some(GetSize { ret }) => {
                     let _ = ret.send(self.handle_get_size().await);
                 }
-                // all senders have been dropped, need to quit.
+                Some(Ping { ret }) => {
+                    let _ = ret.send(Ok(()));
+                }
+                // all senders have been dropped or the shutdown was triggered, need to quit.
                 None => break,
             }
         }
@@ -59,6 +112,14 @@ impl<S: UuidStore> UuidResolverActor<S> {
         self.store.create_uuid(uid, true).await
     }
 
+    async fn handle_create_batch(&self, uids: Vec<String>) -> Vec<Result<Uuid>> {
+        let mut results = Vec::with_capacity(uids.len());
+        for uid in uids {
+            results.push(self.handle_create(uid).await);
+        }
+        results
+    }
+
     async fn handle_get(&self, uid: String) -> Result<Uuid> {
         self.store
             .get_uuid(uid.clone())
@@ -78,6 +139,50 @@ impl<S: UuidStore> UuidResolverActor<S> {
         Ok(result)
     }
 
+    async fn handle_rename(&self, uid: String, new_uid: String, hook: RenameHook) -> Result<Uuid> {
+        if !is_index_uid_valid(&new_uid) {
+            return Err(UuidError::BadlyFormatted(new_uid));
+        }
+        self.store.rename(uid, new_uid, hook).await
+    }
+
+    async fn handle_swap(&self, first: String, second: String) -> Result<()> {
+        self.store.swap(first, second).await
+    }
+
+    async fn handle_create_alias(&self, alias: String, uid: String) -> Result<Uuid> {
+        if !is_index_uid_valid(&alias) {
+            return Err(UuidError::BadlyFormatted(alias));
+        }
+        self.store.create_alias(alias, uid).await
+    }
+
+    async fn handle_delete_alias(&self, alias: String) -> Result<Uuid> {
+        self.store
+            .delete_alias(alias.clone())
+            .await?
+            .ok_or(UuidError::UnexistingAlias(alias))
+    }
+
+    async fn handle_trash(&self, uid: String, deleted_at: DateTime<Utc>) -> Result<Uuid> {
+        self.store
+            .trash(uid.clone(), deleted_at)
+            .await?
+            .ok_or(UuidError::UnexistingIndex(uid))
+    }
+
+    async fn handle_restore(&self, uid: String) -> Result<Uuid> {
+        self.store.restore(uid).await
+    }
+
+    async fn handle_trashed(&self) -> Result<Vec<Uuid>> {
+        self.store.trashed().await
+    }
+
+    async fn handle_purge_trash(&self, deleted_before: DateTime<Utc>) -> Result<Vec<Uuid>> {
+        self.store.purge_trash(deleted_before).await
+    }
+
     async fn handle_snapshot(&self, path: PathBuf) -> Result<Vec<Uuid>> {
         self.store.snapshot(path).await
     }
@@ -1,9 +1,15 @@
 use std::path::{Path, PathBuf};
 
+use chrono::{DateTime, Utc};
 use tokio::sync::{mpsc, oneshot};
 use uuid::Uuid;
 
-use super::{HeedUuidStore, Result, UuidResolveMsg, UuidResolverActor, UuidResolverHandle};
+use crate::index_controller::shutdown::Shutdown;
+
+use super::{
+    ActorError, HeedUuidStore, RenameHook, Result, UuidResolveMsg, UuidResolverActor,
+    UuidResolverHandle,
+};
 
 #[derive(Clone)]
 pub struct UuidResolverHandleImpl {
@@ -11,10 +17,15 @@ pub struct UuidResolverHandleImpl {
 }
 
 impl UuidResolverHandleImpl {
-    pub fn new(path: impl AsRef<Path>) -> anyhow::Result<Self> {
-        let (sender, reveiver) = mpsc::channel(100);
-        let store = HeedUuidStore::new(path)?;
-        let actor = UuidResolverActor::new(reveiver, store);
+    pub fn new(
+        path: impl AsRef<Path>,
+        cascade_aliases: bool,
+        mailbox_size: usize,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<Self> {
+        let (sender, reveiver) = mpsc::channel(mailbox_size);
+        let store = HeedUuidStore::new(path, cascade_aliases)?;
+        let actor = UuidResolverActor::new(reveiver, store, shutdown);
         tokio::spawn(actor.run());
         Ok(Self { sender })
     }
@@ -26,9 +37,9 @@ impl UuidResolverHandle for UuidResolverHandleImpl {
         let (ret, receiver) = oneshot::channel();
         let msg = UuidResolveMsg::Get { uid: name, ret };
         let _ = self.sender.send(msg).await;
-        Ok(receiver
+        receiver
             .await
-            .expect("Uuid resolver actor has been killed")?)
+            .map_err(|_| ActorError::Unavailable("uuid resolver"))?
     }
 
     async fn create(&self, name: String) -> anyhow::Result<Uuid> {
@@ -37,7 +48,19 @@ impl UuidResolverHandle for UuidResolverHandleImpl {
         let _ = self.sender.send(msg).await;
         Ok(receiver
             .await
-            .expect("Uuid resolver actor has been killed")?)
+            .map_err(|_| ActorError::Unavailable("uuid resolver"))??)
+    }
+
+    async fn create_batch(&self, names: Vec<String>) -> Vec<Result<Uuid>> {
+        let (ret, receiver) = oneshot::channel();
+        let count = names.len();
+        let msg = UuidResolveMsg::CreateBatch { uids: names, ret };
+        let _ = self.sender.send(msg).await;
+        receiver.await.unwrap_or_else(|_| {
+            (0..count)
+                .map(|_| Err(ActorError::Unavailable("uuid resolver").into()))
+                .collect()
+        })
     }
 
     async fn delete(&self, name: String) -> anyhow::Result<Uuid> {
@@ -46,7 +69,7 @@ impl UuidResolverHandle for UuidResolverHandleImpl {
         let _ = self.sender.send(msg).await;
         Ok(receiver
             .await
-            .expect("Uuid resolver actor has been killed")?)
+            .map_err(|_| ActorError::Unavailable("uuid resolver"))??)
     }
 
     async fn list(&self) -> anyhow::Result<Vec<(String, Uuid)>> {
@@ -55,7 +78,7 @@ impl UuidResolverHandle for UuidResolverHandleImpl {
         let _ = self.sender.send(msg).await;
         Ok(receiver
             .await
-            .expect("Uuid resolver actor has been killed")?)
+            .map_err(|_| ActorError::Unavailable("uuid resolver"))??)
     }
 
     async fn insert(&self, name: String, uuid: Uuid) -> anyhow::Result<()> {
@@ -64,24 +87,120 @@ impl UuidResolverHandle for UuidResolverHandleImpl {
         let _ = self.sender.send(msg).await;
         Ok(receiver
             .await
-            .expect("Uuid resolver actor has been killed")?)
+            .map_err(|_| ActorError::Unavailable("uuid resolver"))??)
+    }
+
+    async fn rename(&self, uid: String, new_uid: String, hook: RenameHook) -> Result<Uuid> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = UuidResolveMsg::Rename {
+            uid,
+            new_uid,
+            hook,
+            ret,
+        };
+        let _ = self.sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("uuid resolver"))?
+    }
+
+    async fn swap(&self, first: String, second: String) -> Result<()> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = UuidResolveMsg::Swap { first, second, ret };
+        let _ = self.sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("uuid resolver"))?
+    }
+
+    async fn create_alias(&self, alias: String, uid: String) -> Result<Uuid> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = UuidResolveMsg::CreateAlias { alias, uid, ret };
+        let _ = self.sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("uuid resolver"))?
+    }
+
+    async fn delete_alias(&self, alias: String) -> Result<Uuid> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = UuidResolveMsg::DeleteAlias { alias, ret };
+        let _ = self.sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("uuid resolver"))?
+    }
+
+    async fn trash(&self, uid: String, deleted_at: DateTime<Utc>) -> Result<Uuid> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = UuidResolveMsg::Trash {
+            uid,
+            deleted_at,
+            ret,
+        };
+        let _ = self.sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("uuid resolver"))?
+    }
+
+    async fn restore(&self, uid: String) -> Result<Uuid> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = UuidResolveMsg::Restore { uid, ret };
+        let _ = self.sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("uuid resolver"))?
+    }
+
+    async fn trashed(&self) -> Result<Vec<Uuid>> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = UuidResolveMsg::Trashed { ret };
+        let _ = self.sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("uuid resolver"))?
+    }
+
+    async fn purge_trash(&self, deleted_before: DateTime<Utc>) -> Result<Vec<Uuid>> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = UuidResolveMsg::PurgeTrash {
+            deleted_before,
+            ret,
+        };
+        let _ = self.sender.send(msg).await;
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("uuid resolver"))?
     }
 
     async fn snapshot(&self, path: PathBuf) -> Result<Vec<Uuid>> {
         let (ret, receiver) = oneshot::channel();
         let msg = UuidResolveMsg::SnapshotRequest { path, ret };
         let _ = self.sender.send(msg).await;
-        Ok(receiver
+        receiver
             .await
-            .expect("Uuid resolver actor has been killed")?)
+            .map_err(|_| ActorError::Unavailable("uuid resolver"))?
     }
 
     async fn get_size(&self) -> Result<u64> {
         let (ret, receiver) = oneshot::channel();
         let msg = UuidResolveMsg::GetSize { ret };
         let _ = self.sender.send(msg).await;
-        Ok(receiver
+        receiver
+            .await
+            .map_err(|_| ActorError::Unavailable("uuid resolver"))?
+    }
+
+    async fn ping(&self) -> Result<()> {
+        let (ret, receiver) = oneshot::channel();
+        let msg = UuidResolveMsg::Ping { ret };
+        self.sender
+            .send(msg)
+            .await
+            .map_err(|_| ActorError::Unavailable("uuid resolver"))?;
+        receiver
             .await
-            .expect("Uuid resolver actor has been killed")?)
+            .map_err(|_| ActorError::Unavailable("uuid resolver"))?
     }
 }
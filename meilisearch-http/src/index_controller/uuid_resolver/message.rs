@@ -1,9 +1,10 @@
 use std::path::PathBuf;
 
+use chrono::{DateTime, Utc};
 use tokio::sync::oneshot;
 use uuid::Uuid;
 
-use super::Result;
+use super::{RenameHook, Result};
 
 pub enum UuidResolveMsg {
     Get {
@@ -14,6 +15,10 @@ pub enum UuidResolveMsg {
         uid: String,
         ret: oneshot::Sender<Result<Uuid>>,
     },
+    CreateBatch {
+        uids: Vec<String>,
+        ret: oneshot::Sender<Vec<Result<Uuid>>>,
+    },
     Delete {
         uid: String,
         ret: oneshot::Sender<Result<Uuid>>,
@@ -26,6 +31,42 @@ pub enum UuidResolveMsg {
         name: String,
         ret: oneshot::Sender<Result<()>>,
     },
+    Rename {
+        uid: String,
+        new_uid: String,
+        hook: RenameHook,
+        ret: oneshot::Sender<Result<Uuid>>,
+    },
+    Swap {
+        first: String,
+        second: String,
+        ret: oneshot::Sender<Result<()>>,
+    },
+    CreateAlias {
+        alias: String,
+        uid: String,
+        ret: oneshot::Sender<Result<Uuid>>,
+    },
+    DeleteAlias {
+        alias: String,
+        ret: oneshot::Sender<Result<Uuid>>,
+    },
+    Trash {
+        uid: String,
+        deleted_at: DateTime<Utc>,
+        ret: oneshot::Sender<Result<Uuid>>,
+    },
+    Restore {
+        uid: String,
+        ret: oneshot::Sender<Result<Uuid>>,
+    },
+    Trashed {
+        ret: oneshot::Sender<Result<Vec<Uuid>>>,
+    },
+    PurgeTrash {
+        deleted_before: DateTime<Utc>,
+        ret: oneshot::Sender<Result<Vec<Uuid>>>,
+    },
     SnapshotRequest {
         path: PathBuf,
         ret: oneshot::Sender<Result<Vec<Uuid>>>,
@@ -33,4 +74,7 @@ pub enum UuidResolveMsg {
     GetSize {
         ret: oneshot::Sender<Result<u64>>,
     },
+    Ping {
+        ret: oneshot::Sender<Result<()>>,
+    },
 }
@@ -5,9 +5,11 @@ mod store;
 
 use std::path::PathBuf;
 
+use chrono::{DateTime, Utc};
 use thiserror::Error;
 use uuid::Uuid;
 
+use crate::index_controller::ActorError;
 use actor::UuidResolverActor;
 use message::UuidResolveMsg;
 use store::{HeedUuidStore, UuidStore};
@@ -21,16 +23,41 @@ const UUID_STORE_SIZE: usize = 1_073_741_824; //1GiB
 
 pub type Result<T> = std::result::Result<T, UuidError>;
 
+/// Called with the uuid of a renamed index before the rename is committed, the rename is aborted
+/// if it fails.
+pub type RenameHook = Box<dyn FnOnce(Uuid) -> anyhow::Result<()> + Send>;
+
 #[async_trait::async_trait]
 #[cfg_attr(test, automock)]
 pub trait UuidResolverHandle {
     async fn get(&self, name: String) -> Result<Uuid>;
     async fn insert(&self, name: String, uuid: Uuid) -> anyhow::Result<()>;
     async fn create(&self, name: String) -> anyhow::Result<Uuid>;
+    /// Creates several entries at once, each creation succeeds or fails independently.
+    async fn create_batch(&self, names: Vec<String>) -> Vec<Result<Uuid>>;
     async fn delete(&self, name: String) -> anyhow::Result<Uuid>;
     async fn list(&self) -> anyhow::Result<Vec<(String, Uuid)>>;
+    /// Moves the index pointed at by `uid` to `new_uid`, keeping its uuid. The rename is only
+    /// committed once `hook` succeeded.
+    async fn rename(&self, uid: String, new_uid: String, hook: RenameHook) -> Result<Uuid>;
+    /// Swaps the indexes pointed at by two uids.
+    async fn swap(&self, first: String, second: String) -> Result<()>;
+    /// Creates an alias resolving to the same index as `uid`.
+    async fn create_alias(&self, alias: String, uid: String) -> Result<Uuid>;
+    async fn delete_alias(&self, alias: String) -> Result<Uuid>;
+    /// Moves `uid` to the trash, its index is kept until it is restored or purged.
+    async fn trash(&self, uid: String, deleted_at: DateTime<Utc>) -> Result<Uuid>;
+    /// Moves the last index trashed under `uid` back to `uid`, along with its aliases.
+    async fn restore(&self, uid: String) -> Result<Uuid>;
+    /// Returns the uuids of the indexes in the trash.
+    async fn trashed(&self) -> Result<Vec<Uuid>>;
+    /// Removes the indexes trashed before `deleted_before` from the trash, their uuids are
+    /// returned so that their data can be deleted.
+    async fn purge_trash(&self, deleted_before: DateTime<Utc>) -> Result<Vec<Uuid>>;
     async fn snapshot(&self, path: PathBuf) -> Result<Vec<Uuid>>;
     async fn get_size(&self) -> Result<u64>;
+    /// Checks that the actor is processing its messages.
+    async fn ping(&self) -> Result<()>;
 }
 
 #[derive(Debug, Error)]
@@ -47,4 +74,14 @@ pub enum UuidError {
     Uuid(#[from] uuid::Error),
     #[error("Badly formatted index uid: {0}")]
     BadlyFormatted(String),
+    #[error("Alias \"{0}\" doesn't exist.")]
+    UnexistingAlias(String),
+    #[error("Index \"{0}\" isn't in the trash.")]
+    UnexistingTrashedIndex(String),
+    #[error("Index \"{0}\" can't be deleted while it has aliases: {1:?}.")]
+    IndexHasAliases(String, Vec<String>),
+    #[error("{0}")]
+    Actor(#[from] ActorError),
+    #[error("{0}")]
+    RenameHook(anyhow::Error),
 }
@@ -1,13 +1,15 @@
 use std::fs::create_dir_all;
 use std::path::{Path, PathBuf};
 
+use chrono::{DateTime, Utc};
 use heed::{
-    types::{ByteSlice, Str},
-    CompactionOption, Database, Env, EnvOpenOptions,
+    types::{ByteSlice, SerdeJson, Str},
+    CompactionOption, Database, Env, EnvOpenOptions, RoTxn, RwTxn,
 };
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use super::{Result, UuidError, UUID_STORE_SIZE};
+use super::{RenameHook, Result, UuidError, UUID_STORE_SIZE};
 use crate::helpers::EnvSizer;
 
 #[async_trait::async_trait]
@@ -19,24 +21,138 @@ pub trait UuidStore {
     async fn delete(&self, uid: String) -> Result<Option<Uuid>>;
     async fn list(&self) -> Result<Vec<(String, Uuid)>>;
     async fn insert(&self, name: String, uuid: Uuid) -> Result<()>;
+    // Move the uuid of the entry `uid` to `new_uid`, which must not be used already.
+    async fn rename(&self, uid: String, new_uid: String, hook: RenameHook) -> Result<Uuid>;
+    // Atomically exchange the uuids of two existing entries.
+    async fn swap(&self, first: String, second: String) -> Result<()>;
+    // Create an alias pointing to the uuid of the existing entry `uid`. An alias can't share its
+    // name with an entry or another alias.
+    async fn create_alias(&self, alias: String, uid: String) -> Result<Uuid>;
+    async fn delete_alias(&self, alias: String) -> Result<Option<Uuid>>;
+    // Move the entry `uid` to the trash, its uuid is kept until the entry is restored or purged.
+    async fn trash(&self, uid: String, deleted_at: DateTime<Utc>) -> Result<Option<Uuid>>;
+    // Move the last trashed entry named `uid` back, `uid` must not be used already. The aliases
+    // deleted along with the entry are restored as well, unless their name was reused since.
+    async fn restore(&self, uid: String) -> Result<Uuid>;
+    async fn trashed(&self) -> Result<Vec<Uuid>>;
+    // Remove the entries trashed before `deleted_before` from the trash, returning their uuids.
+    async fn purge_trash(&self, deleted_before: DateTime<Utc>) -> Result<Vec<Uuid>>;
     async fn snapshot(&self, path: PathBuf) -> Result<Vec<Uuid>>;
     async fn get_size(&self) -> Result<u64>;
 }
 
 pub struct HeedUuidStore {
     env: Env,
+    /// The uuids of the entries, by uid. They can't be kept in the main database of the env, which
+    /// also holds the names of the other databases.
     db: Database<Str, ByteSlice>,
+    aliases: Database<Str, ByteSlice>,
+    /// The trashed entries, by uuid.
+    trash: Database<Str, SerdeJson<TrashedEntry>>,
+    /// Whether deleting an entry deletes its aliases, or is refused while aliases remain.
+    cascade_aliases: bool,
 }
 
 impl HeedUuidStore {
-    pub fn new(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+    pub fn new(path: impl AsRef<Path>, cascade_aliases: bool) -> anyhow::Result<Self> {
         let path = path.as_ref().join("index_uuids");
         create_dir_all(&path)?;
         let mut options = EnvOpenOptions::new();
         options.map_size(UUID_STORE_SIZE); // 1GB
+        options.max_dbs(3);
         let env = options.open(path)?;
-        let db = env.create_database(None)?;
-        Ok(Self { env, db })
+        let db = match env.open_database(Some(UIDS_DB_NAME))? {
+            Some(db) => db,
+            None => migrate_uids(&env)?,
+        };
+        let aliases = env.create_database(Some(ALIASES_DB_NAME))?;
+        let trash = env.create_database(Some(TRASH_DB_NAME))?;
+        Ok(Self {
+            env,
+            db,
+            aliases,
+            trash,
+            cascade_aliases,
+        })
+    }
+}
+
+const UIDS_DB_NAME: &str = "uids";
+const ALIASES_DB_NAME: &str = "aliases";
+const TRASH_DB_NAME: &str = "trash";
+
+/// Creates the database of the uids and moves there the entries of the stores created before it,
+/// which were kept in the main database. The records of the named databases are left in place.
+fn migrate_uids(env: &Env) -> anyhow::Result<Database<Str, ByteSlice>> {
+    let mut txn = env.write_txn()?;
+    let main: Database<Str, ByteSlice> = env.create_database_with_txn(None, &mut txn)?;
+    let mut entries = Vec::new();
+    for entry in main.iter(&txn)? {
+        let (uid, uuid) = entry?;
+        let is_db_name = [ALIASES_DB_NAME, TRASH_DB_NAME].contains(&uid);
+        if !is_db_name && uuid.len() == 16 {
+            entries.push((uid.to_owned(), Uuid::from_slice(uuid)?));
+        }
+    }
+
+    // An entry named like the new database must be removed before the database is created.
+    for (uid, _) in &entries {
+        main.delete(&mut txn, uid)?;
+    }
+    let db = env.create_database_with_txn(Some(UIDS_DB_NAME), &mut txn)?;
+    for (uid, uuid) in entries {
+        db.put(&mut txn, &uid, uuid.as_bytes())?;
+    }
+    txn.commit()?;
+    Ok(db)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TrashedEntry {
+    uid: String,
+    deleted_at: DateTime<Utc>,
+    /// The aliases deleted along with the entry.
+    #[serde(default)]
+    aliases: Vec<String>,
+}
+
+/// Returns the aliases pointing to `uuid`.
+fn aliases_of(txn: &RoTxn, aliases: Database<Str, ByteSlice>, uuid: Uuid) -> Result<Vec<String>> {
+    let mut result = Vec::new();
+    for entry in aliases.iter(txn)? {
+        let (alias, alias_uuid) = entry?;
+        if Uuid::from_slice(alias_uuid)? == uuid {
+            result.push(alias.to_owned());
+        }
+    }
+    Ok(result)
+}
+
+/// Deletes the entry `uid` along with its aliases, if they can be deleted. Returns the uuid of the
+/// entry and its deleted aliases, if it existed.
+fn delete_entry(
+    txn: &mut RwTxn,
+    db: Database<Str, ByteSlice>,
+    aliases: Database<Str, ByteSlice>,
+    cascade_aliases: bool,
+    uid: String,
+) -> Result<Option<(Uuid, Vec<String>)>> {
+    match db.get(txn, &uid)? {
+        Some(uuid) => {
+            let uuid = Uuid::from_slice(uuid)?;
+            let index_aliases = aliases_of(txn, aliases, uuid)?;
+            if !index_aliases.is_empty() {
+                if !cascade_aliases {
+                    return Err(UuidError::IndexHasAliases(uid, index_aliases));
+                }
+                for alias in &index_aliases {
+                    aliases.delete(txn, alias)?;
+                }
+            }
+            db.delete(txn, &uid)?;
+            Ok(Some((uuid, index_aliases)))
+        }
+        None => Ok(None),
     }
 }
 
@@ -45,8 +161,13 @@ impl UuidStore for HeedUuidStore {
     async fn create_uuid(&self, name: String, err: bool) -> Result<Uuid> {
         let env = self.env.clone();
         let db = self.db;
+        let aliases = self.aliases;
         tokio::task::spawn_blocking(move || {
             let mut txn = env.write_txn()?;
+            if aliases.get(&txn, &name)?.is_some() {
+                return Err(UuidError::NameAlreadyExist);
+            }
+
             match db.get(&txn, &name)? {
                 Some(uuid) => {
                     if err {
@@ -70,9 +191,15 @@ impl UuidStore for HeedUuidStore {
     async fn get_uuid(&self, name: String) -> Result<Option<Uuid>> {
         let env = self.env.clone();
         let db = self.db;
+        let aliases = self.aliases;
         tokio::task::spawn_blocking(move || {
             let txn = env.read_txn()?;
-            match db.get(&txn, &name)? {
+            // The name is resolved through the aliases when it isn't an entry itself.
+            let uuid = match db.get(&txn, &name)? {
+                Some(uuid) => Some(uuid),
+                None => aliases.get(&txn, &name)?,
+            };
+            match uuid {
                 Some(uuid) => {
                     let uuid = Uuid::from_slice(uuid)?;
                     Ok(Some(uuid))
@@ -86,17 +213,13 @@ impl UuidStore for HeedUuidStore {
     async fn delete(&self, uid: String) -> Result<Option<Uuid>> {
         let env = self.env.clone();
         let db = self.db;
+        let aliases = self.aliases;
+        let cascade_aliases = self.cascade_aliases;
         tokio::task::spawn_blocking(move || {
             let mut txn = env.write_txn()?;
-            match db.get(&txn, &uid)? {
-                Some(uuid) => {
-                    let uuid = Uuid::from_slice(uuid)?;
-                    db.delete(&mut txn, &uid)?;
-                    txn.commit()?;
-                    Ok(Some(uuid))
-                }
-                None => Ok(None),
-            }
+            let deleted = delete_entry(&mut txn, db, aliases, cascade_aliases, uid)?;
+            txn.commit()?;
+            Ok(deleted.map(|(uuid, _)| uuid))
         })
         .await?
     }
@@ -129,9 +252,202 @@ impl UuidStore for HeedUuidStore {
         .await?
     }
 
+    async fn rename(&self, uid: String, new_uid: String, hook: RenameHook) -> Result<Uuid> {
+        let env = self.env.clone();
+        let db = self.db;
+        let aliases = self.aliases;
+        tokio::task::spawn_blocking(move || {
+            let mut txn = env.write_txn()?;
+            if db.get(&txn, &new_uid)?.is_some() || aliases.get(&txn, &new_uid)?.is_some() {
+                return Err(UuidError::NameAlreadyExist);
+            }
+
+            let uuid = db
+                .get(&txn, &uid)?
+                .map(Uuid::from_slice)
+                .transpose()?
+                .ok_or_else(|| UuidError::UnexistingIndex(uid.clone()))?;
+            db.delete(&mut txn, &uid)?;
+            db.put(&mut txn, &new_uid, uuid.as_bytes())?;
+            // The rename is aborted along with the transaction if the hook fails.
+            hook(uuid).map_err(UuidError::RenameHook)?;
+            txn.commit()?;
+            Ok(uuid)
+        })
+        .await?
+    }
+
+    async fn swap(&self, first: String, second: String) -> Result<()> {
+        let env = self.env.clone();
+        let db = self.db;
+        tokio::task::spawn_blocking(move || {
+            let mut txn = env.write_txn()?;
+            let first_uuid = db
+                .get(&txn, &first)?
+                .map(Uuid::from_slice)
+                .transpose()?
+                .ok_or_else(|| UuidError::UnexistingIndex(first.clone()))?;
+            let second_uuid = db
+                .get(&txn, &second)?
+                .map(Uuid::from_slice)
+                .transpose()?
+                .ok_or_else(|| UuidError::UnexistingIndex(second.clone()))?;
+            db.put(&mut txn, &first, second_uuid.as_bytes())?;
+            db.put(&mut txn, &second, first_uuid.as_bytes())?;
+            txn.commit()?;
+            Ok(())
+        })
+        .await?
+    }
+
+    async fn create_alias(&self, alias: String, uid: String) -> Result<Uuid> {
+        let env = self.env.clone();
+        let db = self.db;
+        let aliases = self.aliases;
+        tokio::task::spawn_blocking(move || {
+            let mut txn = env.write_txn()?;
+            if db.get(&txn, &alias)?.is_some() || aliases.get(&txn, &alias)?.is_some() {
+                return Err(UuidError::NameAlreadyExist);
+            }
+
+            let uuid = db
+                .get(&txn, &uid)?
+                .map(Uuid::from_slice)
+                .transpose()?
+                .ok_or(UuidError::UnexistingIndex(uid))?;
+            aliases.put(&mut txn, &alias, uuid.as_bytes())?;
+            txn.commit()?;
+            Ok(uuid)
+        })
+        .await?
+    }
+
+    async fn delete_alias(&self, alias: String) -> Result<Option<Uuid>> {
+        let env = self.env.clone();
+        let aliases = self.aliases;
+        tokio::task::spawn_blocking(move || {
+            let mut txn = env.write_txn()?;
+            match aliases.get(&txn, &alias)? {
+                Some(uuid) => {
+                    let uuid = Uuid::from_slice(uuid)?;
+                    aliases.delete(&mut txn, &alias)?;
+                    txn.commit()?;
+                    Ok(Some(uuid))
+                }
+                None => Ok(None),
+            }
+        })
+        .await?
+    }
+
+    async fn trash(&self, uid: String, deleted_at: DateTime<Utc>) -> Result<Option<Uuid>> {
+        let env = self.env.clone();
+        let db = self.db;
+        let aliases = self.aliases;
+        let trash = self.trash;
+        let cascade_aliases = self.cascade_aliases;
+        tokio::task::spawn_blocking(move || {
+            let mut txn = env.write_txn()?;
+            let deleted = delete_entry(&mut txn, db, aliases, cascade_aliases, uid.clone())?;
+            let uuid = match deleted {
+                Some((uuid, aliases)) => {
+                    let entry = TrashedEntry {
+                        uid,
+                        deleted_at,
+                        aliases,
+                    };
+                    trash.put(&mut txn, &uuid.to_string(), &entry)?;
+                    Some(uuid)
+                }
+                None => None,
+            };
+            txn.commit()?;
+            Ok(uuid)
+        })
+        .await?
+    }
+
+    async fn restore(&self, uid: String) -> Result<Uuid> {
+        let env = self.env.clone();
+        let db = self.db;
+        let aliases = self.aliases;
+        let trash = self.trash;
+        tokio::task::spawn_blocking(move || {
+            let mut txn = env.write_txn()?;
+            if db.get(&txn, &uid)?.is_some() || aliases.get(&txn, &uid)?.is_some() {
+                return Err(UuidError::NameAlreadyExist);
+            }
+
+            // The same uid may have been trashed several times, the last index is restored.
+            let mut restored: Option<(String, TrashedEntry)> = None;
+            for entry in trash.iter(&txn)? {
+                let (uuid, entry) = entry?;
+                let is_newer = restored
+                    .as_ref()
+                    .map_or(true, |(_, restored)| entry.deleted_at > restored.deleted_at);
+                if entry.uid == uid && is_newer {
+                    restored = Some((uuid.to_owned(), entry));
+                }
+            }
+            let (key, entry) =
+                restored.ok_or_else(|| UuidError::UnexistingTrashedIndex(uid.clone()))?;
+            let uuid = Uuid::parse_str(&key)?;
+            trash.delete(&mut txn, &key)?;
+            db.put(&mut txn, &uid, uuid.as_bytes())?;
+            for alias in entry.aliases {
+                if db.get(&txn, &alias)?.is_none() && aliases.get(&txn, &alias)?.is_none() {
+                    aliases.put(&mut txn, &alias, uuid.as_bytes())?;
+                }
+            }
+            txn.commit()?;
+            Ok(uuid)
+        })
+        .await?
+    }
+
+    async fn trashed(&self) -> Result<Vec<Uuid>> {
+        let env = self.env.clone();
+        let trash = self.trash;
+        tokio::task::spawn_blocking(move || {
+            let txn = env.read_txn()?;
+            let mut uuids = Vec::new();
+            for entry in trash.iter(&txn)? {
+                let (uuid, _) = entry?;
+                uuids.push(Uuid::parse_str(uuid)?);
+            }
+            Ok(uuids)
+        })
+        .await?
+    }
+
+    async fn purge_trash(&self, deleted_before: DateTime<Utc>) -> Result<Vec<Uuid>> {
+        let env = self.env.clone();
+        let trash = self.trash;
+        tokio::task::spawn_blocking(move || {
+            let mut txn = env.write_txn()?;
+            let mut expired = Vec::new();
+            for entry in trash.iter(&txn)? {
+                let (uuid, entry) = entry?;
+                if entry.deleted_at < deleted_before {
+                    expired.push(uuid.to_owned());
+                }
+            }
+
+            let mut uuids = Vec::with_capacity(expired.len());
+            for key in expired {
+                trash.delete(&mut txn, &key)?;
+                uuids.push(Uuid::parse_str(&key)?);
+            }
+            txn.commit()?;
+            Ok(uuids)
+        })
+        .await?
+    }
+
     async fn snapshot(&self, mut path: PathBuf) -> Result<Vec<Uuid>> {
         let env = self.env.clone();
         let db = self.db;
+        let trash = self.trash;
         tokio::task::spawn_blocking(move || {
             // Write transaction to acquire a lock on the database.
             let txn = env.write_txn()?;
@@ -141,6 +457,11 @@ impl UuidStore for HeedUuidStore {
                 let uuid = Uuid::from_slice(uuid)?;
                 entries.push(uuid)
             }
+            // The trashed indexes are snapshotted as well, so that they can still be restored.
+            for entry in trash.iter(&txn)? {
+                let (uuid, _) = entry?;
+                entries.push(Uuid::parse_str(uuid)?)
+            }
 
             // only perform snapshot if there are indexes
             if !entries.is_empty() {
@@ -158,3 +479,234 @@ impl UuidStore for HeedUuidStore {
         Ok(self.env.size())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[actix_rt::test]
+    async fn resolve_alias() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let store = HeedUuidStore::new(dir.path(), false).unwrap();
+        let uuid = store
+            .create_uuid("products".to_string(), true)
+            .await
+            .unwrap();
+
+        let alias_uuid = store
+            .create_alias("catalog".to_string(), "products".to_string())
+            .await
+            .unwrap();
+        assert_eq!(alias_uuid, uuid);
+        assert_eq!(
+            store.get_uuid("catalog".to_string()).await.unwrap(),
+            Some(uuid)
+        );
+
+        // aliases are not listed nor snapshotted as entries
+        let entries = store.list().await.unwrap();
+        assert_eq!(entries, vec![("products".to_string(), uuid)]);
+        let snapshot_dir = tempfile::tempdir_in(".").unwrap();
+        let uuids = store
+            .snapshot(snapshot_dir.path().to_owned())
+            .await
+            .unwrap();
+        assert_eq!(uuids, vec![uuid]);
+
+        // an alias can't reuse an existing name
+        assert!(matches!(
+            store
+                .create_alias("products".to_string(), "products".to_string())
+                .await,
+            Err(UuidError::NameAlreadyExist)
+        ));
+        assert!(matches!(
+            store.create_uuid("catalog".to_string(), true).await,
+            Err(UuidError::NameAlreadyExist)
+        ));
+
+        assert_eq!(
+            store.delete_alias("catalog".to_string()).await.unwrap(),
+            Some(uuid)
+        );
+        assert_eq!(store.get_uuid("catalog".to_string()).await.unwrap(), None);
+    }
+
+    #[actix_rt::test]
+    async fn migrate_entries_of_the_main_database() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let path = dir.path().join("index_uuids");
+        create_dir_all(&path).unwrap();
+        let mut options = EnvOpenOptions::new();
+        options.map_size(UUID_STORE_SIZE);
+        options.max_dbs(2);
+        let env = options.open(&path).unwrap();
+        // the stores created before the database of the uids kept their entries in the main one
+        let main: Database<Str, ByteSlice> = env.create_database(None).unwrap();
+        env.create_database::<Str, ByteSlice>(Some(ALIASES_DB_NAME))
+            .unwrap();
+        let uuid = Uuid::new_v4();
+        let mut txn = env.write_txn().unwrap();
+        main.put(&mut txn, "products", uuid.as_bytes()).unwrap();
+        txn.commit().unwrap();
+        env.prepare_for_closing().wait();
+
+        let store = HeedUuidStore::new(dir.path(), false).unwrap();
+        store
+            .create_alias("catalog".to_string(), "products".to_string())
+            .await
+            .unwrap();
+        let entries = store.list().await.unwrap();
+        assert_eq!(entries, vec![("products".to_string(), uuid)]);
+    }
+
+    #[actix_rt::test]
+    async fn delete_aliased_entry() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let store = HeedUuidStore::new(dir.path(), false).unwrap();
+        store
+            .create_uuid("products".to_string(), true)
+            .await
+            .unwrap();
+        store
+            .create_alias("catalog".to_string(), "products".to_string())
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            store.delete("products".to_string()).await,
+            Err(UuidError::IndexHasAliases(_, aliases)) if aliases == vec!["catalog".to_string()]
+        ));
+        assert!(store
+            .get_uuid("products".to_string())
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[actix_rt::test]
+    async fn cascade_aliases_deletion() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let store = HeedUuidStore::new(dir.path(), true).unwrap();
+        let uuid = store
+            .create_uuid("products".to_string(), true)
+            .await
+            .unwrap();
+        store
+            .create_alias("catalog".to_string(), "products".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.delete("products".to_string()).await.unwrap(),
+            Some(uuid)
+        );
+        assert_eq!(store.get_uuid("catalog".to_string()).await.unwrap(), None);
+        assert_eq!(
+            store.delete_alias("catalog".to_string()).await.unwrap(),
+            None
+        );
+    }
+
+    #[actix_rt::test]
+    async fn trash_and_restore() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let store = HeedUuidStore::new(dir.path(), false).unwrap();
+        let uuid = store
+            .create_uuid("products".to_string(), true)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store
+                .trash("products".to_string(), Utc::now())
+                .await
+                .unwrap(),
+            Some(uuid)
+        );
+        assert_eq!(store.get_uuid("products".to_string()).await.unwrap(), None);
+        assert!(store.list().await.unwrap().is_empty());
+
+        // the uid can't be restored while it is used by another index
+        store
+            .create_uuid("products".to_string(), true)
+            .await
+            .unwrap();
+        assert!(matches!(
+            store.restore("products".to_string()).await,
+            Err(UuidError::NameAlreadyExist)
+        ));
+        store.delete("products".to_string()).await.unwrap();
+
+        assert_eq!(store.restore("products".to_string()).await.unwrap(), uuid);
+        assert_eq!(
+            store.get_uuid("products".to_string()).await.unwrap(),
+            Some(uuid)
+        );
+        assert!(matches!(
+            store.restore("products".to_string()).await,
+            Err(UuidError::NameAlreadyExist)
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn restore_cascaded_aliases() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let store = HeedUuidStore::new(dir.path(), true).unwrap();
+        let uuid = store
+            .create_uuid("products".to_string(), true)
+            .await
+            .unwrap();
+        for alias in &["catalog", "shop"] {
+            store
+                .create_alias(alias.to_string(), "products".to_string())
+                .await
+                .unwrap();
+        }
+
+        store
+            .trash("products".to_string(), Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(store.get_uuid("catalog".to_string()).await.unwrap(), None);
+        assert_eq!(store.trashed().await.unwrap(), vec![uuid]);
+
+        // an alias whose name was reused in the meantime isn't restored
+        let shop = store.create_uuid("shop".to_string(), true).await.unwrap();
+        assert_eq!(store.restore("products".to_string()).await.unwrap(), uuid);
+        assert_eq!(
+            store.get_uuid("catalog".to_string()).await.unwrap(),
+            Some(uuid)
+        );
+        assert_eq!(
+            store.get_uuid("shop".to_string()).await.unwrap(),
+            Some(shop)
+        );
+        assert!(store.trashed().await.unwrap().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn purge_expired_trash() {
+        let dir = tempfile::tempdir_in(".").unwrap();
+        let store = HeedUuidStore::new(dir.path(), false).unwrap();
+        let now = Utc::now();
+        let old = store.create_uuid("old".to_string(), true).await.unwrap();
+        let recent = store.create_uuid("recent".to_string(), true).await.unwrap();
+        store
+            .trash("old".to_string(), now - chrono::Duration::hours(2))
+            .await
+            .unwrap();
+        store.trash("recent".to_string(), now).await.unwrap();
+
+        let purged = store
+            .purge_trash(now - chrono::Duration::hours(1))
+            .await
+            .unwrap();
+        assert_eq!(purged, vec![old]);
+        assert!(matches!(
+            store.restore("old".to_string()).await,
+            Err(UuidError::UnexistingTrashedIndex(_))
+        ));
+        assert_eq!(store.restore("recent".to_string()).await.unwrap(), recent);
+    }
+}
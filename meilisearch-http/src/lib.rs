@@ -1,4 +1,6 @@
+pub mod analytics;
 pub mod data;
+mod dump;
 pub mod error;
 pub mod helpers;
 mod index;
@@ -17,13 +19,16 @@ macro_rules! create_app {
         use actix_web::App;
         use actix_web::{middleware, web};
         use meilisearch_http::error::payload_error_handler;
+        use meilisearch_http::helpers::logging::{LogFormat, RequestLogger};
+        use meilisearch_http::helpers::metrics::RequestMetrics;
+        use meilisearch_http::helpers::request_id::RequestIdentifier;
         use meilisearch_http::routes::*;
 
         let app = App::new()
             .data($data.clone())
             .app_data(
                 web::JsonConfig::default()
-                    .limit($data.http_payload_size_limit())
+                    .limit($data.http_json_payload_size_limit())
                     .content_type(|_mime| true) // Accept all mime types
                     .error_handler(|err, _req| payload_error_handler(err).into()),
             )
@@ -38,21 +43,51 @@ macro_rules! create_app {
             .configure(synonym::services)
             .configure(health::services)
             .configure(stats::services)
-            .configure(key::services);
-        //.configure(routes::dump::services);
+            .configure(key::services)
+            .configure(dump::services);
+        let app = if $data.metrics_enabled() {
+            app.configure(metrics::services)
+        } else {
+            app
+        };
         let app = if $enable_frontend {
             app.service(load_html).service(load_css)
         } else {
             app.service(running)
         };
-        app.wrap(
-            Cors::default()
-                .send_wildcard()
-                .allowed_headers(vec!["content-type", "x-meili-api-key"])
-                .max_age(86_400), // 24h
-        )
-        .wrap(middleware::Logger::default())
-        .wrap(middleware::Compress::default())
-        .wrap(middleware::NormalizePath::new(TrailingSlash::Trim))
+        let cors = Cors::default()
+            .allow_any_method()
+            .allowed_headers(vec!["content-type", "x-meili-api-key", "authorization"])
+            .allowed_headers($data.auth_header_name())
+            .expose_headers(vec!["content-range"])
+            .max_age(86_400); // 24h
+        let origins = $data.cors_allowed_origins();
+        // The preflight requests are answered by the middleware, the cross-origin requests from
+        // the other origins are rejected.
+        let cors = if origins.iter().any(|origin| origin == "*") {
+            cors.allow_any_origin().send_wildcard()
+        } else {
+            origins
+                .iter()
+                .fold(cors, |cors, origin| cors.allowed_origin(origin))
+        };
+        let json_logs = $data.log_format() == LogFormat::Json;
+        app.wrap(cors)
+            .wrap(middleware::Condition::new(
+                !json_logs,
+                middleware::Logger::default(),
+            ))
+            .wrap(middleware::Condition::new(json_logs, RequestLogger))
+            .wrap(RequestIdentifier)
+            .wrap(middleware::Condition::new(
+                $data.metrics_enabled(),
+                RequestMetrics,
+            ))
+            // The responses already carrying a `Content-Encoding` are left untouched.
+            .wrap(middleware::Condition::new(
+                $data.response_compression_enabled(),
+                middleware::Compress::default(),
+            ))
+            .wrap(middleware::NormalizePath::new(TrailingSlash::Trim))
     }};
 }
@@ -1,12 +1,13 @@
 use std::env;
+use std::time::Duration;
 
 use actix_web::HttpServer;
+use log::{info, warn};
 use main_error::MainError;
-use meilisearch_http::{create_app, Data, Opt};
+use meilisearch_http::helpers::logging::{self, LogFormat};
+use meilisearch_http::{analytics, create_app, Data, Opt};
 use structopt::StructOpt;
 
-//mod analytics;
-
 #[cfg(target_os = "linux")]
 #[global_allocator]
 static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
@@ -40,45 +41,63 @@ async fn main() -> Result<(), MainError> {
             #[cfg(all(not(debug_assertions), feature = "sentry"))]
             if !opt.no_sentry && _sentry.is_enabled() {
                 sentry::integrations::panic::register_panic_handler(); // TODO: This shouldn't be needed when upgrading to sentry 0.19.0. These integrations are turned on by default when using `sentry::init`.
-                sentry::integrations::env_logger::init(None, Default::default());
+                let logger = match opt.log_format {
+                    LogFormat::Json => Some(logger_builder(opt.log_format).build()),
+                    LogFormat::Text => None,
+                };
+                sentry::integrations::env_logger::init(logger, Default::default());
+            } else if opt.log_format == LogFormat::Json {
+                logger_builder(opt.log_format).init();
+            }
+
+            #[cfg(any(debug_assertions, not(feature = "sentry")))]
+            if opt.log_format == LogFormat::Json {
+                logger_builder(opt.log_format).init();
             }
         }
-        "development" => {
-            env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-                .init();
-        }
+        "development" => logger_builder(opt.log_format).init(),
         _ => unreachable!(),
     }
 
-    //if let Some(path) = &opt.import_snapshot {
-    //snapshot::load_snapshot(&opt.db_path, path, opt.ignore_snapshot_if_db_exists, opt.ignore_missing_snapshot)?;
-    //}
-
+    // Importing the snapshot, if any, is done when creating the index controller, before any
+    // actor is started.
     let data = Data::new(opt.clone())?;
 
-    //if !opt.no_analytics {
-    //let analytics_data = data.clone();
-    //let analytics_opt = opt.clone();
-    //thread::spawn(move || analytics::analytics_sender(analytics_data, analytics_opt));
-    //}
-
-    //if let Some(path) = &opt.import_dump {
-    //dump::import_dump(&data, path, opt.dump_batch_size)?;
-    //}
+    if !opt.no_analytics {
+        tokio::task::spawn(analytics::analytics_sender(data.clone(), opt.clone()));
+    }
 
-    //if opt.schedule_snapshot {
-    //snapshot::schedule_snapshot(data.clone(), &opt.snapshot_dir, opt.snapshot_interval_sec.unwrap_or(86400))?;
-    //}
+    if let Some(path) = &opt.import_dump {
+        data.import_dump(path).await?;
+    }
 
     print_launch_resume(&opt, &data);
 
     let enable_frontend = opt.env != "production";
-
-    run_http(data, opt, enable_frontend).await?;
+    let shutdown_timeout = Duration::from_secs(opt.shutdown_timeout_sec);
+
+    tokio::select! {
+        result = run_http(data.clone(), opt, enable_frontend) => result?,
+        _ = tokio::signal::ctrl_c() => {
+            info!("Shutting down, waiting for the updates being processed to finish.");
+            if !data.shutdown(shutdown_timeout).await {
+                warn!("The updates being processed didn't finish in time.");
+            }
+        }
+    }
 
     Ok(())
 }
 
+fn logger_builder(format: LogFormat) -> env_logger::Builder {
+    let mut builder =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"));
+    if format == LogFormat::Json {
+        builder.format(logging::format_json);
+    }
+    builder
+}
+
 async fn run_http(
     data: Data,
     opt: Opt,
@@ -144,16 +163,18 @@ pub fn print_launch_resume(opt: &Opt, data: &Data) {
 
     eprintln!(
         "Anonymous telemetry:\t{:?}",
-        if !opt.no_analytics {
-            "Enabled"
-        } else {
+        if opt.no_analytics {
             "Disabled"
+        } else if opt.analytics_dry_run {
+            "Dry run"
+        } else {
+            "Enabled"
         }
     );
 
     eprintln!();
 
-    if data.api_keys().master.is_some() {
+    if data.api_keys().master_hash.is_some() {
         eprintln!("A Master Key has been set. Requests to MeiliSearch won't be authorized unless you provide an authentication key.");
     } else {
         eprintln!("No master key found; The server will accept unidentified requests. \
@@ -1,4 +1,5 @@
 use std::io::{BufReader, Read};
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::{error, fs};
@@ -12,6 +13,11 @@ use rustls::{
 };
 use structopt::StructOpt;
 
+use crate::helpers::compression::{CompressionFormat, EncryptionKey};
+use crate::helpers::logging::LogFormat;
+use crate::helpers::object_storage::{S3Credentials, S3Uploader};
+pub use crate::index_controller::IndexStoreBackend;
+
 #[derive(Debug, Clone, StructOpt)]
 pub struct IndexerOpts {
     /// The amount of documents to skip before printing
@@ -96,6 +102,16 @@ pub struct Opt {
     #[structopt(long, env = "MEILI_MASTER_KEY")]
     pub master_key: Option<String>,
 
+    /// The name of a header the keys can be given in, for the deployments behind a gateway
+    /// stripping the `Authorization` header. The default headers are still accepted.
+    #[structopt(long, env = "MEILI_AUTH_HEADER_NAME")]
+    pub auth_header_name: Option<String>,
+
+    /// The origins allowed to make cross-origin requests, separated by commas, or `*` to allow all
+    /// of them. Defaults to all the origins in development and to none in production.
+    #[structopt(long, env = "MEILI_CORS_ALLOWED_ORIGINS")]
+    pub cors_allowed_origins: Option<String>,
+
     /// The Sentry DSN to use for error reporting. This defaults to the MeiliSearch Sentry project.
     /// You can disable sentry all together using the `--no-sentry` flag or `MEILI_NO_SENTRY` environment variable.
     #[cfg(all(not(debug_assertions), feature = "sentry"))]
@@ -122,6 +138,29 @@ pub struct Opt {
     #[structopt(long, env = "MEILI_NO_ANALYTICS")]
     pub no_analytics: bool,
 
+    /// Logs the analytics payload instead of sending it, to audit what would be sent.
+    #[structopt(long, env = "MEILI_ANALYTICS_DRY_RUN")]
+    pub analytics_dry_run: bool,
+
+    /// The format of the logs, `json` writes a json object per line, including one for each http
+    /// request with its method, path, status and latency.
+    #[structopt(long, env = "MEILI_LOG_FORMAT", default_value = "text", possible_values = &["text", "json"])]
+    pub log_format: LogFormat,
+
+    /// Exposes the prometheus metrics of the instance on the `/metrics` route.
+    #[structopt(long, env = "MEILI_ENABLE_METRICS")]
+    pub enable_metrics: bool,
+
+    /// Do not compress the responses, they are otherwise compressed with gzip, deflate or brotli
+    /// when the client accepts it.
+    #[structopt(long, env = "MEILI_NO_RESPONSE_COMPRESSION")]
+    pub no_response_compression: bool,
+
+    /// The number of times an update failing transiently, because of an io error or a full
+    /// database, is retried with an exponential backoff before being marked as failed.
+    #[structopt(long, env = "MEILI_UPDATE_MAX_RETRIES", default_value = "0")]
+    pub update_max_retries: u32,
+
     /// The maximum size, in bytes, of the main lmdb database directory
     #[structopt(long, env = "MEILI_MAX_MDB_SIZE", default_value = "100 GiB")]
     pub max_mdb_size: Byte,
@@ -130,10 +169,87 @@ pub struct Opt {
     #[structopt(long, env = "MEILI_MAX_UDB_SIZE", default_value = "10 GiB")]
     pub max_udb_size: Byte,
 
-    /// The maximum size, in bytes, of accepted JSON payloads
-    #[structopt(long, env = "MEILI_HTTP_PAYLOAD_SIZE_LIMIT", default_value = "10 MiB")]
+    /// Where the indexes are stored, the `memory` indexes are lost when the server stops. The rest
+    /// of the database is stored on disk in both cases. It isn't a command line option: only the
+    /// tests keep the indexes in memory.
+    #[structopt(skip)]
+    pub index_store: IndexStoreBackend,
+
+    /// The maximum size, in bytes, of an index: the full indexes grow up to it, and the indexes
+    /// can't be given a larger map size at their creation.
+    #[structopt(long, env = "MEILI_MAX_INDEX_MAP_SIZE", default_value = "1 TiB")]
+    pub max_index_map_size: Byte,
+
+    /// Delete the aliases of an index along with it. By default an index can't be deleted while
+    /// aliases still point to it.
+    #[structopt(long, env = "MEILI_CASCADE_ALIASES_DELETION")]
+    pub cascade_aliases_deletion: bool,
+
+    /// Serves the indexes without allowing to modify them: the documents additions and deletions,
+    /// the settings updates and the creation and deletion of indexes are refused.
+    #[structopt(long, env = "MEILI_READ_ONLY")]
+    pub read_only: bool,
+
+    /// Moves the deleted indexes to a trash instead of deleting them, they can then be restored
+    /// during this number of seconds before being purged.
+    #[structopt(long, env = "MEILI_INDEX_TRASH_RETENTION_SEC")]
+    pub index_trash_retention_sec: Option<u64>,
+
+    /// The maximum number of documents that can be returned by a single search or documents
+    /// request.
+    #[structopt(long, env = "MEILI_MAX_SEARCH_LIMIT", default_value = "1000")]
+    pub max_search_limit: usize,
+
+    /// The number of hits returned by the searches that don't set a limit, when their index
+    /// doesn't define a `pagination.defaultLimit`.
+    #[structopt(long, env = "MEILI_DEFAULT_SEARCH_LIMIT", default_value = "20")]
+    pub default_search_limit: usize,
+
+    /// The number of messages that can wait in the mailbox of an actor before the requests
+    /// sending them are blocked.
+    #[structopt(long, env = "MEILI_ACTOR_MAILBOX_SIZE", default_value = "100")]
+    pub actor_mailbox_size: NonZeroUsize,
+
+    /// The size of the mailbox of the index actor receiving the read requests, defaults to
+    /// `--actor-mailbox-size`.
+    #[structopt(long, env = "MEILI_INDEX_READ_MAILBOX_SIZE")]
+    pub index_read_mailbox_size: Option<NonZeroUsize>,
+
+    /// The size of the mailbox of the index actor receiving the write requests, defaults to
+    /// `--actor-mailbox-size`.
+    #[structopt(long, env = "MEILI_INDEX_WRITE_MAILBOX_SIZE")]
+    pub index_write_mailbox_size: Option<NonZeroUsize>,
+
+    /// The number of read requests, such as searches, the index actor processes concurrently.
+    /// The reads are never blocked by the write being processed.
+    #[structopt(long, env = "MEILI_INDEX_READ_THREADS", default_value = "10")]
+    pub index_read_threads: NonZeroUsize,
+
+    /// The number of milliseconds after which a search is aborted.
+    #[structopt(long, env = "MEILI_SEARCH_TIMEOUT_MS", default_value = "5000")]
+    pub search_timeout_ms: u64,
+
+    /// The number of search results kept in memory and served again to the identical searches,
+    /// until their index is written to. The cache is disabled when it is `0`.
+    #[structopt(long, env = "MEILI_SEARCH_CACHE_SIZE", default_value = "0")]
+    pub search_cache_size: usize,
+
+    /// The number of seconds to wait, on shutdown, for the update being processed to finish.
+    #[structopt(long, env = "MEILI_SHUTDOWN_TIMEOUT_SEC", default_value = "60")]
+    pub shutdown_timeout_sec: u64,
+
+    /// The maximum size, in bytes, of accepted documents payloads
+    #[structopt(long, env = "MEILI_HTTP_PAYLOAD_SIZE_LIMIT", default_value = "100 MB")]
     pub http_payload_size_limit: Byte,
 
+    /// The maximum size, in bytes, of accepted JSON payloads, the documents payloads excepted
+    #[structopt(
+        long,
+        env = "MEILI_HTTP_JSON_PAYLOAD_SIZE_LIMIT",
+        default_value = "10 MiB"
+    )]
+    pub http_json_payload_size_limit: Byte,
+
     /// Read server certificates from CERTFILE.
     /// This should contain PEM-format certificates
     /// in the right order (the first certificate should
@@ -186,6 +302,12 @@ pub struct Opt {
     #[structopt(long, env = "MEILI_SNAPSHOT_DIR", default_value = "snapshots/")]
     pub snapshot_dir: PathBuf,
 
+    /// The directory the snapshots are prepared in before being moved to the snapshot directory,
+    /// and the imported snapshots are decrypted in. The snapshot directory, or the parent of the
+    /// database directory when importing, is used if unset.
+    #[structopt(long, env = "MEILI_SNAPSHOT_TEMP_DIR")]
+    pub snapshot_temp_dir: Option<PathBuf>,
+
     /// Activate snapshot scheduling.
     #[structopt(long, env = "MEILI_SCHEDULE_SNAPSHOT")]
     pub schedule_snapshot: bool,
@@ -194,6 +316,58 @@ pub struct Opt {
     #[structopt(long, env = "MEILI_SNAPSHOT_INTERVAL_SEC", default_value = "86400")] // 24h
     pub snapshot_interval_sec: u64,
 
+    /// The gzip compression level of the snapshots, from 0 (no compression, fastest) to 9 (best
+    /// compression, slowest).
+    #[structopt(long, env = "MEILI_SNAPSHOT_COMPRESSION_LEVEL", default_value = "6", possible_values = &["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"])]
+    pub snapshot_compression_level: u32,
+
+    /// The compression algorithm of the snapshots, zstd snapshots are written with a
+    /// `.snapshot.zst` extension.
+    #[structopt(long, env = "MEILI_SNAPSHOT_FORMAT", default_value = "gzip", possible_values = &["gzip", "zstd"])]
+    pub snapshot_format: CompressionFormat,
+
+    /// Keeps this number of snapshots in the snapshot directory. The snapshots are then
+    /// timestamped, and the oldest ones are removed after each new snapshot.
+    #[structopt(long, env = "MEILI_SNAPSHOT_RETENTION")]
+    pub snapshot_retention: Option<usize>,
+
+    /// Encrypts the snapshots with AES-256-GCM, the key is derived from this passphrase. The
+    /// encrypted snapshots are written with a `.enc` extension, and the imported snapshots with
+    /// this extension are decrypted with the same key.
+    #[structopt(long, env = "MEILI_SNAPSHOT_ENCRYPTION_KEY")]
+    pub snapshot_encryption_key: Option<String>,
+
+    /// The path of a file containing the passphrase of the snapshot encryption key, see
+    /// `--snapshot-encryption-key`.
+    #[structopt(
+        long,
+        env = "MEILI_SNAPSHOT_ENCRYPTION_KEY_PATH",
+        conflicts_with = "snapshot-encryption-key"
+    )]
+    pub snapshot_encryption_key_path: Option<PathBuf>,
+
+    /// Uploads the snapshots to this bucket of an S3-compatible object storage once they are
+    /// created. The credentials are read from the `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and
+    /// `AWS_SESSION_TOKEN` environment variables.
+    #[structopt(long, env = "MEILI_SNAPSHOT_S3_BUCKET")]
+    pub snapshot_s3_bucket: Option<String>,
+
+    /// The url of the object storage the snapshots are uploaded to.
+    #[structopt(
+        long,
+        env = "MEILI_SNAPSHOT_S3_ENDPOINT",
+        default_value = "https://s3.amazonaws.com"
+    )]
+    pub snapshot_s3_endpoint: String,
+
+    /// The region of the snapshots bucket.
+    #[structopt(long, env = "MEILI_SNAPSHOT_S3_REGION", default_value = "us-east-1")]
+    pub snapshot_s3_region: String,
+
+    /// The number of times a failed snapshot upload is retried.
+    #[structopt(long, env = "MEILI_SNAPSHOT_S3_UPLOAD_RETRIES", default_value = "3")]
+    pub snapshot_s3_upload_retries: u32,
+
     /// Folder where dumps are created when the dump route is called.
     #[structopt(long, env = "MEILI_DUMPS_DIR", default_value = "dumps/")]
     pub dumps_dir: PathBuf,
@@ -211,6 +385,46 @@ pub struct Opt {
 }
 
 impl Opt {
+    /// Returns the key the snapshots are encrypted with, if any.
+    pub fn snapshot_encryption_key(&self) -> anyhow::Result<Option<EncryptionKey>> {
+        match (
+            &self.snapshot_encryption_key,
+            &self.snapshot_encryption_key_path,
+        ) {
+            (Some(passphrase), _) => Ok(Some(EncryptionKey::from_passphrase(passphrase))),
+            (None, Some(path)) => Ok(Some(EncryptionKey::from_file(path)?)),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Returns the uploader of the snapshots, if a bucket is configured.
+    pub fn snapshot_uploader(&self) -> anyhow::Result<Option<S3Uploader>> {
+        match self.snapshot_s3_bucket {
+            Some(ref bucket) => Ok(Some(S3Uploader::new(
+                &self.snapshot_s3_endpoint,
+                bucket.clone(),
+                self.snapshot_s3_region.clone(),
+                S3Credentials::from_env()?,
+                self.snapshot_s3_upload_retries,
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the origins allowed to make cross-origin requests, `*` allows all of them.
+    pub fn cors_allowed_origins(&self) -> Vec<String> {
+        match self.cors_allowed_origins {
+            Some(ref origins) => origins
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(String::from)
+                .collect(),
+            None if self.env == "production" => Vec::new(),
+            None => vec!["*".to_string()],
+        }
+    }
+
     pub fn get_ssl_config(&self) -> Result<Option<rustls::ServerConfig>, Box<dyn error::Error>> {
         if let (Some(cert_path), Some(key_path)) = (&self.ssl_cert_path, &self.ssl_key_path) {
             let client_auth = match &self.ssl_auth_path {
@@ -1,19 +1,24 @@
-use actix_web::web::Payload;
-use actix_web::{delete, get, post, put};
-use actix_web::{web, HttpResponse};
+use actix_web::http::header::CONTENT_RANGE;
+use actix_web::web::{Bytes, Payload};
+use actix_web::{delete, get, head, patch, post, put};
+use actix_web::{web, HttpRequest, HttpResponse};
+use async_stream::stream;
 use indexmap::IndexMap;
 use log::error;
 use milli::update::{IndexDocumentsMethod, UpdateFormat};
 use serde::Deserialize;
 use serde_json::Value;
 
-use crate::error::ResponseError;
-use crate::helpers::Authentication;
-use crate::routes::IndexParam;
+use crate::error::{Error, ResponseError};
+use crate::helpers::{Authentication, EnforcedFilter};
+use crate::index_controller::Priority;
+use crate::routes::{error_response, IndexParam};
 use crate::Data;
 
 const DEFAULT_RETRIEVE_DOCUMENTS_OFFSET: usize = 0;
 const DEFAULT_RETRIEVE_DOCUMENTS_LIMIT: usize = 20;
+/// The unit of the `Content-Range` header of the documents listing.
+const DOCUMENTS_RANGE_UNIT: &str = "documents";
 
 macro_rules! guard_content_type {
     ($fn_name:ident, $guard_value:literal) => {
@@ -43,22 +48,36 @@ struct DocumentParam {
 
 pub fn services(cfg: &mut web::ServiceConfig) {
     cfg.service(get_document)
+        .service(document_exists)
         .service(delete_document)
         .service(get_all_documents)
         .service(add_documents)
         .service(update_documents)
+        .service(patch_documents)
         .service(delete_documents)
+        .service(delete_documents_by_filter)
         .service(clear_all_documents);
 }
 
+/// The documents can't be browsed with a key enforcing a filter on the searches, the filter
+/// can't be applied to them.
+fn check_browsable(req: &HttpRequest) -> Result<(), ResponseError> {
+    if req.extensions().get::<EnforcedFilter>().is_some() {
+        return Err(Error::ForbiddenKeyScope.into());
+    }
+    Ok(())
+}
+
 #[get(
     "/indexes/{index_uid}/documents/{document_id}",
     wrap = "Authentication::Public"
 )]
 async fn get_document(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<DocumentParam>,
 ) -> Result<HttpResponse, ResponseError> {
+    check_browsable(&req)?;
     let index = path.index_uid.clone();
     let id = path.document_id.clone();
     match data
@@ -66,15 +85,35 @@ async fn get_document(
         .await
     {
         Ok(document) => Ok(HttpResponse::Ok().json(document)),
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-        }
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Responds with 200 if the document exists and 404 otherwise, without reading the document.
+#[head(
+    "/indexes/{index_uid}/documents/{document_id}",
+    wrap = "Authentication::Public"
+)]
+async fn document_exists(
+    req: HttpRequest,
+    data: web::Data<Data>,
+    path: web::Path<DocumentParam>,
+) -> Result<HttpResponse, ResponseError> {
+    check_browsable(&req)?;
+    let DocumentParam {
+        index_uid,
+        document_id,
+    } = path.into_inner();
+    match data.document_exists(index_uid, document_id).await {
+        Ok(true) => Ok(HttpResponse::Ok().finish()),
+        Ok(false) => Ok(HttpResponse::NotFound().finish()),
+        Err(e) => Ok(error_response(e)),
     }
 }
 
 #[delete(
     "/indexes/{index_uid}/documents/{document_id}",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::DocumentsWrite"
 )]
 async fn delete_document(
     data: web::Data<Data>,
@@ -87,9 +126,7 @@ async fn delete_document(
         Ok(update_status) => {
             Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_status.id() })))
         }
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-        }
+        Err(e) => Ok(error_response(e)),
     }
 }
 
@@ -99,32 +136,110 @@ struct BrowseQuery {
     offset: Option<usize>,
     limit: Option<usize>,
     attributes_to_retrieve: Option<String>,
+    ids: Option<String>,
+    /// `ndjson` streams all the documents, one per line, instead of returning a page of them.
+    format: Option<String>,
 }
 
 #[get("/indexes/{index_uid}/documents", wrap = "Authentication::Public")]
 async fn get_all_documents(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
     params: web::Query<BrowseQuery>,
 ) -> Result<HttpResponse, ResponseError> {
+    check_browsable(&req)?;
     let attributes_to_retrieve = params
         .attributes_to_retrieve
         .as_ref()
         .map(|attrs| attrs.split(',').map(String::from).collect::<Vec<_>>());
 
-    match data
+    match params.format.as_deref() {
+        None | Some("json") => (),
+        Some("ndjson") => {
+            if params.ids.is_some() || params.offset.is_some() || params.limit.is_some() {
+                return Err(Error::BadParameter(
+                    "format".to_string(),
+                    "ndjson exports all the documents and can't be used with ids, offset or limit"
+                        .to_string(),
+                )
+                .into());
+            }
+            return match data
+                .export_documents(path.index_uid.clone(), attributes_to_retrieve)
+                .await
+            {
+                Ok(mut documents) => {
+                    let body = stream! {
+                        // The status is already sent when a read fails, yielding the error
+                        // interrupts the response so that a truncated export can't be mistaken
+                        // for a complete one.
+                        while let Some(line) = documents.recv().await {
+                            yield line.map(Bytes::from).map_err(ResponseError::from);
+                        }
+                    };
+                    Ok(HttpResponse::Ok()
+                        .content_type("application/x-ndjson")
+                        .streaming(Box::pin(body)))
+                }
+                Err(e) => Ok(error_response(e)),
+            };
+        }
+        Some(other) => {
+            return Err(Error::BadParameter(
+                "format".to_string(),
+                format!("unknown format {:?}, expected json or ndjson", other),
+            )
+            .into())
+        }
+    }
+
+    // When ids are given the documents are returned in the same order, with `null` in place of
+    // the missing documents.
+    if let Some(ref ids) = params.ids {
+        let ids = ids.split(',').map(String::from).collect::<Vec<_>>();
+        return match data
+            .retrieve_documents_by_ids(path.index_uid.clone(), ids, attributes_to_retrieve)
+            .await
+        {
+            Ok(documents) => Ok(HttpResponse::Ok().json(documents)),
+            Err(e) => Ok(error_response(e)),
+        };
+    }
+
+    // The documents and their total are read in the same transaction, so that the range is
+    // consistent with the page even while the index is updated.
+    let offset = params.offset.unwrap_or(DEFAULT_RETRIEVE_DOCUMENTS_OFFSET);
+    let (documents, total) = match data
         .retrieve_documents(
             path.index_uid.clone(),
-            params.offset.unwrap_or(DEFAULT_RETRIEVE_DOCUMENTS_OFFSET),
+            offset,
             params.limit.unwrap_or(DEFAULT_RETRIEVE_DOCUMENTS_LIMIT),
             attributes_to_retrieve,
         )
         .await
     {
-        Ok(documents) => Ok(HttpResponse::Ok().json(documents)),
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-        }
+        Ok(documents) => documents,
+        Err(e) => return Ok(error_response(e)),
+    };
+    Ok(HttpResponse::Ok()
+        .insert_header((CONTENT_RANGE, content_range(offset, documents.len(), total)))
+        .json(documents))
+}
+
+/// Formats the `Content-Range` header of a page of `len` documents starting at `offset`, out of
+/// the `total` documents of the index, as `documents 0-19/143`, or `documents */143` when the
+/// page is empty.
+fn content_range(offset: usize, len: usize, total: u64) -> String {
+    match len {
+        0 => format!("{} */{}", DOCUMENTS_RANGE_UNIT, total),
+        len => format!(
+            "{} {}-{}/{}",
+            DOCUMENTS_RANGE_UNIT,
+            offset,
+            offset + len - 1,
+            total
+        ),
     }
 }
 
@@ -132,39 +247,122 @@ async fn get_all_documents(
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct UpdateDocumentsQuery {
     primary_key: Option<String>,
+    /// Fails the update if any of the documents already exists.
+    strict: Option<bool>,
+    /// The format of the payload, used when the `Content-Type` header doesn't specify it.
+    format: Option<String>,
+    /// The delimiter of a CSV payload, a single byte.
+    csv_delimiter: Option<String>,
+    #[serde(default)]
+    priority: Priority,
 }
 
-/// Route used when the payload type is "application/json"
-/// Used to add or replace documents
-#[post("/indexes/{index_uid}/documents", wrap = "Authentication::Private")]
-async fn add_documents(
+fn csv_delimiter(delimiter: Option<&str>) -> Result<Option<u8>, ResponseError> {
+    match delimiter.map(str::as_bytes) {
+        None => Ok(None),
+        Some([delimiter]) => Ok(Some(*delimiter)),
+        Some(_) => Err(Error::BadParameter(
+            "csvDelimiter".to_string(),
+            "the delimiter must be a single byte character".to_string(),
+        )
+        .into()),
+    }
+}
+
+/// Infers the format of the payload from the `Content-Type` header of the request, the `format`
+/// query parameter is used when the header is missing or doesn't designate a format, as for the
+/// `application/x-www-form-urlencoded` header curl sends by default. The payload is JSON otherwise.
+fn update_format(req: &HttpRequest, format: Option<&str>) -> Result<UpdateFormat, ResponseError> {
+    // The parameters of the media type, such as the charset, are ignored.
+    let subtype = req
+        .headers()
+        .get("Content-Type")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .and_then(|essence| essence.trim().splitn(2, '/').nth(1))
+        .map(str::to_lowercase);
+
+    match subtype.as_deref() {
+        Some("json") => return Ok(UpdateFormat::Json),
+        Some("x-ndjson") | Some("ndjson") => return Ok(UpdateFormat::JsonStream),
+        Some("csv") => return Ok(UpdateFormat::Csv),
+        // the structured syntax suffix, as in `application/ld+json`
+        Some(subtype) if subtype.ends_with("+json") => return Ok(UpdateFormat::Json),
+        _ => (),
+    }
+
+    match format {
+        None | Some("json") => Ok(UpdateFormat::Json),
+        Some("ndjson") => Ok(UpdateFormat::JsonStream),
+        Some("csv") => Ok(UpdateFormat::Csv),
+        Some(other) => Err(Error::BadParameter(
+            "format".to_string(),
+            format!("unknown format {:?}, expected json, ndjson or csv", other),
+        )
+        .into()),
+    }
+}
+
+/// Registers the addition of the documents of the payload with the given `method`, shared by
+/// the routes adding, updating and patching documents.
+async fn documents_addition(
     data: web::Data<Data>,
+    req: HttpRequest,
     path: web::Path<IndexParam>,
     params: web::Query<UpdateDocumentsQuery>,
     body: Payload,
+    method: IndexDocumentsMethod,
 ) -> Result<HttpResponse, ResponseError> {
+    let format = update_format(&req, params.format.as_deref())?;
+    let csv_delimiter = csv_delimiter(params.csv_delimiter.as_deref())?;
     let addition_result = data
         .add_documents(
             path.into_inner().index_uid,
-            IndexDocumentsMethod::ReplaceDocuments,
-            UpdateFormat::Json,
+            method,
+            format,
             body,
             params.primary_key.clone(),
+            params.strict.unwrap_or_default(),
+            csv_delimiter,
+            params.priority,
         )
         .await;
 
     match addition_result {
-        Ok(update_status) => {
-            Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_status.id() })))
-        }
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-        }
+        Ok(status) => Ok(HttpResponse::Accepted().json(status)),
+        Err(e) => Ok(error_response(e)),
     }
 }
 
+/// Used to add or replace documents, the payload can be JSON, NDJSON or CSV. An existing document
+/// is entirely replaced by the new version, the fields missing from the payload are dropped.
+#[post(
+    "/indexes/{index_uid}/documents",
+    wrap = "Authentication::DocumentsWrite"
+)]
+async fn add_documents(
+    data: web::Data<Data>,
+    req: HttpRequest,
+    path: web::Path<IndexParam>,
+    params: web::Query<UpdateDocumentsQuery>,
+    body: Payload,
+) -> Result<HttpResponse, ResponseError> {
+    documents_addition(
+        data,
+        req,
+        path,
+        params,
+        body,
+        IndexDocumentsMethod::ReplaceDocuments,
+    )
+    .await
+}
+
 /// Default route for adding documents, this should return an error and redirect to the documentation
-#[post("/indexes/{index_uid}/documents", wrap = "Authentication::Private")]
+#[post(
+    "/indexes/{index_uid}/documents",
+    wrap = "Authentication::DocumentsWrite"
+)]
 async fn add_documents_default(
     _data: web::Data<Data>,
     _path: web::Path<IndexParam>,
@@ -176,7 +374,10 @@ async fn add_documents_default(
 }
 
 /// Default route for adding documents, this should return an error and redirect to the documentation
-#[put("/indexes/{index_uid}/documents", wrap = "Authentication::Private")]
+#[put(
+    "/indexes/{index_uid}/documents",
+    wrap = "Authentication::DocumentsWrite"
+)]
 async fn update_documents_default(
     _data: web::Data<Data>,
     _path: web::Path<IndexParam>,
@@ -187,36 +388,59 @@ async fn update_documents_default(
     todo!()
 }
 
-#[put("/indexes/{index_uid}/documents", wrap = "Authentication::Private")]
+/// Used to add or update documents, the fields of the payload are merged into the existing
+/// documents.
+#[put(
+    "/indexes/{index_uid}/documents",
+    wrap = "Authentication::DocumentsWrite"
+)]
 async fn update_documents(
     data: web::Data<Data>,
+    req: HttpRequest,
     path: web::Path<IndexParam>,
     params: web::Query<UpdateDocumentsQuery>,
     body: web::Payload,
 ) -> Result<HttpResponse, ResponseError> {
-    let addition_result = data
-        .add_documents(
-            path.into_inner().index_uid,
-            IndexDocumentsMethod::UpdateDocuments,
-            UpdateFormat::Json,
-            body,
-            params.primary_key.clone(),
-        )
-        .await;
+    documents_addition(
+        data,
+        req,
+        path,
+        params,
+        body,
+        IndexDocumentsMethod::UpdateDocuments,
+    )
+    .await
+}
 
-    match addition_result {
-        Ok(update) => {
-            Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update.id() })))
-        }
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-        }
-    }
+/// Used to partially update documents: only the fields present in the payload are written, the
+/// other fields of the existing documents are kept as they are. Unlike `POST`, which replaces the
+/// whole document, a document can be patched without resending it entirely. The documents that
+/// don't exist yet are inserted.
+#[patch(
+    "/indexes/{index_uid}/documents",
+    wrap = "Authentication::DocumentsWrite"
+)]
+async fn patch_documents(
+    data: web::Data<Data>,
+    req: HttpRequest,
+    path: web::Path<IndexParam>,
+    params: web::Query<UpdateDocumentsQuery>,
+    body: web::Payload,
+) -> Result<HttpResponse, ResponseError> {
+    documents_addition(
+        data,
+        req,
+        path,
+        params,
+        body,
+        IndexDocumentsMethod::UpdateDocuments,
+    )
+    .await
 }
 
 #[post(
     "/indexes/{index_uid}/documents/delete-batch",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::DocumentsWrite"
 )]
 async fn delete_documents(
     data: web::Data<Data>,
@@ -236,14 +460,43 @@ async fn delete_documents(
         Ok(update_status) => {
             Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_status.id() })))
         }
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct DeleteByFilterRequest {
+    filter: String,
+}
+
+/// Deletes all the documents matching the filter, in a single update. The filter is only parsed
+/// when the update is processed, an invalid filter fails the update.
+#[post(
+    "/indexes/{index_uid}/documents/delete-by-filter",
+    wrap = "Authentication::DocumentsWrite"
+)]
+async fn delete_documents_by_filter(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    body: web::Json<DeleteByFilterRequest>,
+) -> Result<HttpResponse, ResponseError> {
+    match data
+        .delete_documents_by_filter(path.index_uid.clone(), body.into_inner().filter)
+        .await
+    {
+        Ok(update_status) => {
+            Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_status.id() })))
         }
+        Err(e) => Ok(error_response(e)),
     }
 }
 
 /// delete all documents
-#[delete("/indexes/{index_uid}/documents", wrap = "Authentication::Private")]
+#[delete(
+    "/indexes/{index_uid}/documents",
+    wrap = "Authentication::DocumentsWrite"
+)]
 async fn clear_all_documents(
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
@@ -252,8 +505,6 @@ async fn clear_all_documents(
         Ok(update_status) => {
             Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_status.id() })))
         }
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-        }
+        Err(e) => Ok(error_response(e)),
     }
 }
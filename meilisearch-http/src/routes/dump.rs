@@ -1,31 +1,24 @@
 use std::fs::File;
-use std::path::Path;
 
 use actix_web::{get, post};
-use actix_web::{HttpResponse, web};
-use serde::{Deserialize, Serialize};
+use actix_web::{web, HttpResponse};
+use serde::Deserialize;
 
-use crate::dump::{DumpInfo, DumpStatus, compressed_dumps_dir, init_dump_process};
-use crate::Data;
+use crate::dump::{compressed_dumps_dir, init_dump_process, DumpInfo, DumpStatus};
 use crate::error::{Error, ResponseError};
 use crate::helpers::Authentication;
+use crate::Data;
 
 pub fn services(cfg: &mut web::ServiceConfig) {
-    cfg.service(trigger_dump)
-        .service(get_dump_status);
+    cfg.service(trigger_dump).service(get_dump_status);
 }
 
 #[post("/dumps", wrap = "Authentication::Private")]
-async fn trigger_dump(
-    data: web::Data<Data>,
-) -> Result<HttpResponse, ResponseError> {
-    todo!()
-}
-
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct DumpStatusResponse {
-    status: String,
+async fn trigger_dump(data: web::Data<Data>) -> Result<HttpResponse, ResponseError> {
+    match init_dump_process(&data, data.dumps_dir()) {
+        Ok(resume) => Ok(HttpResponse::Accepted().json(resume)),
+        Err(e) => Err(e.into()),
+    }
 }
 
 #[derive(Deserialize)]
@@ -38,5 +31,20 @@ async fn get_dump_status(
     data: web::Data<Data>,
     path: web::Path<DumpParam>,
 ) -> Result<HttpResponse, ResponseError> {
-    todo!()
+    let dumps_dir = data.dumps_dir();
+    let dump_uid = &path.dump_uid;
+
+    if let Some(resume) = DumpInfo::get_current() {
+        if &resume.uid == dump_uid {
+            return Ok(HttpResponse::Ok().json(resume));
+        }
+    }
+
+    if File::open(compressed_dumps_dir(dumps_dir, dump_uid)).is_ok() {
+        let resume = DumpInfo::new(dump_uid.into(), DumpStatus::Done);
+
+        Ok(HttpResponse::Ok().json(resume))
+    } else {
+        Err(Error::not_found("dump does not exist").into())
+    }
 }
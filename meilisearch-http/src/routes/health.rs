@@ -1,13 +1,31 @@
+use std::time::Duration;
+
 use actix_web::get;
 use actix_web::{web, HttpResponse};
 
 use crate::error::ResponseError;
+use crate::Data;
+
+/// Time given to each actor to answer the readiness check.
+const READINESS_TIMEOUT: Duration = Duration::from_secs(1);
 
 pub fn services(cfg: &mut web::ServiceConfig) {
-    cfg.service(get_health);
+    cfg.service(get_health).service(get_readiness);
 }
 
 #[get("/health")]
 async fn get_health() -> Result<HttpResponse, ResponseError> {
     Ok(HttpResponse::Ok().json(serde_json::json!({ "status": "available" })))
 }
+
+/// Checks that all the actors are able to process messages.
+#[get("/health/ready")]
+async fn get_readiness(data: web::Data<Data>) -> Result<HttpResponse, ResponseError> {
+    let unavailable = data.unresponsive_actors(READINESS_TIMEOUT).await;
+    if unavailable.is_empty() {
+        Ok(HttpResponse::Ok().json(serde_json::json!({ "status": "available" })))
+    } else {
+        Ok(HttpResponse::ServiceUnavailable()
+            .json(serde_json::json!({ "status": "unavailable", "unavailable": unavailable })))
+    }
+}
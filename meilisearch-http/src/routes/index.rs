@@ -1,30 +1,67 @@
+use actix_web::dev::BodyEncoding;
+use actix_web::http::header::{ContentEncoding, CONTENT_DISPOSITION};
 use actix_web::{delete, get, post, put};
 use actix_web::{web, HttpResponse};
+use async_stream::stream;
 use chrono::{DateTime, Utc};
+use log::error;
 use serde::{Deserialize, Serialize};
+use tokio::io::AsyncReadExt;
 
-use crate::error::ResponseError;
+use crate::dump::{export_index, import_index};
+use crate::error::{Error, ResponseError};
 use crate::helpers::Authentication;
-use crate::routes::IndexParam;
+use crate::index_controller::{IndexesSort, PruneBefore, UpdateState};
+use crate::routes::{error_response, IndexParam};
 use crate::Data;
 
 pub fn services(cfg: &mut web::ServiceConfig) {
     cfg.service(list_indexes)
         .service(get_index)
         .service(create_index)
+        .service(create_indexes)
         .service(update_index)
         .service(delete_index)
+        .service(delete_indexes)
+        .service(restore_index)
+        .service(export)
+        .service(import)
+        .service(swap_indexes)
+        .service(create_alias)
+        .service(delete_alias)
         .service(get_update_status)
-        .service(get_all_updates_status);
+        .service(abort_update)
+        .service(watch_update)
+        .service(get_all_updates_status)
+        .service(prune_updates);
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct ListIndexesQuery {
+    offset: Option<usize>,
+    /// All the indexes are returned when unset.
+    limit: Option<usize>,
+    #[serde(default)]
+    sort: IndexesSort,
 }
 
 #[get("/indexes", wrap = "Authentication::Private")]
-async fn list_indexes(data: web::Data<Data>) -> Result<HttpResponse, ResponseError> {
-    match data.list_indexes().await {
+async fn list_indexes(
+    data: web::Data<Data>,
+    params: web::Query<ListIndexesQuery>,
+) -> Result<HttpResponse, ResponseError> {
+    let ListIndexesQuery {
+        offset,
+        limit,
+        sort,
+    } = params.into_inner();
+    match data
+        .list_indexes_page(offset.unwrap_or_default(), limit, sort)
+        .await
+    {
         Ok(indexes) => Ok(HttpResponse::Ok().json(indexes)),
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-        }
+        Err(e) => Ok(error_response(e)),
     }
 }
 
@@ -35,9 +72,7 @@ async fn get_index(
 ) -> Result<HttpResponse, ResponseError> {
     match data.index(path.index_uid.clone()).await {
         Ok(meta) => Ok(HttpResponse::Ok().json(meta)),
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-        }
+        Err(e) => Ok(error_response(e)),
     }
 }
 
@@ -46,6 +81,8 @@ async fn get_index(
 struct IndexCreateRequest {
     uid: String,
     primary_key: Option<String>,
+    /// The size in bytes the index can grow to, defaults to `--max-mdb-size`.
+    map_size: Option<usize>,
 }
 
 #[post("/indexes", wrap = "Authentication::Private")]
@@ -54,11 +91,98 @@ async fn create_index(
     body: web::Json<IndexCreateRequest>,
 ) -> Result<HttpResponse, ResponseError> {
     let body = body.into_inner();
-    match data.create_index(body.uid, body.primary_key).await {
+    match data
+        .create_index(body.uid, body.primary_key, body.map_size)
+        .await
+    {
         Ok(meta) => Ok(HttpResponse::Ok().json(meta)),
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-        }
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Creates several indexes, the response lists the outcome of each creation in the order of the
+/// request.
+#[post("/indexes/batch", wrap = "Authentication::Private")]
+async fn create_indexes(
+    data: web::Data<Data>,
+    body: web::Json<Vec<IndexCreateRequest>>,
+) -> Result<HttpResponse, ResponseError> {
+    let indexes: Vec<_> = body
+        .into_inner()
+        .into_iter()
+        .map(|index| (index.uid, index.primary_key, index.map_size))
+        .collect();
+    let uids: Vec<_> = indexes.iter().map(|(uid, _, _)| uid.clone()).collect();
+
+    let outcomes: Vec<_> = data
+        .create_indexes(indexes)
+        .await
+        .into_iter()
+        .zip(uids)
+        .map(|(result, uid)| match result {
+            Ok(meta) => serde_json::json!({ "uid": uid, "status": "created", "index": meta }),
+            Err(e) => {
+                serde_json::json!({ "uid": uid, "status": "failed", "error": e.to_string() })
+            }
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(outcomes))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SwapIndexesRequest {
+    indexes: (String, String),
+}
+
+#[post("/swap-indexes", wrap = "Authentication::Private")]
+async fn swap_indexes(
+    data: web::Data<Data>,
+    body: web::Json<SwapIndexesRequest>,
+) -> Result<HttpResponse, ResponseError> {
+    let (first, second) = body.into_inner().indexes;
+    match data.swap_indexes(first, second).await {
+        Ok(()) => Ok(HttpResponse::Ok().finish()),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct AliasRequest {
+    alias: String,
+    index: String,
+}
+
+#[post("/aliases", wrap = "Authentication::Private")]
+async fn create_alias(
+    data: web::Data<Data>,
+    body: web::Json<AliasRequest>,
+) -> Result<HttpResponse, ResponseError> {
+    let body = body.into_inner();
+    match data
+        .create_alias(body.alias.clone(), body.index.clone())
+        .await
+    {
+        Ok(()) => Ok(HttpResponse::Ok().json(body)),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+#[derive(Deserialize)]
+struct AliasParam {
+    alias: String,
+}
+
+#[delete("/aliases/{alias}", wrap = "Authentication::Private")]
+async fn delete_alias(
+    data: web::Data<Data>,
+    path: web::Path<AliasParam>,
+) -> Result<HttpResponse, ResponseError> {
+    match data.delete_alias(path.into_inner().alias).await {
+        Ok(()) => Ok(HttpResponse::NoContent().finish()),
+        Err(e) => Ok(error_response(e)),
     }
 }
 
@@ -91,9 +215,7 @@ async fn update_index(
         .await
     {
         Ok(meta) => Ok(HttpResponse::Ok().json(meta)),
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-        }
+        Err(e) => Ok(error_response(e)),
     }
 }
 
@@ -104,9 +226,100 @@ async fn delete_index(
 ) -> Result<HttpResponse, ResponseError> {
     match data.delete_index(path.index_uid.clone()).await {
         Ok(_) => Ok(HttpResponse::NoContent().finish()),
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Deletes several indexes, the response lists the outcome of each deletion in the order of the
+/// request.
+#[post("/indexes/batch-delete", wrap = "Authentication::Private")]
+async fn delete_indexes(
+    data: web::Data<Data>,
+    body: web::Json<Vec<String>>,
+) -> Result<HttpResponse, ResponseError> {
+    let uids = body.into_inner();
+
+    let outcomes: Vec<_> = data
+        .delete_indexes(uids.clone())
+        .await
+        .into_iter()
+        .zip(uids)
+        .map(|(result, uid)| match result {
+            Ok(()) => serde_json::json!({ "uid": uid, "status": "deleted" }),
+            Err(e) => {
+                serde_json::json!({ "uid": uid, "status": "failed", "error": e.to_string() })
+            }
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(outcomes))
+}
+
+#[post("/indexes/{index_uid}/restore", wrap = "Authentication::Private")]
+async fn restore_index(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+) -> Result<HttpResponse, ResponseError> {
+    match data.restore_index(path.index_uid.clone()).await {
+        Ok(meta) => Ok(HttpResponse::Ok().json(meta)),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// The size of the chunks the exports are streamed in.
+const EXPORT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Streams a gzipped tarball of the settings and the documents of the index, as JSON lines.
+#[get("/indexes/{index_uid}/export", wrap = "Authentication::Private")]
+async fn export(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let export_file = match export_index(&data, data.dumps_dir(), &path.index_uid).await {
+        Ok(export_file) => export_file,
+        Err(e) => return Ok(error_response(e)),
+    };
+    let mut file = tokio::fs::File::from_std(export_file.reopen().map_err(Error::from)?);
+    let body = stream! {
+        // the temporary file is removed once the whole export has been sent
+        let _export_file = export_file;
+        let mut buffer = vec![0; EXPORT_CHUNK_SIZE];
+        loop {
+            match file.read(&mut buffer).await {
+                Ok(0) => break,
+                Ok(n) => {
+                    let chunk = web::Bytes::copy_from_slice(&buffer[..n]);
+                    yield Ok::<_, actix_web::Error>(chunk);
+                }
+                Err(e) => {
+                    error!("Could not read the export: {}", e);
+                    yield Err(actix_web::Error::from(e));
+                    break;
+                }
+            }
         }
+    };
+    // The tarball is already compressed, the response compression must not encode it again.
+    Ok(HttpResponse::Ok()
+        .content_type("application/gzip")
+        .encoding(ContentEncoding::Identity)
+        .insert_header((
+            CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}.tar.gz\"", path.index_uid),
+        ))
+        .streaming(Box::pin(body)))
+}
+
+/// Imports an archive produced by the export of an index, the index is created if needed.
+#[post("/indexes/{index_uid}/import", wrap = "Authentication::Private")]
+async fn import(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    body: web::Payload,
+) -> Result<HttpResponse, ResponseError> {
+    match import_index(&data, data.dumps_dir(), &path.index_uid, body).await {
+        Ok(update_status) => Ok(HttpResponse::Accepted().json(update_status)),
+        Err(e) => Ok(error_response(e)),
     }
 }
 
@@ -130,22 +343,156 @@ async fn get_update_status(
         .await;
     match result {
         Ok(meta) => Ok(HttpResponse::Ok().json(meta)),
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+#[delete(
+    "/indexes/{index_uid}/updates/{update_id}",
+    wrap = "Authentication::Private"
+)]
+async fn abort_update(
+    data: web::Data<Data>,
+    path: web::Path<UpdateParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let params = path.into_inner();
+    let result = data.abort_update(params.index_uid, params.update_id).await;
+    match result {
+        Ok(meta) => Ok(HttpResponse::Ok().json(meta)),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Streams the status of an update and all its following transitions as server-sent events, the
+/// connection is closed once the update is finished.
+#[get(
+    "/indexes/{index_uid}/updates/{update_id}/watch",
+    wrap = "Authentication::Private"
+)]
+async fn watch_update(
+    data: web::Data<Data>,
+    path: web::Path<UpdateParam>,
+) -> Result<HttpResponse, ResponseError> {
+    let params = path.into_inner();
+    let result = data.watch_update(params.index_uid, params.update_id).await;
+    match result {
+        Ok(mut receiver) => {
+            let events = stream! {
+                while let Some(status) = receiver.recv().await {
+                    let event = match serde_json::to_string(&status) {
+                        Ok(status) => format!("data: {}\n\n", status),
+                        Err(e) => {
+                            error!("Could not serialize update status: {}", e);
+                            break;
+                        }
+                    };
+                    yield Ok::<_, actix_web::Error>(web::Bytes::from(event));
+                }
+            };
+            Ok(HttpResponse::Ok()
+                .content_type("text/event-stream")
+                .streaming(events))
         }
+        Err(e) => Ok(error_response(e)),
     }
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct UpdatesQuery {
+    offset: Option<usize>,
+    limit: Option<usize>,
+    status: Option<UpdateState>,
+    /// `ndjson` streams the updates, one per line, instead of returning them in an array.
+    format: Option<String>,
+}
+
 #[get("/indexes/{index_uid}/updates", wrap = "Authentication::Private")]
 async fn get_all_updates_status(
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
+    params: web::Query<UpdatesQuery>,
 ) -> Result<HttpResponse, ResponseError> {
-    let result = data.get_updates_status(path.into_inner().index_uid).await;
+    let params = params.into_inner();
+    let index_uid = path.into_inner().index_uid;
+    let offset = params.offset.unwrap_or_default();
+    match params.format.as_deref() {
+        None | Some("json") => (),
+        Some("ndjson") => {
+            return match data
+                .stream_updates_status(index_uid, offset, params.limit, params.status)
+                .await
+            {
+                Ok(mut updates) => {
+                    let body = stream! {
+                        while let Some(line) = updates.recv().await {
+                            yield Ok::<_, actix_web::Error>(web::Bytes::from(line));
+                        }
+                    };
+                    Ok(HttpResponse::Ok()
+                        .content_type("application/x-ndjson")
+                        .streaming(Box::pin(body)))
+                }
+                Err(e) => Ok(error_response(e)),
+            };
+        }
+        Some(other) => {
+            return Err(Error::BadParameter(
+                "format".to_string(),
+                format!("unknown format {:?}, expected json or ndjson", other),
+            )
+            .into())
+        }
+    }
+
+    let result = data
+        .get_updates_status(index_uid, offset, params.limit, params.status)
+        .await;
     match result {
         Ok(metas) => Ok(HttpResponse::Ok().json(metas)),
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-        }
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct PruneUpdatesQuery {
+    /// An update id, or an RFC 3339 date: the updates with a lower id, or finished before the date,
+    /// are pruned.
+    before: String,
+    status: Option<UpdateState>,
+}
+
+/// Removes the finished updates of an index, the enqueued and processing updates are kept.
+#[delete("/indexes/{index_uid}/updates", wrap = "Authentication::Private")]
+async fn prune_updates(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    params: web::Query<PruneUpdatesQuery>,
+) -> Result<HttpResponse, ResponseError> {
+    let params = params.into_inner();
+    let before = params.before.parse::<PruneBefore>().map_err(|_| {
+        Error::BadParameter(
+            "before".to_string(),
+            format!("expected an update id or a date, got {:?}", params.before),
+        )
+    })?;
+    if matches!(
+        params.status,
+        Some(UpdateState::Enqueued) | Some(UpdateState::Processing)
+    ) {
+        return Err(Error::BadParameter(
+            "status".to_string(),
+            "only the processed, aborted and failed updates can be pruned".to_string(),
+        )
+        .into());
+    }
+
+    match data
+        .prune_updates(path.into_inner().index_uid, before, params.status)
+        .await
+    {
+        Ok(count) => Ok(HttpResponse::Ok().json(serde_json::json!({ "deletedUpdates": count }))),
+        Err(e) => Ok(error_response(e)),
     }
 }
@@ -1,13 +1,17 @@
-use actix_web::get;
 use actix_web::web;
 use actix_web::HttpResponse;
-use serde::Serialize;
+use actix_web::{get, post};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
+use crate::data::KeyAction;
+use crate::error::{Error, ResponseError};
 use crate::helpers::Authentication;
+use crate::routes::error_response;
 use crate::Data;
 
 pub fn services(cfg: &mut web::ServiceConfig) {
-    cfg.service(list);
+    cfg.service(list).service(create).service(rotate_master);
 }
 
 #[derive(Serialize)]
@@ -18,9 +22,95 @@ struct KeysResponse {
 
 #[get("/keys", wrap = "Authentication::Admin")]
 async fn list(data: web::Data<Data>) -> HttpResponse {
-    let api_keys = data.api_keys.clone();
+    let api_keys = data.api_keys().clone();
     HttpResponse::Ok().json(&KeysResponse {
         private: api_keys.private,
         public: api_keys.public,
     })
 }
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct CreateKeyRequest {
+    indexes: Vec<String>,
+    actions: Vec<KeyAction>,
+    expires_at: Option<DateTime<Utc>>,
+    filter: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateKeyResponse {
+    key: String,
+    indexes: Vec<String>,
+    actions: Vec<KeyAction>,
+    created_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+    filter: Option<String>,
+}
+
+/// Creates a key only allowed to perform the given actions on the given indexes, `*` giving access
+/// to all the indexes. The key is only returned in this response, and expires at `expiresAt` if
+/// set. The searches made with the key only return the documents matching its `filter`, if any.
+#[post("/keys", wrap = "Authentication::Admin")]
+async fn create(
+    data: web::Data<Data>,
+    body: web::Json<CreateKeyRequest>,
+) -> Result<HttpResponse, ResponseError> {
+    let body = body.into_inner();
+    if body.indexes.is_empty() {
+        return Err(Error::bad_parameter("indexes", "a key must give access to an index").into());
+    }
+    if body.actions.is_empty() {
+        return Err(Error::bad_parameter("actions", "a key must allow an action").into());
+    }
+    if body
+        .expires_at
+        .map_or(false, |expires_at| expires_at <= Utc::now())
+    {
+        return Err(Error::bad_parameter("expiresAt", "the expiration date is in the past").into());
+    }
+
+    match data
+        .create_key(body.indexes, body.actions, body.expires_at, body.filter)
+        .await
+    {
+        Ok((key, scope)) => Ok(HttpResponse::Ok().json(CreateKeyResponse {
+            key,
+            indexes: scope.indexes,
+            actions: scope.actions,
+            created_at: scope.created_at,
+            expires_at: scope.expires_at,
+            filter: scope.filter,
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct RotateMasterKeyRequest {
+    master_key: String,
+}
+
+/// Replaces the master key by the given one, it is persisted and used from then on, even after a
+/// restart. The private and public keys derived from the new master key are returned, the previous
+/// ones and the scoped keys are rejected.
+#[post("/keys/rotate-master", wrap = "Authentication::Admin")]
+async fn rotate_master(
+    data: web::Data<Data>,
+    body: web::Json<RotateMasterKeyRequest>,
+) -> Result<HttpResponse, ResponseError> {
+    let master_key = body.into_inner().master_key;
+    if master_key.trim().is_empty() {
+        return Err(Error::bad_parameter("masterKey", "the master key can't be empty").into());
+    }
+
+    match data.rotate_master_key(master_key).await {
+        Ok(api_keys) => Ok(HttpResponse::Ok().json(&KeysResponse {
+            private: api_keys.private,
+            public: api_keys.public,
+        })),
+        Err(e) => Ok(error_response(e)),
+    }
+}
@@ -0,0 +1,20 @@
+use actix_web::get;
+use actix_web::{web, HttpResponse};
+use prometheus::TEXT_FORMAT;
+
+use crate::error::ResponseError;
+use crate::helpers::Authentication;
+use crate::Data;
+
+pub fn services(cfg: &mut web::ServiceConfig) {
+    cfg.service(get_metrics);
+}
+
+/// Renders the metrics in the prometheus text format, the route is only registered when the
+/// metrics are enabled.
+#[get("/metrics", wrap = "Authentication::Private")]
+async fn get_metrics(data: web::Data<Data>) -> Result<HttpResponse, ResponseError> {
+    let metrics = data.metrics().render(&data).await?;
+
+    Ok(HttpResponse::Ok().content_type(TEXT_FORMAT).body(metrics))
+}
@@ -1,21 +1,38 @@
 use actix_web::{get, HttpResponse};
 use serde::{Deserialize, Serialize};
 
+use crate::helpers::RequestId;
+use crate::index_controller::{ActorError, PayloadTooLarge, Priority, ReadOnly, SearchTimeout};
+
 pub mod document;
+pub mod dump;
 pub mod health;
 pub mod index;
 pub mod key;
+pub mod metrics;
 pub mod search;
 pub mod settings;
 pub mod stats;
 pub mod synonym;
-//pub mod dump;
 
 #[derive(Deserialize)]
 pub struct IndexParam {
     index_uid: String,
 }
 
+/// The query parameters of the routes registering a settings update.
+#[derive(Deserialize)]
+pub struct SettingsUpdateQuery {
+    /// The settings updates are processed before the documents updates by default.
+    priority: Option<Priority>,
+}
+
+impl SettingsUpdateQuery {
+    pub fn priority(&self) -> Priority {
+        self.priority.unwrap_or(Priority::High)
+    }
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct IndexUpdateResponse {
@@ -28,6 +45,27 @@ impl IndexUpdateResponse {
     }
 }
 
+/// Builds the response of a failed request: a 503 if one of the actors can't be reached, a 504 if
+/// the search timed out, a 413 if the payload is too large, a 400 otherwise.
+pub fn error_response(error: impl Into<anyhow::Error>) -> HttpResponse {
+    let error = error.into();
+    let mut body = serde_json::json!({ "error": error.to_string() });
+    if let Some(request_id) = RequestId::current() {
+        body["requestId"] = request_id.into();
+    }
+    if ActorError::is_cause_of(&error) {
+        HttpResponse::ServiceUnavailable().json(body)
+    } else if SearchTimeout::is_cause_of(&error) {
+        HttpResponse::GatewayTimeout().json(body)
+    } else if PayloadTooLarge::is_cause_of(&error) {
+        HttpResponse::PayloadTooLarge().json(body)
+    } else if ReadOnly::is_cause_of(&error) {
+        HttpResponse::Forbidden().json(body)
+    } else {
+        HttpResponse::BadRequest().json(body)
+    }
+}
+
 /// Return the dashboard, should not be used in production. See [running]
 #[get("/")]
 pub async fn load_html() -> HttpResponse {
@@ -1,17 +1,28 @@
 use std::collections::HashSet;
 use std::convert::{TryFrom, TryInto};
 
-use actix_web::{get, post, web, HttpResponse};
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use futures::future::join_all;
 use serde::Deserialize;
+use serde_json::{Map, Value};
 
 use crate::error::ResponseError;
-use crate::helpers::Authentication;
-use crate::index::{SearchQuery, DEFAULT_SEARCH_LIMIT};
-use crate::routes::IndexParam;
+use crate::helpers::{Authentication, EnforcedFilter};
+use crate::index::{SearchQuery, DEFAULT_HIGHLIGHT_POST_TAG, DEFAULT_HIGHLIGHT_PRE_TAG};
+use crate::routes::{error_response, IndexParam};
 use crate::Data;
 
 pub fn services(cfg: &mut web::ServiceConfig) {
-    cfg.service(search_with_post).service(search_with_url_query);
+    cfg.service(search_with_post)
+        .service(search_with_url_query)
+        .service(multi_search);
+}
+
+/// Returns the filter the API key of the request enforces on its searches, if any.
+fn enforced_filter(req: &HttpRequest) -> Option<String> {
+    req.extensions()
+        .get::<EnforcedFilter>()
+        .map(|filter| filter.0.clone())
 }
 
 #[derive(Deserialize, Debug)]
@@ -21,13 +32,22 @@ pub struct SearchQueryGet {
     offset: Option<usize>,
     limit: Option<usize>,
     attributes_to_retrieve: Option<String>,
+    attributes_to_search_on: Option<String>,
     attributes_to_crop: Option<String>,
     crop_length: Option<usize>,
     attributes_to_highlight: Option<String>,
-    filters: Option<String>,
+    highlight_pre_tag: Option<String>,
+    highlight_post_tag: Option<String>,
+    filter: Option<String>,
     matches: Option<bool>,
     facet_filters: Option<String>,
-    facet_distributions: Option<String>,
+    facets_distribution: Option<String>,
+    sort: Option<String>,
+    ranking_rules: Option<String>,
+    max_typos: Option<usize>,
+    exhaustive_nb_hits: Option<bool>,
+    show_matches_position: Option<bool>,
+    ids_only: Option<bool>,
 }
 
 impl TryFrom<SearchQueryGet> for SearchQuery {
@@ -38,6 +58,10 @@ impl TryFrom<SearchQueryGet> for SearchQuery {
             .attributes_to_retrieve
             .map(|attrs| attrs.split(',').map(String::from).collect::<Vec<_>>());
 
+        let attributes_to_search_on = other
+            .attributes_to_search_on
+            .map(|attrs| attrs.split(',').map(String::from).collect::<Vec<_>>());
+
         let attributes_to_crop = other
             .attributes_to_crop
             .map(|attrs| attrs.split(',').map(String::from).collect::<Vec<_>>());
@@ -46,10 +70,18 @@ impl TryFrom<SearchQueryGet> for SearchQuery {
             .attributes_to_highlight
             .map(|attrs| attrs.split(',').map(String::from).collect::<HashSet<_>>());
 
-        let facet_distributions = other
-            .facet_distributions
+        let facets_distribution = other
+            .facets_distribution
+            .map(|attrs| attrs.split(',').map(String::from).collect::<Vec<_>>());
+
+        let sort = other
+            .sort
             .map(|attrs| attrs.split(',').map(String::from).collect::<Vec<_>>());
 
+        let ranking_rules = other
+            .ranking_rules
+            .map(|rules| rules.split(',').map(String::from).collect::<Vec<_>>());
+
         let facet_filters = match other.facet_filters {
             Some(ref f) => Some(serde_json::from_str(f)?),
             None => None,
@@ -58,26 +90,41 @@ impl TryFrom<SearchQueryGet> for SearchQuery {
         Ok(Self {
             q: other.q,
             offset: other.offset,
-            limit: other.limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
+            limit: other.limit,
             attributes_to_retrieve,
+            attributes_to_search_on,
             attributes_to_crop,
             crop_length: other.crop_length,
             attributes_to_highlight,
-            filters: other.filters,
+            highlight_pre_tag: other
+                .highlight_pre_tag
+                .unwrap_or_else(|| DEFAULT_HIGHLIGHT_PRE_TAG.to_string()),
+            highlight_post_tag: other
+                .highlight_post_tag
+                .unwrap_or_else(|| DEFAULT_HIGHLIGHT_POST_TAG.to_string()),
+            filter: other.filter,
             matches: other.matches,
             facet_filters,
-            facet_distributions,
+            facets_distribution,
+            sort,
+            ranking_rules,
+            max_typos: other.max_typos,
+            exhaustive_nb_hits: other.exhaustive_nb_hits.unwrap_or_default(),
+            show_matches_position: other.show_matches_position.unwrap_or_default(),
+            ids_only: other.ids_only.unwrap_or_default(),
+            enforced_filter: None,
         })
     }
 }
 
 #[get("/indexes/{index_uid}/search", wrap = "Authentication::Public")]
 async fn search_with_url_query(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
     params: web::Query<SearchQueryGet>,
 ) -> Result<HttpResponse, ResponseError> {
-    let query: SearchQuery = match params.into_inner().try_into() {
+    let mut query: SearchQuery = match params.into_inner().try_into() {
         Ok(q) => q,
         Err(e) => {
             return Ok(
@@ -85,28 +132,73 @@ async fn search_with_url_query(
             )
         }
     };
+    query.enforced_filter = enforced_filter(&req);
     let search_result = data.search(path.into_inner().index_uid, query).await;
     match search_result {
         Ok(docs) => Ok(HttpResponse::Ok().json(docs)),
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-        }
+        Err(e) => Ok(error_response(e)),
     }
 }
 
 #[post("/indexes/{index_uid}/search", wrap = "Authentication::Public")]
 async fn search_with_post(
+    req: HttpRequest,
     data: web::Data<Data>,
     path: web::Path<IndexParam>,
     params: web::Json<SearchQuery>,
 ) -> Result<HttpResponse, ResponseError> {
-    let search_result = data
-        .search(path.into_inner().index_uid, params.into_inner())
-        .await;
+    let mut query = params.into_inner();
+    query.enforced_filter = enforced_filter(&req);
+    let search_result = data.search(path.into_inner().index_uid, query).await;
     match search_result {
         Ok(docs) => Ok(HttpResponse::Ok().json(docs)),
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-        }
+        Err(e) => Ok(error_response(e)),
     }
 }
+
+/// Splits a query of a multi-search into the uid of the index to search and the search query.
+fn parse_multi_search_query(
+    mut query: Map<String, Value>,
+) -> anyhow::Result<(String, SearchQuery)> {
+    let index_uid = match query.remove("indexUid") {
+        Some(Value::String(index_uid)) => index_uid,
+        _ => anyhow::bail!("Each query must have an `indexUid` string."),
+    };
+    let query = serde_json::from_value(Value::Object(query))?;
+    Ok((index_uid, query))
+}
+
+/// Runs several searches, possibly on different indexes, in a single request. The searches are
+/// sent concurrently to the index actor and the results are returned in the order of the queries,
+/// a failed query is reported in place of its result without failing the others.
+#[post("/multi-search", wrap = "Authentication::Public")]
+async fn multi_search(
+    req: HttpRequest,
+    data: web::Data<Data>,
+    body: web::Json<Vec<Map<String, Value>>>,
+) -> Result<HttpResponse, ResponseError> {
+    let data = &data;
+    let enforced_filter = &enforced_filter(&req);
+    let searches = body.into_inner().into_iter().map(|query| async move {
+        let (index_uid, mut query) = match parse_multi_search_query(query) {
+            Ok(query) => query,
+            Err(e) => return serde_json::json!({ "indexUid": null, "error": e.to_string() }),
+        };
+        query.enforced_filter = enforced_filter.clone();
+        let result = data
+            .search(index_uid.clone(), query)
+            .await
+            .and_then(|result| Ok(serde_json::to_value(result)?));
+        match result {
+            Ok(Value::Object(mut result)) => {
+                result.insert("indexUid".to_string(), Value::String(index_uid));
+                Value::Object(result)
+            }
+            Ok(result) => result,
+            Err(e) => serde_json::json!({ "indexUid": index_uid, "error": e.to_string() }),
+        }
+    });
+    let outcomes = join_all(searches).await;
+
+    Ok(HttpResponse::Ok().json(outcomes))
+}
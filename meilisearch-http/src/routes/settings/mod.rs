@@ -1,8 +1,12 @@
-use actix_web::{delete, get, post, web, HttpResponse};
+use std::collections::BTreeSet;
 
-use crate::error::ResponseError;
+use actix_web::{delete, get, post, put, web, HttpResponse};
+
+use crate::error::{Error, ResponseError};
 use crate::helpers::Authentication;
 use crate::index::Settings;
+use crate::routes::synonym::check_synonyms;
+use crate::routes::{error_response, SettingsUpdateQuery};
 use crate::Data;
 
 #[macro_export]
@@ -15,31 +19,31 @@ macro_rules! make_setting_route {
             use crate::error::ResponseError;
             use crate::helpers::Authentication;
             use crate::index::Settings;
+            use crate::routes::{error_response, SettingsUpdateQuery};
 
-            #[actix_web::delete($route, wrap = "Authentication::Private")]
+            #[actix_web::delete($route, wrap = "Authentication::Settings")]
             pub async fn delete(
                 data: web::Data<data::Data>,
                 index_uid: web::Path<String>,
+                params: web::Query<SettingsUpdateQuery>,
             ) -> Result<HttpResponse, ResponseError> {
                 use crate::index::Settings;
                 let settings = Settings {
                     $attr: Some(None),
                     ..Default::default()
                 };
-                match data.update_settings(index_uid.into_inner(), settings, false).await {
+                match data.update_settings(index_uid.into_inner(), settings, false, params.priority()).await {
                     Ok(update_status) => {
                         Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_status.id() })))
                     }
-                    Err(e) => {
-                        Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-                    }
+                    Err(e) => Ok(error_response(e)),
                 }
             }
 
-            #[actix_web::post($route, wrap = "Authentication::Private")]
-            pub async fn update(
+            async fn update_setting(
                 data: actix_web::web::Data<data::Data>,
                 index_uid: actix_web::web::Path<String>,
+                params: actix_web::web::Query<SettingsUpdateQuery>,
                 body: actix_web::web::Json<Option<$type>>,
             ) -> std::result::Result<HttpResponse, ResponseError> {
                 let settings = Settings {
@@ -47,26 +51,43 @@ macro_rules! make_setting_route {
                     ..Default::default()
                 };
 
-                match data.update_settings(index_uid.into_inner(), settings, true).await {
+                match data.update_settings(index_uid.into_inner(), settings, true, params.priority()).await {
                     Ok(update_status) => {
                         Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_status.id() })))
                     }
-                    Err(e) => {
-                        Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-                    }
+                    Err(e) => Ok(error_response(e)),
                 }
             }
 
-            #[actix_web::get($route, wrap = "Authentication::Private")]
+            #[actix_web::post($route, wrap = "Authentication::Settings")]
+            pub async fn update(
+                data: actix_web::web::Data<data::Data>,
+                index_uid: actix_web::web::Path<String>,
+                params: actix_web::web::Query<SettingsUpdateQuery>,
+                body: actix_web::web::Json<Option<$type>>,
+            ) -> std::result::Result<HttpResponse, ResponseError> {
+                update_setting(data, index_uid, params, body).await
+            }
+
+            /// Replaces the setting, the other settings of the index are left untouched.
+            #[actix_web::put($route, wrap = "Authentication::Settings")]
+            pub async fn replace(
+                data: actix_web::web::Data<data::Data>,
+                index_uid: actix_web::web::Path<String>,
+                params: actix_web::web::Query<SettingsUpdateQuery>,
+                body: actix_web::web::Json<Option<$type>>,
+            ) -> std::result::Result<HttpResponse, ResponseError> {
+                update_setting(data, index_uid, params, body).await
+            }
+
+            #[actix_web::get($route, wrap = "Authentication::Settings")]
             pub async fn get(
                 data: actix_web::web::Data<data::Data>,
                 index_uid: actix_web::web::Path<String>,
             ) -> std::result::Result<HttpResponse, ResponseError> {
                 match data.settings(index_uid.into_inner()).await {
                     Ok(settings) => Ok(HttpResponse::Ok().json(settings.$attr)),
-                    Err(e) => {
-                        Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-                    }
+                    Err(e) => Ok(error_response(e)),
                 }
             }
         }
@@ -97,17 +118,17 @@ make_setting_route!(
     stop_words
 );
 
-//make_setting_route!(
-//"/indexes/{index_uid}/settings/distinct-attribute",
-//String,
-//distinct_attribute
-//);
+make_setting_route!(
+    "/indexes/{index_uid}/settings/distinct-attribute",
+    String,
+    distinct_attribute
+);
 
-//make_setting_route!(
-//"/indexes/{index_uid}/settings/ranking-rules",
-//Vec<String>,
-//ranking_rules
-//);
+make_setting_route!(
+    "/indexes/{index_uid}/settings/ranking-rules",
+    Vec<String>,
+    ranking_rules
+);
 
 macro_rules! create_services {
     ($($mod:ident),*) => {
@@ -116,9 +137,14 @@ macro_rules! create_services {
                 .service(update_all)
                 .service(get_all)
                 .service(delete_all)
+                .service(validate)
+                .service(get_filterable_attributes)
+                .service(update_filterable_attributes)
+                .service(delete_filterable_attributes)
                 $(
                     .service($mod::get)
                     .service($mod::update)
+                    .service($mod::replace)
                     .service($mod::delete)
                 )*;
         }
@@ -129,56 +155,174 @@ create_services!(
     attributes_for_faceting,
     displayed_attributes,
     searchable_attributes,
-    stop_words
+    stop_words,
+    distinct_attribute,
+    ranking_rules
 );
 
-#[post("/indexes/{index_uid}/settings", wrap = "Authentication::Private")]
+#[post("/indexes/{index_uid}/settings", wrap = "Authentication::Settings")]
 async fn update_all(
     data: web::Data<Data>,
     index_uid: web::Path<String>,
+    params: web::Query<SettingsUpdateQuery>,
     body: web::Json<Settings>,
 ) -> Result<HttpResponse, ResponseError> {
+    let settings = body.into_inner();
+    if let Some(Some(ref synonyms)) = settings.synonyms {
+        check_synonyms(synonyms)?;
+    }
+    if let Some(Some(ref typo_tolerance)) = settings.typo_tolerance {
+        typo_tolerance
+            .check()
+            .map_err(|e| Error::BadParameter("typoTolerance".to_string(), e.to_string()))?;
+    }
+
     match data
-        .update_settings(index_uid.into_inner(), body.into_inner(), true)
+        .update_settings(index_uid.into_inner(), settings, true, params.priority())
         .await
     {
-        Ok(update_result) => {
-            Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_result.id() })))
-        }
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-        }
+        Ok(update_result) => Ok(
+            HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_result.id() }))
+        ),
+        Err(e) => Ok(error_response(e)),
     }
 }
 
-#[get("/indexes/{index_uid}/settings", wrap = "Authentication::Private")]
+#[get("/indexes/{index_uid}/settings", wrap = "Authentication::Settings")]
 async fn get_all(
     data: web::Data<Data>,
     index_uid: web::Path<String>,
 ) -> Result<HttpResponse, ResponseError> {
     match data.settings(index_uid.into_inner()).await {
         Ok(settings) => Ok(HttpResponse::Ok().json(settings)),
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Returns the names of the attributes that can be used in the filters, these are the attributes
+/// for faceting. They are only set through `attributesForFaceting`.
+#[get(
+    "/indexes/{index_uid}/settings/filterable-attributes",
+    wrap = "Authentication::Settings"
+)]
+async fn get_filterable_attributes(
+    data: web::Data<Data>,
+    index_uid: web::Path<String>,
+) -> Result<HttpResponse, ResponseError> {
+    match data.settings(index_uid.into_inner()).await {
+        Ok(settings) => {
+            let attributes: BTreeSet<_> = settings
+                .attributes_for_faceting
+                .flatten()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(attribute, _)| attribute)
+                .collect();
+            Ok(HttpResponse::Ok().json(attributes))
         }
+        Err(e) => Ok(error_response(e)),
     }
 }
 
-#[delete("/indexes/{index_uid}/settings", wrap = "Authentication::Private")]
+/// Makes the attributes filterable, replacing the current filterable attributes. The attributes
+/// that are already filterable keep their facet type, the type of the others is deduced from the
+/// documents of the index. The attributes are indexed by the enqueued update.
+#[put(
+    "/indexes/{index_uid}/settings/filterable-attributes",
+    wrap = "Authentication::Settings"
+)]
+async fn update_filterable_attributes(
+    data: web::Data<Data>,
+    index_uid: web::Path<String>,
+    params: web::Query<SettingsUpdateQuery>,
+    body: web::Json<Vec<String>>,
+) -> Result<HttpResponse, ResponseError> {
+    let index_uid = index_uid.into_inner();
+    let attributes = body.into_inner();
+    let facet_types = match data
+        .infer_facet_types(index_uid.clone(), attributes.clone())
+        .await
+    {
+        Ok(facet_types) => facet_types,
+        Err(e) => return Ok(error_response(e)),
+    };
+    if let Some(attribute) = attributes.iter().find(|a| !facet_types.contains_key(*a)) {
+        return Err(Error::BadRequest(format!(
+            "The type of {:?} can't be inferred as no document has a value for it, it must be given in attributesForFaceting.",
+            attribute
+        ))
+        .into());
+    }
+    let settings = Settings {
+        attributes_for_faceting: Some(Some(facet_types)),
+        ..Default::default()
+    };
+    match data
+        .update_settings(index_uid, settings, false, params.priority())
+        .await
+    {
+        Ok(update_status) => Ok(HttpResponse::Accepted().json(update_status)),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+#[delete(
+    "/indexes/{index_uid}/settings/filterable-attributes",
+    wrap = "Authentication::Settings"
+)]
+async fn delete_filterable_attributes(
+    data: web::Data<Data>,
+    index_uid: web::Path<String>,
+    params: web::Query<SettingsUpdateQuery>,
+) -> Result<HttpResponse, ResponseError> {
+    let settings = Settings {
+        attributes_for_faceting: Some(None),
+        ..Default::default()
+    };
+    match data
+        .update_settings(index_uid.into_inner(), settings, false, params.priority())
+        .await
+    {
+        Ok(update_status) => Ok(HttpResponse::Accepted().json(update_status)),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Resets all the settings of the index to their default values in a single update.
+#[delete("/indexes/{index_uid}/settings", wrap = "Authentication::Settings")]
 async fn delete_all(
     data: web::Data<Data>,
     index_uid: web::Path<String>,
+    params: web::Query<SettingsUpdateQuery>,
 ) -> Result<HttpResponse, ResponseError> {
     let settings = Settings::cleared();
     match data
-        .update_settings(index_uid.into_inner(), settings, false)
+        .update_settings(index_uid.into_inner(), settings, false, params.priority())
         .await
     {
-        Ok(update_result) => {
-            Ok(HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_result.id() })))
-        }
-        Err(e) => {
-            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "error": e.to_string() })))
-        }
+        Ok(update_result) => Ok(
+            HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_result.id() }))
+        ),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+/// Checks the settings against the current schema of the index without enqueuing an update, the
+/// response lists the errors that would make the update fail and the likely mistakes.
+#[post(
+    "/indexes/{index_uid}/settings/validate",
+    wrap = "Authentication::Settings"
+)]
+async fn validate(
+    data: web::Data<Data>,
+    index_uid: web::Path<String>,
+    body: web::Json<Settings>,
+) -> Result<HttpResponse, ResponseError> {
+    match data
+        .validate_settings(index_uid.into_inner(), body.into_inner())
+        .await
+    {
+        Ok(validation) => Ok(HttpResponse::Ok().json(validation)),
+        Err(e) => Ok(error_response(e)),
     }
 }
@@ -1,47 +1,128 @@
 use std::collections::BTreeMap;
 
-use actix_web::{delete, get, post};
+use actix_web::{delete, get, post, put};
 use actix_web::{web, HttpResponse};
 
-use crate::error::ResponseError;
+use crate::error::{Error, ResponseError};
 use crate::helpers::Authentication;
-use crate::routes::IndexParam;
+use crate::index::Settings;
+use crate::routes::{error_response, IndexParam, SettingsUpdateQuery};
 use crate::Data;
 
 pub fn services(cfg: &mut web::ServiceConfig) {
-    cfg.service(get).service(update).service(delete);
+    cfg.service(get)
+        .service(update)
+        .service(replace)
+        .service(delete);
+}
+
+/// Returns an error if one of the words the synonyms are defined for is empty.
+pub fn check_synonyms(synonyms: &BTreeMap<String, Vec<String>>) -> Result<(), ResponseError> {
+    if synonyms.keys().any(|word| word.trim().is_empty()) {
+        return Err(Error::BadParameter(
+            "synonyms".to_string(),
+            "the synonyms can't be defined for an empty word".to_string(),
+        )
+        .into());
+    }
+    Ok(())
 }
 
 #[get(
     "/indexes/{index_uid}/settings/synonyms",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn get(
-    _data: web::Data<Data>,
-    _path: web::Path<IndexParam>,
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+) -> Result<HttpResponse, ResponseError> {
+    match data.settings(path.into_inner().index_uid).await {
+        Ok(settings) => Ok(HttpResponse::Ok().json(settings.synonyms)),
+        Err(e) => Ok(error_response(e)),
+    }
+}
+
+async fn update_synonyms(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    params: web::Query<SettingsUpdateQuery>,
+    body: web::Json<BTreeMap<String, Vec<String>>>,
 ) -> Result<HttpResponse, ResponseError> {
-    todo!()
+    let synonyms = body.into_inner();
+    check_synonyms(&synonyms)?;
+
+    let settings = Settings {
+        synonyms: Some(Some(synonyms)),
+        ..Default::default()
+    };
+    match data
+        .update_settings(
+            path.into_inner().index_uid,
+            settings,
+            true,
+            params.priority(),
+        )
+        .await
+    {
+        Ok(update_status) => Ok(
+            HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_status.id() }))
+        ),
+        Err(e) => Ok(error_response(e)),
+    }
 }
 
 #[post(
     "/indexes/{index_uid}/settings/synonyms",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn update(
-    _data: web::Data<Data>,
-    _path: web::Path<IndexParam>,
-    _body: web::Json<BTreeMap<String, Vec<String>>>,
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    params: web::Query<SettingsUpdateQuery>,
+    body: web::Json<BTreeMap<String, Vec<String>>>,
+) -> Result<HttpResponse, ResponseError> {
+    update_synonyms(data, path, params, body).await
+}
+
+/// Replaces all the synonyms of the index, the other settings are left untouched.
+#[put(
+    "/indexes/{index_uid}/settings/synonyms",
+    wrap = "Authentication::Settings"
+)]
+async fn replace(
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    params: web::Query<SettingsUpdateQuery>,
+    body: web::Json<BTreeMap<String, Vec<String>>>,
 ) -> Result<HttpResponse, ResponseError> {
-    todo!()
+    update_synonyms(data, path, params, body).await
 }
 
 #[delete(
     "/indexes/{index_uid}/settings/synonyms",
-    wrap = "Authentication::Private"
+    wrap = "Authentication::Settings"
 )]
 async fn delete(
-    _data: web::Data<Data>,
-    _path: web::Path<IndexParam>,
+    data: web::Data<Data>,
+    path: web::Path<IndexParam>,
+    params: web::Query<SettingsUpdateQuery>,
 ) -> Result<HttpResponse, ResponseError> {
-    todo!()
+    let settings = Settings {
+        synonyms: Some(None),
+        ..Default::default()
+    };
+    match data
+        .update_settings(
+            path.into_inner().index_uid,
+            settings,
+            false,
+            params.priority(),
+        )
+        .await
+    {
+        Ok(update_status) => Ok(
+            HttpResponse::Accepted().json(serde_json::json!({ "updateId": update_status.id() }))
+        ),
+        Err(e) => Ok(error_response(e)),
+    }
 }
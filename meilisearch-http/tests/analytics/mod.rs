@@ -0,0 +1,49 @@
+use serde_json::{json, Value};
+
+use meilisearch_http::analytics;
+use meilisearch_http::Opt;
+
+use crate::common::server::default_settings;
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn dry_run_payload_is_anonymized() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let options = Opt {
+        no_analytics: false,
+        analytics_dry_run: true,
+        ..default_settings(dir.path())
+    };
+    let server = Server::new_with_options(options.clone()).await;
+
+    let index = server.index("confidential_index");
+    let documents = json!([
+        { "id": 1, "secret_field": "top secret content" },
+        { "id": 2, "secret_field": "another secret" },
+    ]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(0).await;
+
+    let payload = analytics::payload(&server.service.0, &options, "instance", 0)
+        .await
+        .unwrap();
+
+    for private in &[
+        "confidential_index",
+        "secret_field",
+        "top secret content",
+        "another secret",
+    ] {
+        assert!(
+            !payload.contains(private),
+            "{:?} leaked in {}",
+            private,
+            payload
+        );
+    }
+
+    let payload: Value = serde_json::from_str(&payload).unwrap();
+    let properties = &payload["events"][0]["event_properties"];
+    assert_eq!(properties["number_of_indexes"], 1);
+    assert_eq!(properties["number_of_documents"], json!([2]));
+}
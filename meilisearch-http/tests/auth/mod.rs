@@ -0,0 +1,423 @@
+use std::time::Duration;
+
+use chrono::{TimeZone, Utc};
+use serde_json::{json, Value};
+use tokio::time::sleep;
+
+use meilisearch_http::helpers::Clock;
+use meilisearch_http::Opt;
+
+use crate::common::server::default_settings;
+use crate::common::{sorted_hits_ids, Server};
+
+const MASTER_KEY: &str = "master";
+
+async fn server_with_master_key(dir: &tempfile::TempDir) -> Server {
+    let options = Opt {
+        master_key: Some(MASTER_KEY.to_string()),
+        ..default_settings(dir.path())
+    };
+    Server::new_with_options(options).await
+}
+
+async fn wait_update_with_master_key(server: &Server, index: &str, update_id: u64) -> Value {
+    let url = format!("/indexes/{}/updates/{}", index, update_id);
+    for _ in 0..10 {
+        let (response, code) = server
+            .service
+            .get_with_api_key(&url, Some(MASTER_KEY))
+            .await;
+        assert_eq!(code, 200, "{}", response);
+        if response["status"] == "processed" || response["status"] == "failed" {
+            return response;
+        }
+        sleep(Duration::from_secs(1)).await;
+    }
+    panic!("Timeout waiting for update id");
+}
+
+#[actix_rt::test]
+async fn create_key_requires_master_key() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let server = server_with_master_key(&dir).await;
+    let body = json!({ "indexes": ["movies"], "actions": ["search"] });
+
+    let (_, code) = server
+        .service
+        .post_with_api_key("/keys", body.clone(), None)
+        .await;
+    assert_eq!(code, 401);
+
+    let (response, code) = server
+        .service
+        .post_with_api_key("/keys", body, Some(MASTER_KEY))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["indexes"], json!(["movies"]));
+    assert_eq!(response["actions"], json!(["search"]));
+    assert!(response["key"].as_str().unwrap().len() > 0);
+}
+
+#[actix_rt::test]
+async fn create_key_without_scope() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let server = server_with_master_key(&dir).await;
+
+    let body = json!({ "indexes": [], "actions": ["search"] });
+    let (_, code) = server
+        .service
+        .post_with_api_key("/keys", body, Some(MASTER_KEY))
+        .await;
+    assert_eq!(code, 400);
+
+    let body = json!({ "indexes": ["movies"], "actions": ["deleteEverything"] });
+    let (_, code) = server
+        .service
+        .post_with_api_key("/keys", body, Some(MASTER_KEY))
+        .await;
+    assert_eq!(code, 400);
+}
+
+#[actix_rt::test]
+async fn search_only_key_is_denied_settings_write() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let server = server_with_master_key(&dir).await;
+
+    let (response, code) = server
+        .service
+        .post_with_api_key("/indexes", json!({ "uid": "movies" }), Some(MASTER_KEY))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+
+    let body = json!({ "indexes": ["movies"], "actions": ["search"] });
+    let (response, _) = server
+        .service
+        .post_with_api_key("/keys", body, Some(MASTER_KEY))
+        .await;
+    let key = response["key"].as_str().unwrap().to_string();
+
+    let (response, code) = server
+        .service
+        .post_with_api_key("/indexes/movies/search", json!({ "q": "" }), Some(&key))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+
+    let settings = json!({ "rankingRules": ["words"] });
+    let (response, code) = server
+        .service
+        .post_with_api_key("/indexes/movies/settings", settings, Some(&key))
+        .await;
+    assert_eq!(code, 403);
+    assert_eq!(response["errorCode"], "forbidden_key_scope");
+
+    // The key doesn't give access to the other indexes.
+    let (_, code) = server
+        .service
+        .post_with_api_key("/indexes/other/search", json!({ "q": "" }), Some(&key))
+        .await;
+    assert_eq!(code, 403);
+
+    // The key is not a main key either.
+    let (_, code) = server
+        .service
+        .post_with_api_key("/indexes", json!({ "uid": "other" }), Some(&key))
+        .await;
+    assert_eq!(code, 403);
+}
+
+#[actix_rt::test]
+async fn unknown_key_is_rejected() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let server = server_with_master_key(&dir).await;
+
+    let (response, code) = server
+        .service
+        .post_with_api_key(
+            "/indexes/movies/search",
+            json!({ "q": "" }),
+            Some("unknown"),
+        )
+        .await;
+    assert_eq!(code, 403);
+    assert_eq!(response["errorCode"], "invalid_token");
+}
+
+#[actix_rt::test]
+async fn create_key_already_expired() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let server = server_with_master_key(&dir).await;
+
+    let body = json!({
+        "indexes": ["movies"],
+        "actions": ["search"],
+        "expiresAt": "2000-01-01T00:00:00Z",
+    });
+    let (_, code) = server
+        .service
+        .post_with_api_key("/keys", body, Some(MASTER_KEY))
+        .await;
+    assert_eq!(code, 400);
+}
+
+#[actix_rt::test]
+async fn expired_key_is_rejected() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let server = server_with_master_key(&dir).await;
+
+    let (_, code) = server
+        .service
+        .post_with_api_key("/indexes", json!({ "uid": "movies" }), Some(MASTER_KEY))
+        .await;
+    assert_eq!(code, 200);
+
+    let body = json!({
+        "indexes": ["movies"],
+        "actions": ["search"],
+        "expiresAt": "2100-01-01T00:00:00Z",
+    });
+    let (response, code) = server
+        .service
+        .post_with_api_key("/keys", body, Some(MASTER_KEY))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["expiresAt"], "2100-01-01T00:00:00Z");
+    let key = response["key"].as_str().unwrap().to_string();
+
+    let before_expiration = Clock::fixed(Utc.ymd(2099, 12, 31).and_hms(23, 59, 59));
+    let (response, code) = server
+        .service
+        .post_with_api_key_at(
+            "/indexes/movies/search",
+            json!({ "q": "" }),
+            Some(&key),
+            before_expiration.clone(),
+        )
+        .await;
+    assert_eq!(code, 200, "{}", response);
+
+    let after_expiration = Clock::fixed(Utc.ymd(2100, 1, 1).and_hms(0, 0, 1));
+    let (response, code) = server
+        .service
+        .post_with_api_key_at(
+            "/indexes/movies/search",
+            json!({ "q": "" }),
+            Some(&key),
+            after_expiration,
+        )
+        .await;
+    assert_eq!(code, 403);
+    assert_eq!(response["errorCode"], "expired_token");
+    assert!(response["message"].as_str().unwrap().contains("expired"));
+
+    // The expired key was removed from the key store.
+    let (response, code) = server
+        .service
+        .post_with_api_key_at(
+            "/indexes/movies/search",
+            json!({ "q": "" }),
+            Some(&key),
+            before_expiration,
+        )
+        .await;
+    assert_eq!(code, 403);
+    assert_eq!(response["errorCode"], "invalid_token");
+}
+
+#[actix_rt::test]
+async fn client_filter_cant_escape_key_filter() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let server = server_with_master_key(&dir).await;
+
+    let settings = json!({ "attributesForFaceting": ["tenant_id"] });
+    let (response, code) = server
+        .service
+        .post_with_api_key("/indexes/rows/settings", settings, Some(MASTER_KEY))
+        .await;
+    assert_eq!(code, 202, "{}", response);
+    wait_update_with_master_key(&server, "rows", 0).await;
+
+    let documents = json!([
+        { "id": 1, "tenant_id": 42, "title": "hello" },
+        { "id": 2, "tenant_id": 42, "title": "hello" },
+        { "id": 3, "tenant_id": 7, "title": "hello" },
+    ]);
+    let (response, code) = server
+        .service
+        .post_with_api_key("/indexes/rows/documents", documents, Some(MASTER_KEY))
+        .await;
+    assert_eq!(code, 202, "{}", response);
+    let response = wait_update_with_master_key(&server, "rows", 1).await;
+    assert_eq!(response["status"], "processed", "{}", response);
+
+    let body = json!({ "indexes": ["rows"], "actions": ["search"], "filter": "tenant_id = 42" });
+    let (response, code) = server
+        .service
+        .post_with_api_key("/keys", body, Some(MASTER_KEY))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["filter"], "tenant_id = 42");
+    let key = response["key"].as_str().unwrap().to_string();
+
+    let queries = vec![
+        json!({ "q": "hello" }),
+        json!({ "q": "hello", "filter": "tenant_id = 7" }),
+        json!({ "q": "hello", "filter": "tenant_id = 7 OR tenant_id = 42" }),
+        json!({ "q": "hello", "filter": "NOT tenant_id = 42" }),
+    ];
+    let expected: Vec<Vec<u64>> = vec![vec![1, 2], vec![], vec![1, 2], vec![]];
+    for (query, expected) in queries.into_iter().zip(expected) {
+        let (response, code) = server
+            .service
+            .post_with_api_key("/indexes/rows/search", query.clone(), Some(&key))
+            .await;
+        assert_eq!(code, 200, "{}", response);
+        assert_eq!(sorted_hits_ids(&response), expected, "query: {}", query);
+    }
+
+    // The key filter can't be overridden.
+    let query = json!({ "q": "hello", "enforcedFilter": "tenant_id = 7" });
+    let (_, code) = server
+        .service
+        .post_with_api_key("/indexes/rows/search", query, Some(&key))
+        .await;
+    assert_eq!(code, 400);
+
+    // The documents can't be browsed without the filter.
+    let (_, code) = server
+        .service
+        .get_with_api_key("/indexes/rows/documents", Some(&key))
+        .await;
+    assert_eq!(code, 403);
+    let (_, code) = server
+        .service
+        .get_with_api_key("/indexes/rows/documents/3", Some(&key))
+        .await;
+    assert_eq!(code, 403);
+}
+
+#[actix_rt::test]
+async fn key_in_custom_header() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let options = Opt {
+        master_key: Some(MASTER_KEY.to_string()),
+        auth_header_name: Some("X-Gateway-Key".to_string()),
+        ..default_settings(dir.path())
+    };
+    let server = Server::new_with_options(options).await;
+
+    let (response, code) = server
+        .service
+        .get_with_header("/indexes", ("X-Gateway-Key", MASTER_KEY))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+
+    let (_, code) = server
+        .service
+        .get_with_header("/indexes", ("X-Gateway-Key", "wrong"))
+        .await;
+    assert_eq!(code, 403);
+
+    // The default header is still accepted.
+    let (response, code) = server
+        .service
+        .get_with_api_key("/indexes", Some(MASTER_KEY))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+}
+
+#[actix_rt::test]
+async fn rotate_master_key() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let server = server_with_master_key(&dir).await;
+    let (keys, code) = server
+        .service
+        .get_with_api_key("/keys", Some(MASTER_KEY))
+        .await;
+    assert_eq!(code, 200, "{}", keys);
+    let old_private = keys["private"].as_str().unwrap().to_string();
+    let (response, code) = server
+        .service
+        .post_with_api_key("/indexes", json!({ "uid": "movies" }), Some(MASTER_KEY))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    let body = json!({ "indexes": ["movies"], "actions": ["search"] });
+    let (response, _) = server
+        .service
+        .post_with_api_key("/keys", body, Some(MASTER_KEY))
+        .await;
+    let scoped_key = response["key"].as_str().unwrap().to_string();
+
+    let body = json!({ "masterKey": "new-master" });
+    let (_, code) = server
+        .service
+        .post_with_api_key("/keys/rotate-master", body.clone(), Some(&old_private))
+        .await;
+    assert_eq!(code, 403);
+    let (response, code) = server
+        .service
+        .post_with_api_key("/keys/rotate-master", body, Some(MASTER_KEY))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    let new_private = response["private"].as_str().unwrap().to_string();
+    assert_ne!(new_private, old_private);
+
+    let (_, code) = server
+        .service
+        .get_with_api_key("/indexes", Some(MASTER_KEY))
+        .await;
+    assert_eq!(code, 403);
+    let (_, code) = server
+        .service
+        .get_with_api_key("/indexes", Some(&old_private))
+        .await;
+    assert_eq!(code, 403);
+    let (_, code) = server
+        .service
+        .get_with_api_key("/indexes", Some("new-master"))
+        .await;
+    assert_eq!(code, 200);
+    let (_, code) = server
+        .service
+        .get_with_api_key("/indexes", Some(&new_private))
+        .await;
+    assert_eq!(code, 200);
+
+    // The scoped keys granted with the previous master key are revoked.
+    let (response, code) = server
+        .service
+        .post_with_api_key(
+            "/indexes/movies/search",
+            json!({ "q": "" }),
+            Some(&scoped_key),
+        )
+        .await;
+    assert_eq!(code, 403);
+    assert_eq!(response["errorCode"], "invalid_token");
+
+    // The new master key itself isn't persisted.
+    let rotated_keys_path = dir.path().join("db/keys/rotated-keys");
+    let rotated_keys = std::fs::read_to_string(&rotated_keys_path).unwrap();
+    assert!(!rotated_keys.contains("new-master"));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let metadata = std::fs::metadata(&rotated_keys_path).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+    }
+
+    assert!(server.shutdown(Duration::from_secs(60)).await);
+    drop(server);
+
+    // The new master key is kept after a restart.
+    let server = server_with_master_key(&dir).await;
+    let (_, code) = server
+        .service
+        .get_with_api_key("/indexes", Some(MASTER_KEY))
+        .await;
+    assert_eq!(code, 403);
+    let (_, code) = server
+        .service
+        .get_with_api_key("/keys", Some("new-master"))
+        .await;
+    assert_eq!(code, 200);
+}
@@ -37,6 +37,15 @@ impl Index<'_> {
         self.service.post("/indexes", body).await
     }
 
+    /// Creates the index with a map size of `map_size` bytes.
+    pub async fn create_with_map_size(&self, map_size: u64) -> (Value, StatusCode) {
+        let body = json!({
+            "uid": self.uid,
+            "mapSize": map_size,
+        });
+        self.service.post("/indexes", body).await
+    }
+
     pub async fn update(&self, primary_key: Option<&str>) -> (Value, StatusCode) {
         let body = json!({
             "primaryKey": primary_key,
@@ -46,11 +55,34 @@ impl Index<'_> {
         self.service.put(url, body).await
     }
 
+    pub async fn rename(&self, new_uid: &str) -> (Value, StatusCode) {
+        let body = json!({ "uid": new_uid });
+        let url = format!("/indexes/{}", self.uid);
+        self.service.put(url, body).await
+    }
+
     pub async fn delete(&self) -> (Value, StatusCode) {
         let url = format!("/indexes/{}", self.uid);
         self.service.delete(url).await
     }
 
+    /// Downloads the gzipped tarball of the index.
+    pub async fn export(&self) -> (Vec<u8>, StatusCode) {
+        let url = format!("/indexes/{}/export", self.uid);
+        self.service.get_bytes(url).await
+    }
+
+    /// Imports the export of an index in this index.
+    pub async fn import(&self, export: Vec<u8>) -> (Value, StatusCode) {
+        let url = format!("/indexes/{}/import", self.uid);
+        self.service.post_bytes(url, export).await
+    }
+
+    pub async fn restore(&self) -> (Value, StatusCode) {
+        let url = format!("/indexes/{}/restore", self.uid);
+        self.service.post(url, Value::Null).await
+    }
+
     pub async fn add_documents(
         &self,
         documents: Value,
@@ -75,6 +107,38 @@ impl Index<'_> {
         self.service.put(url, documents).await
     }
 
+    pub async fn patch_documents(
+        &self,
+        documents: Value,
+        primary_key: Option<&str>,
+    ) -> (Value, StatusCode) {
+        let url = match primary_key {
+            Some(key) => format!("/indexes/{}/documents?primaryKey={}", self.uid, key),
+            None => format!("/indexes/{}/documents", self.uid),
+        };
+        self.service.patch(url, documents).await
+    }
+
+    /// Adds the documents and waits for the addition to be processed, returning its status.
+    pub async fn add_documents_and_wait(
+        &self,
+        documents: Value,
+        primary_key: Option<&str>,
+    ) -> Value {
+        let (response, code) = self.add_documents(documents, primary_key).await;
+        assert_eq!(code, 202, "{}", response);
+        self.wait_update_id(response["updateId"].as_u64().unwrap())
+            .await
+    }
+
+    /// Updates the settings and waits for the update to be processed, returning its status.
+    pub async fn update_settings_and_wait(&self, settings: Value) -> Value {
+        let (response, code) = self.update_settings(settings).await;
+        assert_eq!(code, 202, "{}", response);
+        self.wait_update_id(response["updateId"].as_u64().unwrap())
+            .await
+    }
+
     pub async fn wait_update_id(&self, update_id: u64) -> Value {
         // try 10 times to get status, or panic to not wait forever
         let url = format!("/indexes/{}/updates/{}", self.uid, update_id);
@@ -91,11 +155,26 @@ impl Index<'_> {
         panic!("Timeout waiting for update id");
     }
 
+    pub async fn export_documents(&self) -> (String, StatusCode) {
+        let url = format!("/indexes/{}/documents?format=ndjson", self.uid);
+        self.service.get_raw(url).await
+    }
+
     pub async fn get_update(&self, update_id: u64) -> (Value, StatusCode) {
         let url = format!("/indexes/{}/updates/{}", self.uid, update_id);
         self.service.get(url).await
     }
 
+    pub async fn watch_update(&self, update_id: u64) -> (String, StatusCode) {
+        let url = format!("/indexes/{}/updates/{}/watch", self.uid, update_id);
+        self.service.get_raw(url).await
+    }
+
+    pub async fn list_updates_with_query(&self, query: &str) -> (Value, StatusCode) {
+        let url = format!("/indexes/{}/updates?{}", self.uid, query);
+        self.service.get(url).await
+    }
+
     pub async fn list_updates(&self) -> (Value, StatusCode) {
         let url = format!("/indexes/{}/updates", self.uid);
         self.service.get(url).await
@@ -110,6 +189,11 @@ impl Index<'_> {
         self.service.get(url).await
     }
 
+    pub async fn document_exists(&self, id: u64) -> (Vec<u8>, StatusCode) {
+        let url = format!("/indexes/{}/documents/{}", self.uid, id);
+        self.service.head(url).await
+    }
+
     pub async fn get_all_documents(&self, options: GetAllDocumentsOptions) -> (Value, StatusCode) {
         let mut url = format!("/indexes/{}/documents?", self.uid);
         if let Some(limit) = options.limit {
@@ -127,6 +211,10 @@ impl Index<'_> {
             ));
         }
 
+        if let Some(ids) = options.ids {
+            url.push_str(&format!("ids={}&", ids.join(",")));
+        }
+
         self.service.get(url).await
     }
 
@@ -147,6 +235,16 @@ impl Index<'_> {
             .await
     }
 
+    /// Reads the document count of the index directly from the index actor.
+    pub async fn document_count(&self) -> anyhow::Result<u64> {
+        self.service.0.document_count(self.uid.clone()).await
+    }
+
+    pub async fn delete_by_filter(&self, filter: &str) -> (Value, StatusCode) {
+        let url = format!("/indexes/{}/documents/delete-by-filter", self.uid);
+        self.service.post(url, json!({ "filter": filter })).await
+    }
+
     pub async fn settings(&self) -> (Value, StatusCode) {
         let url = format!("/indexes/{}/settings", self.uid);
         self.service.get(url).await
@@ -157,6 +255,11 @@ impl Index<'_> {
         self.service.post(url, settings).await
     }
 
+    pub async fn validate_settings(&self, settings: Value) -> (Value, StatusCode) {
+        let url = format!("/indexes/{}/settings/validate", self.uid);
+        self.service.post(url, settings).await
+    }
+
     pub async fn delete_settings(&self) -> (Value, StatusCode) {
         let url = format!("/indexes/{}/settings", self.uid);
         self.service.delete(url).await
@@ -166,6 +269,11 @@ impl Index<'_> {
         let url = format!("/indexes/{}/stats", self.uid);
         self.service.get(url).await
     }
+
+    pub async fn search(&self, query: Value) -> (Value, StatusCode) {
+        let url = format!("/indexes/{}/search", self.uid);
+        self.service.post(url, query).await
+    }
 }
 
 pub struct GetDocumentOptions;
@@ -175,4 +283,5 @@ pub struct GetAllDocumentsOptions {
     pub limit: Option<usize>,
     pub offset: Option<usize>,
     pub attributes_to_retrieve: Option<Vec<&'static str>>,
+    pub ids: Option<Vec<&'static str>>,
 }
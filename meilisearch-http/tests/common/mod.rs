@@ -2,9 +2,28 @@ pub mod index;
 pub mod server;
 pub mod service;
 
+use serde_json::Value;
+
 pub use index::{GetAllDocumentsOptions, GetDocumentOptions};
 pub use server::Server;
 
+/// Returns the ids of the hits of a search response, in the order of the hits.
+pub fn hits_ids(response: &Value) -> Vec<u64> {
+    response["hits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|hit| hit["id"].as_u64().unwrap())
+        .collect()
+}
+
+/// Returns the sorted ids of the hits of a search response.
+pub fn sorted_hits_ids(response: &Value) -> Vec<u64> {
+    let mut ids = hits_ids(response);
+    ids.sort_unstable();
+    ids
+}
+
 /// Performs a search test on both post and get routes
 #[macro_export]
 macro_rules! test_post_get_search {
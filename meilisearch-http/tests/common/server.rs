@@ -1,4 +1,6 @@
+use std::num::NonZeroUsize;
 use std::path::Path;
+use std::time::Duration;
 
 use actix_web::http::StatusCode;
 use byte_unit::{Byte, ByteUnit};
@@ -7,7 +9,9 @@ use tempdir::TempDir;
 use urlencoding::encode;
 
 use meilisearch_http::data::Data;
-use meilisearch_http::option::{IndexerOpts, Opt};
+use meilisearch_http::helpers::compression::CompressionFormat;
+use meilisearch_http::helpers::logging::LogFormat;
+use meilisearch_http::option::{IndexStoreBackend, IndexerOpts, Opt};
 
 use super::index::Index;
 use super::service::Service;
@@ -62,6 +66,46 @@ impl Server {
     pub async fn stats(&self) -> (Value, StatusCode) {
         self.service.get("/stats").await
     }
+
+    pub async fn multi_search(&self, queries: Value) -> (Value, StatusCode) {
+        self.service.post("/multi-search", queries).await
+    }
+
+    pub async fn create_indexes(&self, indexes: Value) -> (Value, StatusCode) {
+        self.service.post("/indexes/batch", indexes).await
+    }
+
+    pub async fn delete_indexes(&self, uids: Value) -> (Value, StatusCode) {
+        self.service.post("/indexes/batch-delete", uids).await
+    }
+
+    pub async fn swap_indexes(&self, first: &str, second: &str) -> (Value, StatusCode) {
+        let body = serde_json::json!({ "indexes": [first, second] });
+        self.service.post("/swap-indexes", body).await
+    }
+
+    pub async fn create_alias(&self, alias: &str, index: &str) -> (Value, StatusCode) {
+        let body = serde_json::json!({ "alias": alias, "index": index });
+        self.service.post("/aliases", body).await
+    }
+
+    pub async fn delete_alias(&self, alias: &str) -> (Value, StatusCode) {
+        let url = format!("/aliases/{}", alias);
+        self.service.delete(url).await
+    }
+
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        self.service.0.shutdown(timeout).await
+    }
+
+    pub async fn create_dump(&self) -> (Value, StatusCode) {
+        self.service.post("/dumps", Value::Null).await
+    }
+
+    pub async fn dump_status(&self, uid: impl AsRef<str>) -> (Value, StatusCode) {
+        let url = format!("/dumps/{}/status", uid.as_ref());
+        self.service.get(url).await
+    }
 }
 
 pub fn default_settings(dir: impl AsRef<Path>) -> Opt {
@@ -71,11 +115,33 @@ pub fn default_settings(dir: impl AsRef<Path>) -> Opt {
         dump_batch_size: 16,
         http_addr: "127.0.0.1:7700".to_owned(),
         master_key: None,
+        auth_header_name: None,
+        cors_allowed_origins: None,
         env: "development".to_owned(),
         no_analytics: true,
+        analytics_dry_run: false,
+        log_format: LogFormat::Text,
+        enable_metrics: false,
+        no_response_compression: false,
+        update_max_retries: 0,
         max_mdb_size: Byte::from_unit(4.0, ByteUnit::GiB).unwrap(),
         max_udb_size: Byte::from_unit(4.0, ByteUnit::GiB).unwrap(),
-        http_payload_size_limit: Byte::from_unit(10.0, ByteUnit::MiB).unwrap(),
+        index_store: IndexStoreBackend::Disk,
+        max_index_map_size: Byte::from_unit(1.0, ByteUnit::TiB).unwrap(),
+        max_search_limit: 1000,
+        default_search_limit: 20,
+        actor_mailbox_size: NonZeroUsize::new(100).unwrap(),
+        index_read_mailbox_size: None,
+        index_write_mailbox_size: None,
+        index_read_threads: NonZeroUsize::new(10).unwrap(),
+        search_timeout_ms: 5000,
+        search_cache_size: 0,
+        cascade_aliases_deletion: false,
+        read_only: false,
+        index_trash_retention_sec: None,
+        shutdown_timeout_sec: 60,
+        http_payload_size_limit: Byte::from_unit(100.0, ByteUnit::MB).unwrap(),
+        http_json_payload_size_limit: Byte::from_unit(10.0, ByteUnit::MiB).unwrap(),
         ssl_cert_path: None,
         ssl_key_path: None,
         ssl_auth_path: None,
@@ -87,8 +153,18 @@ pub fn default_settings(dir: impl AsRef<Path>) -> Opt {
         ignore_missing_snapshot: false,
         ignore_snapshot_if_db_exists: false,
         snapshot_dir: ".".into(),
+        snapshot_temp_dir: None,
         schedule_snapshot: false,
         snapshot_interval_sec: 0,
+        snapshot_compression_level: 6,
+        snapshot_format: CompressionFormat::Gzip,
+        snapshot_retention: None,
+        snapshot_encryption_key: None,
+        snapshot_encryption_key_path: None,
+        snapshot_s3_bucket: None,
+        snapshot_s3_endpoint: "https://s3.amazonaws.com".to_string(),
+        snapshot_s3_region: "us-east-1".to_string(),
+        snapshot_s3_upload_retries: 3,
         import_dump: None,
         indexer_options: IndexerOpts::default(),
         #[cfg(all(not(debug_assertions), feature = "sentry"))]
@@ -1,8 +1,14 @@
-use actix_web::{http::StatusCode, test};
+use actix_web::dev::Service as _;
+use actix_web::http::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
+use actix_web::http::{HeaderMap, Method, StatusCode};
+use actix_web::test;
+use meilisearch_error::ErrorCode;
 use serde_json::Value;
 
 use meilisearch_http::create_app;
 use meilisearch_http::data::Data;
+use meilisearch_http::error::ResponseError;
+use meilisearch_http::helpers::Clock;
 
 pub struct Service(pub Data);
 
@@ -22,6 +28,80 @@ impl Service {
         (response, status_code)
     }
 
+    /// Send a test post request, authenticated with the given API key if any. The authentication
+    /// errors returned by the middleware are converted to responses.
+    pub async fn post_with_api_key(
+        &self,
+        url: impl AsRef<str>,
+        body: Value,
+        api_key: Option<&str>,
+    ) -> (Value, StatusCode) {
+        self.post_with_api_key_at(url, body, api_key, Clock::system())
+            .await
+    }
+
+    /// Same as `post_with_api_key`, the expiration of the key is checked against `clock`.
+    pub async fn post_with_api_key_at(
+        &self,
+        url: impl AsRef<str>,
+        body: Value,
+        api_key: Option<&str>,
+        clock: Clock,
+    ) -> (Value, StatusCode) {
+        let req = test::TestRequest::post().uri(url.as_ref()).set_json(&body);
+        self.call_with_api_key(req, api_key, clock).await
+    }
+
+    /// Send a test get request, authenticated with the given API key if any.
+    pub async fn get_with_api_key(
+        &self,
+        url: impl AsRef<str>,
+        api_key: Option<&str>,
+    ) -> (Value, StatusCode) {
+        let req = test::TestRequest::get().uri(url.as_ref());
+        self.call_with_api_key(req, api_key, Clock::system()).await
+    }
+
+    /// Send a test get request with the given header, the authentication errors returned by the
+    /// middleware are converted to responses.
+    pub async fn get_with_header(
+        &self,
+        url: impl AsRef<str>,
+        header: (&str, &str),
+    ) -> (Value, StatusCode) {
+        let req = test::TestRequest::get()
+            .uri(url.as_ref())
+            .insert_header(header);
+        self.call_with_api_key(req, None, Clock::system()).await
+    }
+
+    async fn call_with_api_key(
+        &self,
+        mut req: test::TestRequest,
+        api_key: Option<&str>,
+        clock: Clock,
+    ) -> (Value, StatusCode) {
+        let mut app = test::init_service(create_app!(&self.0, true).app_data(clock)).await;
+
+        if let Some(api_key) = api_key {
+            req = req.insert_header(("X-Meili-API-Key", api_key));
+        }
+        match app.call(req.to_request()).await {
+            Ok(res) => {
+                let status_code = res.status();
+                let body = test::read_body(res).await;
+                let response = serde_json::from_slice(&body).unwrap_or_default();
+                (response, status_code)
+            }
+            Err(e) => {
+                let error = e
+                    .as_error::<ResponseError>()
+                    .expect("unexpected middleware error");
+                (serde_json::to_value(error).unwrap(), error.http_status())
+            }
+        }
+    }
+
     /// Send a test post request from a text body, with a `content-type:application/json` header.
     pub async fn post_str(
         &self,
@@ -43,6 +123,45 @@ impl Service {
         (response, status_code)
     }
 
+    /// Send a test post request from a text body, with the given `content-type` header.
+    pub async fn post_with_content_type(
+        &self,
+        url: impl AsRef<str>,
+        body: impl AsRef<str>,
+        content_type: &str,
+    ) -> (Value, StatusCode) {
+        let mut app = test::init_service(create_app!(&self.0, true)).await;
+
+        let req = test::TestRequest::post()
+            .uri(url.as_ref())
+            .set_payload(body.as_ref().to_string())
+            .insert_header(("content-type", content_type))
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        let status_code = res.status();
+
+        let body = test::read_body(res).await;
+        let response = serde_json::from_slice(&body).unwrap_or_default();
+        (response, status_code)
+    }
+
+    /// Send a test post request with a binary body.
+    pub async fn post_bytes(&self, url: impl AsRef<str>, body: Vec<u8>) -> (Value, StatusCode) {
+        let mut app = test::init_service(create_app!(&self.0, true)).await;
+
+        let req = test::TestRequest::post()
+            .uri(url.as_ref())
+            .set_payload(body)
+            .insert_header(("content-type", "application/gzip"))
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        let status_code = res.status();
+
+        let body = test::read_body(res).await;
+        let response = serde_json::from_slice(&body).unwrap_or_default();
+        (response, status_code)
+    }
+
     pub async fn get(&self, url: impl AsRef<str>) -> (Value, StatusCode) {
         let mut app = test::init_service(create_app!(&self.0, true)).await;
 
@@ -55,6 +174,127 @@ impl Service {
         (response, status_code)
     }
 
+    /// Send a test get request and returns the raw body, for the responses that are not JSON.
+    pub async fn get_raw(&self, url: impl AsRef<str>) -> (String, StatusCode) {
+        let mut app = test::init_service(create_app!(&self.0, true)).await;
+
+        let req = test::TestRequest::get().uri(url.as_ref()).to_request();
+        let res = test::call_service(&mut app, req).await;
+        let status_code = res.status();
+
+        let body = test::read_body(res).await;
+        let response = String::from_utf8_lossy(&body).into_owned();
+        (response, status_code)
+    }
+
+    /// Send a test get request and returns the raw bytes of the body, for the binary responses.
+    pub async fn get_bytes(&self, url: impl AsRef<str>) -> (Vec<u8>, StatusCode) {
+        let mut app = test::init_service(create_app!(&self.0, true)).await;
+
+        let req = test::TestRequest::get().uri(url.as_ref()).to_request();
+        let res = test::call_service(&mut app, req).await;
+        let status_code = res.status();
+
+        let body = test::read_body(res).await;
+        (body.to_vec(), status_code)
+    }
+
+    /// Send a test post request accepting the given encoding, the raw body is returned along with
+    /// the encoding of the response, if any.
+    pub async fn post_accept_encoding(
+        &self,
+        url: impl AsRef<str>,
+        body: Value,
+        encoding: &str,
+    ) -> (Vec<u8>, Option<String>, StatusCode) {
+        let mut app = test::init_service(create_app!(&self.0, true)).await;
+
+        let req = test::TestRequest::post()
+            .uri(url.as_ref())
+            .set_json(&body)
+            .insert_header((ACCEPT_ENCODING, encoding))
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        let status_code = res.status();
+        let content_encoding = res
+            .headers()
+            .get(CONTENT_ENCODING)
+            .map(|value| value.to_str().unwrap().to_string());
+
+        let body = test::read_body(res).await;
+        (body.to_vec(), content_encoding, status_code)
+    }
+
+    /// Same as `post_accept_encoding` for a get request.
+    pub async fn get_accept_encoding(
+        &self,
+        url: impl AsRef<str>,
+        encoding: &str,
+    ) -> (Vec<u8>, Option<String>, StatusCode) {
+        let mut app = test::init_service(create_app!(&self.0, true)).await;
+
+        let req = test::TestRequest::get()
+            .uri(url.as_ref())
+            .insert_header((ACCEPT_ENCODING, encoding))
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        let status_code = res.status();
+        let content_encoding = res
+            .headers()
+            .get(CONTENT_ENCODING)
+            .map(|value| value.to_str().unwrap().to_string());
+
+        let body = test::read_body(res).await;
+        (body.to_vec(), content_encoding, status_code)
+    }
+
+    /// Send a test get request with the given headers, the headers of the response are returned
+    /// along with its body.
+    pub async fn get_with_headers(
+        &self,
+        url: impl AsRef<str>,
+        headers: &[(&str, &str)],
+    ) -> (Value, HeaderMap, StatusCode) {
+        let mut app = test::init_service(create_app!(&self.0, true)).await;
+
+        let mut req = test::TestRequest::get().uri(url.as_ref());
+        for header in headers {
+            req = req.insert_header(*header);
+        }
+        let res = test::call_service(&mut app, req.to_request()).await;
+        let status_code = res.status();
+        let headers = res.headers().clone();
+
+        let body = test::read_body(res).await;
+        let response = serde_json::from_slice(&body).unwrap_or_default();
+        (response, headers, status_code)
+    }
+
+    /// Send a test request with the given headers, only the status and the headers of the
+    /// response are returned. The errors returned by the middlewares are converted to responses.
+    pub async fn request_headers(
+        &self,
+        method: Method,
+        url: impl AsRef<str>,
+        headers: &[(&str, &str)],
+    ) -> (HeaderMap, StatusCode) {
+        let mut app = test::init_service(create_app!(&self.0, true)).await;
+
+        let mut req = test::TestRequest::default()
+            .method(method)
+            .uri(url.as_ref());
+        for header in headers {
+            req = req.insert_header(*header);
+        }
+        match app.call(req.to_request()).await {
+            Ok(res) => (res.headers().clone(), res.status()),
+            Err(e) => {
+                let res = e.as_response_error().error_response();
+                (res.headers().clone(), res.status())
+            }
+        }
+    }
+
     pub async fn put(&self, url: impl AsRef<str>, body: Value) -> (Value, StatusCode) {
         let mut app = test::init_service(create_app!(&self.0, true)).await;
 
@@ -70,6 +310,36 @@ impl Service {
         (response, status_code)
     }
 
+    pub async fn patch(&self, url: impl AsRef<str>, body: Value) -> (Value, StatusCode) {
+        let mut app = test::init_service(create_app!(&self.0, true)).await;
+
+        let req = test::TestRequest::patch()
+            .uri(url.as_ref())
+            .set_json(&body)
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        let status_code = res.status();
+
+        let body = test::read_body(res).await;
+        let response = serde_json::from_slice(&body).unwrap_or_default();
+        (response, status_code)
+    }
+
+    /// Sends a `HEAD` request, the raw body is returned as it is expected to be empty.
+    pub async fn head(&self, url: impl AsRef<str>) -> (Vec<u8>, StatusCode) {
+        let mut app = test::init_service(create_app!(&self.0, true)).await;
+
+        let req = test::TestRequest::default()
+            .method(Method::HEAD)
+            .uri(url.as_ref())
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        let status_code = res.status();
+
+        let body = test::read_body(res).await;
+        (body.to_vec(), status_code)
+    }
+
     pub async fn delete(&self, url: impl AsRef<str>) -> (Value, StatusCode) {
         let mut app = test::init_service(create_app!(&self.0, true)).await;
 
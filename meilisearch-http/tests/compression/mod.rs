@@ -0,0 +1,81 @@
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+use serde_json::{json, Value};
+use tar::Archive;
+
+use meilisearch_http::Opt;
+
+use crate::common::server::default_settings;
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn search_response_is_compressed() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .add_documents(json!([{ "id": 1, "content": "hello world" }]), None)
+        .await;
+    index.wait_update_id(0).await;
+
+    let (body, encoding, code) = server
+        .service
+        .post_accept_encoding("/indexes/test/search", json!({ "q": "hello" }), "gzip")
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(encoding.as_deref(), Some("gzip"));
+
+    let mut decoded = String::new();
+    GzDecoder::new(body.as_slice())
+        .read_to_string(&mut decoded)
+        .unwrap();
+    let response: Value = serde_json::from_str(&decoded).unwrap();
+    assert_eq!(response["hits"][0]["id"], 1);
+}
+
+#[actix_rt::test]
+async fn export_is_not_compressed_twice() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .add_documents(json!([{ "id": 1, "content": "hello world" }]), None)
+        .await;
+    index.wait_update_id(0).await;
+
+    let (body, encoding, code) = server
+        .service
+        .get_accept_encoding("/indexes/test/export", "gzip")
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(encoding, None);
+
+    // the body is the gzipped tarball itself
+    let export = tempfile::tempdir_in(".").unwrap();
+    Archive::new(GzDecoder::new(body.as_slice()))
+        .unpack(export.path())
+        .unwrap();
+    let documents = std::fs::read_to_string(export.path().join("documents.jsonl")).unwrap();
+    let document: Value = serde_json::from_str(documents.lines().next().unwrap()).unwrap();
+    assert_eq!(document, json!({ "id": 1, "content": "hello world" }));
+}
+
+#[actix_rt::test]
+async fn responses_are_not_compressed_when_disabled() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let options = Opt {
+        no_response_compression: true,
+        ..default_settings(dir.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+    index.create(None).await;
+
+    let (body, encoding, code) = server
+        .service
+        .post_accept_encoding("/indexes/test/search", json!({ "q": "hello" }), "gzip")
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(encoding, None);
+    let response: Value = serde_json::from_slice(&body).unwrap();
+    assert!(response["hits"].as_array().unwrap().is_empty());
+}
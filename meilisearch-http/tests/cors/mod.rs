@@ -0,0 +1,75 @@
+use actix_web::http::header::ACCESS_CONTROL_ALLOW_ORIGIN;
+use actix_web::http::Method;
+
+use meilisearch_http::Opt;
+
+use crate::common::server::default_settings;
+use crate::common::Server;
+
+async fn server_with_origins(dir: &tempfile::TempDir, origins: Option<&str>, env: &str) -> Server {
+    let options = Opt {
+        cors_allowed_origins: origins.map(String::from),
+        env: env.to_string(),
+        ..default_settings(dir.path())
+    };
+    Server::new_with_options(options).await
+}
+
+#[actix_rt::test]
+async fn allowed_origin() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let server = server_with_origins(
+        &dir,
+        Some("https://example.com, https://other.com"),
+        "development",
+    )
+    .await;
+
+    let (headers, code) = server
+        .service
+        .request_headers(Method::GET, "/health", &[("Origin", "https://other.com")])
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "https://other.com");
+
+    // The preflight requests are answered without reaching the routes.
+    let (headers, code) = server
+        .service
+        .request_headers(
+            Method::OPTIONS,
+            "/indexes/test/search",
+            &[
+                ("Origin", "https://example.com"),
+                ("Access-Control-Request-Method", "POST"),
+            ],
+        )
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "https://example.com");
+
+    let (headers, _code) = server
+        .service
+        .request_headers(Method::GET, "/health", &[("Origin", "https://evil.com")])
+        .await;
+    assert!(headers.get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+}
+
+#[actix_rt::test]
+async fn default_origins() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let server = server_with_origins(&dir, None, "development").await;
+    let (headers, code) = server
+        .service
+        .request_headers(Method::GET, "/health", &[("Origin", "https://example.com")])
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let server = server_with_origins(&dir, None, "production").await;
+    let (headers, _code) = server
+        .service
+        .request_headers(Method::GET, "/health", &[("Origin", "https://example.com")])
+        .await;
+    assert!(headers.get(ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+}
@@ -1,6 +1,12 @@
+use std::time::Duration;
+
+use byte_unit::Byte;
 use chrono::DateTime;
 use serde_json::{json, Value};
 
+use meilisearch_http::Opt;
+
+use crate::common::server::default_settings;
 use crate::common::{GetAllDocumentsOptions, Server};
 
 #[actix_rt::test]
@@ -50,6 +56,54 @@ async fn add_documents_no_index_creation() {
     assert_eq!(response["primaryKey"], "id");
 }
 
+#[actix_rt::test]
+async fn add_documents_reports_index_creation() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = json!([{ "sku": "a1", "content": "foo" }]);
+    let (response, code) = index.add_documents(documents, Some("sku")).await;
+    assert_eq!(code, 202);
+    assert_eq!(response["updateId"], 0);
+    assert_eq!(response["indexCreated"], true);
+    assert_eq!(response["primaryKey"], "sku");
+    index.wait_update_id(0).await;
+
+    // the primary key of the existing index takes precedence over the given one
+    let documents = json!([{ "sku": "a2", "id": 1 }]);
+    let (response, code) = index.add_documents(documents, Some("id")).await;
+    assert_eq!(code, 202);
+    assert_eq!(response["indexCreated"], false);
+    assert_eq!(response["primaryKey"], "sku");
+}
+
+#[actix_rt::test]
+async fn add_documents_without_primary_key_reports_inference() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let (response, code) = index
+        .add_documents(json!([{ "id": 1, "content": "foo" }]), None)
+        .await;
+    assert_eq!(code, 202);
+    assert_eq!(response["indexCreated"], true);
+    // the primary key is inferred once the documents are indexed, it is reported by the update
+    assert_eq!(response["primaryKey"], Value::Null);
+    let response = index.wait_update_id(0).await;
+    assert_eq!(response["status"], "processed");
+    assert_eq!(
+        response["success"]["DocumentsAddition"]["primary_key"],
+        "id"
+    );
+
+    let (response, code) = index
+        .add_documents(json!([{ "id": 2, "content": "bar" }]), None)
+        .await;
+    assert_eq!(code, 202);
+    assert_eq!(response["indexCreated"], false);
+    assert_eq!(response["primaryKey"], "id");
+}
+
 #[actix_rt::test]
 async fn document_add_create_index_bad_uid() {
     let server = Server::new().await;
@@ -277,6 +331,62 @@ async fn update_document() {
     );
 }
 
+#[actix_rt::test]
+async fn patch_document_keeps_untouched_fields() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents = json!([
+        {
+            "id": 1,
+            "title": "foo",
+            "content": "bar",
+        }
+    ]);
+    let (_response, code) = index.add_documents(documents, None).await;
+    assert_eq!(code, 202);
+    index.wait_update_id(0).await;
+
+    let documents = json!([
+        {
+            "id": 1,
+            "title": "baz",
+        }
+    ]);
+    let (_response, code) = index.patch_documents(documents, None).await;
+    assert_eq!(code, 202);
+    index.wait_update_id(1).await;
+
+    let (response, code) = index.get_update(1).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["status"], "processed");
+
+    let (response, code) = index.get_document(1, None).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["title"], "baz");
+    assert_eq!(response["content"], "bar");
+}
+
+#[actix_rt::test]
+async fn patch_unexisting_document_inserts_it() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let (_response, code) = index
+        .patch_documents(json!([{ "id": 1, "title": "foo" }]), Some("id"))
+        .await;
+    assert_eq!(code, 202);
+    index.wait_update_id(0).await;
+
+    let (response, code) = index.get_update(0).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["status"], "processed");
+
+    let (response, code) = index.get_document(1, None).await;
+    assert_eq!(code, 200);
+    assert_eq!(response, json!({ "id": 1, "title": "foo" }));
+}
+
 #[actix_rt::test]
 async fn add_larger_dataset() {
     let server = Server::new().await;
@@ -352,3 +462,400 @@ async fn update_documents_bad_primary_key() {
     assert_eq!(code, 200);
     assert_eq!(response["status"], "failed");
 }
+
+#[actix_rt::test]
+async fn strict_add_documents_with_colliding_id() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(Some("id")).await;
+    index
+        .add_documents(json!([{ "id": 1, "content": "foo" }]), None)
+        .await;
+    index.wait_update_id(0).await;
+
+    let documents = json!([
+        { "id": 2, "content": "bar" },
+        { "id": 1, "content": "baz" },
+    ]);
+    let url = format!("/indexes/{}/documents?strict=true", index.uid);
+    let (_response, code) = server.service.post(url, documents).await;
+    assert_eq!(code, 202);
+    index.wait_update_id(1).await;
+
+    let (response, code) = index.get_update(1).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["status"], "failed");
+    assert_eq!(
+        response["error"],
+        "Documents with ids [\"1\"] already exist."
+    );
+
+    // none of the documents of the failed update were added
+    let (response, code) = index.get_document(2, None).await;
+    assert_eq!(code, 400, "{}", response);
+    let (response, code) = index.get_document(1, None).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["content"], "foo");
+}
+
+#[actix_rt::test]
+async fn strict_add_new_documents() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(Some("id")).await;
+    index
+        .add_documents(json!([{ "id": 1, "content": "foo" }]), None)
+        .await;
+    index.wait_update_id(0).await;
+
+    let url = format!("/indexes/{}/documents?strict=true", index.uid);
+    let (_response, code) = server
+        .service
+        .post(url, json!([{ "id": 2, "content": "bar" }]))
+        .await;
+    assert_eq!(code, 202);
+    index.wait_update_id(1).await;
+
+    let (response, code) = index.get_update(1).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["status"], "processed");
+}
+
+#[actix_rt::test]
+async fn add_documents_ndjson() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = "{\"id\": 1, \"content\": \"foo\"}\n{\"id\": 2, \"content\": \"bar\"}\n";
+    let url = format!("/indexes/{}/documents", index.uid);
+    let (_response, code) = server
+        .service
+        .post_with_content_type(url, documents, "application/x-ndjson")
+        .await;
+    assert_eq!(code, 202);
+    index.wait_update_id(0).await;
+
+    let (response, code) = index.get_update(0).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["status"], "processed");
+    assert_eq!(response["success"]["DocumentsAddition"]["nb_documents"], 2);
+    let (response, code) = index.get_document(2, None).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["content"], "bar");
+}
+
+#[actix_rt::test]
+async fn add_documents_csv() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = "id,content\n1,foo\n2,bar\n";
+    let url = format!("/indexes/{}/documents", index.uid);
+    let (_response, code) = server
+        .service
+        .post_with_content_type(url, documents, "text/csv")
+        .await;
+    assert_eq!(code, 202);
+    index.wait_update_id(0).await;
+
+    let (response, code) = index.get_update(0).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["status"], "processed");
+    assert_eq!(response["success"]["DocumentsAddition"]["nb_documents"], 2);
+    let (response, code) = index.get_document(1, None).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["content"], "foo");
+}
+
+#[actix_rt::test]
+async fn add_documents_json_with_charset() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = r#"[{"id": 1, "content": "foo"}]"#;
+    let url = format!("/indexes/{}/documents", index.uid);
+    let (_response, code) = server
+        .service
+        .post_with_content_type(url, documents, "application/json; charset=utf-8")
+        .await;
+    assert_eq!(code, 202);
+    index.wait_update_id(0).await;
+
+    let (response, code) = index.get_update(0).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["status"], "processed");
+    assert_eq!(response["success"]["DocumentsAddition"]["nb_documents"], 1);
+}
+
+#[actix_rt::test]
+async fn add_documents_format_query_parameter() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = "id,content\n1,foo\n";
+    let url = format!("/indexes/{}/documents?format=csv", index.uid);
+    let (_response, code) = server
+        .service
+        .post_with_content_type(url, documents, "text/plain")
+        .await;
+    assert_eq!(code, 202);
+    index.wait_update_id(0).await;
+
+    let (response, code) = index.get_update(0).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["status"], "processed");
+    assert_eq!(response["success"]["DocumentsAddition"]["nb_documents"], 1);
+}
+
+#[actix_rt::test]
+async fn add_documents_unknown_content_type() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let url = format!("/indexes/{}/documents", index.uid);
+    // curl sends the payloads of `-d` as a form by default
+    let content_types = [
+        "application/x-www-form-urlencoded",
+        "application/json; charset=utf-8",
+        "application/vnd.api+json",
+    ];
+    for (update_id, content_type) in content_types.iter().enumerate() {
+        let documents = format!("[{{\"id\": {}, \"content\": \"foo\"}}]", update_id);
+        let (response, code) = server
+            .service
+            .post_with_content_type(&url, documents, content_type)
+            .await;
+        assert_eq!(code, 202, "{}: {}", content_type, response);
+        let response = index.wait_update_id(update_id as u64).await;
+        assert_eq!(response["status"], "processed", "{}", content_type);
+    }
+
+    let url = format!("/indexes/{}/documents?format=csv", index.uid);
+    let (response, code) = server
+        .service
+        .post_with_content_type(
+            url,
+            "id,content\n3,bar\n",
+            "application/x-www-form-urlencoded",
+        )
+        .await;
+    assert_eq!(code, 202, "{}", response);
+    let response = index.wait_update_id(3).await;
+    assert_eq!(response["status"], "processed");
+    let (response, code) = index.get_document(3, None).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["content"], "bar");
+}
+
+#[actix_rt::test]
+async fn add_documents_csv_with_delimiter() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = "id;content;price\n1;foo, bar;3,5\n2;\"baz;qux\";4\n";
+    let url = format!("/indexes/{}/documents?csvDelimiter=%3B", index.uid);
+    let (_response, code) = server
+        .service
+        .post_with_content_type(url, documents, "text/csv")
+        .await;
+    assert_eq!(code, 202);
+    index.wait_update_id(0).await;
+
+    let (response, code) = index.get_update(0).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["status"], "processed");
+    assert_eq!(response["success"]["DocumentsAddition"]["nb_documents"], 2);
+    let (response, code) = index.get_document(1, None).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["content"], "foo, bar");
+    assert_eq!(response["price"], "3,5");
+    let (response, code) = index.get_document(2, None).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["content"], "baz;qux");
+}
+
+#[actix_rt::test]
+async fn add_documents_csv_with_multibyte_delimiter() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let url = format!("/indexes/{}/documents?csvDelimiter=%C2%A7", index.uid);
+    let (response, code) = server
+        .service
+        .post_with_content_type(url, "id§content\n1§foo\n", "text/csv")
+        .await;
+    assert_eq!(code, 400);
+    assert_eq!(
+        response["message"],
+        "Url parameter csvDelimiter error: the delimiter must be a single byte character"
+    );
+}
+
+#[actix_rt::test]
+async fn add_documents_payload_too_large() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let options = Opt {
+        http_payload_size_limit: Byte::from_bytes(1024),
+        ..default_settings(dir.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+
+    let documents: Vec<_> = (0..100)
+        .map(|id| json!({ "id": id, "content": "some content to fill the payload" }))
+        .collect();
+    let (response, code) = index.add_documents(json!(documents), None).await;
+    assert_eq!(code, 413);
+    assert!(response["error"].as_str().unwrap().contains("1024"));
+
+    // Nothing was registered, not even the index.
+    let (_response, code) = index.get().await;
+    assert_eq!(code, 400);
+}
+
+/// Documents taking several MiB once indexed.
+fn large_documents() -> Value {
+    let documents: Vec<_> = (0..2000)
+        .map(|id| {
+            let words: Vec<_> = (0..100).map(|i| format!("word{}", id * 100 + i)).collect();
+            json!({ "id": id, "content": words.join(" ") })
+        })
+        .collect();
+    json!(documents)
+}
+
+#[actix_rt::test]
+async fn add_documents_grows_full_index() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let options = Opt {
+        max_mdb_size: Byte::from_bytes(1024 * 1024),
+        max_index_map_size: Byte::from_bytes(512 * 1024 * 1024),
+        ..default_settings(dir.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+
+    let (response, code) = index.add_documents(large_documents(), Some("id")).await;
+    assert_eq!(code, 202);
+    let update = index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    assert_eq!(update["status"], "processed", "{}", update);
+
+    let (response, _code) = index.stats().await;
+    assert_eq!(response["numberOfDocuments"], 2000);
+    let (response, _code) = index.get().await;
+    let mapped = response["size"]["mapped"].as_u64().unwrap();
+    assert!(mapped > 1024 * 1024, "{}", mapped);
+    assert!(mapped.is_power_of_two(), "{}", mapped);
+}
+
+#[actix_rt::test]
+async fn add_documents_to_index_at_maximum_size() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let options = Opt {
+        max_mdb_size: Byte::from_bytes(1024 * 1024),
+        max_index_map_size: Byte::from_bytes(2 * 1024 * 1024),
+        ..default_settings(dir.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+
+    let (response, code) = index.add_documents(large_documents(), Some("id")).await;
+    assert_eq!(code, 202);
+    let update = index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    assert_eq!(update["status"], "failed");
+    assert_eq!(
+        update["error"],
+        "The index reached the maximum size of 2097152 bytes."
+    );
+}
+
+#[actix_rt::test]
+async fn add_documents_read_only() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let server = Server::new_with_options(default_settings(dir.path())).await;
+    let index = server.index("test");
+    let (_response, code) = index
+        .add_documents(json!([{ "id": 1, "content": "foo" }]), Some("id"))
+        .await;
+    assert_eq!(code, 202);
+    index.wait_update_id(0).await;
+    assert!(server.shutdown(Duration::from_secs(60)).await);
+    drop(server);
+
+    let options = Opt {
+        read_only: true,
+        ..default_settings(dir.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+
+    let (response, code) = index
+        .add_documents(json!([{ "id": 2, "content": "bar" }]), None)
+        .await;
+    assert_eq!(code, 403);
+    assert_eq!(
+        response["error"],
+        "The server is read-only, the indexes can't be modified."
+    );
+    let (_response, code) = index.update_settings(json!({ "stopWords": ["a"] })).await;
+    assert_eq!(code, 403);
+    let (_response, code) = server.index("other").create(None).await;
+    assert_eq!(code, 403);
+
+    let (response, code) = index.search(json!({ "q": "foo" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+    let (response, _code) = index.stats().await;
+    assert_eq!(response["numberOfDocuments"], 1);
+}
+
+#[actix_rt::test]
+async fn strict_add_documents_with_duplicate_ids() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(Some("id")).await;
+
+    let documents = json!([
+        { "id": 1, "content": "foo" },
+        { "id": 2, "content": "bar" },
+        { "id": 1, "content": "baz" },
+    ]);
+    let url = format!("/indexes/{}/documents?strict=true", index.uid);
+    let (_response, code) = server.service.post(url, documents).await;
+    assert_eq!(code, 202);
+    index.wait_update_id(0).await;
+
+    let (response, code) = index.get_update(0).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["status"], "failed");
+    assert_eq!(
+        response["error"],
+        "Documents with ids [\"1\"] appear several times in the payload."
+    );
+
+    // none of the documents of the failed update were added
+    let (response, code) = index.get_document(1, None).await;
+    assert_eq!(code, 400, "{}", response);
+    let (response, code) = index.get_document(2, None).await;
+    assert_eq!(code, 400, "{}", response);
+}
+
+#[actix_rt::test]
+async fn json_payload_limit_does_not_apply_to_documents() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let options = Opt {
+        http_json_payload_size_limit: Byte::from_bytes(1024),
+        ..default_settings(dir.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+
+    let documents: Vec<_> = (0..100)
+        .map(|id| json!({ "id": id, "content": "some content to fill the payload" }))
+        .collect();
+    let (_response, code) = index.add_documents(json!(documents), None).await;
+    assert_eq!(code, 202);
+
+    let stop_words: Vec<_> = (0..200).map(|i| format!("word{}", i)).collect();
+    let (_response, code) = index
+        .update_settings(json!({ "stopWords": stop_words }))
+        .await;
+    assert_eq!(code, 413);
+}
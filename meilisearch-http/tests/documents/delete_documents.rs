@@ -36,6 +36,28 @@ async fn delete_one_document() {
     assert_eq!(code, 400);
 }
 
+#[actix_rt::test]
+async fn document_count_after_addition_and_deletion() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .add_documents(
+            json!([
+                { "id": 0, "content": "foo" },
+                { "id": 1, "content": "bar" },
+                { "id": 2, "content": "baz" },
+            ]),
+            None,
+        )
+        .await;
+    index.wait_update_id(0).await;
+    assert_eq!(index.document_count().await.unwrap(), 3);
+
+    index.delete_document(1).await;
+    index.wait_update_id(1).await;
+    assert_eq!(index.document_count().await.unwrap(), 2);
+}
+
 #[actix_rt::test]
 async fn clear_all_documents_unexisting_index() {
     let server = Server::new().await;
@@ -123,3 +145,61 @@ async fn delete_no_document_batch() {
     assert_eq!(code, 200);
     assert_eq!(response.as_array().unwrap().len(), 3);
 }
+
+#[actix_rt::test]
+async fn delete_documents_by_numeric_range_filter() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .update_settings(json!({ "attributesForFaceting": { "price": "integer" } }))
+        .await;
+    index.wait_update_id(0).await;
+    let documents = json!([
+        { "id": 1, "price": 5 },
+        { "id": 2, "price": 15 },
+        { "id": 3, "price": 25 },
+        { "id": 4, "price": 35 },
+    ]);
+    index.add_documents(documents, Some("id")).await;
+    index.wait_update_id(1).await;
+
+    let (_response, code) = index.delete_by_filter("price > 10 AND price < 30").await;
+    assert_eq!(code, 202);
+    let response = index.wait_update_id(2).await;
+    assert_eq!(response["status"], "processed");
+    assert_eq!(response["success"]["DocumentDeletion"]["deleted"], 2);
+
+    let (response, code) = index
+        .get_all_documents(GetAllDocumentsOptions::default())
+        .await;
+    assert_eq!(code, 200);
+    let ids: Vec<_> = response
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|document| document["id"].as_u64().unwrap())
+        .collect();
+    assert_eq!(ids, vec![1, 4]);
+}
+
+#[actix_rt::test]
+async fn delete_documents_by_invalid_filter() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .add_documents(json!([{ "id": 1, "price": 5 }]), Some("id"))
+        .await;
+    index.wait_update_id(0).await;
+
+    let (_response, code) = index.delete_by_filter("price >").await;
+    assert_eq!(code, 202);
+    let response = index.wait_update_id(1).await;
+    assert_eq!(response["status"], "failed");
+    assert!(response["error"].is_string());
+
+    let (response, code) = index
+        .get_all_documents(GetAllDocumentsOptions::default())
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(response.as_array().unwrap().len(), 1);
+}
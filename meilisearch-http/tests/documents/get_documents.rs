@@ -1,7 +1,7 @@
 use crate::common::GetAllDocumentsOptions;
 use crate::common::Server;
 
-use serde_json::json;
+use serde_json::{json, Value};
 
 // TODO: partial test since we are testing error, amd error is not yet fully implemented in
 // transplant
@@ -46,6 +46,23 @@ async fn get_document() {
     );
 }
 
+#[actix_rt::test]
+async fn document_exists() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = json!([{ "id": 0, "content": "foobar" }]);
+    index.add_documents(documents, None).await;
+    index.wait_update_id(0).await;
+
+    let (body, code) = index.document_exists(0).await;
+    assert_eq!(code, 200);
+    assert!(body.is_empty());
+
+    let (body, code) = index.document_exists(1).await;
+    assert_eq!(code, 404);
+    assert!(body.is_empty());
+}
+
 #[actix_rt::test]
 async fn get_unexisting_index_all_documents() {
     let server = Server::new().await;
@@ -120,6 +137,39 @@ async fn test_get_all_documents_limit() {
     assert_eq!(response.as_array().unwrap()[0]["id"], 0);
 }
 
+#[actix_rt::test]
+async fn test_get_all_documents_limit_exceeds_max() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.load_test_set().await;
+
+    let (response, code) = index
+        .get_all_documents(GetAllDocumentsOptions {
+            limit: Some(1001),
+            ..Default::default()
+        })
+        .await;
+    assert_eq!(code, 400);
+    assert!(response["error"].is_string());
+}
+
+#[actix_rt::test]
+async fn test_get_documents_by_ids() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.load_test_set().await;
+
+    let (response, code) = index
+        .get_all_documents(GetAllDocumentsOptions {
+            ids: Some(vec!["3", "999", "1"]),
+            attributes_to_retrieve: Some(vec!["id"]),
+            ..Default::default()
+        })
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(response, json!([{ "id": 3 }, null, { "id": 1 }]));
+}
+
 #[actix_rt::test]
 async fn test_get_all_documents_offset() {
     let server = Server::new().await;
@@ -233,3 +283,89 @@ async fn get_documents_displayed_attributes() {
     assert_eq!(response.as_object().unwrap().keys().count(), 1);
     assert!(response.as_object().unwrap().get("gender").is_some());
 }
+
+#[actix_rt::test]
+async fn export_documents_as_ndjson() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents: Vec<_> = (0..300)
+        .map(|id| json!({ "id": id, "content": format!("document {}", id) }))
+        .collect();
+    index.add_documents(json!(documents), None).await;
+    index.wait_update_id(0).await;
+
+    let (body, code) = index.export_documents().await;
+    assert_eq!(code, 200);
+    let lines: Vec<Value> = body
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 300);
+    let mut ids: Vec<_> = lines
+        .iter()
+        .map(|doc| doc["id"].as_u64().unwrap())
+        .collect();
+    ids.sort_unstable();
+    assert_eq!(ids, (0..300).collect::<Vec<_>>());
+}
+
+#[actix_rt::test]
+async fn export_documents_unknown_format() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+
+    let (response, code) = server
+        .service
+        .get("/indexes/test/documents?format=xml")
+        .await;
+    assert_eq!(code, 400);
+    assert!(response["error"].as_str().unwrap().contains("format"));
+}
+
+#[actix_rt::test]
+async fn export_documents_with_pagination() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+
+    for query in &["ids=1,2", "offset=10", "limit=10"] {
+        let url = format!("/indexes/test/documents?format=ndjson&{}", query);
+        let (response, code) = server.service.get(url).await;
+        assert_eq!(code, 400, "{}", query);
+        assert!(response["error"].as_str().unwrap().contains("ndjson"));
+    }
+}
+
+/// Returns the number of documents listed at `url` along with the `Content-Range` header.
+async fn content_range(server: &Server, url: &str) -> (usize, String) {
+    let (response, headers, code) = server.service.get_with_headers(url, &[]).await;
+    assert_eq!(code, 200, "{}", response);
+    let content_range = headers["content-range"].to_str().unwrap().to_string();
+    (response.as_array().unwrap().len(), content_range)
+}
+
+#[actix_rt::test]
+async fn get_documents_content_range() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents: Vec<_> = (0..50).map(|id| json!({ "id": id })).collect();
+    index.add_documents(json!(documents), None).await;
+    index.wait_update_id(0).await;
+
+    let (len, range) = content_range(&server, "/indexes/test/documents?offset=10&limit=15").await;
+    assert_eq!(len, 15);
+    assert_eq!(range, "documents 10-24/50");
+
+    let (len, range) = content_range(&server, "/indexes/test/documents").await;
+    assert_eq!(len, 20);
+    assert_eq!(range, "documents 0-19/50");
+
+    let (len, range) = content_range(&server, "/indexes/test/documents?offset=40&limit=20").await;
+    assert_eq!(len, 10);
+    assert_eq!(range, "documents 40-49/50");
+
+    let (len, range) = content_range(&server, "/indexes/test/documents?offset=60").await;
+    assert_eq!(len, 0);
+    assert_eq!(range, "documents */50");
+}
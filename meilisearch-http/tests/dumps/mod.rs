@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+use serde_json::json;
+use tokio::time::sleep;
+
+use crate::common::index::Index;
+use crate::common::server::default_settings;
+use crate::common::{GetAllDocumentsOptions, Server};
+
+/// Creates a dump and waits for it to be done.
+async fn dump(server: &Server) -> String {
+    // only one dump can be in progress at once, the tests may run concurrently
+    let mut created = server.create_dump().await;
+    for _ in 0..10 {
+        if created.1 == 202 {
+            break;
+        }
+        sleep(Duration::from_secs(1)).await;
+        created = server.create_dump().await;
+    }
+    let (response, code) = created;
+    assert_eq!(code, 202, "{}", response);
+    let uid = response["uid"].as_str().unwrap().to_string();
+
+    // try 10 times to get the dump status, or panic to not wait forever
+    for _ in 0..10 {
+        let (response, _code) = server.dump_status(&uid).await;
+        if response["status"] == "done" {
+            return uid;
+        }
+        sleep(Duration::from_secs(1)).await;
+    }
+
+    panic!("Timeout waiting for dump");
+}
+
+async fn wait_last_update(index: &Index<'_>) {
+    let (response, code) = index.list_updates().await;
+    assert_eq!(code, 200);
+    let update = index
+        .wait_update_id(response[0]["updateId"].as_u64().unwrap())
+        .await;
+    assert_eq!(update["status"], "processed", "{}", update);
+}
+
+#[actix_rt::test]
+async fn create_dump() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.load_test_set().await;
+
+    let (response, code) = server.create_dump().await;
+    assert_eq!(code, 202);
+    assert_eq!(response["status"], "in_progress");
+
+    let uid = response["uid"].as_str().unwrap().to_string();
+
+    // try 10 times to get the dump status, or panic to not wait forever
+    for _ in 0..10 {
+        let (response, code) = server.dump_status(&uid).await;
+        assert_eq!(code, 200, "response: {}", response);
+        assert_eq!(response["uid"], uid);
+
+        if response["status"] == "done" {
+            return;
+        }
+
+        assert_eq!(response["status"], "in_progress", "response: {}", response);
+        sleep(Duration::from_secs(1)).await;
+    }
+
+    panic!("Timeout waiting for dump");
+}
+
+#[actix_rt::test]
+async fn get_unexisting_dump_status() {
+    let server = Server::new().await;
+    let (_response, code) = server.dump_status("foobar").await;
+    assert_eq!(code, 404);
+}
+
+#[actix_rt::test]
+async fn import_dump_next_to_existing_indexes() {
+    let source_dir = tempfile::tempdir_in(".").unwrap();
+    let source = Server::new_with_options(default_settings(source_dir.path())).await;
+    let index = source.index("movies");
+    let (response, _code) = index
+        .update_settings(json!({ "displayedAttributes": ["id", "title"] }))
+        .await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    let documents = json!([
+        { "id": 1, "title": "Carol", "year": 2015 },
+        { "id": 2, "title": "Wonder Woman", "year": 2017 },
+    ]);
+    let (response, _code) = index.add_documents(documents, Some("id")).await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    let dump_uid = dump(&source).await;
+    let dump_path = source_dir
+        .path()
+        .join("dump")
+        .join(format!("{}.dump", dump_uid));
+
+    let server = Server::new().await;
+    let other = server.index("other");
+    let (response, _code) = other
+        .add_documents(json!([{ "id": 1, "content": "foo" }]), Some("id"))
+        .await;
+    other
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    server.service.0.import_dump(&dump_path).await.unwrap();
+
+    let movies = server.index("movies");
+    wait_last_update(&movies).await;
+    let (response, code) = movies.get().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["primaryKey"], "id");
+    let (response, code) = movies.settings().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["displayedAttributes"], json!(["id", "title"]));
+    let (response, code) = movies
+        .get_all_documents(GetAllDocumentsOptions::default())
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(response.as_array().unwrap().len(), 2);
+
+    // the index absent from the dump is left untouched
+    let (response, code) = other.get_document(1, None).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["content"], "foo");
+}
+
+#[actix_rt::test]
+async fn import_dump_merges_documents_in_existing_index() {
+    let source_dir = tempfile::tempdir_in(".").unwrap();
+    let source = Server::new_with_options(default_settings(source_dir.path())).await;
+    let index = source.index("movies");
+    let (response, _code) = index
+        .add_documents(json!([{ "id": 1, "title": "Carol" }]), Some("id"))
+        .await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    let dump_uid = dump(&source).await;
+    let dump_path = source_dir
+        .path()
+        .join("dump")
+        .join(format!("{}.dump", dump_uid));
+
+    let server = Server::new().await;
+    let movies = server.index("movies");
+    let documents = json!([
+        { "id": 1, "year": 2015 },
+        { "id": 2, "title": "Wonder Woman" },
+    ]);
+    let (response, _code) = movies.add_documents(documents, Some("id")).await;
+    movies
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    server.service.0.import_dump(&dump_path).await.unwrap();
+    wait_last_update(&movies).await;
+
+    let (response, code) = movies.get_document(1, None).await;
+    assert_eq!(code, 200);
+    assert_eq!(response, json!({ "id": 1, "title": "Carol", "year": 2015 }));
+    let (response, code) = movies.get_document(2, None).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["title"], "Wonder Woman");
+}
+
+#[actix_rt::test]
+async fn import_dump_keeps_the_map_size() {
+    let source_dir = tempfile::tempdir_in(".").unwrap();
+    let source = Server::new_with_options(default_settings(source_dir.path())).await;
+    let index = source.index("movies");
+    let (response, code) = index.create_with_map_size(100 * 1024 * 1024).await;
+    assert_eq!(code, 200, "{}", response);
+    let dump_uid = dump(&source).await;
+    let dump_path = source_dir
+        .path()
+        .join("dump")
+        .join(format!("{}.dump", dump_uid));
+
+    let server = Server::new().await;
+    server.service.0.import_dump(&dump_path).await.unwrap();
+
+    let (response, code) = server.index("movies").get().await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["size"]["mapped"], 100 * 1024 * 1024);
+}
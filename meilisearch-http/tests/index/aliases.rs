@@ -0,0 +1,90 @@
+use serde_json::json;
+
+use crate::common::server::default_settings;
+use crate::common::Server;
+
+use meilisearch_http::Opt;
+
+#[actix_rt::test]
+async fn search_through_alias() {
+    let server = Server::new().await;
+    let index = server.index("products");
+    index
+        .add_documents(json!([{ "id": 1, "title": "a product" }]), None)
+        .await;
+    index.wait_update_id(0).await;
+
+    let (_response, code) = server.create_alias("catalog", "products").await;
+    assert_eq!(code, 200);
+
+    let (response, code) = server
+        .index("catalog")
+        .search(json!({ "q": "product" }))
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(response["hits"][0]["title"], "a product");
+
+    // aliases are not listed as indexes
+    let (response, code) = server.list_indexes().await;
+    assert_eq!(code, 200);
+    assert_eq!(response.as_array().unwrap().len(), 1);
+
+    let (_response, code) = server.delete_alias("catalog").await;
+    assert_eq!(code, 204);
+    let (_response, code) = server
+        .index("catalog")
+        .search(json!({ "q": "product" }))
+        .await;
+    assert_eq!(code, 400);
+}
+
+#[actix_rt::test]
+async fn create_alias_of_unexisting_index() {
+    let server = Server::new().await;
+    let (_response, code) = server.create_alias("catalog", "products").await;
+    assert_eq!(code, 400);
+}
+
+#[actix_rt::test]
+async fn create_alias_with_existing_name() {
+    let server = Server::new().await;
+    server.index("products").create(None).await;
+    server.index("catalog").create(None).await;
+    let (_response, code) = server.create_alias("catalog", "products").await;
+    assert_eq!(code, 400);
+}
+
+#[actix_rt::test]
+async fn delete_aliased_index_is_rejected() {
+    let server = Server::new().await;
+    let index = server.index("products");
+    index.create(None).await;
+    server.create_alias("catalog", "products").await;
+
+    let (_response, code) = index.delete().await;
+    assert_eq!(code, 400);
+    assert_eq!(index.get().await.1, 200);
+
+    server.delete_alias("catalog").await;
+    let (_response, code) = index.delete().await;
+    assert_eq!(code, 204);
+}
+
+#[actix_rt::test]
+async fn delete_aliased_index_cascades() {
+    let temp = tempfile::tempdir_in(".").unwrap();
+    let options = Opt {
+        cascade_aliases_deletion: true,
+        ..default_settings(temp.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("products");
+    index.create(None).await;
+    server.create_alias("catalog", "products").await;
+
+    let (_response, code) = index.delete().await;
+    assert_eq!(code, 204);
+    assert_eq!(server.index("catalog").get().await.1, 400);
+    let (_response, code) = server.delete_alias("catalog").await;
+    assert_eq!(code, 400);
+}
@@ -1,5 +1,11 @@
+use byte_unit::{Byte, ByteUnit};
+use serde_json::{json, Value};
+
+use crate::common::server::default_settings;
 use crate::common::Server;
-use serde_json::Value;
+
+use meilisearch_http::option::IndexStoreBackend;
+use meilisearch_http::Opt;
 
 #[actix_rt::test]
 async fn create_index_no_primary_key() {
@@ -72,3 +78,111 @@ async fn test_create_multiple_indexes() {
     assert_eq!(index3.get().await.1, 200);
     assert_eq!(index4.get().await.1, 400);
 }
+
+#[actix_rt::test]
+async fn create_indexes_batch() {
+    let server = Server::new().await;
+    server.index("existing").create(None).await;
+
+    let (response, code) = server
+        .create_indexes(serde_json::json!([
+            { "uid": "first", "primaryKey": "id" },
+            { "uid": "existing" },
+            { "uid": "second" },
+            { "uid": "first" },
+        ]))
+        .await;
+    assert_eq!(code, 200);
+
+    let outcomes = response.as_array().unwrap();
+    assert_eq!(outcomes.len(), 4);
+    assert_eq!(outcomes[0]["uid"], "first");
+    assert_eq!(outcomes[0]["status"], "created");
+    assert_eq!(outcomes[0]["index"]["primaryKey"], "id");
+    assert_eq!(outcomes[1]["uid"], "existing");
+    assert_eq!(outcomes[1]["status"], "failed");
+    assert!(outcomes[1]["error"].is_string());
+    assert_eq!(outcomes[2]["uid"], "second");
+    assert_eq!(outcomes[2]["status"], "created");
+    assert_eq!(outcomes[3]["uid"], "first");
+    assert_eq!(outcomes[3]["status"], "failed");
+
+    assert_eq!(server.index("first").get().await.1, 200);
+    assert_eq!(server.index("second").get().await.1, 200);
+    let (response, _code) = server.list_indexes().await;
+    assert_eq!(response.as_array().unwrap().len(), 3);
+}
+
+#[actix_rt::test]
+async fn create_index_with_map_size() {
+    let temp = tempfile::tempdir_in(".").unwrap();
+    let options = Opt {
+        max_mdb_size: Byte::from_unit(1.0, ByteUnit::MiB).unwrap(),
+        max_index_map_size: Byte::from_unit(200.0, ByteUnit::MiB).unwrap(),
+        ..default_settings(temp.path())
+    };
+    let server = Server::new_with_options(options).await;
+
+    let index = server.index("test");
+    let (response, code) = index.create_with_map_size(100 * 1024 * 1024).await;
+    assert_eq!(code, 200, "{}", response);
+    let (response, _code) = index.get().await;
+    assert_eq!(response["size"]["mapped"], 100 * 1024 * 1024);
+
+    // the documents don't fit in the default map size
+    let documents: Vec<_> = (0..2000)
+        .map(|id| {
+            let words: Vec<_> = (0..100).map(|i| format!("word{}", id * 100 + i)).collect();
+            json!({ "id": id, "content": words.join(" ") })
+        })
+        .collect();
+    let (response, _code) = index.add_documents(json!(documents), Some("id")).await;
+    let update = index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    assert_eq!(update["status"], "processed", "{}", update);
+    let (response, _code) = index.stats().await;
+    assert_eq!(response["numberOfDocuments"], 2000);
+    let (response, _code) = index.get().await;
+    assert_eq!(response["size"]["mapped"], 100 * 1024 * 1024);
+}
+
+#[actix_rt::test]
+async fn create_index_with_map_size_above_maximum() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let (response, code) = index.create_with_map_size(2 << 40).await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(index.get().await.1, 400);
+}
+
+#[actix_rt::test]
+async fn create_index_in_memory() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let options = Opt {
+        index_store: IndexStoreBackend::Memory,
+        ..default_settings(dir.path())
+    };
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+    let (response, code) = index.create(Some("id")).await;
+    assert_eq!(code, 200, "{}", response);
+
+    let (_response, code) = index
+        .add_documents(json!([{ "id": 1, "content": "foo" }]), None)
+        .await;
+    assert_eq!(code, 202);
+    let update = index.wait_update_id(0).await;
+    assert_eq!(update["status"], "processed", "{}", update);
+    let (response, code) = index.search(json!({ "q": "foo" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["hits"], json!([{ "id": 1, "content": "foo" }]));
+
+    let (_response, code) = index.delete().await;
+    assert_eq!(code, 204);
+    let (_response, code) = index.get().await;
+    assert_eq!(code, 400);
+
+    // Only the indexes are kept out of the database directory.
+    assert!(!dir.path().join("indexes").exists());
+}
@@ -1,5 +1,21 @@
+use chrono::{Duration, Utc};
+use serde_json::json;
+
+use crate::common::server::default_settings;
 use crate::common::Server;
 
+use meilisearch_http::helpers::Clock;
+use meilisearch_http::Opt;
+
+/// A server retaining the deleted indexes for an hour.
+async fn server_with_trash(dir: &tempfile::TempDir) -> Server {
+    let options = Opt {
+        index_trash_retention_sec: Some(3600),
+        ..default_settings(dir.path())
+    };
+    Server::new_with_options(options).await
+}
+
 #[actix_rt::test]
 async fn create_and_delete_index() {
     let server = Server::new().await;
@@ -23,3 +39,95 @@ async fn delete_unexisting_index() {
 
     assert_eq!(code, 400);
 }
+
+#[actix_rt::test]
+async fn delete_indexes_batch() {
+    let server = Server::new().await;
+    server.index("first").create(None).await;
+    server.index("second").create(None).await;
+
+    let (response, code) = server
+        .delete_indexes(json!(["first", "missing", "second"]))
+        .await;
+    assert_eq!(code, 200);
+
+    let outcomes = response.as_array().unwrap();
+    assert_eq!(outcomes.len(), 3);
+    assert_eq!(outcomes[0]["uid"], "first");
+    assert_eq!(outcomes[0]["status"], "deleted");
+    assert_eq!(outcomes[1]["uid"], "missing");
+    assert_eq!(outcomes[1]["status"], "failed");
+    assert!(outcomes[1]["error"].is_string());
+    assert_eq!(outcomes[2]["uid"], "second");
+    assert_eq!(outcomes[2]["status"], "deleted");
+
+    assert_eq!(server.index("first").get().await.1, 400);
+    assert_eq!(server.index("second").get().await.1, 400);
+}
+
+#[actix_rt::test]
+async fn delete_and_restore_index() {
+    let temp = tempfile::tempdir_in(".").unwrap();
+    let server = server_with_trash(&temp).await;
+    let index = server.index("test");
+    index
+        .add_documents(json!([{ "id": 1, "title": "a document" }]), None)
+        .await;
+    index.wait_update_id(0).await;
+
+    let (_response, code) = index.delete().await;
+    assert_eq!(code, 204);
+    assert_eq!(index.get().await.1, 400);
+
+    let (response, code) = index.restore().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["uid"], "test");
+    let (response, code) = index.get_document(1, None).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["title"], "a document");
+
+    // the index isn't in the trash anymore
+    let (_response, code) = index.restore().await;
+    assert_eq!(code, 400);
+}
+
+#[actix_rt::test]
+async fn restore_index_with_used_uid() {
+    let temp = tempfile::tempdir_in(".").unwrap();
+    let server = server_with_trash(&temp).await;
+    let index = server.index("test");
+    index.create(None).await;
+    index.delete().await;
+    index.create(None).await;
+
+    let (_response, code) = index.restore().await;
+    assert_eq!(code, 400);
+}
+
+#[actix_rt::test]
+async fn purge_trash_after_retention() {
+    let temp = tempfile::tempdir_in(".").unwrap();
+    let server = server_with_trash(&temp).await;
+    let index = server.index("test");
+    index.create(None).await;
+    index.delete().await;
+
+    let purged = server
+        .service
+        .0
+        .purge_index_trash(&Clock::fixed(Utc::now() + Duration::minutes(30)))
+        .await
+        .unwrap();
+    assert_eq!(purged, 0);
+
+    let purged = server
+        .service
+        .0
+        .purge_index_trash(&Clock::fixed(Utc::now() + Duration::hours(2)))
+        .await
+        .unwrap();
+    assert_eq!(purged, 1);
+
+    let (_response, code) = index.restore().await;
+    assert_eq!(code, 400);
+}
@@ -0,0 +1,130 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::{json, Value};
+use tar::{Archive, Builder, Header};
+
+use crate::common::{GetAllDocumentsOptions, Server};
+
+#[actix_rt::test]
+async fn export_index() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .add_documents(
+            json!([
+                { "id": 1, "title": "first" },
+                { "id": 2, "title": "second" },
+            ]),
+            None,
+        )
+        .await;
+    index.wait_update_id(0).await;
+    index
+        .update_settings(json!({ "searchableAttributes": ["title"] }))
+        .await;
+    index.wait_update_id(1).await;
+
+    let (body, code) = index.export().await;
+    assert_eq!(code, 200);
+
+    let dir = tempfile::tempdir_in(".").unwrap();
+    Archive::new(GzDecoder::new(body.as_slice()))
+        .unpack(dir.path())
+        .unwrap();
+
+    let documents = std::fs::read_to_string(dir.path().join("documents.jsonl")).unwrap();
+    let documents = documents
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect::<Vec<Value>>();
+    assert_eq!(
+        documents,
+        vec![
+            json!({ "id": 1, "title": "first" }),
+            json!({ "id": 2, "title": "second" }),
+        ]
+    );
+
+    let settings = std::fs::read_to_string(dir.path().join("settings.json")).unwrap();
+    let settings: Value = serde_json::from_str(&settings).unwrap();
+    assert_eq!(settings["searchableAttributes"], json!(["title"]));
+}
+
+#[actix_rt::test]
+async fn export_unexisting_index() {
+    let server = Server::new().await;
+    let (_body, code) = server.index("test").export().await;
+    assert_eq!(code, 400);
+}
+
+#[actix_rt::test]
+async fn import_export_in_new_index() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .add_documents(
+            json!([
+                { "id": 1, "title": "first" },
+                { "id": 2, "title": "second" },
+            ]),
+            None,
+        )
+        .await;
+    index.wait_update_id(0).await;
+    index
+        .update_settings(json!({ "searchableAttributes": ["title"] }))
+        .await;
+    index.wait_update_id(1).await;
+    let (export, code) = index.export().await;
+    assert_eq!(code, 200);
+
+    let copy = server.index("copy");
+    let (response, code) = copy.import(export).await;
+    assert_eq!(code, 202, "response: {}", response);
+    let response = copy
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    assert_eq!(response["status"], "processed");
+
+    let (response, code) = copy.get().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["primaryKey"], "id");
+    let (copied, _code) = copy
+        .get_all_documents(GetAllDocumentsOptions::default())
+        .await;
+    let (documents, _code) = index
+        .get_all_documents(GetAllDocumentsOptions::default())
+        .await;
+    assert_eq!(copied, documents);
+    let (settings, _code) = copy.settings().await;
+    assert_eq!(settings["searchableAttributes"], json!(["title"]));
+}
+
+#[actix_rt::test]
+async fn import_export_of_unsupported_version() {
+    let server = Server::new().await;
+    let metadata = serde_json::to_vec(&json!({
+        "indexes": [],
+        "dbVersion": "0.1.0",
+        "dumpVersion": "V0",
+    }))
+    .unwrap();
+    let mut header = Header::new_gnu();
+    header.set_size(metadata.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    builder
+        .append_data(&mut header, "metadata.json", metadata.as_slice())
+        .unwrap();
+    let export = builder.into_inner().unwrap().finish().unwrap();
+
+    let (response, code) = server.index("test").import(export).await;
+    assert_eq!(code, 400);
+    assert_eq!(
+        response["error"],
+        "Unsupported dump version \"V0\", only version \"V1\" can be imported."
+    );
+    assert_eq!(server.index("test").get().await.1, 400);
+}
@@ -1,6 +1,8 @@
-use crate::common::Server;
+use byte_unit::{Byte, ByteUnit};
 use serde_json::Value;
 
+use crate::common::Server;
+
 #[actix_rt::test]
 async fn create_and_get_index() {
     let server = Server::new().await;
@@ -18,7 +20,30 @@ async fn create_and_get_index() {
     assert!(response.get("updatedAt").is_some());
     assert_eq!(response["createdAt"], response["updatedAt"]);
     assert_eq!(response["primaryKey"], Value::Null);
-    assert_eq!(response.as_object().unwrap().len(), 5);
+    assert!(response["size"]["used"].as_u64().unwrap() > 0);
+    assert!(response["size"]["mapped"].as_u64().unwrap() > 0);
+    assert_eq!(response.as_object().unwrap().len(), 6);
+}
+
+#[actix_rt::test]
+async fn index_size_grows_with_documents() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+
+    let (response, code) = index.get().await;
+    assert_eq!(code, 200);
+    let empty_size = response["size"]["used"].as_u64().unwrap();
+
+    index.load_test_set().await;
+
+    let (response, code) = index.get().await;
+    assert_eq!(code, 200);
+    assert!(response["size"]["used"].as_u64().unwrap() > empty_size);
+    assert_eq!(
+        response["size"]["mapped"].as_u64().unwrap(),
+        Byte::from_unit(4.0, ByteUnit::GiB).unwrap().get_bytes() as u64
+    );
 }
 
 // TODO: partial test since we are testing error, amd error is not yet fully implemented in
@@ -62,3 +87,42 @@ async fn list_multiple_indexes() {
         .find(|entry| entry["uid"] == "test1" && entry["primaryKey"] == "key")
         .is_some());
 }
+
+#[actix_rt::test]
+async fn list_indexes_paginated() {
+    let server = Server::new().await;
+    for uid in &["c", "a", "d", "b", "e"] {
+        server.index(uid).create(None).await;
+    }
+
+    let uids = |response: &Value| -> Vec<String> {
+        response
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|index| index["uid"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let (response, code) = server.service.get("/indexes?limit=2").await;
+    assert_eq!(code, 200);
+    assert_eq!(uids(&response), vec!["a", "b"]);
+
+    let (response, code) = server.service.get("/indexes?offset=2&limit=2").await;
+    assert_eq!(code, 200);
+    assert_eq!(uids(&response), vec!["c", "d"]);
+
+    let (response, code) = server.service.get("/indexes?offset=4&limit=2").await;
+    assert_eq!(code, 200);
+    assert_eq!(uids(&response), vec!["e"]);
+
+    let (response, code) = server
+        .service
+        .get("/indexes?sort=createdAt&offset=1&limit=3")
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(uids(&response), vec!["a", "d", "b"]);
+
+    let (_response, code) = server.service.get("/indexes?sort=size").await;
+    assert_eq!(code, 400);
+}
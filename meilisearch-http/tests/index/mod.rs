@@ -1,5 +1,8 @@
+mod aliases;
 mod create_index;
 mod delete_index;
+mod export;
 mod get_index;
 mod stats;
+mod swap_indexes;
 mod update_index;
@@ -51,3 +51,94 @@ async fn stats() {
     assert_eq!(response["fieldsDistribution"]["name"], 1);
     assert_eq!(response["fieldsDistribution"]["age"], 1);
 }
+
+#[actix_rt::test]
+async fn stats_unexisting_index() {
+    let server = Server::new().await;
+    let (_response, code) = server.index("test").stats().await;
+    assert_eq!(code, 404);
+}
+
+#[actix_rt::test]
+async fn stats_fields_distribution() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(Some("id")).await;
+
+    let documents = json!([
+        {
+            "id": 1,
+            "title": "Carol",
+            "genre": "romance",
+        },
+        {
+            "id": 2,
+            "title": "Wonder Woman",
+        },
+        {
+            "id": 3,
+            "genre": "action",
+            "release_date": 2017,
+        }
+    ]);
+
+    let (response, code) = index.add_documents(documents, None).await;
+    assert_eq!(code, 202);
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = index.stats().await;
+
+    assert_eq!(code, 200);
+    assert_eq!(
+        response["fieldsDistribution"],
+        json!({
+            "id": 3,
+            "title": 2,
+            "genre": 2,
+            "release_date": 1,
+        })
+    );
+}
+
+#[actix_rt::test]
+async fn clear_documents_resets_fields_distribution() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let (response, _code) = index
+        .update_settings(json!({ "displayedAttributes": ["name"] }))
+        .await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let documents = json!([{ "id": 1, "name": "Alexey" }, { "id": 2, "age": 45 }]);
+    let (response, _code) = index.add_documents(documents, Some("id")).await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = index.clear_all_documents().await;
+    assert_eq!(code, 202);
+    let update = index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    assert_eq!(update["status"], "processed");
+
+    let (response, code) = index.stats().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["numberOfDocuments"], 0);
+    assert!(response["fieldsDistribution"]
+        .as_object()
+        .unwrap()
+        .is_empty());
+
+    let (response, code) = index.get().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["primaryKey"], "id");
+
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["displayedAttributes"], json!(["name"]));
+}
@@ -0,0 +1,56 @@
+use serde_json::json;
+
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn swap_indexes() {
+    let server = Server::new().await;
+    let products = server.index("products");
+    products
+        .add_documents(json!([{ "id": 1, "title": "old product" }]), None)
+        .await;
+    products.wait_update_id(0).await;
+
+    let products_new = server.index("products_new");
+    products_new
+        .add_documents(
+            json!([
+                { "id": 1, "title": "new product" },
+                { "id": 2, "title": "another new product" },
+            ]),
+            None,
+        )
+        .await;
+    products_new.wait_update_id(0).await;
+
+    let (_response, code) = server.swap_indexes("products", "products_new").await;
+    assert_eq!(code, 200);
+
+    let (response, code) = products.search(json!({ "q": "product" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 2);
+    assert_eq!(response["hits"][0]["title"], "new product");
+
+    let (response, code) = products_new.search(json!({ "q": "product" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+    assert_eq!(response["hits"][0]["title"], "old product");
+}
+
+#[actix_rt::test]
+async fn swap_unexisting_index() {
+    let server = Server::new().await;
+    let index = server.index("products");
+    index.create(None).await;
+
+    let (_response, code) = server.swap_indexes("products", "products_new").await;
+    assert_eq!(code, 400);
+
+    let (_response, code) = server.swap_indexes("products_new", "products").await;
+    assert_eq!(code, 400);
+
+    // the existing index is left untouched
+    let (response, code) = index.get().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["uid"], "products");
+}
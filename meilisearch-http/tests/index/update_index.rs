@@ -1,5 +1,6 @@
 use crate::common::Server;
 use chrono::DateTime;
+use serde_json::json;
 
 #[actix_rt::test]
 async fn update_primary_key() {
@@ -39,19 +40,43 @@ async fn update_nothing() {
     assert_eq!(response, update);
 }
 
-// TODO: partial test since we are testing error, amd error is not yet fully implemented in
-// transplant
 #[actix_rt::test]
-async fn update_existing_primary_key() {
+async fn update_existing_primary_key_of_empty_index() {
     let server = Server::new().await;
     let index = server.index("test");
     let (_response, code) = index.create(Some("primary")).await;
 
     assert_eq!(code, 200);
 
-    let (_update, code) = index.update(Some("primary2")).await;
+    let (response, code) = index.update(Some("primary2")).await;
+
+    assert_eq!(code, 200);
+    assert_eq!(response["primaryKey"], "primary2");
+}
+
+#[actix_rt::test]
+async fn update_existing_primary_key_of_non_empty_index() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let (response, _code) = index
+        .add_documents(serde_json::json!([{ "id": 1, "other": 2 }]), Some("id"))
+        .await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = index.update(Some("other")).await;
 
     assert_eq!(code, 400);
+    assert_eq!(
+        response["error"],
+        "The primary key can't be changed, the index must be empty first"
+    );
+    assert_eq!(index.get().await.0["primaryKey"], "id");
+
+    // setting the same primary key again is not a change
+    let (_response, code) = index.update(Some("id")).await;
+    assert_eq!(code, 200);
 }
 
 // TODO: partial test since we are testing error, amd error is not yet fully implemented in
@@ -62,3 +87,98 @@ async fn test_unexisting_index() {
     let (_response, code) = server.index("test").update(None).await;
     assert_eq!(code, 400);
 }
+
+#[actix_rt::test]
+async fn rename_index_keeps_updates() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    index
+        .add_documents(
+            serde_json::from_str(include_str!("../assets/test_set.json")).unwrap(),
+            None,
+        )
+        .await;
+    index
+        .add_documents(serde_json::json!([{ "id": 1000, "content": "foo" }]), None)
+        .await;
+
+    let (response, code) = index.rename("renamed").await;
+    assert_eq!(code, 200);
+    assert_eq!(response["uid"], "renamed");
+    assert_eq!(index.get().await.1, 400);
+
+    let renamed = server.index("renamed");
+    renamed.wait_update_id(1).await;
+    let (response, code) = renamed.get_update(1).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["status"], "processed");
+
+    let (response, code) = renamed.list_updates().await;
+    assert_eq!(code, 200);
+    assert_eq!(response.as_array().unwrap().len(), 2);
+
+    let (response, code) = renamed.get_document(1000, None).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["content"], "foo");
+}
+
+#[actix_rt::test]
+async fn rename_index_to_existing_uid() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    server.index("other").create(None).await;
+
+    let (response, code) = index.rename("other").await;
+    assert_eq!(code, 400);
+    assert_eq!(response["error"], "Name already exist.");
+    assert_eq!(index.get().await.1, 200);
+}
+
+#[actix_rt::test]
+async fn rename_index_with_rejected_primary_key() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(Some("id")).await;
+    index.add_documents(json!([{ "id": 1 }]), None).await;
+    index.wait_update_id(0).await;
+
+    // the primary key can't change once there are documents, the rename is rolled back
+    let body = json!({ "uid": "renamed", "primaryKey": "other" });
+    let (_response, code) = server.service.put("/indexes/test", body).await;
+    assert_eq!(code, 400);
+    let (response, code) = index.get().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["primaryKey"], "id");
+    assert_eq!(server.index("renamed").get().await.1, 400);
+}
+
+#[actix_rt::test]
+async fn updates_bump_updated_at() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let (response, code) = index.create(None).await;
+    assert_eq!(code, 200);
+    let created_at = DateTime::parse_from_rfc3339(response["createdAt"].as_str().unwrap()).unwrap();
+    let created_at_str = response["createdAt"].clone();
+
+    index.add_documents(json!([{ "id": 1 }]), None).await;
+    index.wait_update_id(0).await;
+
+    let (response, _code) = index.get().await;
+    let first_update =
+        DateTime::parse_from_rfc3339(response["updatedAt"].as_str().unwrap()).unwrap();
+    assert!(first_update > created_at);
+    assert_eq!(response["createdAt"], created_at_str);
+
+    index
+        .update_settings(json!({ "typoTolerance": { "enabled": false } }))
+        .await;
+    index.wait_update_id(1).await;
+
+    let (response, _code) = index.get().await;
+    let second_update =
+        DateTime::parse_from_rfc3339(response["updatedAt"].as_str().unwrap()).unwrap();
+    assert!(second_update > first_update);
+}
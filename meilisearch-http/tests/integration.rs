@@ -1,6 +1,14 @@
+mod analytics;
+mod auth;
 mod common;
+mod compression;
+mod cors;
 mod documents;
+mod dumps;
 mod index;
+mod logging;
+mod metrics;
+mod request_id;
 mod search;
 mod settings;
 mod snapshot;
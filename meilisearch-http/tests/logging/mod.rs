@@ -0,0 +1,84 @@
+use std::sync::{Mutex, Once};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use once_cell::sync::Lazy;
+use serde_json::Value;
+
+use meilisearch_http::helpers::logging::{LogFormat, REQUEST_LOG_TARGET};
+use meilisearch_http::Opt;
+
+use crate::common::server::default_settings;
+use crate::common::Server;
+
+/// Keeps the request logs and the warnings. The logger is global to the test executable, so the
+/// lines of all the tests running concurrently are captured.
+pub struct CaptureLogger {
+    lines: Mutex<Vec<String>>,
+}
+
+impl CaptureLogger {
+    /// Returns whether a line containing `pattern` was logged.
+    pub fn contains(&self, pattern: &str) -> bool {
+        self.lines
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|line| line.contains(pattern))
+    }
+}
+
+impl Log for CaptureLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target() == REQUEST_LOG_TARGET || metadata.level() <= Level::Warn
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.lines.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Lazy<CaptureLogger> = Lazy::new(|| CaptureLogger {
+    lines: Mutex::default(),
+});
+
+/// Installs the capture logger, once for the whole test executable, and returns it.
+pub fn capture_logs() -> &'static CaptureLogger {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        log::set_logger(&*LOGGER).unwrap();
+        log::set_max_level(LevelFilter::Info);
+    });
+    &LOGGER
+}
+
+#[actix_rt::test]
+async fn requests_are_logged_as_json() {
+    let logger = capture_logs();
+
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let options = Opt {
+        log_format: LogFormat::Json,
+        ..default_settings(dir.path())
+    };
+    let server = Server::new_with_options(options).await;
+
+    let (_, code) = server.service.get("/indexes/json_logging").await;
+    assert_eq!(code, 404);
+
+    let lines = logger.lines.lock().unwrap();
+    let line = lines
+        .iter()
+        .find(|line| line.contains("/indexes/json_logging"))
+        .expect("the request wasn't logged");
+    let line: Value = serde_json::from_str(line).unwrap();
+    assert_eq!(line["level"], "INFO");
+    assert_eq!(line["method"], "GET");
+    assert_eq!(line["path"], "/indexes/json_logging");
+    assert_eq!(line["status"], 404);
+    assert!(line["latency_ms"].as_f64().unwrap() >= 0.0);
+    assert!(line["timestamp"].is_string());
+}
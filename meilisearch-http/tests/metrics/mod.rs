@@ -0,0 +1,80 @@
+use serde_json::json;
+
+use meilisearch_http::Opt;
+
+use crate::common::server::default_settings;
+use crate::common::Server;
+
+/// Returns the value of the sample of `metric` whose labels contain all the `labels`.
+fn sample(metrics: &str, metric: &str, labels: &[&str]) -> Option<f64> {
+    metrics
+        .lines()
+        .filter(|line| line.starts_with(&format!("{}{{", metric)))
+        .find(|line| labels.iter().all(|label| line.contains(label)))
+        .and_then(|line| line.rsplit(' ').next())
+        .map(|value| value.parse().unwrap())
+}
+
+#[actix_rt::test]
+async fn metrics_are_disabled_by_default() {
+    let server = Server::new().await;
+    let (_, code) = server.service.get_raw("/metrics").await;
+    assert_eq!(code, 404);
+}
+
+#[actix_rt::test]
+async fn requests_are_counted() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let options = Opt {
+        enable_metrics: true,
+        ..default_settings(dir.path())
+    };
+    let server = Server::new_with_options(options).await;
+
+    let index = server.index("test");
+    index
+        .add_documents(json!([{ "id": 1 }, { "id": 2 }]), None)
+        .await;
+    index.wait_update_id(0).await;
+
+    for _ in 0..3 {
+        let (_, code) = server.service.get("/indexes/test/settings").await;
+        assert_eq!(code, 200);
+    }
+
+    let (metrics, code) = server.service.get_raw("/metrics").await;
+    assert_eq!(code, 200);
+
+    let route = r#"route="/indexes/{index_uid}/settings""#;
+    let labels = [r#"method="GET""#, route, r#"status="200""#];
+    assert_eq!(
+        sample(&metrics, "meilisearch_http_requests_total", &labels),
+        Some(3.0),
+        "{}",
+        metrics
+    );
+    assert_eq!(
+        sample(
+            &metrics,
+            "meilisearch_http_request_duration_seconds_count",
+            &labels
+        ),
+        Some(3.0)
+    );
+    assert_eq!(
+        sample(
+            &metrics,
+            "meilisearch_index_documents",
+            &[r#"index="test""#]
+        ),
+        Some(2.0)
+    );
+    assert_eq!(
+        sample(
+            &metrics,
+            "meilisearch_update_queue_depth",
+            &[r#"index="test""#]
+        ),
+        Some(0.0)
+    );
+}
@@ -0,0 +1,78 @@
+use actix_web::http::Method;
+
+use meilisearch_http::Opt;
+
+use crate::common::server::default_settings;
+use crate::common::Server;
+
+const REQUEST_ID: &str = "5d21b4e0-request";
+
+#[actix_rt::test]
+async fn request_id_is_echoed() {
+    let server = Server::new().await;
+
+    let (_, headers, code) = server
+        .service
+        .get_with_headers("/health", &[("X-Request-Id", REQUEST_ID)])
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(headers["x-request-id"], REQUEST_ID);
+}
+
+#[actix_rt::test]
+async fn request_id_is_generated_when_missing() {
+    let server = Server::new().await;
+
+    let (_, headers, code) = server.service.get_with_headers("/health", &[]).await;
+    assert_eq!(code, 200);
+    assert!(!headers["x-request-id"].is_empty());
+}
+
+#[actix_rt::test]
+async fn request_id_in_errors() {
+    let server = Server::new().await;
+    server.index("test").create(None).await;
+
+    let (response, headers, code) = server
+        .service
+        .get_with_headers("/indexes/unknown", &[("X-Request-Id", REQUEST_ID)])
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["requestId"], REQUEST_ID);
+    assert_eq!(headers["x-request-id"], REQUEST_ID);
+
+    let (response, headers, code) = server
+        .service
+        .get_with_headers(
+            "/indexes/test/documents?format=xml",
+            &[("X-Request-Id", REQUEST_ID)],
+        )
+        .await;
+    assert_eq!(code, 400, "{}", response);
+    assert_eq!(response["requestId"], REQUEST_ID);
+    assert_eq!(headers["x-request-id"], REQUEST_ID);
+}
+
+#[actix_rt::test]
+async fn request_id_in_authentication_errors() {
+    let dir = tempfile::tempdir_in(".").unwrap();
+    let options = Opt {
+        master_key: Some("master".to_string()),
+        ..default_settings(dir.path())
+    };
+    let server = Server::new_with_options(options).await;
+
+    let (response, code) = server
+        .service
+        .get_with_header("/indexes", ("X-Request-Id", REQUEST_ID))
+        .await;
+    assert_eq!(code, 401, "{}", response);
+    assert_eq!(response["requestId"], REQUEST_ID);
+
+    let (headers, code) = server
+        .service
+        .request_headers(Method::GET, "/indexes", &[("X-Request-Id", REQUEST_ID)])
+        .await;
+    assert_eq!(code, 401);
+    assert_eq!(headers["x-request-id"], REQUEST_ID);
+}
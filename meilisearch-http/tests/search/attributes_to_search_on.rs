@@ -0,0 +1,72 @@
+use serde_json::json;
+
+use crate::common::{sorted_hits_ids, Server};
+
+#[actix_rt::test]
+async fn restrict_search_to_attributes() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = json!([
+        { "id": 1, "title": "the ocean", "description": "a blue planet" },
+        { "id": 2, "title": "a green planet", "description": "the ocean view" },
+        { "id": 3, "title": "mountains", "description": "the forest" },
+    ]);
+    let (response, _code) = index.add_documents(documents, Some("id")).await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = index.search(json!({ "q": "ocean" })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(sorted_hits_ids(&response), vec![1, 2]);
+
+    let (response, code) = index
+        .search(json!({ "q": "ocean", "attributesToSearchOn": ["title"] }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(sorted_hits_ids(&response), vec![1]);
+    assert_eq!(response["nbHits"], 1);
+
+    // the searchable attributes of the index are left untouched
+    let (response, code) = index.search(json!({ "q": "ocean" })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(sorted_hits_ids(&response), vec![1, 2]);
+}
+
+#[actix_rt::test]
+async fn search_on_attribute_not_searchable() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let (response, _code) = index
+        .update_settings(json!({ "searchableAttributes": ["title"] }))
+        .await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    let documents = json!([{ "id": 1, "title": "the ocean", "description": "the ocean" }]);
+    let (response, _code) = index.add_documents(documents, Some("id")).await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = index
+        .search(json!({ "q": "ocean", "attributesToSearchOn": ["description"] }))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+}
+
+#[actix_rt::test]
+async fn search_on_unknown_attribute() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = json!([{ "id": 1, "title": "the ocean" }]);
+    let (response, _code) = index.add_documents(documents, Some("id")).await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = index
+        .search(json!({ "q": "ocean", "attributesToSearchOn": ["titel"] }))
+        .await;
+    assert_eq!(code, 400, "{}", response);
+}
@@ -0,0 +1,51 @@
+use std::collections::HashSet;
+
+use serde_json::json;
+
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn one_hit_per_distinct_value() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = json!([
+        { "id": 1, "title": "running shoes", "brand": "nike" },
+        { "id": 2, "title": "running shoes", "brand": "adidas" },
+        { "id": 3, "title": "running shoes", "brand": "nike" },
+        { "id": 4, "title": "running shoes", "brand": "puma" },
+        { "id": 5, "title": "running shoes", "brand": "adidas" },
+    ]);
+    let (response, _code) = index.add_documents(documents, Some("id")).await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = index.search(json!({ "q": "shoes" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 5);
+
+    let (response, code) = index
+        .update_settings(json!({ "distinctAttribute": "brand" }))
+        .await;
+    assert_eq!(code, 202);
+    let update = index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    assert_eq!(update["status"], "processed");
+
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["distinctAttribute"], "brand");
+
+    let (response, code) = index.search(json!({ "q": "shoes" })).await;
+    assert_eq!(code, 200);
+    let brands: Vec<_> = response["hits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|hit| hit["brand"].as_str().unwrap().to_string())
+        .collect();
+    let unique: HashSet<_> = brands.iter().cloned().collect();
+    assert_eq!(brands.len(), 3);
+    assert_eq!(unique.len(), 3);
+}
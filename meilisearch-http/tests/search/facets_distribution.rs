@@ -0,0 +1,79 @@
+use serde_json::json;
+
+use crate::common::index::Index;
+use crate::common::Server;
+
+async fn setup(index: &Index<'_>) {
+    index
+        .update_settings_and_wait(json!({ "attributesForFaceting": { "genre": "string" } }))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "title": "die hard", "genre": "action" },
+        { "id": 2, "title": "taken", "genre": "action" },
+        { "id": 3, "title": "titanic", "genre": "drama" },
+    ]);
+    index.add_documents_and_wait(documents, Some("id")).await;
+}
+
+#[actix_rt::test]
+async fn facets_distribution() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .search(json!({ "facetsDistribution": ["genre"] }))
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(
+        response["facetsDistribution"],
+        json!({ "genre": { "action": 2, "drama": 1 } })
+    );
+}
+
+#[actix_rt::test]
+async fn facets_distribution_empty_result() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .search(json!({ "q": "unknown", "facetsDistribution": ["genre"] }))
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(response["facetsDistribution"], json!({ "genre": {} }));
+}
+
+#[actix_rt::test]
+async fn facets_distribution_not_faceted_attribute() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .search(json!({ "facetsDistribution": ["title"] }))
+        .await;
+    assert_eq!(code, 400);
+    assert!(response["error"].is_string());
+}
+
+#[actix_rt::test]
+async fn facets_distribution_ignores_post_filtered_documents() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+    let documents = json!([{ "id": 4, "title": "hard to die", "genre": "drama" }]);
+    index.add_documents_and_wait(documents, Some("id")).await;
+
+    // the last document matches both words of the phrase, but not in order
+    let (response, code) = index
+        .search(json!({ "q": "\"die hard\"", "facetsDistribution": ["genre"] }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["nbHits"], 1);
+    assert_eq!(
+        response["facetsDistribution"],
+        json!({ "genre": { "action": 1 } })
+    );
+}
@@ -0,0 +1,111 @@
+use serde_json::json;
+
+use crate::common::index::Index;
+use crate::common::{sorted_hits_ids, Server};
+
+async fn setup(index: &Index<'_>) {
+    index
+        .update_settings_and_wait(json!({
+            "attributesForFaceting": {
+                "price": "integer",
+                "genre": "string",
+                "color": "string",
+            }
+        }))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "price": 5, "genre": "action", "color": "red" },
+        { "id": 2, "price": 15, "genre": "action", "color": "blue" },
+        { "id": 3, "price": 25, "genre": "drama", "color": "blue" },
+        { "id": 4, "price": 35, "genre": "action", "color": "blue" },
+    ]);
+    index.add_documents_and_wait(documents, Some("id")).await;
+}
+
+#[actix_rt::test]
+async fn filter_numeric_range() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .search(json!({ "filter": "price > 10 AND price < 30" }))
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(sorted_hits_ids(&response), vec![2, 3]);
+}
+
+#[actix_rt::test]
+async fn filter_equality_conjunction() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .search(json!({ "filter": "genre = action AND color = blue" }))
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(sorted_hits_ids(&response), vec![2, 4]);
+}
+
+#[actix_rt::test]
+async fn invalid_filter() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index.search(json!({ "filter": "price >" })).await;
+    assert_eq!(code, 400);
+    assert!(response["error"].is_string());
+}
+
+#[actix_rt::test]
+async fn filter_on_attribute_made_filterable() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = json!([
+        { "id": 1, "price": 5 },
+        { "id": 2, "price": 15 },
+        { "id": 3, "price": 25 },
+    ]);
+    index.add_documents(documents, Some("id")).await;
+    index.wait_update_id(0).await;
+
+    let url = format!("/indexes/{}/settings/filterable-attributes", index.uid);
+    let (response, code) = server.service.put(&url, json!(["price"])).await;
+    assert_eq!(code, 202);
+    assert_eq!(response["status"], "enqueued");
+    let update = index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    assert_eq!(update["status"], "processed");
+
+    let (response, _code) = server.service.get(&url).await;
+    assert_eq!(response, json!(["price"]));
+    let (settings, _code) = index.settings().await;
+    assert_eq!(
+        settings["attributesForFaceting"],
+        json!({ "price": "integer" })
+    );
+
+    let (response, code) = index.search(json!({ "filter": "price > 10" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(sorted_hits_ids(&response), vec![2, 3]);
+}
+
+#[actix_rt::test]
+async fn filterable_attribute_without_values() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(Some("id")).await;
+
+    // nothing to infer the type of the attribute from
+    let url = format!("/indexes/{}/settings/filterable-attributes", index.uid);
+    let (response, code) = server.service.put(&url, json!(["price"])).await;
+    assert_eq!(code, 400);
+    assert!(response["error"]
+        .as_str()
+        .unwrap()
+        .contains("attributesForFaceting"));
+}
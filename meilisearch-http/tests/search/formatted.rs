@@ -0,0 +1,137 @@
+use serde_json::json;
+
+use crate::common::index::Index;
+use crate::common::Server;
+
+async fn setup(index: &Index<'_>) {
+    let documents = json!([
+        { "id": 1, "title": "The quick brown fox jumps over the lazy dog" },
+    ]);
+    index.add_documents_and_wait(documents, Some("id")).await;
+}
+
+#[actix_rt::test]
+async fn highlight_two_terms() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .search(json!({ "q": "quick dog", "attributesToHighlight": ["title"] }))
+        .await;
+    assert_eq!(code, 200);
+    let hit = &response["hits"][0];
+    assert_eq!(hit["title"], "The quick brown fox jumps over the lazy dog");
+    assert_eq!(
+        hit["_formatted"]["title"],
+        "The <em>quick</em> brown fox jumps over the lazy <em>dog</em>"
+    );
+}
+
+#[actix_rt::test]
+async fn highlight_prefix_with_custom_tags() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .search(json!({
+            "q": "brown ju",
+            "attributesToHighlight": ["title"],
+            "highlightPreTag": "<mark>",
+            "highlightPostTag": "</mark>",
+        }))
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(
+        response["hits"][0]["_formatted"]["title"],
+        "The quick <mark>brown</mark> fox <mark>jumps</mark> over the lazy dog"
+    );
+}
+
+#[actix_rt::test]
+async fn crop_window_size() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .search(json!({ "q": "fox", "attributesToCrop": ["title"], "cropLength": 3 }))
+        .await;
+    assert_eq!(code, 200);
+    let hit = &response["hits"][0];
+    assert_eq!(hit["title"], "The quick brown fox jumps over the lazy dog");
+    assert_eq!(hit["_formatted"]["title"], "brown fox jumps");
+}
+
+#[actix_rt::test]
+async fn crop_match_near_the_end() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .search(json!({ "q": "dog", "attributesToCrop": ["title"], "cropLength": 4 }))
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(
+        response["hits"][0]["_formatted"]["title"],
+        "over the lazy dog"
+    );
+}
+
+#[actix_rt::test]
+async fn crop_without_match() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .search(json!({ "attributesToCrop": ["id", "title"], "cropLength": 2 }))
+        .await;
+    assert_eq!(code, 200);
+    let hit = &response["hits"][0];
+    assert_eq!(hit["_formatted"]["id"], 1);
+    assert_eq!(hit["_formatted"]["title"], "The quick");
+}
+
+#[actix_rt::test]
+async fn matches_position_in_bytes() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = json!([
+        { "id": 1, "title": "Le café élégant", "tags": ["thé", "café"] },
+    ]);
+    index.add_documents(documents, Some("id")).await;
+    index.wait_update_id(0).await;
+
+    let (response, code) = index
+        .search(json!({ "q": "café élégant", "showMatchesPosition": true }))
+        .await;
+    assert_eq!(code, 200);
+    let hit = &response["hits"][0];
+    assert_eq!(
+        hit["_matchesPosition"],
+        json!({
+            "title": [{ "start": 3, "length": 5 }, { "start": 9, "length": 9 }],
+            "tags": [{ "start": 0, "length": 5, "index": 1 }],
+        })
+    );
+
+    // the offsets delimit the matched words in the UTF-8 bytes of the attribute
+    let title = hit["title"].as_str().unwrap();
+    assert_eq!(&title[3..8], "café");
+    assert_eq!(&title[9..18], "élégant");
+    assert!(hit.get("_formatted").is_none());
+}
+
+#[actix_rt::test]
+async fn matches_position_is_not_shown_by_default() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index.search(json!({ "q": "quick" })).await;
+    assert_eq!(code, 200);
+    assert!(response["hits"][0].get("_matchesPosition").is_none());
+}
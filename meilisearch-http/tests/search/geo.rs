@@ -0,0 +1,130 @@
+use serde_json::json;
+
+use crate::common::index::Index;
+use crate::common::{hits_ids, Server};
+
+/// The Hôtel de Ville, in Paris.
+const CENTER: (f64, f64) = (48.8566, 2.3522);
+
+async fn setup(index: &Index<'_>) {
+    index
+        .update_settings_and_wait(json!({
+            "attributesForFaceting": { "_geo": "geo", "kind": "string" }
+        }))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "name": "Eiffel Tower", "kind": "monument", "_geo": { "lat": 48.8584, "lng": 2.2945 } },
+        { "id": 2, "name": "Louvre", "kind": "museum", "_geo": { "lat": 48.8606, "lng": 2.3376 } },
+        { "id": 3, "name": "Notre-Dame", "kind": "monument", "_geo": { "lat": 48.8530, "lng": 2.3499 } },
+        { "id": 4, "name": "Fourvière", "kind": "monument", "_geo": { "lat": 45.7623, "lng": 4.8228 } },
+        { "id": 5, "name": "Somewhere", "kind": "monument" },
+    ]);
+    let response = index.add_documents_and_wait(documents, Some("id")).await;
+    assert_eq!(response["status"], "processed", "{}", response);
+}
+
+#[actix_rt::test]
+async fn filter_by_radius_sorted_by_distance() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .search(json!({
+            "filter": format!("_geoRadius({}, {}, 5000)", CENTER.0, CENTER.1),
+            "sort": [format!("_geoPoint({}, {}):asc", CENTER.0, CENTER.1)],
+        }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(hits_ids(&response), vec![3, 2, 1]);
+    assert_eq!(response["nbHits"], 3);
+
+    // the radius is combined with the other filters
+    let (response, code) = index
+        .search(json!({
+            "filter": format!("_geoRadius({}, {}, 5000) AND kind = monument", CENTER.0, CENTER.1),
+            "sort": [format!("_geoPoint({}, {}):desc", CENTER.0, CENTER.1)],
+        }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(hits_ids(&response), vec![1, 3]);
+}
+
+#[actix_rt::test]
+async fn sort_by_distance_without_radius() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    // the documents without location are placed last
+    let (response, code) = index
+        .search(json!({ "sort": [format!("_geoPoint({}, {}):asc", CENTER.0, CENTER.1)] }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(hits_ids(&response), vec![3, 2, 1, 4, 5]);
+}
+
+#[actix_rt::test]
+async fn geo_search_requires_faceted_geo() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let (response, _code) = index
+        .add_documents(
+            json!([{ "id": 1, "_geo": { "lat": 48.8, "lng": 2.3 } }]),
+            None,
+        )
+        .await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (_response, code) = index
+        .search(json!({ "filter": "_geoRadius(48.8, 2.3, 1000)" }))
+        .await;
+    assert_eq!(code, 400);
+    let (_response, code) = index
+        .search(json!({ "sort": ["_geoPoint(48.8, 2.3):asc"] }))
+        .await;
+    assert_eq!(code, 400);
+}
+
+#[actix_rt::test]
+async fn invalid_geo_is_rejected() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, _code) = index
+        .add_documents(json!([{ "id": 6, "_geo": { "lat": "north" } }]), None)
+        .await;
+    let response = index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    assert_eq!(response["status"], "failed", "{}", response);
+
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["attributesForFaceting"]["_geo"], "geo");
+}
+
+#[actix_rt::test]
+async fn geo_must_be_faceted_as_geo() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+
+    for facet_types in &[
+        json!({ "_geo": "string" }),
+        json!({ "_geo": "geo", "location": "geo" }),
+    ] {
+        let response = index
+            .update_settings_and_wait(json!({ "attributesForFaceting": facet_types }))
+            .await;
+        assert_eq!(response["status"], "failed", "{}", response);
+    }
+
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200);
+    assert!(response["attributesForFaceting"]["_geo"].is_null());
+}
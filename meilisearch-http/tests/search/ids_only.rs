@@ -0,0 +1,44 @@
+use serde_json::json;
+
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn search_ids_only() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let (response, _code) = index
+        .update_settings(json!({ "attributesForFaceting": { "price": "integer" } }))
+        .await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    let documents = json!([
+        { "id": 1, "title": "hello world", "price": 30 },
+        { "id": 2, "title": "hello", "price": 10 },
+        { "id": 3, "title": "goodbye", "price": 20 },
+    ]);
+    let (response, _code) = index.add_documents(documents, Some("id")).await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = index
+        .search(json!({
+            "q": "hello",
+            "idsOnly": true,
+            "sort": ["price:asc"],
+            "attributesToRetrieve": ["title"],
+            "attributesToHighlight": ["title"],
+        }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"], json!([{ "id": 2 }, { "id": 1 }]));
+    assert_eq!(response["nbHits"], 2);
+
+    let (response, code) = index
+        .service
+        .get("/indexes/test/search?q=goodbye&idsOnly=true")
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"], json!([{ "id": 3 }]));
+}
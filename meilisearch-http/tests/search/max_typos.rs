@@ -0,0 +1,79 @@
+use serde_json::json;
+
+use crate::common::Server;
+use crate::logging::capture_logs;
+
+#[actix_rt::test]
+async fn search_without_typos() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = json!([
+        { "id": 1, "title": "hello world" },
+        { "id": 2, "title": "goodbye" },
+    ]);
+    index.add_documents(documents, Some("id")).await;
+    index.wait_update_id(0).await;
+
+    let (response, code) = index.search(json!({ "q": "hallo" })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(
+        response["hits"],
+        json!([{ "id": 1, "title": "hello world" }])
+    );
+
+    let (response, code) = index.search(json!({ "q": "hallo", "maxTypos": 0 })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"], json!([]));
+
+    let (response, code) = index.search(json!({ "q": "hello", "maxTypos": 0 })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+
+    // More typos than the engine supports are clamped, with a warning.
+    let logger = capture_logs();
+    let (response, code) = index.search(json!({ "q": "hallo", "maxTypos": 10 })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+    assert!(logger.contains("The search allows 10 typos, it is clamped"));
+
+    let (response, code) = index
+        .service
+        .get("/indexes/test/search?q=hallo&maxTypos=0")
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"], json!([]));
+}
+
+#[actix_rt::test]
+async fn max_typos_applies_to_each_word() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = json!([
+        { "id": 1, "title": "hello world" },
+        { "id": 2, "title": "extraordinary" },
+    ]);
+    index.add_documents(documents, Some("id")).await;
+    index.wait_update_id(0).await;
+
+    // Each word is allowed one typo, even though the query has two.
+    let (response, code) = index
+        .search(json!({ "q": "hallo wurld", "maxTypos": 1 }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"][0]["id"], 1, "{}", response);
+
+    // A long word is allowed two typos by the engine, the cap still applies to it.
+    let (response, code) = index.search(json!({ "q": "extreordinery" })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"][0]["id"], 2, "{}", response);
+    let (response, code) = index
+        .search(json!({ "q": "extreordinery", "maxTypos": 1 }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"], json!([]));
+    let (response, code) = index
+        .search(json!({ "q": "extraordinery", "maxTypos": 1 }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["hits"][0]["id"], 2, "{}", response);
+}
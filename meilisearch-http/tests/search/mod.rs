@@ -1,2 +1,17 @@
 // This modules contains all the test concerning search. Each particular feture of the search
 // should be tested in its own module to isolate tests and keep the tests readable.
+
+mod attributes_to_search_on;
+mod distinct;
+mod facets_distribution;
+mod filters;
+mod formatted;
+mod geo;
+mod ids_only;
+mod max_typos;
+mod multi_search;
+mod pagination;
+mod phrase;
+mod ranking_rules;
+mod sort;
+mod split_join_words;
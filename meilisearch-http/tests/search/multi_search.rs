@@ -0,0 +1,66 @@
+use serde_json::json;
+
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn multi_search_two_indexes() {
+    let server = Server::new().await;
+    let movies = server.index("movies");
+    movies
+        .add_documents(json!([{ "id": 1, "title": "hello world" }]), None)
+        .await;
+    movies.wait_update_id(0).await;
+    let books = server.index("books");
+    books
+        .add_documents(
+            json!([
+                { "id": 1, "title": "hello there" },
+                { "id": 2, "title": "goodbye" },
+            ]),
+            None,
+        )
+        .await;
+    books.wait_update_id(0).await;
+
+    let (response, code) = server
+        .multi_search(json!([
+            { "indexUid": "movies", "q": "hello" },
+            { "indexUid": "books", "q": "goodbye", "limit": 1 },
+        ]))
+        .await;
+    assert_eq!(code, 200);
+    let results = response.as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["indexUid"], "movies");
+    assert_eq!(
+        results[0]["hits"],
+        json!([{ "id": 1, "title": "hello world" }])
+    );
+    assert_eq!(results[1]["indexUid"], "books");
+    assert_eq!(results[1]["limit"], 1);
+    assert_eq!(results[1]["hits"], json!([{ "id": 2, "title": "goodbye" }]));
+}
+
+#[actix_rt::test]
+async fn multi_search_failed_query() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .add_documents(json!([{ "id": 1, "title": "hello" }]), None)
+        .await;
+    index.wait_update_id(0).await;
+
+    let (response, code) = server
+        .multi_search(json!([
+            { "indexUid": "unexisting", "q": "hello" },
+            { "q": "hello" },
+            { "indexUid": "test", "q": "hello" },
+        ]))
+        .await;
+    assert_eq!(code, 200);
+    let results = response.as_array().unwrap();
+    assert_eq!(results[0]["indexUid"], "unexisting");
+    assert!(results[0]["error"].is_string());
+    assert!(results[1]["error"].is_string());
+    assert_eq!(results[2]["hits"], json!([{ "id": 1, "title": "hello" }]));
+}
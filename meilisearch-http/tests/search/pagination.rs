@@ -0,0 +1,85 @@
+use serde_json::json;
+
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn search_limit_exceeds_max() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.load_test_set().await;
+
+    let (response, code) = index.search(json!({ "limit": 1001 })).await;
+    assert_eq!(code, 400);
+    assert!(response["error"].is_string());
+
+    let (_response, code) = index.search(json!({ "limit": 1000 })).await;
+    assert_eq!(code, 200);
+}
+
+#[actix_rt::test]
+async fn search_offset_overflow() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.load_test_set().await;
+
+    let (response, code) = index
+        .search(json!({ "offset": usize::MAX, "limit": 10 }))
+        .await;
+    assert_eq!(code, 400);
+    assert!(response["error"].is_string());
+}
+
+#[actix_rt::test]
+async fn search_offset_past_the_results() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.load_test_set().await;
+
+    let (response, code) = index.search(json!({ "offset": 500, "limit": 10 })).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["hits"], json!([]));
+    assert_eq!(response["offset"], 500);
+    assert_eq!(response["limit"], 10);
+    assert_eq!(response["estimatedTotalHits"], response["nbHits"]);
+}
+
+#[actix_rt::test]
+async fn search_hits_are_estimated_unless_exhaustive() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents: Vec<_> = (0..50)
+        .map(|id| json!({ "id": id, "content": format!("hello {}", id) }))
+        .collect();
+    index.add_documents(json!(documents), Some("id")).await;
+    index.wait_update_id(0).await;
+
+    let (response, code) = index.search(json!({ "q": "hello", "limit": 5 })).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["exhaustiveNbHits"], false);
+
+    let (response, code) = index
+        .search(json!({ "q": "hello", "limit": 5, "exhaustiveNbHits": true }))
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(response["exhaustiveNbHits"], true);
+    assert_eq!(response["nbHits"], 50);
+    assert_eq!(response["estimatedTotalHits"], 50);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 5);
+}
+
+#[actix_rt::test]
+async fn search_hits_are_exact_when_the_window_is_not_filled() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = json!([
+        { "id": 1, "content": "hello" },
+        { "id": 2, "content": "world" },
+    ]);
+    index.add_documents(documents, Some("id")).await;
+    index.wait_update_id(0).await;
+
+    let (response, code) = index.search(json!({ "q": "hello" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["exhaustiveNbHits"], true);
+    assert_eq!(response["nbHits"], 1);
+}
@@ -0,0 +1,76 @@
+use serde_json::json;
+
+use crate::common::{hits_ids, Server};
+
+#[actix_rt::test]
+async fn phrase_matches_contiguous_words() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = json!([
+        { "id": 1, "title": "Moving to New York" },
+        { "id": 2, "title": "A new life in York" },
+        { "id": 3, "title": "York, a new city" },
+        { "id": 4, "title": "Paris", "tags": ["new", "york"] },
+    ]);
+    let (response, _code) = index.add_documents(documents, Some("id")).await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = index.search(json!({ "q": "new york" })).await;
+    assert_eq!(code, 200, "{}", response);
+    let mut words = hits_ids(&response);
+    words.sort_unstable();
+    assert_eq!(words, vec![1, 2, 3, 4]);
+
+    let (response, code) = index.search(json!({ "q": "\"new york\"" })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(hits_ids(&response), vec![1]);
+    assert_eq!(response["nbHits"], 1);
+
+    // the phrase can be combined with other words
+    let (response, code) = index.search(json!({ "q": "\"a new\" york" })).await;
+    assert_eq!(code, 200, "{}", response);
+    let mut phrase = hits_ids(&response);
+    phrase.sort_unstable();
+    assert_eq!(phrase, vec![2, 3]);
+}
+
+#[actix_rt::test]
+async fn phrase_search_is_paginated() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = json!([
+        { "id": 1, "title": "Moving to New York" },
+        { "id": 2, "title": "A new life in York" },
+        { "id": 3, "title": "York, a new city" },
+        { "id": 4, "title": "Paris", "tags": ["new", "york"] },
+        { "id": 5, "title": "New York, New York" },
+        { "id": 6, "title": "The New York times" },
+    ]);
+    let (response, _code) = index.add_documents(documents, Some("id")).await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    // the pages are filled with the documents matching the phrase, whatever their rank
+    let mut ids = Vec::new();
+    for offset in 0..3 {
+        let (response, code) = index
+            .search(json!({ "q": "\"new york\"", "offset": offset, "limit": 1 }))
+            .await;
+        assert_eq!(code, 200, "{}", response);
+        assert_eq!(response["hits"].as_array().unwrap().len(), 1);
+        ids.extend(hits_ids(&response));
+    }
+    ids.sort_unstable();
+    assert_eq!(ids, vec![1, 5, 6]);
+
+    let (response, code) = index
+        .search(json!({ "q": "\"new york\"", "offset": 3, "limit": 1 }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert!(hits_ids(&response).is_empty());
+    assert_eq!(response["nbHits"], 3);
+    assert_eq!(response["exhaustiveNbHits"], true);
+}
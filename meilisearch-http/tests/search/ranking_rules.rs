@@ -0,0 +1,101 @@
+use serde_json::json;
+
+use crate::common::index::Index;
+use crate::common::{hits_ids, Server};
+
+async fn setup(index: &Index<'_>) {
+    let documents = json!([
+        { "id": 1, "title": "hello", "date": 2010 },
+        { "id": 2, "title": "hello", "date": 2021 },
+        { "id": 3, "title": "hello", "date": 2015 },
+        { "id": 4, "title": "hello", "date": 2018 },
+    ]);
+    index.add_documents_and_wait(documents, Some("id")).await;
+}
+
+#[actix_rt::test]
+async fn override_ranking_rules() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (default, code) = index.search(json!({ "q": "hello" })).await;
+    assert_eq!(code, 200);
+
+    let (overridden, code) = index
+        .search(json!({ "q": "hello", "rankingRules": ["desc(date)"] }))
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(hits_ids(&overridden), vec![2, 4, 3, 1]);
+    assert_ne!(hits_ids(&default), hits_ids(&overridden));
+
+    // the index settings are left untouched
+    let (response, code) = index.search(json!({ "q": "hello" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(hits_ids(&response), hits_ids(&default));
+}
+
+#[actix_rt::test]
+async fn override_ranking_rules_with_relevancy_rules() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .search(json!({
+            "q": "hello",
+            "rankingRules": ["asc(date)", "words", "typo", "proximity", "attribute", "exactness"],
+        }))
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(hits_ids(&response), vec![1, 3, 4, 2]);
+}
+
+#[actix_rt::test]
+async fn override_invalid_ranking_rule() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .search(json!({ "q": "hello", "rankingRules": ["recency"] }))
+        .await;
+    assert_eq!(code, 400);
+    assert!(response["error"].is_string());
+
+    let (response, code) = index
+        .search(json!({ "q": "hello", "rankingRules": ["typo", "words"] }))
+        .await;
+    assert_eq!(code, 400);
+    assert!(response["error"].is_string());
+}
+
+#[actix_rt::test]
+async fn searchable_attributes_order_sets_their_importance() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = json!([
+        { "id": 1, "title": "apple", "body": "orange" },
+        { "id": 2, "title": "orange", "body": "apple" },
+    ]);
+    index.add_documents(documents, Some("id")).await;
+    index.wait_update_id(0).await;
+
+    let cases = vec![
+        (1, json!(["title", "body"]), vec![1, 2]),
+        (2, json!(["body", "title"]), vec![2, 1]),
+    ];
+    for (update_id, attributes, expected) in cases {
+        index
+            .update_settings(json!({ "searchableAttributes": attributes }))
+            .await;
+        index.wait_update_id(update_id).await;
+
+        let (settings, _) = index.settings().await;
+        assert_eq!(settings["searchableAttributes"], attributes);
+
+        let (response, code) = index.search(json!({ "q": "apple" })).await;
+        assert_eq!(code, 200);
+        assert_eq!(hits_ids(&response), expected);
+    }
+}
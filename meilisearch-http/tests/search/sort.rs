@@ -0,0 +1,102 @@
+use serde_json::json;
+
+use crate::common::index::Index;
+use crate::common::{hits_ids, Server};
+
+async fn setup(index: &Index<'_>) {
+    index
+        .update_settings_and_wait(json!({
+            "attributesForFaceting": { "price": "integer", "genre": "string" }
+        }))
+        .await;
+
+    let documents = json!([
+        { "id": 1, "title": "hello", "price": 30, "genre": "drama" },
+        { "id": 2, "title": "hello", "price": 10, "genre": "action" },
+        { "id": 3, "title": "hello", "price": 20, "genre": "action" },
+        { "id": 4, "title": "hello", "price": 10, "genre": "drama" },
+    ]);
+    index.add_documents_and_wait(documents, Some("id")).await;
+}
+
+#[actix_rt::test]
+async fn sort_ascending_number() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .search(json!({ "q": "hello", "sort": ["price:asc"] }))
+        .await;
+    assert_eq!(code, 200);
+    let prices = response["hits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|hit| hit["price"].as_u64().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(prices, vec![10, 10, 20, 30]);
+}
+
+#[actix_rt::test]
+async fn sort_multiple_criteria() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .search(json!({ "q": "hello", "sort": ["genre:asc", "price:desc"] }))
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(hits_ids(&response), vec![3, 2, 1, 4]);
+
+    let (response, code) = index
+        .search(
+            json!({ "q": "hello", "sort": ["genre:asc", "price:desc"], "offset": 1, "limit": 2 }),
+        )
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(hits_ids(&response), vec![2, 1]);
+}
+
+#[actix_rt::test]
+async fn sort_not_sortable_attribute() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .search(json!({ "q": "hello", "sort": ["title:asc"] }))
+        .await;
+    assert_eq!(code, 400);
+    assert!(response["error"].is_string());
+}
+
+#[actix_rt::test]
+async fn placeholder_search_with_filter_and_sort() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .search(json!({ "q": "", "filter": "genre = drama", "sort": ["price:asc"] }))
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(hits_ids(&response), vec![4, 1]);
+    assert_eq!(response["nbHits"], 2);
+
+    let (response, code) = index
+        .search(
+            json!({ "filter": "genre = action", "sort": ["price:desc"], "offset": 1, "limit": 1 }),
+        )
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(hits_ids(&response), vec![2]);
+
+    // Without a sort, the documents come back in their natural order.
+    let (response, code) = index
+        .search(json!({ "q": "  ", "filter": "genre = action" }))
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(hits_ids(&response), vec![2, 3]);
+}
@@ -0,0 +1,72 @@
+use serde_json::json;
+
+use crate::common::{sorted_hits_ids, Server};
+
+#[actix_rt::test]
+async fn disable_split_join_words() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = json!([
+        { "id": 1, "title": "iphone" },
+        { "id": 2, "title": "i have a phone" },
+    ]);
+    let (response, _code) = index.add_documents(documents, Some("id")).await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = index.search(json!({ "q": "i phone" })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(sorted_hits_ids(&response), vec![1, 2]);
+
+    let (response, code) = index
+        .update_settings(json!({ "splitJoinWords": false }))
+        .await;
+    assert_eq!(code, 202);
+    let update = index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    assert_eq!(update["status"], "processed");
+
+    let (response, code) = index.search(json!({ "q": "i phone" })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(sorted_hits_ids(&response), vec![2]);
+    assert_eq!(response["nbHits"], 1);
+
+    // resetting the setting enables it again
+    let (response, _code) = index
+        .update_settings(json!({ "splitJoinWords": null }))
+        .await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    let (response, code) = index.search(json!({ "q": "i phone" })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(sorted_hits_ids(&response), vec![1, 2]);
+}
+
+#[actix_rt::test]
+async fn disable_split_words() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = json!([
+        { "id": 1, "title": "iphone" },
+        { "id": 2, "title": "i phone" },
+    ]);
+    index.add_documents_and_wait(documents, Some("id")).await;
+    index
+        .update_settings_and_wait(json!({ "splitJoinWords": false }))
+        .await;
+
+    // without typos, the second document only matches the word of the query split in two
+    let (response, code) = index.search(json!({ "q": "iphone", "maxTypos": 0 })).await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(sorted_hits_ids(&response), vec![1]);
+
+    // each word of the query is checked
+    let (response, code) = index
+        .search(json!({ "q": "iphone case", "maxTypos": 0 }))
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(sorted_hits_ids(&response), vec![1]);
+}
@@ -16,7 +16,7 @@ async fn get_settings() {
     let (response, code) = index.settings().await;
     assert_eq!(code, 200);
     let settings = response.as_object().unwrap();
-    assert_eq!(settings.keys().len(), 5);
+    assert_eq!(settings.keys().len(), 10);
     assert_eq!(settings["displayedAttributes"], json!(["*"]));
     assert_eq!(settings["searchableAttributes"], json!(["*"]));
     assert_eq!(settings["attributesForFaceting"], json!({}));
@@ -31,6 +31,19 @@ async fn get_settings() {
         ])
     );
     assert_eq!(settings["stopWords"], json!([]));
+    assert_eq!(settings["distinctAttribute"], json!(null));
+    assert_eq!(settings["synonyms"], json!({}));
+    assert_eq!(
+        settings["typoTolerance"],
+        json!({
+            "enabled": true,
+            "minWordSizeForOneTypo": 5,
+            "minWordSizeForTwoTypos": 9,
+            "disableOnAttributes": []
+        })
+    );
+    assert_eq!(settings["pagination"], json!({ "defaultLimit": null }));
+    assert_eq!(settings["splitJoinWords"], json!(true));
 }
 
 #[actix_rt::test]
@@ -97,6 +110,127 @@ async fn reset_all_settings() {
     assert_eq!(response["stopWords"], json!([]));
 }
 
+#[actix_rt::test]
+async fn reset_all_settings_to_defaults() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    let (default_settings, _code) = index.settings().await;
+
+    index
+        .update_settings(json!({
+            "displayedAttributes": ["foo"],
+            "searchableAttributes": ["bar"],
+            "attributesForFaceting": { "price": "integer" },
+            "rankingRules": ["desc(price)", "typo", "words"],
+            "stopWords": ["the", "a"],
+        }))
+        .await;
+    index.wait_update_id(0).await;
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200);
+    assert_eq!(
+        response["attributesForFaceting"],
+        json!({ "price": "integer" })
+    );
+    assert_eq!(
+        response["rankingRules"],
+        json!(["desc(price)", "typo", "words"])
+    );
+
+    let (response, code) = index.delete_settings().await;
+    assert_eq!(code, 202);
+    let update = index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    assert_eq!(update["status"], "processed");
+
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200);
+    assert_eq!(response, default_settings);
+}
+
+#[actix_rt::test]
+async fn reset_one_setting() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .update_settings(json!({
+            "rankingRules": ["typo", "words"],
+            "stopWords": ["the", "a"],
+        }))
+        .await;
+    index.wait_update_id(0).await;
+
+    let url = format!("/indexes/{}/settings/stop-words", index.uid);
+    let (response, code) = server.service.delete(url).await;
+    assert_eq!(code, 202);
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["stopWords"], json!([]));
+    assert_eq!(response["rankingRules"], json!(["typo", "words"]));
+
+    let url = format!("/indexes/{}/settings/ranking-rules", index.uid);
+    let (response, code) = server.service.delete(url).await;
+    assert_eq!(code, 202);
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200);
+    assert_eq!(
+        response["rankingRules"],
+        json!(["words", "typo", "proximity", "attribute", "exactness"])
+    );
+}
+
+#[actix_rt::test]
+async fn get_settings_sub_resources() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .update_settings(json!({
+            "rankingRules": ["desc(price)", "typo", "words"],
+            "searchableAttributes": ["title", "description"],
+            "displayedAttributes": ["title", "price"],
+            "attributesForFaceting": { "price": "integer", "genre": "string" },
+        }))
+        .await;
+    index.wait_update_id(0).await;
+
+    let expected = vec![
+        ("ranking-rules", json!(["desc(price)", "typo", "words"])),
+        ("searchable-attributes", json!(["title", "description"])),
+        ("displayed-attributes", json!(["title", "price"])),
+        (
+            "attributes-for-faceting",
+            json!({ "price": "integer", "genre": "string" }),
+        ),
+        ("filterable-attributes", json!(["genre", "price"])),
+    ];
+    for (setting, value) in expected {
+        let url = format!("/indexes/{}/settings/{}", index.uid, setting);
+        let (response, code) = server.service.get(url).await;
+        assert_eq!(code, 200, "{}", setting);
+        assert_eq!(response, value, "{}", setting);
+    }
+}
+
+#[actix_rt::test]
+async fn get_filterable_attributes_unexisting_index() {
+    let server = Server::new().await;
+    let (_response, code) = server
+        .service
+        .get("/indexes/test/settings/filterable-attributes")
+        .await;
+    assert_eq!(code, 400);
+}
+
 #[actix_rt::test]
 async fn update_setting_unexisting_index() {
     let server = Server::new().await;
@@ -169,5 +303,7 @@ test_setting_routes!(
     attributes_for_faceting,
     displayed_attributes,
     searchable_attributes,
-    stop_words
+    stop_words,
+    distinct_attribute,
+    ranking_rules
 );
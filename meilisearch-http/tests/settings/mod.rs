@@ -1 +1,6 @@
 mod get_settings;
+mod pagination;
+mod stop_words;
+mod synonyms;
+mod typo_tolerance;
+mod validate;
@@ -0,0 +1,43 @@
+use serde_json::json;
+
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn default_limit_of_the_index() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents: Vec<_> = (0..30).map(|id| json!({ "id": id })).collect();
+    let (response, _code) = index.add_documents(json!(documents), Some("id")).await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    // the default limit of the server applies first
+    let (response, code) = index.search(json!({})).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 20);
+    assert_eq!(response["limit"], 20);
+
+    let (response, code) = index
+        .update_settings(json!({ "pagination": { "defaultLimit": 5 } }))
+        .await;
+    assert_eq!(code, 202);
+    let update = index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    assert_eq!(update["status"], "processed");
+
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["pagination"], json!({ "defaultLimit": 5 }));
+
+    let (response, code) = index.search(json!({})).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 5);
+    assert_eq!(response["limit"], 5);
+
+    // an explicit limit still overrides the default
+    let (response, code) = index.search(json!({ "limit": 12 })).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["hits"].as_array().unwrap().len(), 12);
+}
@@ -0,0 +1,49 @@
+use serde_json::json;
+
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn set_get_and_clear_stop_words() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .update_settings(json!({ "displayedAttributes": ["title"] }))
+        .await;
+    index.wait_update_id(0).await;
+    let url = format!("/indexes/{}/settings/stop-words", index.uid);
+
+    let (response, code) = server.service.put(&url, json!(["the", "a", "of"])).await;
+    assert_eq!(code, 202);
+    let update = index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    assert_eq!(update["status"], "processed");
+
+    let (response, code) = server.service.get(&url).await;
+    assert_eq!(code, 200);
+    assert_eq!(response, json!(["a", "of", "the"]));
+
+    // the other settings are left untouched
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200);
+    assert_eq!(response["displayedAttributes"], json!(["title"]));
+
+    // the list is replaced, not merged
+    let (response, code) = server.service.put(&url, json!(["an"])).await;
+    assert_eq!(code, 202);
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    let (response, code) = server.service.get(&url).await;
+    assert_eq!(code, 200);
+    assert_eq!(response, json!(["an"]));
+
+    let (response, code) = server.service.delete(&url).await;
+    assert_eq!(code, 202);
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    let (response, code) = server.service.get(&url).await;
+    assert_eq!(code, 200);
+    assert_eq!(response, json!([]));
+}
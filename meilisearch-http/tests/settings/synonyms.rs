@@ -0,0 +1,79 @@
+use serde_json::json;
+
+use crate::common::Server;
+
+#[actix_rt::test]
+async fn synonyms_affect_search() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let documents = json!([
+        { "id": 1, "title": "a fast car" },
+        { "id": 2, "title": "a slow bike" },
+    ]);
+    let (response, _code) = index.add_documents(documents, Some("id")).await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = index.search(json!({ "q": "automobile" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["nbHits"], 0);
+
+    let url = format!("/indexes/{}/settings/synonyms", index.uid);
+    let (response, code) = server
+        .service
+        .put(&url, json!({ "automobile": ["car"] }))
+        .await;
+    assert_eq!(code, 202);
+    let update = index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    assert_eq!(update["status"], "processed");
+
+    let (response, code) = server.service.get(&url).await;
+    assert_eq!(code, 200);
+    assert_eq!(response, json!({ "automobile": ["car"] }));
+
+    let (response, code) = index.search(json!({ "q": "automobile" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["nbHits"], 1);
+    assert_eq!(response["hits"][0]["id"], 1);
+
+    let (response, code) = server.service.delete(&url).await;
+    assert_eq!(code, 202);
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = server.service.get(&url).await;
+    assert_eq!(code, 200);
+    assert_eq!(response, json!({}));
+    let (response, code) = index.search(json!({ "q": "automobile" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["nbHits"], 0);
+}
+
+#[actix_rt::test]
+async fn invalid_synonyms() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    let url = format!("/indexes/{}/settings/synonyms", index.uid);
+
+    let (_response, code) = server.service.put(&url, json!({ "": ["car"] })).await;
+    assert_eq!(code, 400);
+
+    let (_response, code) = server
+        .service
+        .put(&url, json!({ "car": "automobile" }))
+        .await;
+    assert_eq!(code, 400);
+
+    let (_response, code) = server.service.put(&url, json!(["car"])).await;
+    assert_eq!(code, 400);
+
+    let (_response, code) = index
+        .update_settings(json!({ "synonyms": { " ": ["car"] } }))
+        .await;
+    assert_eq!(code, 400);
+}
@@ -0,0 +1,108 @@
+use serde_json::json;
+
+use crate::common::index::Index;
+use crate::common::{sorted_hits_ids, Server};
+
+async fn setup(index: &Index<'_>) {
+    let documents = json!([
+        { "id": 1, "title": "wooden chair", "sku": "kitchen" },
+        { "id": 2, "title": "kitchen table", "sku": "woodtab" },
+    ]);
+    index.add_documents_and_wait(documents, Some("id")).await;
+}
+
+#[actix_rt::test]
+async fn disable_typos_on_attribute() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index.search(json!({ "q": "kitchan" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(sorted_hits_ids(&response), vec![1, 2]);
+
+    let (response, code) = index
+        .update_settings(json!({ "typoTolerance": { "disableOnAttributes": ["sku"] } }))
+        .await;
+    assert_eq!(code, 202);
+    let update = index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+    assert_eq!(update["status"], "processed");
+
+    let (response, code) = index.settings().await;
+    assert_eq!(code, 200);
+    assert_eq!(
+        response["typoTolerance"],
+        json!({
+            "enabled": true,
+            "minWordSizeForOneTypo": 5,
+            "minWordSizeForTwoTypos": 9,
+            "disableOnAttributes": ["sku"]
+        })
+    );
+
+    // the typo only matches in the title now
+    let (response, code) = index.search(json!({ "q": "kitchan" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(sorted_hits_ids(&response), vec![2]);
+    assert_eq!(response["nbHits"], 1);
+
+    // the exact words still match in the sku
+    let (response, code) = index.search(json!({ "q": "kitchen" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(sorted_hits_ids(&response), vec![1, 2]);
+}
+
+#[actix_rt::test]
+async fn disable_typos() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, _code) = index
+        .update_settings(json!({ "typoTolerance": { "enabled": false } }))
+        .await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = index.search(json!({ "q": "kitchan" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["nbHits"], 0);
+}
+
+#[actix_rt::test]
+async fn unordered_typo_thresholds() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    let (_response, code) = index
+        .update_settings(json!({
+            "typoTolerance": { "minWordSizeForOneTypo": 10, "minWordSizeForTwoTypos": 4 }
+        }))
+        .await;
+    assert_eq!(code, 400);
+}
+
+#[actix_rt::test]
+async fn typo_thresholds_apply_per_word() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+    index
+        .add_documents_and_wait(json!([{ "id": 3, "title": "wooden stool" }]), None)
+        .await;
+
+    let (response, code) = index.search(json!({ "q": "kitchan woodan" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(sorted_hits_ids(&response), vec![1, 2, 3]);
+
+    index
+        .update_settings_and_wait(json!({ "typoTolerance": { "minWordSizeForOneTypo": 7 } }))
+        .await;
+
+    // the typo is still allowed on `kitchan`, but not on `woodan`
+    let (response, code) = index.search(json!({ "q": "kitchan woodan" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(sorted_hits_ids(&response), vec![1, 2]);
+}
@@ -0,0 +1,70 @@
+use serde_json::json;
+
+use crate::common::index::Index;
+use crate::common::Server;
+
+async fn setup(index: &Index<'_>) {
+    let documents = json!([
+        { "id": 1, "title": "wooden chair", "price": 30 },
+        { "id": 2, "title": "kitchen table", "price": 120 },
+    ]);
+    index.add_documents_and_wait(documents, Some("id")).await;
+    index
+        .update_settings_and_wait(json!({ "attributesForFaceting": { "price": "integer" } }))
+        .await;
+}
+
+#[actix_rt::test]
+async fn validate_valid_settings() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .validate_settings(json!({
+            "searchableAttributes": ["title"],
+            "rankingRules": ["desc(price)", "typo", "words"],
+        }))
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(response, json!({ "errors": [], "warnings": [] }));
+
+    // nothing was enqueued
+    let (updates, _) = index.list_updates().await;
+    assert_eq!(updates.as_array().unwrap().len(), 2);
+}
+
+#[actix_rt::test]
+async fn validate_settings_with_unknown_attribute() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    setup(&index).await;
+
+    let (response, code) = index
+        .validate_settings(json!({
+            "displayedAttributes": ["title", "color"],
+            "rankingRules": ["asc(color)", "typo", "words"],
+        }))
+        .await;
+    assert_eq!(code, 200);
+
+    let errors = response["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].as_str().unwrap().contains("asc(color)"));
+    let warnings = response["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].as_str().unwrap().contains("color"));
+
+    let (updates, _) = index.list_updates().await;
+    assert_eq!(updates.as_array().unwrap().len(), 2);
+}
+
+#[actix_rt::test]
+async fn validate_settings_of_unexisting_index() {
+    let server = Server::new().await;
+    let (_response, code) = server
+        .index("test")
+        .validate_settings(json!({ "rankingRules": ["typo"] }))
+        .await;
+    assert_eq!(code, 400);
+}
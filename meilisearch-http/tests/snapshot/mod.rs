@@ -1,8 +1,9 @@
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use crate::common::server::default_settings;
 use crate::common::GetAllDocumentsOptions;
 use crate::common::Server;
+use serde_json::json;
 use tokio::time::sleep;
 
 use meilisearch_http::Opt;
@@ -51,3 +52,62 @@ async fn perform_snapshot() {
 
     assert_eq!(response, response_from_snapshot);
 }
+
+#[actix_rt::test]
+async fn import_scheduled_snapshot() {
+    let temp = tempfile::tempdir_in(".").unwrap();
+    let snapshot_dir = tempfile::tempdir_in(".").unwrap();
+    let snapshot_path = snapshot_dir.path().join("db.snapshot");
+
+    let options = Opt {
+        snapshot_dir: snapshot_dir.path().to_owned(),
+        snapshot_interval_sec: 1,
+        schedule_snapshot: true,
+        ..default_settings(temp.path())
+    };
+
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+    index.load_test_set().await;
+    let indexed_at = SystemTime::now();
+
+    let (response, code) = index
+        .get_all_documents(GetAllDocumentsOptions::default())
+        .await;
+    assert_eq!(code, 200);
+
+    // try 10 times to find a snapshot taken after the documents were indexed
+    let mut snapshotted = false;
+    for _ in 0..10 {
+        sleep(Duration::from_secs(1)).await;
+        let modified = std::fs::metadata(&snapshot_path).and_then(|meta| meta.modified());
+        if matches!(modified, Ok(modified) if modified > indexed_at) {
+            snapshotted = true;
+            break;
+        }
+    }
+    assert!(snapshotted, "no snapshot was taken after the indexation");
+
+    let temp = tempfile::tempdir_in(".").unwrap();
+    let options = Opt {
+        import_snapshot: Some(snapshot_path),
+        ..default_settings(temp.path())
+    };
+
+    let server = Server::new_with_options(options).await;
+    let index = server.index("test");
+
+    let (response_from_snapshot, code) = index
+        .get_all_documents(GetAllDocumentsOptions::default())
+        .await;
+    assert_eq!(code, 200);
+    assert_eq!(response, response_from_snapshot);
+
+    let (update, code) = index.get_update(0).await;
+    assert_eq!(code, 200);
+    assert_eq!(update["status"], "processed");
+
+    let (response, code) = index.search(json!({ "q": "" })).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["nbHits"], 77);
+}
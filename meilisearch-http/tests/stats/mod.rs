@@ -22,6 +22,15 @@ async fn test_healthyness() {
     assert_eq!(response["status"], "available");
 }
 
+#[actix_rt::test]
+async fn test_readiness() {
+    let server = Server::new().await;
+
+    let (response, status_code) = server.service.get("/health/ready").await;
+    assert_eq!(status_code, 200);
+    assert_eq!(response["status"], "available");
+}
+
 #[actix_rt::test]
 async fn stats() {
     let server = Server::new().await;
@@ -74,3 +83,39 @@ async fn stats() {
     assert_eq!(response["indexes"]["test"]["fieldsDistribution"]["name"], 1);
     assert_eq!(response["indexes"]["test"]["fieldsDistribution"]["age"], 1);
 }
+
+#[actix_rt::test]
+async fn stats_multiple_indexes() {
+    let server = Server::new().await;
+
+    let index1 = server.index("test1");
+    let (_, code) = index1.create(Some("id")).await;
+    assert_eq!(code, 200);
+
+    let index2 = server.index("test2");
+    let (_, code) = index2.create(Some("id")).await;
+    assert_eq!(code, 200);
+
+    let (response, code) = index1
+        .add_documents(json!([{ "id": 1 }, { "id": 2 }, { "id": 3 }]), None)
+        .await;
+    assert_eq!(code, 202);
+    index1
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = index2.add_documents(json!([{ "id": 1 }]), None).await;
+    assert_eq!(code, 202);
+    index2
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = server.stats().await;
+
+    assert_eq!(code, 200);
+    assert!(response["databaseSize"].as_u64().unwrap() > 0);
+    assert!(response["lastUpdate"].is_string());
+    assert_eq!(response["indexes"].as_object().unwrap().len(), 2);
+    assert_eq!(response["indexes"]["test1"]["numberOfDocuments"], 3);
+    assert_eq!(response["indexes"]["test2"]["numberOfDocuments"], 1);
+}
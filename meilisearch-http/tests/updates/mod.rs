@@ -1,3 +1,9 @@
+use std::time::Duration;
+
+use chrono::DateTime;
+use tokio::time::sleep;
+
+use crate::common::server::default_settings;
 use crate::common::Server;
 
 #[actix_rt::test]
@@ -35,6 +41,35 @@ async fn get_update_status() {
     // TODO check resonse format, as per #48
 }
 
+#[actix_rt::test]
+async fn processed_update_has_timestamps() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index
+        .add_documents(serde_json::json!([{ "id": 1, "content": "foobar" }]), None)
+        .await;
+    index.wait_update_id(0).await;
+
+    let (response, code) = index.get_update(0).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["status"], "processed");
+
+    let date =
+        |field: &str| DateTime::parse_from_rfc3339(response[field].as_str().unwrap()).unwrap();
+    let enqueued_at = date("enqueuedAt");
+    let started_processing_at = date("startedProcessingAt");
+    let finished_at = date("finishedAt");
+    assert!(finished_at >= started_processing_at);
+    assert!(started_processing_at >= enqueued_at);
+
+    let duration = response["duration"].as_f64().unwrap();
+    let expected = (finished_at - started_processing_at)
+        .num_microseconds()
+        .unwrap() as f64
+        / 1e6;
+    assert!((duration - expected).abs() < 1e-6);
+}
+
 #[actix_rt::test]
 async fn list_updates_unexisting_index() {
     let server = Server::new().await;
@@ -67,3 +102,409 @@ async fn list_updates() {
     assert_eq!(code, 200);
     assert_eq!(response.as_array().unwrap().len(), 1);
 }
+
+#[actix_rt::test]
+async fn list_updates_pagination() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    for i in 0..50 {
+        index
+            .add_documents(serde_json::json!([{ "id": i }]), Some("id"))
+            .await;
+    }
+
+    let mut ids = Vec::new();
+    for offset in (0..50).step_by(10) {
+        let (response, code) = index
+            .list_updates_with_query(&format!("offset={}&limit=10", offset))
+            .await;
+        assert_eq!(code, 200);
+        let page = response.as_array().unwrap();
+        assert_eq!(page.len(), 10);
+        ids.extend(
+            page.iter()
+                .map(|update| update["updateId"].as_u64().unwrap()),
+        );
+    }
+    assert_eq!(ids, (0..50).rev().collect::<Vec<_>>());
+
+    let (response, code) = index.list_updates_with_query("offset=50&limit=10").await;
+    assert_eq!(code, 200);
+    assert!(response.as_array().unwrap().is_empty());
+}
+
+#[actix_rt::test]
+async fn list_updates_status_filter() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    index
+        .add_documents(serde_json::json!([{ "id": 1 }]), Some("id"))
+        .await;
+    // the update fails because the document has no primary key
+    let (response, _code) = index
+        .add_documents(serde_json::json!([{ "name": "no id" }]), None)
+        .await;
+    index
+        .wait_update_id(response["updateId"].as_u64().unwrap())
+        .await;
+
+    let (response, code) = index.list_updates_with_query("status=failed").await;
+    assert_eq!(code, 200);
+    let updates = response.as_array().unwrap();
+    assert_eq!(updates.len(), 1);
+    assert_eq!(updates[0]["updateId"], 1);
+    assert_eq!(updates[0]["status"], "failed");
+
+    let (response, code) = index.list_updates_with_query("status=processed").await;
+    assert_eq!(code, 200);
+    assert_eq!(response.as_array().unwrap().len(), 1);
+    assert_eq!(response[0]["updateId"], 0);
+}
+
+#[actix_rt::test]
+async fn stream_updates_as_ndjson() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    for i in 0..30 {
+        index
+            .add_documents(serde_json::json!([{ "id": i }]), Some("id"))
+            .await;
+    }
+    index.wait_update_id(9).await;
+
+    // The updates are listed by descending id whatever their state.
+    let service = &server.service;
+    let stream = |query: &'static str| {
+        let url = format!("/indexes/test/updates?format=ndjson&{}", query);
+        async move {
+            let (body, code) = service.get_raw(url).await;
+            assert_eq!(code, 200, "{}", body);
+            body.lines()
+                .map(|line| {
+                    let update: serde_json::Value = serde_json::from_str(line).unwrap();
+                    update["updateId"].as_u64().unwrap()
+                })
+                .collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(stream("").await, (0..30).rev().collect::<Vec<_>>());
+    assert_eq!(
+        stream("offset=5&limit=10").await,
+        (15..25).rev().collect::<Vec<_>>()
+    );
+
+    index.wait_update_id(29).await;
+    assert_eq!(stream("status=processed&limit=3").await, vec![29, 28, 27]);
+
+    let (response, code) = service.get("/indexes/test/updates?format=xml").await;
+    assert_eq!(code, 400);
+    assert!(response["error"].as_str().unwrap().contains("format"));
+}
+
+#[actix_rt::test]
+async fn prune_finished_updates() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    for i in 0..10 {
+        index
+            .add_documents(serde_json::json!([{ "id": i }]), Some("id"))
+            .await;
+    }
+    // the update fails because the document has no primary key
+    index
+        .add_documents(serde_json::json!([{ "name": "no id" }]), None)
+        .await;
+    index.wait_update_id(10).await;
+
+    let (response, code) = server
+        .service
+        .delete("/indexes/test/updates?before=5&status=processed")
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["deletedUpdates"], 5);
+    let (response, _code) = index.list_updates().await;
+    let ids: Vec<_> = response
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|update| update["updateId"].as_u64().unwrap())
+        .collect();
+    assert_eq!(ids, (5..11).rev().collect::<Vec<_>>());
+    let (_response, code) = index.get_update(2).await;
+    assert_eq!(code, 400);
+
+    // The updates finished before the date are pruned whatever their state.
+    let (response, code) = server
+        .service
+        .delete("/indexes/test/updates?before=2100-01-01T00:00:00Z")
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["deletedUpdates"], 6);
+    let (response, _code) = index.list_updates().await;
+    assert!(response.as_array().unwrap().is_empty());
+
+    // The documents are still there.
+    let (response, _code) = index.get_all_documents(Default::default()).await;
+    assert_eq!(response.as_array().unwrap().len(), 10);
+}
+
+#[actix_rt::test]
+async fn pruned_update_ids_are_not_reused() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    index
+        .add_documents(serde_json::json!([{ "id": 1 }]), Some("id"))
+        .await;
+    // the last update fails because the document has no primary key
+    index
+        .add_documents(serde_json::json!([{ "name": "no id" }]), None)
+        .await;
+    index.wait_update_id(1).await;
+
+    let (response, code) = server
+        .service
+        .delete("/indexes/test/updates?before=2100-01-01T00:00:00Z")
+        .await;
+    assert_eq!(code, 200, "{}", response);
+    assert_eq!(response["deletedUpdates"], 2);
+
+    let (response, code) = index
+        .add_documents(serde_json::json!([{ "id": 2 }]), Some("id"))
+        .await;
+    assert_eq!(code, 202, "{}", response);
+    assert_eq!(response["updateId"], 2);
+    let response = index.wait_update_id(2).await;
+    assert_eq!(response["status"], "processed");
+}
+
+#[actix_rt::test]
+async fn prune_updates_bad_parameters() {
+    let server = Server::new().await;
+    server.index("test").create(None).await;
+
+    let (_response, code) = server
+        .service
+        .delete("/indexes/test/updates?before=yesterday")
+        .await;
+    assert_eq!(code, 400);
+    let (_response, code) = server
+        .service
+        .delete("/indexes/test/updates?before=10&status=enqueued")
+        .await;
+    assert_eq!(code, 400);
+    let (_response, code) = server
+        .service
+        .delete("/indexes/unexisting/updates?before=10")
+        .await;
+    assert_eq!(code, 400);
+}
+
+#[actix_rt::test]
+async fn watch_update() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    // a large update is enqueued first, so the watched update doesn't start right away
+    index
+        .add_documents(
+            serde_json::from_str(include_str!("../assets/test_set.json")).unwrap(),
+            None,
+        )
+        .await;
+    index
+        .add_documents(serde_json::json!([{ "id": 1, "content": "foobar" }]), None)
+        .await;
+
+    let (response, code) = index.watch_update(1).await;
+    assert_eq!(code, 200);
+
+    let statuses: Vec<String> = response
+        .lines()
+        .filter_map(|line| line.strip_prefix("data: "))
+        .map(|event| {
+            let event: serde_json::Value = serde_json::from_str(event).unwrap();
+            assert_eq!(event["updateId"], 1);
+            event["status"].as_str().unwrap().to_string()
+        })
+        .collect();
+    assert!(statuses.contains(&"processing".to_string()));
+    assert_eq!(statuses.last().unwrap(), "processed");
+}
+
+#[actix_rt::test]
+async fn watch_unexisting_update() {
+    let server = Server::new().await;
+    let index = server.index("test");
+    index.create(None).await;
+    let (_response, code) = index.watch_update(0).await;
+    assert_eq!(code, 400);
+}
+
+#[actix_rt::test]
+async fn shutdown_waits_for_the_processing_update() {
+    let temp = tempfile::tempdir_in(".").unwrap();
+    let server = Server::new_with_options(default_settings(temp.path())).await;
+    let index = server.index("test");
+
+    let documents: Vec<_> = (0..50_000)
+        .map(|id| serde_json::json!({ "id": id, "content": format!("document {}", id) }))
+        .collect();
+    let (_, code) = index
+        .add_documents(serde_json::json!(documents), Some("id"))
+        .await;
+    assert_eq!(code, 202);
+
+    // wait for the update to start being processed
+    loop {
+        let (response, _) = index.get_update(0).await;
+        if response["status"] != "enqueued" {
+            break;
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+
+    assert!(server.shutdown(Duration::from_secs(60)).await);
+
+    // no update is accepted anymore
+    let (_, code) = index
+        .add_documents(serde_json::json!([{ "id": 0 }]), None)
+        .await;
+    assert_eq!(code, 503);
+    drop(server);
+
+    let server = Server::new_with_options(default_settings(temp.path())).await;
+    let (response, code) = server.index("test").get_update(0).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["status"], "processed");
+}
+
+#[actix_rt::test]
+async fn settings_update_is_processed_before_documents() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents: Vec<_> = (0..50_000)
+        .map(|id| serde_json::json!({ "id": id, "content": format!("document {}", id) }))
+        .collect();
+    let (_, code) = index
+        .add_documents(serde_json::json!(documents), Some("id"))
+        .await;
+    assert_eq!(code, 202);
+
+    // wait for the big update to start being processed
+    loop {
+        let (response, _) = index.get_update(0).await;
+        if response["status"] != "enqueued" {
+            break;
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+
+    let (_, code) = index
+        .add_documents(serde_json::json!([{ "id": 0, "content": "foobar" }]), None)
+        .await;
+    assert_eq!(code, 202);
+    let (_, code) = index
+        .update_settings(serde_json::json!({ "displayedAttributes": ["id"] }))
+        .await;
+    assert_eq!(code, 202);
+    index.wait_update_id(1).await;
+
+    let (documents, _) = index.get_update(1).await;
+    let (settings, _) = index.get_update(2).await;
+    assert_eq!(settings["status"], "processed");
+    let date = |update: &serde_json::Value, field: &str| {
+        DateTime::parse_from_rfc3339(update[field].as_str().unwrap()).unwrap()
+    };
+    assert!(date(&settings, "finishedAt") <= date(&documents, "startedProcessingAt"));
+}
+
+#[actix_rt::test]
+async fn enqueued_update_is_resumed_after_restart() {
+    let temp = tempfile::tempdir_in(".").unwrap();
+    let server = Server::new_with_options(default_settings(temp.path())).await;
+    let index = server.index("test");
+
+    let documents: Vec<_> = (0..50_000)
+        .map(|id| serde_json::json!({ "id": id, "content": format!("document {}", id) }))
+        .collect();
+    index
+        .add_documents(serde_json::json!(documents), Some("id"))
+        .await;
+
+    // wait for the update to start being processed
+    loop {
+        let (response, _) = index.get_update(0).await;
+        if response["status"] != "enqueued" {
+            break;
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+
+    // still enqueued when the instance stops
+    let (_, code) = index
+        .add_documents(serde_json::json!([{ "id": 50_000 }]), None)
+        .await;
+    assert_eq!(code, 202);
+    assert!(server.shutdown(Duration::from_secs(60)).await);
+    drop(server);
+
+    let server = Server::new_with_options(default_settings(temp.path())).await;
+    let index = server.index("test");
+    // the update is processed without its index being accessed through the update actor
+    for _ in 0..1000 {
+        if index.document_count().await.unwrap() == 50_001 {
+            break;
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(index.document_count().await.unwrap(), 50_001);
+
+    let (response, code) = index.get_update(1).await;
+    assert_eq!(code, 200);
+    assert_eq!(response["status"], "processed");
+}
+
+#[actix_rt::test]
+async fn processing_update_reports_progress() {
+    let server = Server::new().await;
+    let index = server.index("test");
+
+    let documents: Vec<_> = (0..50_000)
+        .map(|id| serde_json::json!({ "id": id, "content": format!("document {}", id) }))
+        .collect();
+    let (_, code) = index
+        .add_documents(serde_json::json!(documents), Some("id"))
+        .await;
+    assert_eq!(code, 202);
+
+    let mut percentages = Vec::new();
+    loop {
+        let (response, code) = index.get_update(0).await;
+        assert_eq!(code, 200);
+        match response["status"].as_str().unwrap() {
+            "processing" => {
+                if let Some(percentage) = response["progress"]["percentage"].as_f64() {
+                    percentages.push(percentage);
+                }
+            }
+            "processed" => {
+                // The progress isn't kept once the update is processed.
+                assert!(response.get("progress").is_none());
+                break;
+            }
+            "enqueued" => (),
+            status => panic!("unexpected status {}: {}", status, response),
+        }
+        sleep(Duration::from_millis(10)).await;
+    }
+
+    assert!(percentages.iter().any(|&percentage| percentage > 0.0));
+    assert!(percentages.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(percentages.iter().all(|&percentage| percentage <= 100.0));
+}